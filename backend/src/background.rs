@@ -0,0 +1,75 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+/// Work spawned by requests that hasn't finished yet
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static IDLE: Notify = Notify::const_new();
+
+/// Decrements the in-flight count when a task ends, including by panicking.
+struct InFlight;
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if IN_FLIGHT.fetch_sub(1, Ordering::SeqCst) == 1 {
+            IDLE.notify_waiters();
+        }
+    }
+}
+
+/// Runs work a request kicked off, like webhook or email dispatch, without holding up the
+/// response. Unlike a bare `tokio::spawn`, shutdown waits for it through [`drain`].
+pub fn spawn<F>(task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+    let guard = InFlight;
+    tokio::spawn(async move {
+        let _guard = guard;
+        task.await;
+    });
+}
+
+/// Waits up to `timeout` for spawned work to finish. Returns false if some was still running.
+pub async fn drain(timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, async {
+        loop {
+            let idle = IDLE.notified();
+            tokio::pin!(idle);
+            // Register before checking the count so a task finishing in between still wakes us
+            idle.as_mut().enable();
+            if IN_FLIGHT.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    })
+    .await
+    .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_spawned_work() {
+        let done = Arc::new(AtomicBool::new(false));
+        let flag = done.clone();
+        spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        assert!(drain(Duration::from_secs(5)).await);
+        assert!(done.load(Ordering::SeqCst));
+
+        spawn(tokio::time::sleep(Duration::from_secs(60)));
+        assert!(!drain(Duration::from_millis(20)).await);
+    }
+}
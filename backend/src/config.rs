@@ -1,4 +1,5 @@
 use std::env;
+use std::time::Duration;
 
 pub struct Config {
     pub database_url: String,
@@ -19,6 +20,415 @@ impl Config {
     }
 }
 
+/// Cross-origin settings for browsers calling the API from another origin.
+#[derive(Clone, Debug, Default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API. When empty, any origin is allowed but credentials aren't.
+    pub allowed_origins: Vec<String>,
+    /// Allowed methods; empty allows whatever the preflight asks for.
+    pub allowed_methods: Vec<String>,
+    /// Allowed request headers; empty allows whatever the preflight asks for.
+    pub allowed_headers: Vec<String>,
+}
+
+impl CorsConfig {
+    /// Reads comma-separated `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS` and
+    /// `CORS_ALLOWED_HEADERS`.
+    pub fn from_env() -> Self {
+        Self {
+            allowed_origins: list_var("CORS_ALLOWED_ORIGINS"),
+            allowed_methods: list_var("CORS_ALLOWED_METHODS"),
+            allowed_headers: list_var("CORS_ALLOWED_HEADERS"),
+        }
+    }
+}
+
+/// Rules new passwords must meet on registration, reset and change.
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_letter: bool,
+    pub require_digit: bool,
+    pub require_uppercase: bool,
+    pub require_symbol: bool,
+    /// Rejected regardless of the other rules, compared case-insensitively
+    pub denylist: Vec<String>,
+}
+
+/// Among the most common leaked passwords that would otherwise pass the default rules
+const COMMON_PASSWORDS: [&str; 20] = [
+    "password1",
+    "password12",
+    "password123",
+    "password1234",
+    "passw0rd",
+    "abc12345",
+    "abcd1234",
+    "qwerty123",
+    "qwerty12",
+    "1q2w3e4r",
+    "1qaz2wsx",
+    "iloveyou1",
+    "letmein1",
+    "welcome1",
+    "welcome123",
+    "admin123",
+    "monkey123",
+    "dragon123",
+    "football1",
+    "trustno1",
+];
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_letter: true,
+            require_digit: true,
+            require_uppercase: false,
+            require_symbol: false,
+            denylist: COMMON_PASSWORDS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// The default policy, tuned by `PASSWORD_MIN_LENGTH`, `PASSWORD_REQUIRE_UPPERCASE`,
+    /// `PASSWORD_REQUIRE_SYMBOL` and a comma-separated `PASSWORD_DENYLIST` of extra entries.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(min_length) = env::var("PASSWORD_MIN_LENGTH")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            policy.min_length = min_length;
+        }
+        policy.require_uppercase = flag_var("PASSWORD_REQUIRE_UPPERCASE");
+        policy.require_symbol = flag_var("PASSWORD_REQUIRE_SYMBOL");
+        policy.denylist.extend(list_var("PASSWORD_DENYLIST"));
+        policy
+    }
+}
+
+/// How write paths retry once SQLite has given up waiting on another writer's lock.
+#[derive(Clone, Copy, Debug)]
+pub struct BusyRetry {
+    /// Retries after the first attempt; 0 fails straight away
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub base_delay: Duration,
+}
+
+impl Default for BusyRetry {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+impl BusyRetry {
+    /// The defaults, tuned by `DB_BUSY_RETRIES` and `DB_BUSY_BACKOFF_MS`.
+    pub fn from_env() -> Self {
+        let mut retry = Self::default();
+        if let Some(max_retries) = env::var("DB_BUSY_RETRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            retry.max_retries = max_retries;
+        }
+        if let Some(ms) = env::var("DB_BUSY_BACKOFF_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            retry.base_delay = Duration::from_millis(ms);
+        }
+        retry
+    }
+}
+
+/// Longest a session or API key goes without its last use being written again
+pub(crate) const MAX_TOUCH_INTERVAL_SECS: u64 = 60;
+
+/// When a login session ends for lack of use.
+#[derive(Clone, Copy, Debug)]
+pub struct SessionPolicy {
+    /// Sessions with no authenticated request for this long are rejected; `None` never expires
+    /// them, leaving only the token lifetimes
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+impl SessionPolicy {
+    /// The default, overridden by `SESSION_IDLE_TIMEOUT_MINUTES`; 0 turns idle expiry off.
+    pub fn from_env() -> Self {
+        match env::var("SESSION_IDLE_TIMEOUT_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            Some(0) => Self { idle_timeout: None },
+            Some(minutes) => Self {
+                idle_timeout: Some(Duration::from_secs(minutes * 60)),
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// SQLite datetime modifier for the start of the activity window.
+    pub(crate) fn idle_cutoff(&self) -> Option<String> {
+        self.idle_timeout
+            .map(|timeout| format!("-{} seconds", timeout.as_secs()))
+    }
+
+    /// SQLite datetime modifier for how stale a session's recorded use may get before a request
+    /// records it again. Kept to a tenth of the idle timeout so skipping the write can't expire
+    /// an active session.
+    pub(crate) fn touch_cutoff(&self) -> String {
+        let secs = self
+            .idle_timeout
+            .map_or(MAX_TOUCH_INTERVAL_SECS, |timeout| {
+                (timeout.as_secs() / 10).min(MAX_TOUCH_INTERVAL_SECS)
+            });
+        format!("-{secs} seconds")
+    }
+}
+
+/// Fallback for `JWT_SECRET` in development
+pub(crate) const DEV_JWT_SECRET: &str = "payme-secret-key-change-in-production";
+
+/// `JWT_SECRET`, or [`DEV_JWT_SECRET`] when it isn't set.
+pub(crate) fn jwt_secret() -> String {
+    env::var("JWT_SECRET").unwrap_or_else(|_| DEV_JWT_SECRET.to_string())
+}
+
+/// Key two-factor secrets are encrypted with at rest, from `TOTP_ENCRYPTION_KEY`. It is separate
+/// from the token signing keys so rotating those never locks anyone out. Without it, two-factor
+/// auth can't be set up.
+#[derive(Clone, Debug, Default)]
+pub struct TwoFactorConfig {
+    pub encryption_key: Option<String>,
+}
+
+impl TwoFactorConfig {
+    pub fn from_env() -> Self {
+        Self {
+            encryption_key: env::var("TOTP_ENCRYPTION_KEY")
+                .ok()
+                .filter(|key| !key.is_empty()),
+        }
+    }
+}
+
+/// A named secret access tokens are signed with; the name is carried as the token's `kid`.
+#[derive(Clone, Debug)]
+pub struct SigningKey {
+    pub id: String,
+    pub secret: String,
+}
+
+/// Keys for signing and verifying access tokens. New tokens are signed with `current`; tokens
+/// signed with one of `previous` stay valid until they expire, so the signing key can be rotated
+/// without logging everyone out. Dropping a key from `previous` retires it.
+#[derive(Clone, Debug)]
+pub struct JwtKeys {
+    pub current: SigningKey,
+    pub previous: Vec<SigningKey>,
+}
+
+impl Default for JwtKeys {
+    fn default() -> Self {
+        Self {
+            current: SigningKey {
+                id: "default".to_string(),
+                secret: jwt_secret(),
+            },
+            previous: Vec::new(),
+        }
+    }
+}
+
+impl JwtKeys {
+    /// Signs with `JWT_SIGNING_KEY`, named by `JWT_KEY_ID`, falling back to `JWT_SECRET` as the
+    /// `default` key. `JWT_PREVIOUS_KEYS` lists keys still accepted, as comma-separated `id:secret`.
+    pub fn from_env() -> Self {
+        let mut keys = Self::default();
+        if let Ok(secret) = env::var("JWT_SIGNING_KEY") {
+            keys.current = SigningKey {
+                id: env::var("JWT_KEY_ID").unwrap_or_else(|_| "default".to_string()),
+                secret,
+            };
+        }
+        keys.previous = list_var("JWT_PREVIOUS_KEYS")
+            .into_iter()
+            .filter_map(|entry| {
+                let (id, secret) = entry.split_once(':')?;
+                Some(SigningKey {
+                    id: id.to_string(),
+                    secret: secret.to_string(),
+                })
+            })
+            .collect();
+        keys
+    }
+
+    /// Keys a token may have been signed with: the one its `kid` names, or for tokens issued
+    /// before they carried one, every key.
+    pub fn verification_keys(&self, kid: Option<&str>) -> Vec<&SigningKey> {
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .filter(|key| kid.is_none_or(|kid| key.id == kid))
+            .collect()
+    }
+}
+
+/// Largest request bodies accepted, in bytes, for each group of routes.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimits {
+    /// Every route without a larger limit of its own
+    pub default: usize,
+    /// JSON import and bulk item endpoints
+    pub bulk: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            default: 2 * 1024 * 1024,
+            bulk: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl BodyLimits {
+    /// The defaults, overridden by `MAX_BODY_BYTES` and `MAX_BULK_BODY_BYTES`.
+    pub fn from_env() -> Self {
+        let mut limits = Self::default();
+        if let Some(bytes) = env::var("MAX_BODY_BYTES").ok().and_then(|v| v.parse().ok()) {
+            limits.default = bytes;
+        }
+        if let Some(bytes) = env::var("MAX_BULK_BODY_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            limits.bulk = bytes;
+        }
+        limits
+    }
+}
+
+/// Where webhooks may deliver. Both relaxations exist for local development and testing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebhookPolicy {
+    /// Accept plain `http://` endpoints as well as `https://`
+    pub allow_http: bool,
+    /// Deliver to loopback, private and link-local addresses
+    pub allow_private_hosts: bool,
+}
+
+impl WebhookPolicy {
+    /// Https to public addresses only, unless `WEBHOOK_ALLOW_HTTP` or
+    /// `WEBHOOK_ALLOW_PRIVATE_HOSTS` is set.
+    pub fn from_env() -> Self {
+        Self {
+            allow_http: flag_var("WEBHOOK_ALLOW_HTTP"),
+            allow_private_hosts: flag_var("WEBHOOK_ALLOW_PRIVATE_HOSTS"),
+        }
+    }
+}
+
+/// Budget categories a new account starts with, so the dashboard isn't empty on first login.
+#[derive(Clone, Debug)]
+pub struct DefaultCategories {
+    pub labels: Vec<String>,
+}
+
+impl Default for DefaultCategories {
+    fn default() -> Self {
+        Self {
+            labels: [
+                "Housing",
+                "Groceries",
+                "Transport",
+                "Utilities",
+                "Dining",
+                "Entertainment",
+            ]
+            .iter()
+            .map(|label| label.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl DefaultCategories {
+    /// The defaults, replaced by a comma-separated `DEFAULT_CATEGORIES`; set it empty to seed
+    /// nothing.
+    pub fn from_env() -> Self {
+        if env::var("DEFAULT_CATEGORIES").is_ok() {
+            Self {
+                labels: list_var("DEFAULT_CATEGORIES"),
+            }
+        } else {
+            Self::default()
+        }
+    }
+}
+
+/// Deployment settings the router is built with.
+#[derive(Clone, Debug, Default)]
+pub struct AppOptions {
+    pub cors: CorsConfig,
+    pub password_policy: PasswordPolicy,
+    pub busy_retry: BusyRetry,
+    pub session: SessionPolicy,
+    pub body_limits: BodyLimits,
+    pub jwt_keys: JwtKeys,
+    pub default_categories: DefaultCategories,
+    pub two_factor: TwoFactorConfig,
+    pub webhooks: WebhookPolicy,
+}
+
+impl AppOptions {
+    pub fn from_env() -> Self {
+        Self {
+            cors: CorsConfig::from_env(),
+            password_policy: PasswordPolicy::from_env(),
+            busy_retry: BusyRetry::from_env(),
+            session: SessionPolicy::from_env(),
+            body_limits: BodyLimits::from_env(),
+            jwt_keys: JwtKeys::from_env(),
+            default_categories: DefaultCategories::from_env(),
+            two_factor: TwoFactorConfig::from_env(),
+            webhooks: WebhookPolicy::from_env(),
+        }
+    }
+}
+
+fn flag_var(name: &str) -> bool {
+    matches!(
+        env::var(name).as_deref().map(str::to_lowercase).as_deref(),
+        Ok("1" | "true" | "yes")
+    )
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
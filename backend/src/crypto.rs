@@ -0,0 +1,205 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit as AeadKeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{
+    password_hash::rand_core::{OsRng, RngCore},
+    Argon2,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::error::PaymeError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SALT_LEN: usize = 16;
+/// AES-GCM's 96-bit nonce and 128-bit tag
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Ciphertext plus everything needed to reverse it given the same passphrase.
+pub struct Sealed {
+    pub salt: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+impl Sealed {
+    /// Packs the bundle into a single string for storage in a database column.
+    fn to_base64(&self) -> String {
+        let mut packed = Vec::with_capacity(
+            self.salt.len() + self.nonce.len() + self.tag.len() + self.ciphertext.len(),
+        );
+        packed.extend_from_slice(&self.salt);
+        packed.extend_from_slice(&self.nonce);
+        packed.extend_from_slice(&self.tag);
+        packed.extend_from_slice(&self.ciphertext);
+        BASE64.encode(packed)
+    }
+
+    fn from_base64(packed: &str) -> Result<Self, PaymeError> {
+        let packed = BASE64
+            .decode(packed)
+            .map_err(|e| PaymeError::Internal(format!("Corrupt sealed value: {e}")))?;
+        if packed.len() < SALT_LEN + NONCE_LEN + TAG_LEN {
+            return Err(PaymeError::Internal("Corrupt sealed value".to_string()));
+        }
+
+        let (salt, rest) = packed.split_at(SALT_LEN);
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (tag, ciphertext) = rest.split_at(TAG_LEN);
+        Ok(Self {
+            salt: salt.to_vec(),
+            nonce: nonce.to_vec(),
+            ciphertext: ciphertext.to_vec(),
+            tag: tag.to_vec(),
+        })
+    }
+}
+
+/// Derives a key from a high-entropy server secret, where Argon2's stretching is unnecessary.
+fn derive_key_from_secret(secret: &[u8], salt: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(salt);
+    mac.update(b"enc");
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&mac.finalize().into_bytes());
+    key
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn stretch(passphrase: &[u8], salt: &[u8]) -> Result<[u8; 32], PaymeError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|e| PaymeError::Internal(format!("Key derivation failed: {e}")))?;
+    Ok(key)
+}
+
+/// AES-256-GCM with the salt as associated data, so it can't be swapped between bundles.
+fn encrypt(key: &[u8; 32], salt: Vec<u8>, plaintext: &[u8]) -> Result<Sealed, PaymeError> {
+    let nonce = random_bytes(NONCE_LEN);
+    let mut ciphertext = Aes256Gcm::new_from_slice(key)
+        .expect("AES-256 takes a 32-byte key")
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &salt,
+            },
+        )
+        .map_err(|_| PaymeError::Internal("Encryption failed".to_string()))?;
+    let tag = ciphertext.split_off(ciphertext.len() - TAG_LEN);
+
+    Ok(Sealed {
+        salt,
+        nonce,
+        ciphertext,
+        tag,
+    })
+}
+
+fn decrypt(key: &[u8; 32], sealed: &Sealed) -> Result<Vec<u8>, PaymeError> {
+    if sealed.salt.len() != SALT_LEN
+        || sealed.nonce.len() != NONCE_LEN
+        || sealed.tag.len() != TAG_LEN
+    {
+        return Err(PaymeError::BadRequest(
+            "Malformed encrypted bundle".to_string(),
+        ));
+    }
+
+    let mut combined = sealed.ciphertext.clone();
+    combined.extend_from_slice(&sealed.tag);
+    Aes256Gcm::new_from_slice(key)
+        .expect("AES-256 takes a 32-byte key")
+        .decrypt(
+            Nonce::from_slice(&sealed.nonce),
+            Payload {
+                msg: &combined,
+                aad: &sealed.salt,
+            },
+        )
+        .map_err(|_| PaymeError::InvalidPassphrase)
+}
+
+/// Encrypts `plaintext` with AES-256-GCM under a key stretched from `passphrase` with Argon2id.
+pub fn seal(passphrase: &[u8], plaintext: &[u8]) -> Result<Sealed, PaymeError> {
+    let salt = random_bytes(SALT_LEN);
+    let key = stretch(passphrase, &salt)?;
+    encrypt(&key, salt, plaintext)
+}
+
+/// Reverses [`seal`]. A wrong passphrase or a tampered bundle fails authentication and yields
+/// [`PaymeError::InvalidPassphrase`] instead of garbage.
+pub fn open(passphrase: &[u8], sealed: &Sealed) -> Result<Vec<u8>, PaymeError> {
+    decrypt(&stretch(passphrase, &sealed.salt)?, sealed)
+}
+
+/// Marks values stored by [`seal_at_rest`], so the format can change without guessing
+const AT_REST_PREFIX: &str = "v1:";
+
+/// Encrypts a value for a database column with AES-256-GCM under a high-entropy server key.
+pub fn seal_at_rest(key: &[u8], plaintext: &[u8]) -> Result<String, PaymeError> {
+    let salt = random_bytes(SALT_LEN);
+    let sealed = encrypt(&derive_key_from_secret(key, &salt), salt, plaintext)?;
+    Ok(format!("{AT_REST_PREFIX}{}", sealed.to_base64()))
+}
+
+/// Reverses [`seal_at_rest`]. Fails if `key` isn't the one the value was sealed with.
+pub fn open_at_rest(key: &[u8], stored: &str) -> Result<Vec<u8>, PaymeError> {
+    let packed = stored
+        .strip_prefix(AT_REST_PREFIX)
+        .ok_or_else(|| PaymeError::Internal("Corrupt sealed value".to_string()))?;
+    let sealed = Sealed::from_base64(packed)?;
+    decrypt(&derive_key_from_secret(key, &sealed.salt), &sealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_round_trip() {
+        let sealed = seal(b"correct horse", b"hello payme").unwrap();
+        assert_ne!(sealed.ciphertext, b"hello payme");
+        assert_eq!(open(b"correct horse", &sealed).unwrap(), b"hello payme");
+    }
+
+    #[test]
+    fn test_open_wrong_passphrase() {
+        let sealed = seal(b"correct horse", b"hello payme").unwrap();
+        assert!(matches!(
+            open(b"battery staple", &sealed),
+            Err(PaymeError::InvalidPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_at_rest_round_trip() {
+        let stored = seal_at_rest(b"server key", b"JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!(
+            open_at_rest(b"server key", &stored).unwrap(),
+            b"JBSWY3DPEHPK3PXP"
+        );
+        assert!(open_at_rest(b"other key", &stored).is_err());
+    }
+
+    #[test]
+    fn test_open_tampered_ciphertext() {
+        let mut sealed = seal(b"correct horse", b"hello payme").unwrap();
+        sealed.ciphertext[0] ^= 1;
+        assert!(matches!(
+            open(b"correct horse", &sealed),
+            Err(PaymeError::InvalidPassphrase)
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+use std::future::Future;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::error::PaymeError;
+use crate::money::Money;
+
+/// Currency assumed for users and items created before currencies were tracked
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Active ISO 4217 alphabetic codes
+const ISO_4217: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWL",
+];
+
+/// Uppercases `code` and checks it against ISO 4217.
+pub fn normalize(code: &str) -> Result<String, PaymeError> {
+    let code = code.trim().to_ascii_uppercase();
+    if ISO_4217.binary_search(&code.as_str()).is_ok() {
+        Ok(code)
+    } else {
+        Err(PaymeError::BadRequest(format!("Unknown currency: {code}")))
+    }
+}
+
+/// The user's base currency, which budgets and un-suffixed totals are expressed in.
+pub async fn base_currency(pool: &sqlx::SqlitePool, user_id: i64) -> Result<String, PaymeError> {
+    let currency: Option<String> =
+        sqlx::query_scalar("SELECT base_currency FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(currency.unwrap_or_else(|| DEFAULT_CURRENCY.to_string()))
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CurrencyTotal {
+    pub currency: String,
+    #[schema(value_type = f64)]
+    pub total: Money,
+}
+
+/// Sums amounts per currency, ordered by currency code. Amounts in different currencies
+/// are never added together.
+pub fn totals_by_currency<'a>(
+    amounts: impl IntoIterator<Item = (&'a str, Money)>,
+) -> Vec<CurrencyTotal> {
+    let mut totals: std::collections::BTreeMap<&str, Money> = std::collections::BTreeMap::new();
+    for (currency, amount) in amounts {
+        *totals.entry(currency).or_default() += amount;
+    }
+    totals
+        .into_iter()
+        .map(|(currency, total)| CurrencyTotal {
+            currency: currency.to_string(),
+            total,
+        })
+        .collect()
+}
+
+/// Source of exchange rates between two currencies as of a given date.
+pub trait ExchangeRateProvider {
+    /// Units of `to` per unit of `from` on `on`, or `MissingExchangeRate` if none is known.
+    fn rate(
+        &self,
+        from: &str,
+        to: &str,
+        on: NaiveDate,
+    ) -> impl Future<Output = Result<f64, PaymeError>> + Send;
+}
+
+/// Rates the user has entered through `/api/exchange-rates`. The most recent rate on or
+/// before the date applies, and a rate entered for the opposite direction is inverted.
+pub struct UserRates<'a> {
+    pool: &'a SqlitePool,
+    user_id: i64,
+}
+
+impl<'a> UserRates<'a> {
+    pub fn new(pool: &'a SqlitePool, user_id: i64) -> Self {
+        Self { pool, user_id }
+    }
+}
+
+impl ExchangeRateProvider for UserRates<'_> {
+    async fn rate(&self, from: &str, to: &str, on: NaiveDate) -> Result<f64, PaymeError> {
+        if from == to {
+            return Ok(1.0);
+        }
+
+        let rate: Option<f64> = sqlx::query_scalar(
+            r#"
+            SELECT CASE WHEN from_currency = ?2 THEN rate ELSE 1.0 / rate END
+            FROM exchange_rates
+            WHERE user_id = ?1
+              AND ((from_currency = ?2 AND to_currency = ?3) OR (from_currency = ?3 AND to_currency = ?2))
+              AND effective_on <= ?4
+            ORDER BY effective_on DESC, from_currency = ?2 DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(self.user_id)
+        .bind(from)
+        .bind(to)
+        .bind(on)
+        .fetch_optional(self.pool)
+        .await?;
+
+        rate.ok_or_else(|| PaymeError::MissingExchangeRate {
+            from: from.to_string(),
+            to: to.to_string(),
+            on,
+        })
+    }
+}
+
+/// Sums amounts in `base`, converting each foreign amount at the rate for its own date. Each
+/// converted amount is rounded to the cent before it's added.
+pub async fn sum_in_base<P: ExchangeRateProvider>(
+    provider: &P,
+    base: &str,
+    amounts: &[(&str, Money, NaiveDate)],
+) -> Result<Money, PaymeError> {
+    let mut total = Money::ZERO;
+    for &(currency, amount, on) in amounts {
+        total += if currency == base {
+            amount
+        } else {
+            amount.scaled(provider.rate(currency, base, on).await?)
+        };
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_sorted() {
+        assert!(ISO_4217.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" eur ").unwrap(), "EUR");
+        assert!(normalize("XYZ").is_err());
+        assert!(normalize("US").is_err());
+    }
+
+    #[test]
+    fn test_totals_by_currency() {
+        let totals = totals_by_currency([
+            ("USD", Money::from_cents(1000)),
+            ("EUR", Money::from_cents(500)),
+            ("USD", Money::from_cents(250)),
+        ]);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].currency, "EUR");
+        assert_eq!(totals[0].total, Money::from_cents(500));
+        assert_eq!(totals[1].currency, "USD");
+        assert_eq!(totals[1].total, Money::from_cents(1250));
+    }
+
+    struct StubRates;
+
+    impl ExchangeRateProvider for StubRates {
+        async fn rate(&self, from: &str, to: &str, on: NaiveDate) -> Result<f64, PaymeError> {
+            match (from, to) {
+                ("EUR", "USD") if on >= NaiveDate::from_ymd_opt(2024, 6, 1).unwrap() => Ok(1.25),
+                ("EUR", "USD") => Ok(1.5),
+                _ => Err(PaymeError::MissingExchangeRate {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    on,
+                }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sum_in_base_converts_per_item_date() {
+        let may = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let june = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+
+        let total = sum_in_base(
+            &StubRates,
+            "USD",
+            &[
+                ("USD", Money::from_cents(10000), june),
+                ("EUR", Money::from_cents(5000), june),
+                ("EUR", Money::from_cents(2000), may),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(total, Money::from_cents(10000 + 6250 + 3000));
+
+        let missing =
+            sum_in_base(&StubRates, "USD", &[("GBP", Money::from_cents(1000), june)]).await;
+        assert!(matches!(
+            missing,
+            Err(PaymeError::MissingExchangeRate { ref from, .. }) if from == "GBP"
+        ));
+    }
+}
@@ -1,5 +1,11 @@
+use std::future::Future;
+
 use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
 
+use crate::config::BusyRetry;
+use crate::error::PaymeError;
+use crate::money::Money;
+
 pub async fn create_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
     let pool = SqlitePoolOptions::new()
         .max_connections(5)
@@ -8,6 +14,165 @@ pub async fn create_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error>
     Ok(pool)
 }
 
+/// SQLITE_BUSY or SQLITE_LOCKED, including their extended codes.
+fn is_busy(error: &sqlx::Error) -> bool {
+    let Some(code) = error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .and_then(|c| c.parse::<i32>().ok())
+    else {
+        return false;
+    };
+    matches!(code & 0xff, 5 | 6)
+}
+
+/// Runs a write, running it again with exponential backoff while SQLite reports the database
+/// busy. `write` must be safe to repeat, which in practice means doing all its writes in one
+/// transaction. Any other error is returned as is; still being busy after the last retry
+/// becomes [`PaymeError::DatabaseBusy`].
+pub async fn retry_busy<T, F, Fut>(retry: &BusyRetry, mut write: F) -> Result<T, PaymeError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, PaymeError>>,
+{
+    let mut delay = retry.base_delay;
+    for _ in 0..retry.max_retries {
+        match write().await {
+            Err(PaymeError::Database(e)) if is_busy(&e) => {
+                tracing::warn!("Database busy, retrying in {:?}", delay);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    match write().await {
+        Err(PaymeError::Database(e)) if is_busy(&e) => Err(PaymeError::DatabaseBusy),
+        result => result,
+    }
+}
+
+/// Statements that move a REAL column holding decimal amounts to INTEGER cents. SQLite can't
+/// change a column's type in place, so the cents go into a new column that then takes the old
+/// one's name.
+macro_rules! to_cents {
+    ($table:literal, $column:literal, $definition:literal) => {
+        (
+            $table,
+            $column,
+            [
+                concat!(
+                    "ALTER TABLE ",
+                    $table,
+                    " ADD COLUMN ",
+                    $column,
+                    "_cents ",
+                    $definition
+                ),
+                concat!(
+                    "UPDATE ",
+                    $table,
+                    " SET ",
+                    $column,
+                    "_cents = CAST(ROUND(",
+                    $column,
+                    " * 100) AS INTEGER)"
+                ),
+                concat!("ALTER TABLE ", $table, " DROP COLUMN ", $column),
+                concat!(
+                    "ALTER TABLE ",
+                    $table,
+                    " RENAME COLUMN ",
+                    $column,
+                    "_cents TO ",
+                    $column
+                ),
+            ],
+        )
+    };
+}
+
+/// Every money column. They hold whole cents so sums and running balances stay exact; databases
+/// from before that stored them as REAL and are converted once by these statements.
+const CENT_COLUMNS: [(&str, &str, [&str; 4]); 24] = [
+    to_cents!("users", "savings", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!("users", "retirement_savings", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!("users", "savings_goal", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!("users", "savings_adjustment", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!(
+        "users",
+        "retirement_savings_adjustment",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!("fixed_expenses", "amount", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!(
+        "fixed_expense_amounts",
+        "amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!(
+        "fixed_expense_amounts",
+        "previous_amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!(
+        "budget_categories",
+        "default_amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!("income_entries", "amount", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!("income_entries", "gross_amount", "INTEGER"),
+    to_cents!(
+        "income_entries",
+        "withholding",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!(
+        "monthly_budgets",
+        "allocated_amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!("items", "amount", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!(
+        "monthly_fixed_expenses",
+        "amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!("monthly_savings", "savings", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!(
+        "monthly_savings",
+        "retirement_savings",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!(
+        "monthly_savings",
+        "savings_goal",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!(
+        "custom_savings_goals",
+        "current_amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!(
+        "custom_savings_goals",
+        "target_amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+    to_cents!("savings_accounts", "balance", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!("savings_accounts", "goal", "INTEGER"),
+    to_cents!("savings_transfers", "amount", "INTEGER NOT NULL DEFAULT 0"),
+    to_cents!(
+        "retirement_breakdown_items",
+        "amount",
+        "INTEGER NOT NULL DEFAULT 0"
+    ),
+];
+
+/// `income_entries.amount` as a monthly figure, kept in step with `frequency` by SQLite so every
+/// insert path gets it
+const MONTHLY_AMOUNT_COLUMN: &str = "ALTER TABLE income_entries ADD COLUMN monthly_amount INTEGER GENERATED ALWAYS AS (CAST(ROUND(CASE frequency WHEN 'biweekly' THEN amount * 26 / 12.0 WHEN 'weekly' THEN amount * 52 / 12.0 WHEN 'annual' THEN amount / 12.0 ELSE amount END) AS INTEGER)) VIRTUAL";
+
 pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"
@@ -15,8 +180,8 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             username TEXT NOT NULL UNIQUE,
             password_hash TEXT NOT NULL,
-            savings REAL NOT NULL DEFAULT 0,
-            savings_goal REAL NOT NULL DEFAULT 0,
+            savings INTEGER NOT NULL DEFAULT 0,
+            savings_goal INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL DEFAULT (datetime('now'))
         )
         "#,
@@ -24,22 +189,23 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
-    sqlx::query("ALTER TABLE users ADD COLUMN savings REAL NOT NULL DEFAULT 0")
+    sqlx::query("ALTER TABLE users ADD COLUMN savings INTEGER NOT NULL DEFAULT 0")
         .execute(pool)
         .await
         .ok();
 
-    sqlx::query("ALTER TABLE users ADD COLUMN retirement_savings REAL NOT NULL DEFAULT 0")
+    sqlx::query("ALTER TABLE users ADD COLUMN retirement_savings INTEGER NOT NULL DEFAULT 0")
         .execute(pool)
         .await
         .ok();
 
-    sqlx::query("ALTER TABLE users ADD COLUMN savings_goal REAL NOT NULL DEFAULT 0")
+    sqlx::query("ALTER TABLE users ADD COLUMN savings_goal INTEGER NOT NULL DEFAULT 0")
         .execute(pool)
         .await
         .ok();
 
-    sqlx::query("UPDATE users SET retirement_savings = roth_ira WHERE retirement_savings = 0 AND roth_ira IS NOT NULL AND roth_ira > 0")
+    // Only while the balance is still REAL; once it holds cents the decimal would be misread
+    sqlx::query("UPDATE users SET retirement_savings = roth_ira WHERE retirement_savings = 0 AND roth_ira IS NOT NULL AND roth_ira > 0 AND (SELECT type FROM pragma_table_info('users') WHERE name = 'retirement_savings') = 'REAL'")
         .execute(pool)
         .await
         .ok();
@@ -50,7 +216,7 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             user_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
         )
         "#,
@@ -58,13 +224,56 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    sqlx::query("ALTER TABLE fixed_expenses ADD COLUMN category TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE fixed_expenses ADD COLUMN due_day INTEGER")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE fixed_expenses ADD COLUMN active INTEGER NOT NULL DEFAULT 1")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE fixed_expenses ADD COLUMN start_month TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE fixed_expenses ADD COLUMN end_month TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    // Scheduled amount changes of a fixed expense, each in effect from a month onwards
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS fixed_expense_amounts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fixed_expense_id INTEGER NOT NULL,
+            effective_from TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            previous_amount INTEGER NOT NULL,
+            UNIQUE(fixed_expense_id, effective_from),
+            FOREIGN KEY (fixed_expense_id) REFERENCES fixed_expenses(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS budget_categories (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             user_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            default_amount REAL NOT NULL,
+            default_amount INTEGER NOT NULL,
             color TEXT NOT NULL DEFAULT '#71717a',
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
         )
@@ -79,6 +288,22 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await;
 
+    let _ = sqlx::query(
+        "ALTER TABLE budget_categories ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0",
+    )
+    .execute(pool)
+    .await;
+
+    let _ = sqlx::query("ALTER TABLE budget_categories ADD COLUMN icon TEXT")
+        .execute(pool)
+        .await;
+
+    let _ = sqlx::query(
+        "ALTER TABLE budget_categories ADD COLUMN enforce_budget INTEGER NOT NULL DEFAULT 0",
+    )
+    .execute(pool)
+    .await;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS months (
@@ -102,7 +327,7 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE
         )
         "#,
@@ -110,13 +335,30 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    let _ = sqlx::query("ALTER TABLE income_entries ADD COLUMN gross_amount INTEGER")
+        .execute(pool)
+        .await;
+
+    let _ =
+        sqlx::query("ALTER TABLE income_entries ADD COLUMN withholding INTEGER NOT NULL DEFAULT 0")
+            .execute(pool)
+            .await;
+
+    let _ = sqlx::query(
+        "ALTER TABLE income_entries ADD COLUMN frequency TEXT NOT NULL DEFAULT 'monthly'",
+    )
+    .execute(pool)
+    .await;
+
+    let _ = sqlx::query(MONTHLY_AMOUNT_COLUMN).execute(pool).await;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS monthly_budgets (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL,
             category_id INTEGER NOT NULL,
-            allocated_amount REAL NOT NULL,
+            allocated_amount INTEGER NOT NULL,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE,
             FOREIGN KEY (category_id) REFERENCES budget_categories(id) ON DELETE CASCADE,
             UNIQUE(month_id, category_id)
@@ -126,6 +368,21 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    let _ =
+        sqlx::query("ALTER TABLE monthly_budgets ADD COLUMN rollover INTEGER NOT NULL DEFAULT 0")
+            .execute(pool)
+            .await;
+
+    let _ = sqlx::query(
+        "ALTER TABLE monthly_budgets ADD COLUMN carry_overspend INTEGER NOT NULL DEFAULT 0",
+    )
+    .execute(pool)
+    .await;
+
+    let _ = sqlx::query("ALTER TABLE monthly_budgets ADD COLUMN allocation_percent REAL")
+        .execute(pool)
+        .await;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS items (
@@ -133,7 +390,7 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             month_id INTEGER NOT NULL,
             category_id INTEGER NOT NULL,
             description TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
             spent_on TEXT NOT NULL,
             savings_destination TEXT NOT NULL DEFAULT 'none',
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE,
@@ -174,7 +431,7 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE
         )
         "#,
@@ -182,14 +439,29 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    sqlx::query("ALTER TABLE monthly_fixed_expenses ADD COLUMN category TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE monthly_fixed_expenses ADD COLUMN due_day INTEGER")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE monthly_fixed_expenses ADD COLUMN paid INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await
+        .ok();
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS monthly_savings (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL UNIQUE,
-            savings REAL NOT NULL DEFAULT 0,
-            retirement_savings REAL NOT NULL DEFAULT 0,
-            savings_goal REAL NOT NULL DEFAULT 0,
+            savings INTEGER NOT NULL DEFAULT 0,
+            retirement_savings INTEGER NOT NULL DEFAULT 0,
+            savings_goal INTEGER NOT NULL DEFAULT 0,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE
         )
         "#,
@@ -203,8 +475,102 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             user_id INTEGER NOT NULL,
             name TEXT NOT NULL,
-            current_amount REAL NOT NULL DEFAULT 0,
-            target_amount REAL NOT NULL,
+            current_amount INTEGER NOT NULL DEFAULT 0,
+            target_amount INTEGER NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS savings_accounts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            balance INTEGER NOT NULL DEFAULT 0,
+            goal INTEGER,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            UNIQUE (user_id, name)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("ALTER TABLE savings_accounts ADD COLUMN goal_date TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS savings_allocation_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            savings_account_id INTEGER NOT NULL,
+            percent REAL NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (savings_account_id) REFERENCES savings_accounts(id) ON DELETE CASCADE,
+            UNIQUE (user_id, savings_account_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS savings_transfers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            from_account_id INTEGER NOT NULL,
+            to_account_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (from_account_id) REFERENCES savings_accounts(id) ON DELETE CASCADE,
+            FOREIGN KEY (to_account_id) REFERENCES savings_accounts(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS idempotency_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            endpoint TEXT NOT NULL,
+            idempotency_key TEXT NOT NULL,
+            request_hash TEXT NOT NULL,
+            status INTEGER,
+            content_type TEXT,
+            body BLOB,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            UNIQUE (user_id, endpoint, idempotency_key)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS import_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            total INTEGER NOT NULL,
+            processed INTEGER NOT NULL DEFAULT 0,
+            errors TEXT NOT NULL DEFAULT '[]',
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            finished_at TEXT,
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
         )
         "#,
@@ -218,7 +584,289 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             user_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("ALTER TABLE users ADD COLUMN email TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email ON users(email)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE users ADD COLUMN totp_secret TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN totp_enabled INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN base_currency TEXT NOT NULL DEFAULT 'USD'")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE items ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD'")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE items ADD COLUMN deleted_at TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE items ADD COLUMN version INTEGER NOT NULL DEFAULT 1")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        "ALTER TABLE items ADD COLUMN savings_account_id INTEGER REFERENCES savings_accounts(id)",
+    )
+    .execute(pool)
+    .await
+    .ok();
+
+    // Identity and last change of an item, used to merge exports from other devices. The uid is
+    // assigned when the item is first exported.
+    sqlx::query("ALTER TABLE items ADD COLUMN uid TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE items ADD COLUMN updated_at TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_items_uid ON items(uid)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE users ADD COLUMN alert_warning_percent REAL NOT NULL DEFAULT 80")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN alert_critical_percent REAL NOT NULL DEFAULT 100")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN notify_month_closed INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN notify_savings_goal INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN notify_monthly_digest INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await
+        .ok();
+
+    // `YYYY-MM` of the last month a digest was queued for, so each month is sent only once
+    sqlx::query("ALTER TABLE users ADD COLUMN last_digest_month TEXT")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN rounding_mode TEXT NOT NULL DEFAULT 'half_up'")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC'")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS email_outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            recipient TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            body TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            last_error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            sent_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS households (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS household_members (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            household_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            role TEXT NOT NULL DEFAULT 'member',
+            invited_by INTEGER,
+            accepted_at TEXT,
+            FOREIGN KEY (household_id) REFERENCES households(id) ON DELETE CASCADE,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            UNIQUE(household_id, user_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("ALTER TABLE months ADD COLUMN household_id INTEGER REFERENCES households(id) ON DELETE SET NULL")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("ALTER TABLE months ADD COLUMN is_locked INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            events TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            next_attempt_at TEXT NOT NULL DEFAULT (datetime('now')),
+            claimed_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            delivered_at TEXT,
+            FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS item_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            category_id INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            default_amount INTEGER NOT NULL,
+            savings_destination TEXT NOT NULL DEFAULT 'none',
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES budget_categories(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS receipts (
+            item_id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            content_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            uploaded_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS exchange_rates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate REAL NOT NULL,
+            effective_on DATE NOT NULL,
+            UNIQUE (user_id, from_currency, to_currency, effective_on),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS two_factor_backup_codes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            code_hash TEXT NOT NULL,
+            used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS password_reset_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            expires_at TEXT NOT NULL,
+            used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
         )
         "#,
@@ -226,38 +874,160 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS refresh_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            family_id TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            expires_at TEXT NOT NULL,
+            used_at TEXT,
+            revoked_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sessions (
+            family_id TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            last_used_at TEXT NOT NULL DEFAULT (datetime('now')),
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            prefix TEXT NOT NULL,
+            key_hash TEXT NOT NULL UNIQUE,
+            scopes TEXT NOT NULL,
+            expires_at TEXT,
+            last_used_at TEXT,
+            revoked_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // The generated column reads `amount`, which can't be dropped while it does
+    let monthly_amount: Option<String> = sqlx::query_scalar(
+        "SELECT type FROM pragma_table_xinfo('income_entries') WHERE name = 'monthly_amount'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if monthly_amount.as_deref() == Some("REAL") {
+        sqlx::query("ALTER TABLE income_entries DROP COLUMN monthly_amount")
+            .execute(pool)
+            .await?;
+    }
+
+    for (table, column, statements) in CENT_COLUMNS {
+        let declared: Option<String> =
+            sqlx::query_scalar("SELECT type FROM pragma_table_info(?) WHERE name = ?")
+                .bind(table)
+                .bind(column)
+                .fetch_optional(pool)
+                .await?;
+        if declared.as_deref() != Some("REAL") {
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        for statement in statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+    }
+
+    let _ = sqlx::query(MONTHLY_AMOUNT_COLUMN).execute(pool).await;
+
+    // The part of each balance that was set by hand rather than moved by savings items. When
+    // the columns first appear, existing balances are taken as correct and whatever the items
+    // don't account for becomes the adjustment.
+    let adjustments_added =
+        sqlx::query("ALTER TABLE users ADD COLUMN savings_adjustment INTEGER NOT NULL DEFAULT 0")
+            .execute(pool)
+            .await
+            .is_ok();
+
+    sqlx::query(
+        "ALTER TABLE users ADD COLUMN retirement_savings_adjustment INTEGER NOT NULL DEFAULT 0",
+    )
+    .execute(pool)
+    .await
+    .ok();
+
+    if adjustments_added {
+        sqlx::query(
+            r#"
+            UPDATE users SET
+                savings_adjustment = savings - COALESCE((
+                    SELECT SUM(CASE i.savings_destination WHEN 'savings' THEN i.amount WHEN 'savings_withdrawal' THEN -i.amount ELSE 0 END)
+                    FROM items i JOIN months m ON i.month_id = m.id
+                    WHERE m.user_id = users.id AND i.deleted_at IS NULL
+                ), 0),
+                retirement_savings_adjustment = retirement_savings - COALESCE((
+                    SELECT SUM(CASE i.savings_destination WHEN 'retirement_savings' THEN i.amount WHEN 'retirement_savings_withdrawal' THEN -i.amount ELSE 0 END)
+                    FROM items i JOIN months m ON i.month_id = m.id
+                    WHERE m.user_id = users.id AND i.deleted_at IS NULL
+                ), 0)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+    }
+
     // Migration: Backfill existing months with current fixed expenses and savings
     // This ensures existing data is preserved when upgrading
-    let existing_months: Vec<(i64, i64)> = sqlx::query_as(
-        "SELECT id, user_id FROM months WHERE id NOT IN (SELECT DISTINCT month_id FROM monthly_fixed_expenses)",
+    let existing_months: Vec<(i64, i64, i32, i32)> = sqlx::query_as(
+        "SELECT id, user_id, year, month FROM months WHERE id NOT IN (SELECT DISTINCT month_id FROM monthly_fixed_expenses)",
     )
     .fetch_all(pool)
     .await
     .unwrap_or_default();
 
-    for (month_id, user_id) in existing_months {
+    for (month_id, user_id, year, month) in existing_months {
         // Copy current fixed expenses to this month
-        let fixed_expenses: Vec<(String, f64)> =
-            sqlx::query_as("SELECT label, amount FROM fixed_expenses WHERE user_id = ?")
+        let fixed_expenses: Vec<(String, Money, Option<String>, Option<i64>)> =
+            sqlx::query_as(crate::handlers::fixed_expenses::TEMPLATES_FOR_PERIOD)
                 .bind(user_id)
+                .bind(crate::handlers::fixed_expenses::period(year, month))
                 .fetch_all(pool)
                 .await
                 .unwrap_or_default();
 
-        for (label, amount) in fixed_expenses {
+        for (label, amount, category, due_day) in fixed_expenses {
             sqlx::query(
-                "INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, ?, ?)",
+                "INSERT INTO monthly_fixed_expenses (month_id, label, amount, category, due_day) VALUES (?, ?, ?, ?, ?)",
             )
             .bind(month_id)
             .bind(&label)
             .bind(amount)
+            .bind(&category)
+            .bind(due_day)
             .execute(pool)
             .await
             .ok();
         }
 
         // Copy current savings values to this month
-        let user_savings: Option<(f64, f64, f64)> = sqlx::query_as(
+        let user_savings: Option<(Money, Money, Money)> = sqlx::query_as(
             "SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?",
         )
         .bind(user_id)
@@ -0,0 +1,509 @@
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+
+use crate::background;
+use crate::tls;
+
+/// Notification kinds, each with its own opt-in column on `users`
+pub const MONTH_CLOSED: &str = "month_closed";
+pub const SAVINGS_GOAL_REACHED: &str = "savings_goal_reached";
+pub const MONTHLY_DIGEST: &str = "monthly_digest";
+/// Account mail, sent to anyone with an address whatever their notification settings
+pub const PASSWORD_RESET: &str = "password_reset";
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+pub trait EmailSender: Send + Sync {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a>;
+}
+
+/// Used when no mail server is configured: messages are logged and counted as sent.
+pub struct NoopSender;
+
+impl EmailSender for NoopSender {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a> {
+        Box::pin(async move {
+            tracing::info!(
+                "No SMTP_HOST configured; dropping email to {}: {}",
+                email.to,
+                email.subject
+            );
+            Ok(())
+        })
+    }
+}
+
+/// How the connection to the SMTP server is encrypted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Upgrade with STARTTLS after connecting; refused servers aren't sent anything
+    StartTls,
+    /// TLS from the first byte, usually on port 465
+    Implicit,
+    /// Plain text, only allowed to a relay on the same machine
+    None,
+}
+
+pub struct SmtpAuth {
+    pub username: String,
+    pub password: String,
+}
+
+/// SMTP with STARTTLS or implicit TLS, and AUTH PLAIN when credentials are set. Plain text is
+/// only used for a loopback relay, since the mail carries password reset links.
+pub struct SmtpSender {
+    pub host: String,
+    pub port: u16,
+    pub from: String,
+    pub tls: SmtpTls,
+    pub auth: Option<SmtpAuth>,
+}
+
+impl EmailSender for SmtpSender {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a> {
+        Box::pin(async move {
+            tokio::time::timeout(SEND_TIMEOUT, self.deliver(email))
+                .await
+                .map_err(|_| "timed out".to_string())?
+        })
+    }
+}
+
+impl SmtpSender {
+    async fn deliver(&self, email: &Email) -> Result<(), String> {
+        if self.tls == SmtpTls::None && !is_loopback(&self.host) {
+            return Err(format!(
+                "refusing plain SMTP to {}; set SMTP_TLS to starttls or implicit",
+                self.host
+            ));
+        }
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| format!("connect failed: {e}"))?;
+
+        match self.tls {
+            SmtpTls::Implicit => {
+                let mut stream = BufReader::new(self.handshake(stream).await?);
+                expect_reply(&mut stream, 220).await?;
+                self.transaction(&mut stream, email).await
+            }
+            SmtpTls::StartTls => {
+                let mut stream = BufReader::new(stream);
+                expect_reply(&mut stream, 220).await?;
+                command(&mut stream, "EHLO payme", 250).await?;
+                command(&mut stream, "STARTTLS", 220).await?;
+                let stream = self.handshake(stream.into_inner()).await?;
+                self.transaction(&mut BufReader::new(stream), email).await
+            }
+            SmtpTls::None => {
+                let mut stream = BufReader::new(stream);
+                expect_reply(&mut stream, 220).await?;
+                self.transaction(&mut stream, email).await
+            }
+        }
+    }
+
+    async fn handshake(&self, stream: TcpStream) -> Result<TlsStream<TcpStream>, String> {
+        let server_name = ServerName::try_from(self.host.clone())
+            .map_err(|e| format!("invalid server name: {e}"))?;
+        TlsConnector::from(tls::CLIENT_CONFIG.clone())
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| format!("tls handshake failed: {e}"))
+    }
+
+    /// Everything after the greeting, and after STARTTLS if used: EHLO, AUTH and the message.
+    async fn transaction<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: &mut BufReader<S>,
+        email: &Email,
+    ) -> Result<(), String> {
+        command(stream, "EHLO payme", 250).await?;
+        if let Some(auth) = &self.auth {
+            let credentials = BASE64.encode(format!("\0{}\0{}", auth.username, auth.password));
+            command(stream, &format!("AUTH PLAIN {credentials}"), 235).await?;
+        }
+        command(stream, &format!("MAIL FROM:<{}>", self.from), 250).await?;
+        command(stream, &format!("RCPT TO:<{}>", email.to), 250).await?;
+        command(stream, "DATA", 354).await?;
+
+        stream
+            .write_all(message(&self.from, email).as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        expect_reply(stream, 250).await?;
+
+        let _ = stream.write_all(b"QUIT\r\n").await;
+        Ok(())
+    }
+}
+
+/// Loopback addresses and `localhost`, where plain SMTP never leaves the machine.
+fn is_loopback(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Sends one command line and checks the reply.
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+    line: &str,
+    expected: u16,
+) -> Result<(), String> {
+    stream
+        .write_all(format!("{line}\r\n").as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    expect_reply(stream, expected).await
+}
+
+/// Reads a possibly multi-line reply and checks its status code.
+async fn expect_reply<R: AsyncBufReadExt + Unpin>(
+    reader: &mut R,
+    expected: u16,
+) -> Result<(), String> {
+    loop {
+        let mut line = String::new();
+        if reader
+            .read_line(&mut line)
+            .await
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            return Err("connection closed".to_string());
+        }
+        let code: u16 = line
+            .get(..3)
+            .and_then(|c| c.parse().ok())
+            .ok_or_else(|| format!("malformed reply: {}", line.trim_end()))?;
+        // "250-..." continues the reply, "250 ..." ends it
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        return if code == expected {
+            Ok(())
+        } else {
+            Err(format!("server replied {}", line.trim_end()))
+        };
+    }
+}
+
+/// The DATA section: headers, the dot-stuffed body, and the terminating line.
+fn message(from: &str, email: &Email) -> String {
+    let mut data = format!(
+        "From: {from}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        email.to,
+        email.subject,
+        Utc::now().to_rfc2822()
+    );
+    for line in email.body.lines() {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push_str(".\r\n");
+    data
+}
+
+/// The SMTP sender when `SMTP_HOST` is set, otherwise the no-op sender. `SMTP_TLS` picks
+/// `starttls`, `implicit` or `none`, defaulting to `none` for a loopback relay and `starttls`
+/// otherwise; `SMTP_USERNAME` and `SMTP_PASSWORD` enable AUTH.
+pub fn sender_from_env() -> Arc<dyn EmailSender> {
+    let host = match std::env::var("SMTP_HOST") {
+        Ok(host) if !host.is_empty() => host,
+        _ => return Arc::new(NoopSender),
+    };
+    let tls = match std::env::var("SMTP_TLS")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "implicit" => SmtpTls::Implicit,
+        "starttls" => SmtpTls::StartTls,
+        "none" => SmtpTls::None,
+        _ if is_loopback(&host) => SmtpTls::None,
+        _ => SmtpTls::StartTls,
+    };
+    let auth = match (
+        std::env::var("SMTP_USERNAME"),
+        std::env::var("SMTP_PASSWORD"),
+    ) {
+        (Ok(username), Ok(password)) if !username.is_empty() => {
+            Some(SmtpAuth { username, password })
+        }
+        _ => None,
+    };
+
+    Arc::new(SmtpSender {
+        host,
+        port: std::env::var("SMTP_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(match tls {
+                SmtpTls::Implicit => 465,
+                SmtpTls::StartTls => 587,
+                SmtpTls::None => 25,
+            }),
+        from: std::env::var("SMTP_FROM").unwrap_or_else(|_| "payme@localhost".to_string()),
+        tls,
+        auth,
+    })
+}
+
+/// Queues an email to `user_id` if they have an address and `kind` is one they get, and kicks off
+/// sending. Failures are logged rather than returned so they never fail the triggering request.
+pub async fn enqueue(pool: &SqlitePool, user_id: i64, kind: &str, subject: &str, body: &str) {
+    match try_enqueue(pool, user_id, kind, subject, body).await {
+        Ok(true) => {
+            let pool = pool.clone();
+            background::spawn(async move { dispatch_due(&pool, sender_from_env().as_ref()).await });
+        }
+        Ok(false) => {}
+        Err(e) => tracing::error!("Failed to enqueue {} email: {}", kind, e),
+    }
+}
+
+async fn try_enqueue(
+    pool: &SqlitePool,
+    user_id: i64,
+    kind: &str,
+    subject: &str,
+    body: &str,
+) -> Result<bool, sqlx::Error> {
+    let recipient: Option<Option<String>> = match kind {
+        MONTH_CLOSED => {
+            sqlx::query_scalar("SELECT email FROM users WHERE id = ? AND notify_month_closed = 1")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?
+        }
+        SAVINGS_GOAL_REACHED => {
+            sqlx::query_scalar("SELECT email FROM users WHERE id = ? AND notify_savings_goal = 1")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?
+        }
+        MONTHLY_DIGEST => {
+            sqlx::query_scalar("SELECT email FROM users WHERE id = ? AND notify_monthly_digest = 1")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?
+        }
+        PASSWORD_RESET => {
+            sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?
+        }
+        _ => None,
+    };
+    let Some(Some(recipient)) = recipient else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        "INSERT INTO email_outbox (user_id, kind, recipient, subject, body) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(kind)
+    .bind(&recipient)
+    .bind(subject)
+    .bind(body)
+    .execute(pool)
+    .await?;
+    Ok(true)
+}
+
+/// Sends every queued email. Each is claimed before sending so concurrent runs don't send twice;
+/// a failed send is logged and kept with its error rather than retried.
+pub async fn dispatch_due(pool: &SqlitePool, sender: &dyn EmailSender) {
+    let due: Vec<(i64, String, String, String)> = match sqlx::query_as(
+        "SELECT id, recipient, subject, body FROM email_outbox WHERE status = 'pending' ORDER BY id LIMIT ?",
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("Failed to load queued emails: {}", e);
+            return;
+        }
+    };
+
+    for (id, to, subject, body) in due {
+        let claimed = sqlx::query(
+            "UPDATE email_outbox SET status = 'sending' WHERE id = ? AND status = 'pending'",
+        )
+        .bind(id)
+        .execute(pool)
+        .await;
+        match claimed {
+            Ok(result) if result.rows_affected() == 1 => {}
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::error!("Failed to claim email {}: {}", id, e);
+                continue;
+            }
+        }
+
+        let email = Email { to, subject, body };
+        let outcome = sender.send(&email).await;
+        if let Err(error) = &outcome {
+            tracing::warn!("Email {} to {} failed: {}", id, email.to, error);
+        }
+        let update = match outcome {
+            Ok(()) => sqlx::query(
+                "UPDATE email_outbox SET status = 'sent', sent_at = datetime('now') WHERE id = ?",
+            )
+            .bind(id),
+            Err(error) => sqlx::query(
+                "UPDATE email_outbox SET status = 'failed', last_error = ? WHERE id = ?",
+            )
+            .bind(error)
+            .bind(id),
+        };
+        if let Err(e) = update.execute(pool).await {
+            tracing::error!("Failed to record result of email {}: {}", id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// A fake SMTP server on a local port that accepts everything except STARTTLS, and returns
+    /// what the client sent.
+    async fn fake_server() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            writer.write_all(b"220 test ready\r\n").await.unwrap();
+
+            let mut transcript = String::new();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                transcript.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-test\r\n250 8BITMIME\r\n"
+                } else if line.starts_with("STARTTLS") {
+                    b"454 TLS not available\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            transcript
+        });
+        (port, server)
+    }
+
+    fn email() -> Email {
+        Email {
+            to: "user@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "First line\n.leading dot".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_sender_dialogue() {
+        let (port, server) = fake_server().await;
+
+        let sender = SmtpSender {
+            host: "127.0.0.1".to_string(),
+            port,
+            from: "payme@example.com".to_string(),
+            tls: SmtpTls::None,
+            auth: None,
+        };
+        sender.send(&email()).await.unwrap();
+
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains("MAIL FROM:<payme@example.com>\r\n"));
+        assert!(transcript.contains("RCPT TO:<user@example.com>\r\n"));
+        assert!(transcript.contains("Subject: Hello\r\n"));
+        assert!(transcript.contains("\r\n..leading dot\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_sender_stops_when_starttls_is_refused() {
+        let (port, server) = fake_server().await;
+
+        let sender = SmtpSender {
+            host: "127.0.0.1".to_string(),
+            port,
+            from: "payme@example.com".to_string(),
+            tls: SmtpTls::StartTls,
+            auth: Some(SmtpAuth {
+                username: "payme".to_string(),
+                password: "secret".to_string(),
+            }),
+        };
+        let error = sender.send(&email()).await.unwrap_err();
+        assert!(error.contains("454"));
+
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains("STARTTLS\r\n"));
+        assert!(!transcript.contains("AUTH"));
+        assert!(!transcript.contains("MAIL FROM"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_sender_refuses_plain_text_to_remote_host() {
+        let sender = SmtpSender {
+            host: "smtp.example.com".to_string(),
+            port: 25,
+            from: "payme@example.com".to_string(),
+            tls: SmtpTls::None,
+            auth: None,
+        };
+        let error = sender.send(&email()).await.unwrap_err();
+        assert!(error.contains("refusing plain SMTP"));
+    }
+}
@@ -1,7 +1,9 @@
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
+use serde_json::json;
 use thiserror::Error;
 use validator::ValidationErrors;
 
@@ -19,25 +21,126 @@ pub enum PaymeError {
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Forbidden")]
+    Forbidden,
+
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Month is closed")]
+    MonthClosed,
+
+    #[error("Month is locked")]
+    MonthLocked,
+
+    #[error("Invalid passphrase")]
+    InvalidPassphrase,
+
+    #[error("No exchange rate from {from} to {to} on or before {on}")]
+    MissingExchangeRate {
+        from: String,
+        to: String,
+        on: chrono::NaiveDate,
+    },
+
+    #[error("Request body is larger than the {limit} byte limit")]
+    PayloadTooLarge { limit: usize },
+
+    #[error("Database is busy; try again shortly")]
+    DatabaseBusy,
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
-impl IntoResponse for PaymeError {
-    fn into_response(self) -> Response {
-        let status = match &self {
+impl PaymeError {
+    pub fn status(&self) -> StatusCode {
+        match self {
             PaymeError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
             PaymeError::Validation(_) => StatusCode::BAD_REQUEST,
             PaymeError::NotFound => StatusCode::NOT_FOUND,
             PaymeError::Unauthorized => StatusCode::UNAUTHORIZED,
+            PaymeError::Forbidden => StatusCode::FORBIDDEN,
             PaymeError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            PaymeError::Conflict(_) => StatusCode::CONFLICT,
+            PaymeError::MonthClosed => StatusCode::BAD_REQUEST,
+            PaymeError::MonthLocked => StatusCode::LOCKED,
+            PaymeError::InvalidPassphrase => StatusCode::UNPROCESSABLE_ENTITY,
+            PaymeError::MissingExchangeRate { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            PaymeError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            PaymeError::DatabaseBusy => StatusCode::SERVICE_UNAVAILABLE,
             PaymeError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        };
+        }
+    }
+
+    /// Stable machine-readable identifier; clients should branch on this, not the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaymeError::Database(_) => "DATABASE",
+            PaymeError::Validation(_) => "VALIDATION",
+            PaymeError::NotFound => "NOT_FOUND",
+            PaymeError::Unauthorized => "UNAUTHORIZED",
+            PaymeError::Forbidden => "FORBIDDEN",
+            PaymeError::BadRequest(_) => "BAD_REQUEST",
+            PaymeError::Conflict(_) => "CONFLICT",
+            PaymeError::MonthClosed => "MONTH_CLOSED",
+            PaymeError::MonthLocked => "MONTH_LOCKED",
+            PaymeError::InvalidPassphrase => "INVALID_PASSPHRASE",
+            PaymeError::MissingExchangeRate { .. } => "MISSING_EXCHANGE_RATE",
+            PaymeError::PayloadTooLarge { .. } => "PAYLOAD_TOO_LARGE",
+            PaymeError::DatabaseBusy => "DATABASE_BUSY",
+            PaymeError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// Message safe to show clients. Server-side failures are not echoed back.
+    fn public_message(&self) -> String {
+        match self {
+            PaymeError::Database(_) | PaymeError::Internal(_) => {
+                "Internal server error".to_string()
+            }
+            PaymeError::BadRequest(msg) | PaymeError::Conflict(msg) => msg.clone(),
+            PaymeError::Validation(_) => "Validation failed".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for PaymeError {
+    fn into_response(self) -> Response {
+        let status = self.status();
         tracing::error!("{self}");
-        status.into_response()
+
+        let mut error = json!({
+            "code": self.code(),
+            "message": self.public_message(),
+            "status": status.as_u16(),
+        });
+
+        if let PaymeError::Validation(errors) = &self {
+            let fields: serde_json::Map<String, serde_json::Value> = errors
+                .field_errors()
+                .into_iter()
+                .map(|(field, errs)| {
+                    let messages = errs
+                        .iter()
+                        .map(|e| {
+                            e.message
+                                .as_ref()
+                                .map(|m| m.to_string())
+                                .unwrap_or_else(|| e.code.to_string())
+                        })
+                        .collect::<Vec<_>>();
+                    (field.to_string(), json!(messages))
+                })
+                .collect();
+            error["fields"] = serde_json::Value::Object(fields);
+        }
+
+        (status, Json(json!({ "error": error }))).into_response()
     }
 }
 
@@ -60,6 +163,14 @@ mod tests {
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 
+    #[test]
+    fn test_forbidden_status() {
+        let error = PaymeError::Forbidden;
+        assert_eq!(error.code(), "FORBIDDEN");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
     #[test]
     fn test_bad_request_status() {
         let error = PaymeError::BadRequest("test".to_string());
@@ -67,6 +178,72 @@ mod tests {
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn test_conflict_status() {
+        let error = PaymeError::Conflict("test".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_invalid_passphrase_status() {
+        let error = PaymeError::InvalidPassphrase;
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_missing_exchange_rate_status() {
+        let error = PaymeError::MissingExchangeRate {
+            from: "EUR".to_string(),
+            to: "USD".to_string(),
+            on: chrono::NaiveDate::from_ymd_opt(2024, 6, 15).unwrap(),
+        };
+        assert_eq!(error.code(), "MISSING_EXCHANGE_RATE");
+        assert_eq!(
+            error.to_string(),
+            "No exchange rate from EUR to USD on or before 2024-06-15"
+        );
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_month_closed_status() {
+        let error = PaymeError::MonthClosed;
+        assert_eq!(error.code(), "MONTH_CLOSED");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_month_locked_status() {
+        let error = PaymeError::MonthLocked;
+        assert_eq!(error.code(), "MONTH_LOCKED");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::LOCKED);
+    }
+
+    #[test]
+    fn test_payload_too_large_status() {
+        let error = PaymeError::PayloadTooLarge { limit: 1024 };
+        assert_eq!(error.code(), "PAYLOAD_TOO_LARGE");
+        assert_eq!(
+            error.to_string(),
+            "Request body is larger than the 1024 byte limit"
+        );
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_database_busy_status() {
+        let error = PaymeError::DatabaseBusy;
+        assert_eq!(error.code(), "DATABASE_BUSY");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[test]
     fn test_internal_status() {
         let error = PaymeError::Internal("test".to_string());
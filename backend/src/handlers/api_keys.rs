@@ -0,0 +1,198 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::error::PaymeError;
+use crate::handlers::auth::{hash_token, random_token};
+use crate::middleware::auth::{Claims, API_KEY_PREFIX};
+
+/// `read` allows GET requests; `write` allows everything else
+pub const API_KEY_SCOPES: [&str; 2] = ["read", "write"];
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateApiKey {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// Defaults to both `read` and `write`
+    pub scopes: Option<Vec<String>>,
+    /// Days until the key stops working; keys without one last until revoked
+    #[validate(range(min = 1, max = 3650))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    /// Start of the key, to tell keys apart without revealing them
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+    pub created_at: String,
+    /// The key itself; only returned when it is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ApiKeyRow {
+    id: i64,
+    name: String,
+    prefix: String,
+    scopes: String,
+    expires_at: Option<String>,
+    last_used_at: Option<String>,
+    revoked_at: Option<String>,
+    created_at: String,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        ApiKey {
+            id: row.id,
+            name: row.name,
+            prefix: row.prefix,
+            scopes: row.scopes.split(',').map(str::to_string).collect(),
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+            revoked_at: row.revoked_at,
+            created_at: row.created_at,
+            key: None,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/api-keys",
+    responses(
+        (status = 200, body = [ApiKey]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "List API keys",
+    description = "Lists the user's API keys, including revoked ones, with when each was last used. The keys themselves are not included."
+)]
+pub async fn list_api_keys(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<ApiKey>>, PaymeError> {
+    let rows: Vec<ApiKeyRow> = sqlx::query_as(
+        "SELECT id, name, prefix, scopes, expires_at, last_used_at, revoked_at, created_at FROM api_keys WHERE user_id = ? ORDER BY id",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(rows.into_iter().map(ApiKey::from).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/api-keys",
+    request_body = CreateApiKey,
+    responses(
+        (status = 201, body = ApiKey),
+        (status = 400, description = "Unknown scope"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Create API key",
+    description = "Mints a key for scripts to send as `Authorization: Bearer pk_...` in place of a login session. Keys work on data routes and `/api/auth/me`; the other routes under `/api/auth`, this one included, refuse them with 403. The response includes the key, which is not shown again; only its SHA-256 digest is stored."
+)]
+pub async fn create_api_key(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<CreateApiKey>,
+) -> Result<(StatusCode, Json<ApiKey>), PaymeError> {
+    payload.validate()?;
+
+    let mut scopes = payload
+        .scopes
+        .unwrap_or_else(|| API_KEY_SCOPES.map(str::to_string).to_vec());
+    if scopes.is_empty() {
+        return Err(PaymeError::BadRequest(
+            "An API key needs at least one scope".to_string(),
+        ));
+    }
+    if let Some(unknown) = scopes
+        .iter()
+        .find(|s| !API_KEY_SCOPES.contains(&s.as_str()))
+    {
+        return Err(PaymeError::BadRequest(format!("Unknown scope: {unknown}")));
+    }
+    scopes.sort();
+    scopes.dedup();
+
+    let key = format!("{API_KEY_PREFIX}{}", random_token());
+    let prefix = key[..API_KEY_PREFIX.len() + 6].to_string();
+    let expires_at = payload.expires_in_days.map(|days| {
+        (Utc::now() + Duration::days(days))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    });
+
+    let (id, created_at): (i64, String) = sqlx::query_as(
+        "INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at) VALUES (?, ?, ?, ?, ?, ?) RETURNING id, created_at",
+    )
+    .bind(claims.sub)
+    .bind(&payload.name)
+    .bind(&prefix)
+    .bind(hash_token(&key))
+    .bind(scopes.join(","))
+    .bind(&expires_at)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiKey {
+            id,
+            name: payload.name,
+            prefix,
+            scopes,
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+            created_at,
+            key: Some(key),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/api-keys/{id}",
+    params(("id" = i64, Path, description = "API key ID")),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Revoke API key",
+    description = "Stops the key from authenticating. It stays in the list, marked revoked, so its last use remains visible."
+)]
+pub async fn revoke_api_key(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(key_id): Path<i64>,
+) -> Result<StatusCode, PaymeError> {
+    sqlx::query(
+        "UPDATE api_keys SET revoked_at = datetime('now') WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(key_id)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
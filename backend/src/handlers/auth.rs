@@ -1,19 +1,30 @@
+use argon2::password_hash::rand_core::RngCore;
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
 use axum::{extract::State, response::IntoResponse, Json};
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{encode, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::SqlitePool;
+use std::sync::Arc;
 use url::Url;
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError, ValidationErrors};
 
+use crate::config::{DefaultCategories, JwtKeys, PasswordPolicy, SessionPolicy, TwoFactorConfig};
+use crate::crypto;
+use crate::currency;
+use crate::email;
 use crate::error::PaymeError;
 use crate::middleware::auth::Claims;
+use crate::money::RoundingMode;
+use crate::timezone;
+use crate::totp;
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct AuthRequest {
@@ -21,6 +32,15 @@ pub struct AuthRequest {
     pub username: String,
     #[validate(length(min = 6, max = 128))]
     pub password: String,
+    /// Current authenticator code; required at login once two-factor auth is enabled
+    #[serde(default)]
+    pub totp_code: Option<String>,
+    /// Single-use backup code, accepted instead of `totp_code`
+    #[serde(default)]
+    pub backup_code: Option<String>,
+    /// On registration, start with no budget categories instead of the default set
+    #[serde(default)]
+    pub skip_default_categories: bool,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -29,24 +49,103 @@ pub struct AuthResponse {
     pub username: String,
 }
 
+/// Lifetime of the JWT access token
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+/// Lifetime of a refresh token; each rotation issues a fresh one with the full lifetime
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+const REFRESH_COOKIE: &str = "refresh_token";
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
+
+/// Issues an access token plus a refresh token and sets both as cookies.
+///
+/// Refresh tokens descend from a login through rotation and share its `family_id`, which lets
+/// reuse of a rotated-out token revoke every descendant at once. The family is also the session
+/// the access token names, whose activity the auth middleware tracks for idle expiry.
+async fn issue_session(
+    pool: &SqlitePool,
+    jar: CookieJar,
+    keys: &JwtKeys,
+    user_id: i64,
+    username: &str,
+    family_id: Option<String>,
+) -> Result<CookieJar, PaymeError> {
+    let family_id = family_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let claims = Claims {
+        sub: user_id,
+        username: username.to_string(),
+        exp: (Utc::now() + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp() as usize,
+        sid: Some(family_id.clone()),
+    };
+
+    let header = Header {
+        kid: Some(keys.current.id.clone()),
+        ..Header::default()
+    };
+    let token = encode(
+        &header,
+        &claims,
+        &EncodingKey::from_secret(keys.current.secret.as_bytes()),
+    )
+    .map_err(|e| PaymeError::Internal(e.to_string()))?;
+
+    let refresh_token = random_token();
+    // Refreshing doesn't count as activity, so only a new login starts the idle clock
+    sqlx::query(
+        "INSERT INTO sessions (family_id, user_id) VALUES (?, ?) ON CONFLICT(family_id) DO NOTHING",
+    )
+    .bind(&family_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES (?, ?, ?, datetime('now', ?))",
+    )
+    .bind(user_id)
+    .bind(&family_id)
+    .bind(hash_token(&refresh_token))
+    .bind(format!("+{REFRESH_TOKEN_TTL_DAYS} days"))
+    .execute(pool)
+    .await?;
+
+    let cookie = Cookie::build(("token", token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES))
+        .build();
+
+    let refresh_cookie = Cookie::build((REFRESH_COOKIE, refresh_token))
+        .path(REFRESH_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .build();
+
+    Ok(jar.add(cookie).add(refresh_cookie))
+}
+
 #[utoipa::path(
     post,
     path = "/api/auth/register",
     request_body = AuthRequest,
     responses(
         (status = 200, description = "User registered successfully", body = AuthResponse),
+        (status = 400, description = "Invalid username, or a password that fails the password policy"),
         (status = 409, description = "Username already exists"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Auth",
     summary = "Register a new account",
-    description = "Creates a new user record. Returns the newly created user's ID and username."
+    description = "Creates a new user record along with the configured default budget categories, unless `skip_default_categories` is set. Returns the newly created user's ID and username."
 )]
 pub async fn register(
     State(pool): State<SqlitePool>,
+    axum::Extension(policy): axum::Extension<Arc<PasswordPolicy>>,
+    axum::Extension(default_categories): axum::Extension<Arc<DefaultCategories>>,
     Json(payload): Json<AuthRequest>,
 ) -> Result<impl IntoResponse, PaymeError> {
     payload.validate()?;
+    validate_password_strength(&policy, "password", &payload.password)?;
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
     let password_hash = argon2
@@ -54,20 +153,45 @@ pub async fn register(
         .map_err(|e| PaymeError::Internal(e.to_string()))?
         .to_string();
 
+    let mut tx = pool.begin().await?;
     let result = sqlx::query_scalar::<_, i64>(
         "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
     )
     .bind(&payload.username)
     .bind(&password_hash)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    if !payload.skip_default_categories {
+        seed_default_categories(&mut tx, result, &default_categories).await?;
+    }
+    tx.commit().await?;
+
     Ok(Json(AuthResponse {
         id: result,
         username: payload.username,
     }))
 }
 
+/// Gives a new user the default categories, in order and with no budget. Labels the user already
+/// has are skipped, so seeding twice creates nothing new.
+async fn seed_default_categories(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+    defaults: &DefaultCategories,
+) -> Result<(), PaymeError> {
+    for label in &defaults.labels {
+        sqlx::query(
+            "INSERT INTO budget_categories (user_id, label, default_amount, sort_order) SELECT ?1, ?2, 0, (SELECT COALESCE(MAX(sort_order) + 1, 0) FROM budget_categories WHERE user_id = ?1) WHERE NOT EXISTS (SELECT 1 FROM budget_categories WHERE user_id = ?1 AND label = ?2)",
+        )
+        .bind(user_id)
+        .bind(label)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     post,
     path = "/api/auth/login",
@@ -79,20 +203,23 @@ pub async fn register(
     ),
     tag = "Auth",
     summary = "Authenticate user",
-    description = "Verifies credentials and issues a JWT token."
+    description = "Verifies credentials and issues a short-lived JWT access token plus a rotating refresh token, both as cookies. Accounts with two-factor auth enabled must also send a valid `totp_code`, or an unused `backup_code`, which is consumed."
 )]
 pub async fn login(
     State(pool): State<SqlitePool>,
+    axum::Extension(jwt_keys): axum::Extension<Arc<JwtKeys>>,
+    axum::Extension(two_factor): axum::Extension<Arc<TwoFactorConfig>>,
     jar: CookieJar,
     Json(payload): Json<AuthRequest>,
 ) -> Result<impl IntoResponse, PaymeError> {
     payload.validate()?;
-    let user: (i64, String, String) =
-        sqlx::query_as("SELECT id, username, password_hash FROM users WHERE username = ?")
-            .bind(&payload.username)
-            .fetch_optional(&pool)
-            .await?
-            .ok_or(PaymeError::Unauthorized)?;
+    let user: (i64, String, String, bool, Option<String>) = sqlx::query_as(
+        "SELECT id, username, password_hash, totp_enabled, totp_secret FROM users WHERE username = ?",
+    )
+    .bind(&payload.username)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::Unauthorized)?;
 
     let parsed_hash =
         PasswordHash::new(&user.2).map_err(|e| PaymeError::Internal(e.to_string()))?;
@@ -100,31 +227,27 @@ pub async fn login(
         .verify_password(payload.password.as_bytes(), &parsed_hash)
         .map_err(|_| PaymeError::Unauthorized)?;
 
-    let secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "payme-secret-key-change-in-production".to_string());
-
-    let claims = Claims {
-        sub: user.0,
-        username: user.1.clone(),
-        exp: (Utc::now() + Duration::days(30)).timestamp() as usize,
-    };
-
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
-    .map_err(|e| PaymeError::Internal(e.to_string()))?;
+    if user.3 {
+        let secret = user
+            .4
+            .as_deref()
+            .ok_or_else(|| PaymeError::Internal("2FA enabled without a secret".to_string()))?;
+        let verified = match (&payload.totp_code, &payload.backup_code) {
+            (Some(code), _) => {
+                totp::verify(&open_totp_secret(&two_factor, secret)?, code, unix_now())
+            }
+            (None, Some(code)) => consume_backup_code(&pool, user.0, code).await?,
+            (None, None) => false,
+        };
+        if !verified {
+            return Err(PaymeError::Unauthorized);
+        }
+    }
 
-    let cookie = Cookie::build(("token", token))
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .max_age(time::Duration::days(30))
-        .build();
+    let jar = issue_session(&pool, jar, &jwt_keys, user.0, &user.1, None).await?;
 
     Ok((
-        jar.add(cookie),
+        jar,
         Json(AuthResponse {
             id: user.0,
             username: user.1,
@@ -141,16 +264,43 @@ pub async fn login(
     ),
     tag = "Auth",
     summary = "Log out user",
-    description = "Clears the authentication token by setting the session cookie to expire immediately."
+    description = "Revokes the refresh token family of the current session and expires both session cookies."
 )]
-pub async fn logout(jar: CookieJar) -> impl IntoResponse {
+pub async fn logout(
+    State(pool): State<SqlitePool>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, PaymeError> {
+    if let Some(refresh) = jar.get(REFRESH_COOKIE) {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = datetime('now')
+            WHERE revoked_at IS NULL
+              AND family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = ?)
+            "#,
+        )
+        .bind(hash_token(refresh.value()))
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "DELETE FROM sessions WHERE family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = ?)",
+        )
+        .bind(hash_token(refresh.value()))
+        .execute(&pool)
+        .await?;
+    }
+
     let cookie = Cookie::build(("token", ""))
         .path("/")
         .http_only(true)
         .max_age(time::Duration::seconds(0))
         .build();
+    let refresh_cookie = Cookie::build((REFRESH_COOKIE, ""))
+        .path(REFRESH_COOKIE_PATH)
+        .http_only(true)
+        .max_age(time::Duration::seconds(0))
+        .build();
 
-    jar.add(cookie)
+    Ok(jar.add(cookie).add(refresh_cookie))
 }
 
 #[utoipa::path(
@@ -252,24 +402,30 @@ pub struct ChangePasswordRequest {
     pub current_password: String,
     #[validate(length(min = 6, max = 128))]
     pub new_password: String,
+    /// Sign out every other session by revoking their refresh tokens
+    #[serde(default)]
+    pub revoke_other_sessions: bool,
 }
 
 #[utoipa::path(
-    put,
+    method(post, put),
     path = "/api/auth/change-password",
     request_body = ChangePasswordRequest,
     responses(
         (status = 200, description = "Password changed successfully"),
+        (status = 400, description = "New password is too weak or matches the current one"),
         (status = 401, description = "Invalid current password"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Auth",
     summary = "Change password",
-    description = "Updates the authenticated user's password."
+    description = "Updates the authenticated user's password after verifying the current one. Optionally revokes all other sessions."
 )]
 pub async fn change_password(
     State(pool): State<SqlitePool>,
+    jar: CookieJar,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(policy): axum::Extension<Arc<PasswordPolicy>>,
     Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<impl IntoResponse, PaymeError> {
     payload.validate()?;
@@ -286,6 +442,13 @@ pub async fn change_password(
         .verify_password(payload.current_password.as_bytes(), &parsed_hash)
         .map_err(|_| PaymeError::Unauthorized)?;
 
+    if payload.new_password == payload.current_password {
+        return Err(PaymeError::BadRequest(
+            "New password must differ from the current password".to_string(),
+        ));
+    }
+    validate_password_strength(&policy, "new_password", &payload.new_password)?;
+
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
     let new_password_hash = argon2
@@ -293,11 +456,41 @@ pub async fn change_password(
         .map_err(|e| PaymeError::Internal(e.to_string()))?
         .to_string();
 
+    let mut tx = pool.begin().await?;
+
     sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
         .bind(&new_password_hash)
         .bind(claims.sub)
-        .execute(&pool)
+        .execute(&mut *tx)
+        .await?;
+
+    if payload.revoke_other_sessions {
+        let current = jar
+            .get(REFRESH_COOKIE)
+            .map(|c| hash_token(c.value()))
+            .unwrap_or_default();
+
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = datetime('now')
+            WHERE user_id = ? AND revoked_at IS NULL
+              AND family_id NOT IN (SELECT family_id FROM refresh_tokens WHERE token_hash = ?)
+            "#,
+        )
+        .bind(claims.sub)
+        .bind(&current)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM sessions WHERE user_id = ? AND family_id NOT IN (SELECT family_id FROM refresh_tokens WHERE token_hash = ?)",
+        )
+        .bind(claims.sub)
+        .bind(&current)
+        .execute(&mut *tx)
         .await?;
+    }
+
+    tx.commit().await?;
 
     Ok(Json(
         serde_json::json!({"message": "Password changed successfully"}),
@@ -359,3 +552,924 @@ pub async fn clear_all_data(
         Json(serde_json::json!({"message": "All data cleared"})),
     ))
 }
+
+/// Lifetime of a password reset token
+const RESET_TOKEN_TTL_MINUTES: i64 = 30;
+
+pub(crate) fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Checks a new password against the policy. Every rule it fails is reported as a separate
+/// error on `field`, so clients can show the user exactly what to fix.
+pub fn validate_password_strength(
+    policy: &PasswordPolicy,
+    field: &'static str,
+    password: &str,
+) -> Result<(), PaymeError> {
+    let rules = [
+        (
+            password.chars().count() >= policy.min_length,
+            "min_length",
+            format!("Must be at least {} characters", policy.min_length),
+        ),
+        (
+            !policy.require_letter || password.chars().any(|c| c.is_alphabetic()),
+            "letter",
+            "Must contain a letter".to_string(),
+        ),
+        (
+            !policy.require_digit || password.chars().any(|c| c.is_ascii_digit()),
+            "digit",
+            "Must contain a digit".to_string(),
+        ),
+        (
+            !policy.require_uppercase || password.chars().any(|c| c.is_uppercase()),
+            "uppercase",
+            "Must contain an uppercase letter".to_string(),
+        ),
+        (
+            !policy.require_symbol || password.chars().any(|c| !c.is_alphanumeric()),
+            "symbol",
+            "Must contain a symbol".to_string(),
+        ),
+        (
+            !policy
+                .denylist
+                .iter()
+                .any(|common| common.eq_ignore_ascii_case(password)),
+            "common",
+            "Is too common".to_string(),
+        ),
+    ];
+
+    let mut errors = ValidationErrors::new();
+    for (passed, code, message) in rules {
+        if !passed {
+            errors.add(
+                field,
+                ValidationError::new(code).with_message(message.into()),
+            );
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(PaymeError::Validation(errors))
+    }
+}
+
+/// Reset and refresh tokens are only ever stored as their SHA-256 digest.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(email)]
+    pub email: String,
+    /// The email can reset the password, so changing it takes the current one
+    #[validate(length(min = 6, max = 128))]
+    pub current_password: String,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/change-email",
+    request_body = ChangeEmailRequest,
+    responses(
+        (status = 200, description = "Email changed successfully"),
+        (status = 401, description = "Invalid current password"),
+        (status = 409, description = "Email already in use"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Change email",
+    description = "Sets the email address used for password recovery after verifying the current password."
+)]
+pub async fn change_email(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    payload.validate()?;
+
+    let user: (String,) = sqlx::query_as("SELECT password_hash FROM users WHERE id = ?")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+    let parsed_hash =
+        PasswordHash::new(&user.0).map_err(|e| PaymeError::Internal(e.to_string()))?;
+    Argon2::default()
+        .verify_password(payload.current_password.as_bytes(), &parsed_hash)
+        .map_err(|_| PaymeError::Unauthorized)?;
+
+    let email = payload.email.trim().to_lowercase();
+
+    let taken: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE email = ? AND id != ?")
+        .bind(&email)
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?;
+    if taken.is_some() {
+        return Err(PaymeError::Conflict("Email already in use".to_string()));
+    }
+
+    sqlx::query("UPDATE users SET email = ? WHERE id = ?")
+        .bind(&email)
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Email changed successfully"}),
+    ))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ChangeBaseCurrencyRequest {
+    /// ISO 4217 code, e.g. "EUR"
+    pub currency: String,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/base-currency",
+    request_body = ChangeBaseCurrencyRequest,
+    responses(
+        (status = 200, description = "Base currency changed successfully"),
+        (status = 400, description = "Unknown currency code"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Change base currency",
+    description = "Sets the currency that new items default to and that budgets and totals are reported in. Existing items keep their currency."
+)]
+pub async fn change_base_currency(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<ChangeBaseCurrencyRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    let currency = currency::normalize(&payload.currency)?;
+
+    sqlx::query("UPDATE users SET base_currency = ? WHERE id = ?")
+        .bind(&currency)
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Base currency changed successfully", "currency": currency}),
+    ))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ChangeRoundingModeRequest {
+    pub mode: RoundingMode,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/rounding-mode",
+    request_body = ChangeRoundingModeRequest,
+    responses(
+        (status = 200, description = "Rounding mode changed successfully"),
+        (status = 422, description = "Unknown rounding mode"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Change rounding mode",
+    description = "Sets how derived amounts that fall exactly between two cents are rounded: `half_up` (the default) or `half_even`. It applies to percentage budgets, the daily safe-to-spend figure and savings contribution splits, and month summaries report it as `rounding_mode` so clients can round the same way."
+)]
+pub async fn change_rounding_mode(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<ChangeRoundingModeRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    sqlx::query("UPDATE users SET rounding_mode = ? WHERE id = ?")
+        .bind(payload.mode.as_str())
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Rounding mode changed successfully", "mode": payload.mode}),
+    ))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ChangeTimezoneRequest {
+    /// IANA timezone name, e.g. "Europe/Berlin"
+    pub timezone: String,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/timezone",
+    request_body = ChangeTimezoneRequest,
+    responses(
+        (status = 200, description = "Timezone changed successfully"),
+        (status = 400, description = "Unknown timezone"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Change timezone",
+    description = "Sets the timezone whose midnight starts the user's days and months. It decides which month is current, the date new items default to and the default period of stats. Timestamps are still stored in UTC."
+)]
+pub async fn change_timezone(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<ChangeTimezoneRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    let timezone = timezone::parse(&payload.timezone)?;
+
+    sqlx::query("UPDATE users SET timezone = ? WHERE id = ?")
+        .bind(timezone.name())
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Timezone changed successfully", "timezone": timezone.name()}),
+    ))
+}
+
+/// Which notification emails the user receives; all are off until opted in, and none are sent
+/// without an email address on the account.
+#[derive(Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct NotificationPreferences {
+    /// A summary of each month when it is closed
+    pub month_closed: bool,
+    /// A note when savings first reach the savings goal
+    pub savings_goal: bool,
+    /// A recap of the previous month at the start of each month
+    #[serde(default)]
+    pub monthly_digest: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/notifications",
+    responses(
+        (status = 200, body = NotificationPreferences),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Get notification preferences"
+)]
+pub async fn get_notification_preferences(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<NotificationPreferences>, PaymeError> {
+    let preferences = sqlx::query_as(
+        "SELECT notify_month_closed AS month_closed, notify_savings_goal AS savings_goal, notify_monthly_digest AS monthly_digest FROM users WHERE id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+
+    Ok(Json(preferences))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/notifications",
+    request_body = NotificationPreferences,
+    responses(
+        (status = 200, body = NotificationPreferences),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Update notification preferences",
+    description = "Opts in to or out of notification emails. Emails go to the account's address and are sent in the background; delivery failures are logged on the server."
+)]
+pub async fn update_notification_preferences(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<NotificationPreferences>,
+) -> Result<Json<NotificationPreferences>, PaymeError> {
+    sqlx::query(
+        "UPDATE users SET notify_month_closed = ?, notify_savings_goal = ?, notify_monthly_digest = ? WHERE id = ?",
+    )
+    .bind(payload.month_closed)
+    .bind(payload.savings_goal)
+    .bind(payload.monthly_digest)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(payload))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(length(min = 3, max = 254))]
+    pub email: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/forgot-password",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "If the email belongs to an account, a reset token has been issued"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Request a password reset",
+    description = "Issues a single-use reset token valid for 30 minutes and emails it to the account's address. Always returns 200 so the response does not reveal whether the email is registered."
+)]
+pub async fn forgot_password(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    payload.validate()?;
+
+    let user_id: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+        .bind(payload.email.trim().to_lowercase())
+        .fetch_optional(&pool)
+        .await?;
+
+    if let Some(user_id) = user_id {
+        let token = random_token();
+
+        sqlx::query(
+            "DELETE FROM password_reset_tokens WHERE user_id = ? AND (used_at IS NOT NULL OR expires_at <= datetime('now'))",
+        )
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES (?, ?, datetime('now', ?))",
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(format!("+{RESET_TOKEN_TTL_MINUTES} minutes"))
+        .execute(&pool)
+        .await?;
+
+        let body = format!(
+            "Use this code to reset your payme password:\n\n{token}\n\nIt expires in {RESET_TOKEN_TTL_MINUTES} minutes. If you didn't ask for a reset, you can ignore this email."
+        );
+        email::enqueue(
+            &pool,
+            user_id,
+            email::PASSWORD_RESET,
+            "Reset your payme password",
+            &body,
+        )
+        .await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "If that email is registered, a reset link has been sent"
+    })))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 6, max = 128))]
+    pub new_password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/reset-password",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password reset successfully"),
+        (status = 400, description = "Invalid, expired or already used token, or weak password"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Reset password",
+    description = "Consumes a reset token and sets a new password. Any other outstanding tokens for the account are invalidated, and every session is signed out."
+)]
+pub async fn reset_password(
+    State(pool): State<SqlitePool>,
+    axum::Extension(policy): axum::Extension<Arc<PasswordPolicy>>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    payload.validate()?;
+    validate_password_strength(&policy, "new_password", &payload.new_password)?;
+
+    let mut tx = pool.begin().await?;
+
+    // Claiming the token and checking it in one statement keeps concurrent resets single-use.
+    let user_id: i64 = sqlx::query_scalar(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = datetime('now')
+        WHERE token_hash = ? AND used_at IS NULL AND expires_at > datetime('now')
+        RETURNING user_id
+        "#,
+    )
+    .bind(hash_token(&payload.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| PaymeError::BadRequest("Invalid or expired reset token".to_string()))?;
+
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(payload.new_password.as_bytes(), &salt)
+        .map_err(|e| PaymeError::Internal(e.to_string()))?
+        .to_string();
+
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+        .bind(&password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "UPDATE password_reset_tokens SET used_at = datetime('now') WHERE user_id = ? AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = datetime('now') WHERE user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    // Access tokens already handed out end with their session
+    sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Password reset successfully"}),
+    ))
+}
+
+fn unix_now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+/// Decrypts the user's stored TOTP secret.
+fn open_totp_secret(two_factor: &TwoFactorConfig, stored: &str) -> Result<String, PaymeError> {
+    let key = two_factor
+        .encryption_key
+        .as_deref()
+        .ok_or_else(|| PaymeError::Internal("TOTP_ENCRYPTION_KEY is not set".to_string()))?;
+    let secret = crypto::open_at_rest(key.as_bytes(), stored)
+        .map_err(|_| PaymeError::Internal("Unable to decrypt 2FA secret".to_string()))?;
+    String::from_utf8(secret).map_err(|e| PaymeError::Internal(e.to_string()))
+}
+
+/// Number of backup codes issued at a time
+const BACKUP_CODE_COUNT: usize = 10;
+
+/// Replaces all of the user's backup codes with fresh ones and returns them in plain text.
+/// Only hashes are stored, so this is the one chance to show them.
+async fn regenerate_backup_codes(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<String>, PaymeError> {
+    let codes: Vec<String> = (0..BACKUP_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            OsRng.fill_bytes(&mut bytes);
+            let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            format!("{}-{}", &hex[..5], &hex[5..])
+        })
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM two_factor_backup_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    for code in &codes {
+        sqlx::query("INSERT INTO two_factor_backup_codes (user_id, code_hash) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(hash_token(&normalize_backup_code(code)))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+/// Backup codes are compared without case, dashes or whitespace so they can be typed loosely.
+fn normalize_backup_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Marks a matching unused backup code as used; false if there was none.
+async fn consume_backup_code(
+    pool: &SqlitePool,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, PaymeError> {
+    let result = sqlx::query(
+        "UPDATE two_factor_backup_codes SET used_at = datetime('now') WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(hash_token(&normalize_backup_code(code)))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorSetupResponse {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code
+    pub otpauth_url: String,
+    /// Single-use recovery codes; they are not shown again
+    pub backup_codes: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BackupCodesResponse {
+    /// Single-use recovery codes; they are not shown again
+    pub backup_codes: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/setup",
+    responses(
+        (status = 200, description = "Secret generated; confirm it with /api/auth/2fa/verify", body = TwoFactorSetupResponse),
+        (status = 400, description = "Two-factor auth is not configured on this server"),
+        (status = 409, description = "Two-factor auth is already enabled"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Start two-factor setup",
+    description = "Generates a new TOTP secret and stores it encrypted, along with a fresh set of single-use backup codes. Login is unaffected until the secret is confirmed."
+)]
+pub async fn setup_two_factor(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(two_factor): axum::Extension<Arc<TwoFactorConfig>>,
+) -> Result<Json<TwoFactorSetupResponse>, PaymeError> {
+    let key = two_factor.encryption_key.as_deref().ok_or_else(|| {
+        PaymeError::BadRequest("Two-factor auth is not configured on this server".to_string())
+    })?;
+    let enabled: bool = sqlx::query_scalar("SELECT totp_enabled FROM users WHERE id = ?")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+    if enabled {
+        return Err(PaymeError::Conflict(
+            "Two-factor auth is already enabled".to_string(),
+        ));
+    }
+
+    let secret = totp::generate_secret();
+    let sealed = crypto::seal_at_rest(key.as_bytes(), secret.as_bytes())?;
+
+    sqlx::query("UPDATE users SET totp_secret = ? WHERE id = ?")
+        .bind(sealed)
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+    let backup_codes = regenerate_backup_codes(&pool, claims.sub).await?;
+
+    Ok(Json(TwoFactorSetupResponse {
+        otpauth_url: totp::otpauth_url(&secret, &claims.username),
+        secret,
+        backup_codes,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/backup-codes",
+    responses(
+        (status = 200, description = "New backup codes", body = BackupCodesResponse),
+        (status = 400, description = "Two-factor auth is not enabled"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Regenerate backup codes",
+    description = "Issues a new set of single-use backup codes for logging in without the authenticator app. All previous codes stop working."
+)]
+pub async fn regenerate_two_factor_backup_codes(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<BackupCodesResponse>, PaymeError> {
+    let enabled: bool = sqlx::query_scalar("SELECT totp_enabled FROM users WHERE id = ?")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+    if !enabled {
+        return Err(PaymeError::BadRequest(
+            "Two-factor auth is not enabled".to_string(),
+        ));
+    }
+
+    Ok(Json(BackupCodesResponse {
+        backup_codes: regenerate_backup_codes(&pool, claims.sub).await?,
+    }))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TwoFactorVerifyRequest {
+    #[validate(length(equal = 6))]
+    pub code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/verify",
+    request_body = TwoFactorVerifyRequest,
+    responses(
+        (status = 200, description = "Two-factor auth enabled"),
+        (status = 400, description = "No pending setup or invalid code"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Confirm two-factor setup",
+    description = "Checks a code from the authenticator app against the pending secret and enables two-factor auth."
+)]
+pub async fn verify_two_factor(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(two_factor): axum::Extension<Arc<TwoFactorConfig>>,
+    Json(payload): Json<TwoFactorVerifyRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    payload.validate()?;
+
+    let stored: Option<String> = sqlx::query_scalar("SELECT totp_secret FROM users WHERE id = ?")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+    let stored = stored.ok_or_else(|| {
+        PaymeError::BadRequest("Two-factor setup has not been started".to_string())
+    })?;
+
+    if !totp::verify(
+        &open_totp_secret(&two_factor, &stored)?,
+        &payload.code,
+        unix_now(),
+    ) {
+        return Err(PaymeError::BadRequest("Invalid code".to_string()));
+    }
+
+    sqlx::query("UPDATE users SET totp_enabled = 1 WHERE id = ?")
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Two-factor authentication enabled"}),
+    ))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    /// Refresh token; falls back to the `refresh_token` cookie when omitted
+    pub refresh_token: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    request_body(content = Option<RefreshRequest>),
+    responses(
+        (status = 200, description = "New access and refresh tokens issued as cookies", body = AuthResponse),
+        (status = 401, description = "Refresh token missing, expired, revoked or reused, or its session idle too long"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Refresh the session",
+    description = "Exchanges a refresh token for a new access token and a rotated refresh token. The presented token is invalidated; presenting it again revokes every token in its family. A session left idle past the inactivity timeout can't be refreshed and is ended; refreshing itself doesn't count as activity."
+)]
+pub async fn refresh(
+    State(pool): State<SqlitePool>,
+    axum::Extension(session): axum::Extension<SessionPolicy>,
+    axum::Extension(jwt_keys): axum::Extension<Arc<JwtKeys>>,
+    jar: CookieJar,
+    payload: Option<Json<RefreshRequest>>,
+) -> Result<impl IntoResponse, PaymeError> {
+    let presented = payload
+        .and_then(|Json(p)| p.refresh_token)
+        .or_else(|| jar.get(REFRESH_COOKIE).map(|c| c.value().to_string()))
+        .ok_or(PaymeError::Unauthorized)?;
+
+    let row: (i64, i64, String, bool, bool, bool) = sqlx::query_as(
+        r#"
+        SELECT id, user_id, family_id,
+               used_at IS NOT NULL,
+               revoked_at IS NOT NULL,
+               expires_at <= datetime('now')
+        FROM refresh_tokens
+        WHERE token_hash = ?
+        "#,
+    )
+    .bind(hash_token(&presented))
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::Unauthorized)?;
+    let (token_id, user_id, family_id, used, revoked, expired) = row;
+
+    if revoked || expired {
+        return Err(PaymeError::Unauthorized);
+    }
+
+    // Families from before activity was tracked have no session row and count as active
+    let idle: Option<bool> = sqlx::query_scalar(
+        "SELECT ?2 IS NOT NULL AND last_used_at <= datetime('now', ?2) FROM sessions WHERE family_id = ?1",
+    )
+    .bind(&family_id)
+    .bind(session.idle_cutoff())
+    .fetch_optional(&pool)
+    .await?;
+    if idle == Some(true) {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = datetime('now') WHERE family_id = ? AND revoked_at IS NULL",
+        )
+        .bind(&family_id)
+        .execute(&pool)
+        .await?;
+        sqlx::query("DELETE FROM sessions WHERE family_id = ?")
+            .bind(&family_id)
+            .execute(&pool)
+            .await?;
+        return Err(PaymeError::Unauthorized);
+    }
+
+    // Claim the token; losing this race means it was already rotated and is being replayed.
+    let claimed = sqlx::query(
+        "UPDATE refresh_tokens SET used_at = datetime('now') WHERE id = ? AND used_at IS NULL",
+    )
+    .bind(token_id)
+    .execute(&pool)
+    .await?
+    .rows_affected();
+
+    if used || claimed == 0 {
+        tracing::warn!("Refresh token reuse detected for user {user_id}; revoking family");
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = datetime('now') WHERE family_id = ? AND revoked_at IS NULL",
+        )
+        .bind(&family_id)
+        .execute(&pool)
+        .await?;
+        return Err(PaymeError::Unauthorized);
+    }
+
+    let username: String = sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::Unauthorized)?;
+
+    let jar = issue_session(&pool, jar, &jwt_keys, user_id, &username, Some(family_id)).await?;
+
+    Ok((
+        jar,
+        Json(AuthResponse {
+            id: user_id,
+            username,
+        }),
+    ))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct DeleteAccountRequest {
+    #[validate(length(min = 6, max = 128))]
+    pub password: String,
+}
+
+/// Removes every row belonging to `user_id`, children first, so nothing depends on
+/// `ON DELETE CASCADE` being honoured by the connection.
+async fn delete_user_rows(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+) -> Result<(), PaymeError> {
+    const STATEMENTS: [&str; 32] = [
+        "DELETE FROM receipts WHERE user_id = ?",
+        "DELETE FROM items WHERE month_id IN (SELECT id FROM months WHERE user_id = ?)",
+        "DELETE FROM income_entries WHERE month_id IN (SELECT id FROM months WHERE user_id = ?)",
+        "DELETE FROM monthly_budgets WHERE month_id IN (SELECT id FROM months WHERE user_id = ?)",
+        "DELETE FROM monthly_fixed_expenses WHERE month_id IN (SELECT id FROM months WHERE user_id = ?)",
+        "DELETE FROM monthly_savings WHERE month_id IN (SELECT id FROM months WHERE user_id = ?)",
+        "DELETE FROM monthly_snapshots WHERE month_id IN (SELECT id FROM months WHERE user_id = ?)",
+        "DELETE FROM months WHERE user_id = ?",
+        "DELETE FROM savings_allocation_rules WHERE user_id = ?",
+        "DELETE FROM savings_transfers WHERE user_id = ?",
+        "DELETE FROM savings_accounts WHERE user_id = ?",
+        "DELETE FROM fixed_expense_amounts WHERE fixed_expense_id IN (SELECT id FROM fixed_expenses WHERE user_id = ?)",
+        "DELETE FROM fixed_expenses WHERE user_id = ?",
+        "DELETE FROM item_templates WHERE user_id = ?",
+        "DELETE FROM budget_categories WHERE user_id = ?",
+        "DELETE FROM custom_savings_goals WHERE user_id = ?",
+        "DELETE FROM retirement_breakdown_items WHERE user_id = ?",
+        "DELETE FROM password_reset_tokens WHERE user_id = ?",
+        "DELETE FROM refresh_tokens WHERE user_id = ?",
+        "DELETE FROM sessions WHERE user_id = ?",
+        "DELETE FROM api_keys WHERE user_id = ?",
+        "DELETE FROM email_outbox WHERE user_id = ?",
+        "DELETE FROM two_factor_backup_codes WHERE user_id = ?",
+        // Owned households pass to the earliest accepted member; ones nobody else has joined go
+        // away along with their pending invites
+        "UPDATE household_members SET role = 'owner' WHERE id IN (SELECT MIN(m.id) FROM household_members m JOIN household_members o ON o.household_id = m.household_id WHERE o.user_id = ?1 AND o.role = 'owner' AND m.user_id != ?1 AND m.accepted_at IS NOT NULL GROUP BY m.household_id)",
+        "DELETE FROM household_members WHERE user_id != ?1 AND household_id IN (SELECT household_id FROM household_members WHERE user_id = ?1) AND household_id NOT IN (SELECT household_id FROM household_members WHERE user_id != ?1 AND accepted_at IS NOT NULL)",
+        "DELETE FROM households WHERE id IN (SELECT household_id FROM household_members WHERE user_id = ?1) AND id NOT IN (SELECT household_id FROM household_members WHERE user_id != ?1)",
+        "DELETE FROM household_members WHERE user_id = ?",
+        "DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE user_id = ?)",
+        "DELETE FROM webhooks WHERE user_id = ?",
+        "DELETE FROM exchange_rates WHERE user_id = ?",
+        "DELETE FROM idempotency_keys WHERE user_id = ?",
+        "DELETE FROM import_jobs WHERE user_id = ?",
+    ];
+
+    for statement in STATEMENTS {
+        sqlx::query(statement)
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    sqlx::query("DELETE FROM users WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/account",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 200, description = "Account and all associated data deleted"),
+        (status = 401, description = "Invalid password"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Auth",
+    summary = "Delete account",
+    description = "Permanently deletes the account and every row belonging to it in a single transaction. Repeating the call after a successful deletion is a no-op."
+)]
+pub async fn delete_account(
+    State(pool): State<SqlitePool>,
+    jar: CookieJar,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<impl IntoResponse, PaymeError> {
+    payload.validate()?;
+
+    let mut tx = pool.begin().await?;
+
+    let password_hash: Option<String> =
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE id = ?")
+            .bind(claims.sub)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    if let Some(password_hash) = password_hash {
+        let parsed_hash =
+            PasswordHash::new(&password_hash).map_err(|e| PaymeError::Internal(e.to_string()))?;
+        Argon2::default()
+            .verify_password(payload.password.as_bytes(), &parsed_hash)
+            .map_err(|_| PaymeError::Unauthorized)?;
+
+        delete_user_rows(&mut tx, claims.sub).await?;
+    }
+
+    tx.commit().await?;
+
+    let cookie = Cookie::build(("token", ""))
+        .path("/")
+        .http_only(true)
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let refresh_cookie = Cookie::build((REFRESH_COOKIE, ""))
+        .path(REFRESH_COOKIE_PATH)
+        .http_only(true)
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    Ok((
+        jar.add(cookie).add(refresh_cookie),
+        Json(serde_json::json!({"message": "Account deleted"})),
+    ))
+}
@@ -1,16 +1,20 @@
+use std::collections::HashSet;
+
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::error::PaymeError;
+use crate::handlers::items;
 use crate::middleware::auth::Claims;
 use crate::models::{BudgetCategory, MonthlyBudget};
+use crate::money::{self, Money};
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateCategory {
@@ -18,7 +22,14 @@ pub struct CreateCategory {
     pub label: String,
     #[validate(range(min = 0.0))]
     pub default_amount: f64,
+    /// `#rrggbb`; defaults to grey
+    #[validate(custom(function = "validate_hex_color"))]
     pub color: Option<String>,
+    /// Short icon name such as `shopping-cart`
+    #[validate(custom(function = "validate_icon"))]
+    pub icon: Option<String>,
+    /// Reject expenses that would take the category past its monthly budget; defaults to false
+    pub enforce_budget: Option<bool>,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -27,13 +38,246 @@ pub struct UpdateCategory {
     pub label: Option<String>,
     #[validate(range(min = 0.0))]
     pub default_amount: Option<f64>,
+    #[validate(custom(function = "validate_hex_color"))]
     pub color: Option<String>,
+    #[validate(custom(function = "validate_icon"))]
+    pub icon: Option<String>,
+    pub enforce_budget: Option<bool>,
+}
+
+/// `validator` check for category colors: `#` followed by six hex digits.
+pub fn validate_hex_color(color: &str) -> Result<(), ValidationError> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(()),
+        _ => {
+            Err(ValidationError::new("hex_color")
+                .with_message("Colors must look like #1a2b3c".into()))
+        }
+    }
+}
+
+/// `validator` check for icon names: up to 32 lowercase letters, digits and dashes.
+pub fn validate_icon(icon: &str) -> Result<(), ValidationError> {
+    let valid = (1..=32).contains(&icon.len())
+        && icon
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("icon")
+            .with_message("Icons are up to 32 lowercase letters, digits and dashes".into()))
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MergeCategory {
+    /// Category that receives the source's items
+    pub target_id: i64,
+    /// Add the source's monthly budgets to the target's instead of dropping them (defaults to true)
+    #[serde(default = "default_fold_budgets")]
+    pub fold_budgets: bool,
+}
+
+fn default_fold_budgets() -> bool {
+    true
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MergedCategory {
+    pub category: BudgetCategory,
+    pub items_moved: u64,
+    pub budgets_folded: u64,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ReorderCategories {
+    /// Every category id, in the desired display order
+    #[validate(length(min = 1))]
+    pub category_ids: Vec<i64>,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateMonthlyBudget {
+    /// Switches the budget to a fixed amount
+    #[validate(range(min = 0.0))]
+    pub allocated_amount: Option<f64>,
+    pub rollover: Option<bool>,
+    pub carry_overspend: Option<bool>,
+    /// Switches the budget to a percentage of the month's income
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub allocation_percent: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct BulkBudgetEntry {
+    pub category_id: i64,
+    /// Fixed amount to budget
     #[validate(range(min = 0.0))]
-    pub allocated_amount: f64,
+    pub allocated_amount: Option<f64>,
+    /// Share of the month's net income to budget instead
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub allocation_percent: Option<f64>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct BulkBudgetUpdate {
+    /// One entry per category; each sets either an amount or a percentage
+    #[validate(length(min = 1, max = 500), nested)]
+    pub budgets: Vec<BulkBudgetEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BudgetTemplate {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub categories: Vec<TemplateCategory>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TemplateCategory {
+    pub label: &'static str,
+    pub color: &'static str,
+    pub default_amount: f64,
+    /// Share of monthly income the category is budgeted, instead of a fixed amount
+    pub allocation_percent: Option<f64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ApplyBudgetTemplate {
+    /// `key` of one of the templates from `GET /api/budget/templates`
+    pub template: String,
+    /// Also remove categories the template doesn't have, as long as nothing was recorded in them
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AppliedBudgetTemplate {
+    pub created: Vec<BudgetCategory>,
+    /// Template categories skipped because a category with the same label already exists
+    pub existing: Vec<String>,
+    /// Labels of categories removed by `replace`
+    pub removed: Vec<String>,
+}
+
+/// (label, color, default amount, share of income)
+type PresetCategory = (&'static str, &'static str, f64, Option<f64>);
+
+const BUDGET_TEMPLATES: [(&str, &str, &str, &[PresetCategory]); 3] = [
+    (
+        "50-30-20",
+        "50/30/20",
+        "Half of income for needs, 30% for wants and 20% for savings.",
+        &[
+            ("Needs", "#5a7d5a", 0.0, Some(50.0)),
+            ("Wants", "#c4a35a", 0.0, Some(30.0)),
+            ("Savings", "#6b8e8e", 0.0, Some(20.0)),
+        ],
+    ),
+    (
+        "student",
+        "Student",
+        "A lean budget around rent, food and study costs.",
+        &[
+            ("Rent", "#5a7d5a", 600.0, None),
+            ("Groceries", "#c4a35a", 250.0, None),
+            ("Transport", "#6b8e8e", 60.0, None),
+            ("Books & Supplies", "#d4694a", 50.0, None),
+            ("Going Out", "#71717a", 80.0, None),
+        ],
+    ),
+    (
+        "family",
+        "Family",
+        "Shares of household income for the usual family expenses.",
+        &[
+            ("Housing", "#5a7d5a", 0.0, Some(30.0)),
+            ("Groceries", "#c4a35a", 0.0, Some(15.0)),
+            ("Childcare", "#d4694a", 0.0, Some(10.0)),
+            ("Transport", "#6b8e8e", 0.0, Some(10.0)),
+            ("Health", "#71717a", 0.0, Some(5.0)),
+            ("Savings", "#6b8e8e", 0.0, Some(15.0)),
+            ("Everything Else", "#71717a", 0.0, Some(15.0)),
+        ],
+    ),
+];
+
+fn budget_templates() -> Vec<BudgetTemplate> {
+    BUDGET_TEMPLATES
+        .iter()
+        .map(|(key, name, description, categories)| BudgetTemplate {
+            key,
+            name,
+            description,
+            categories: categories
+                .iter()
+                .map(
+                    |&(label, color, default_amount, allocation_percent)| TemplateCategory {
+                        label,
+                        color,
+                        default_amount,
+                        allocation_percent,
+                    },
+                )
+                .collect(),
+        })
+        .collect()
+}
+
+async fn find_monthly_budget(
+    pool: &SqlitePool,
+    month_id: i64,
+    budget_id: i64,
+) -> Result<MonthlyBudget, PaymeError> {
+    let row: BudgetRow = sqlx::query_as(
+        r#"
+        SELECT mb.id, mb.month_id, mb.category_id, mb.allocated_amount,
+               mb.rollover, mb.carry_overspend, mb.allocation_percent,
+               (SELECT COALESCE(SUM(monthly_amount), 0) FROM income_entries WHERE month_id = mb.month_id) AS income
+        FROM monthly_budgets mb
+        WHERE mb.id = ? AND mb.month_id = ?
+        "#,
+    )
+    .bind(budget_id)
+    .bind(month_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+    let mut budgets = round_allocations(pool, month_id, vec![row]).await?;
+    Ok(budgets.remove(0))
+}
+
+#[derive(sqlx::FromRow)]
+struct BudgetRow {
+    #[sqlx(flatten)]
+    budget: MonthlyBudget,
+    /// The month's net income, which percentage allocations are a share of
+    income: Money,
+}
+
+/// Works out percentage allocations from the month's income, rounded to the cent with the month
+/// owner's rounding mode.
+async fn round_allocations(
+    pool: &SqlitePool,
+    month_id: i64,
+    rows: Vec<BudgetRow>,
+) -> Result<Vec<MonthlyBudget>, PaymeError> {
+    let owner: i64 = sqlx::query_scalar("SELECT user_id FROM months WHERE id = ?")
+        .bind(month_id)
+        .fetch_one(pool)
+        .await?;
+    let mode = money::rounding_mode(pool, owner).await?;
+    Ok(rows
+        .into_iter()
+        .map(|BudgetRow { mut budget, income }| {
+            if let Some(percent) = budget.allocation_percent {
+                budget.allocated_amount = income.percent(percent, mode);
+            }
+            budget
+        })
+        .collect())
 }
 
 #[utoipa::path(
@@ -45,14 +289,14 @@ pub struct UpdateMonthlyBudget {
     ),
     tag = "Configuration",
     summary = "List all categories",
-    description = "Retrieves all budget categories used as templates for new months."
+    description = "Retrieves all budget categories used as templates for new months, in display order."
 )]
 pub async fn list_categories(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
 ) -> Result<Json<Vec<BudgetCategory>>, PaymeError> {
     let categories: Vec<BudgetCategory> = sqlx::query_as(
-        "SELECT id, user_id, label, default_amount, color FROM budget_categories WHERE user_id = ?",
+        "SELECT id, user_id, label, default_amount, color, sort_order, icon, enforce_budget FROM budget_categories WHERE user_id = ? ORDER BY sort_order, id",
     )
     .bind(claims.sub)
     .fetch_all(&pool)
@@ -71,7 +315,7 @@ pub async fn list_categories(
     ),
     tag = "Configuration",
     summary = "Create a category",
-    description = "Creates a new category template."
+    description = "Creates a new category template, placed after the existing categories."
 )]
 pub async fn create_category(
     State(pool): State<SqlitePool>,
@@ -80,13 +324,17 @@ pub async fn create_category(
 ) -> Result<Json<BudgetCategory>, PaymeError> {
     payload.validate()?;
     let color = payload.color.unwrap_or_else(|| "#71717a".to_string());
-    let id: i64 = sqlx::query_scalar(
-        "INSERT INTO budget_categories (user_id, label, default_amount, color) VALUES (?, ?, ?, ?) RETURNING id",
+    let enforce_budget = payload.enforce_budget.unwrap_or(false);
+    let default_amount = Money::from_f64(payload.default_amount);
+    let (id, sort_order): (i64, i64) = sqlx::query_as(
+        "INSERT INTO budget_categories (user_id, label, default_amount, color, icon, enforce_budget, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(sort_order) + 1, 0) FROM budget_categories WHERE user_id = ?1)) RETURNING id, sort_order",
     )
     .bind(claims.sub)
     .bind(&payload.label)
-    .bind(payload.default_amount)
+    .bind(default_amount)
     .bind(&color)
+    .bind(&payload.icon)
+    .bind(enforce_budget)
     .fetch_one(&pool)
     .await?;
 
@@ -102,7 +350,7 @@ pub async fn create_category(
         )
         .bind(month_id)
         .bind(id)
-        .bind(payload.default_amount)
+        .bind(default_amount)
         .execute(&pool)
         .await
         .ok();
@@ -112,8 +360,11 @@ pub async fn create_category(
         id,
         user_id: claims.sub,
         label: payload.label,
-        default_amount: payload.default_amount,
+        default_amount,
         color,
+        sort_order,
+        icon: payload.icon,
+        enforce_budget,
     }))
 }
 
@@ -128,7 +379,7 @@ pub async fn create_category(
     ),
     tag = "Configuration",
     summary = "Update a category",
-    description = "Updates the label or default amount for a category template."
+    description = "Updates the label, default amount, color or icon of a category template, or turns budget enforcement on or off with `enforce_budget`."
 )]
 pub async fn update_category(
     State(pool): State<SqlitePool>,
@@ -138,7 +389,7 @@ pub async fn update_category(
 ) -> Result<Json<BudgetCategory>, PaymeError> {
     payload.validate()?;
     let existing: BudgetCategory = sqlx::query_as(
-        "SELECT id, user_id, label, default_amount, color FROM budget_categories WHERE id = ? AND user_id = ?",
+        "SELECT id, user_id, label, default_amount, color, sort_order, icon, enforce_budget FROM budget_categories WHERE id = ? AND user_id = ?",
     )
     .bind(category_id)
     .bind(claims.sub)
@@ -147,15 +398,21 @@ pub async fn update_category(
     .ok_or(PaymeError::NotFound)?;
 
     let label = payload.label.unwrap_or(existing.label);
-    let default_amount = payload.default_amount.unwrap_or(existing.default_amount);
+    let default_amount = payload
+        .default_amount
+        .map_or(existing.default_amount, Money::from_f64);
     let color = payload.color.unwrap_or(existing.color);
+    let icon = payload.icon.or(existing.icon);
+    let enforce_budget = payload.enforce_budget.unwrap_or(existing.enforce_budget);
 
     sqlx::query(
-        "UPDATE budget_categories SET label = ?, default_amount = ?, color = ? WHERE id = ?",
+        "UPDATE budget_categories SET label = ?, default_amount = ?, color = ?, icon = ?, enforce_budget = ? WHERE id = ?",
     )
     .bind(&label)
     .bind(default_amount)
     .bind(&color)
+    .bind(&icon)
+    .bind(enforce_budget)
     .bind(category_id)
     .execute(&pool)
     .await?;
@@ -166,9 +423,60 @@ pub async fn update_category(
         label,
         default_amount,
         color,
+        sort_order: existing.sort_order,
+        icon,
+        enforce_budget,
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/categories/reorder",
+    request_body = ReorderCategories,
+    responses(
+        (status = 200, description = "Categories in their new order", body = [BudgetCategory]),
+        (status = 400, description = "List does not contain each category exactly once"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Configuration",
+    summary = "Reorder categories",
+    description = "Sets the display order of all categories. The list must contain every category id exactly once."
+)]
+pub async fn reorder_categories(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<ReorderCategories>,
+) -> Result<Json<Vec<BudgetCategory>>, PaymeError> {
+    payload.validate()?;
+
+    let mut existing: Vec<i64> =
+        sqlx::query_scalar("SELECT id FROM budget_categories WHERE user_id = ?")
+            .bind(claims.sub)
+            .fetch_all(&pool)
+            .await?;
+    let mut requested = payload.category_ids.clone();
+    existing.sort_unstable();
+    requested.sort_unstable();
+    if existing != requested {
+        return Err(PaymeError::BadRequest(
+            "Reorder must list every category exactly once".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    for (position, category_id) in payload.category_ids.iter().enumerate() {
+        sqlx::query("UPDATE budget_categories SET sort_order = ? WHERE id = ? AND user_id = ?")
+            .bind(position as i64)
+            .bind(category_id)
+            .bind(claims.sub)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    list_categories(State(pool), axum::Extension(claims)).await
+}
+
 #[utoipa::path(
     delete,
     path = "/api/categories/{id}",
@@ -191,6 +499,120 @@ pub async fn delete_category(
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/categories/{id}/merge",
+    params(("id" = i64, Path, description = "Category to merge away")),
+    request_body = MergeCategory,
+    responses(
+        (status = 200, description = "The target category after the merge", body = MergedCategory),
+        (status = 400, description = "Source and target are the same category"),
+        (status = 404, description = "Category not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Configuration",
+    summary = "Merge a category into another",
+    description = "Moves every item and item template from the source category to the target, then deletes the source, all in one transaction. With `fold_budgets`, each month's source budget is added to the target's: percentage budgets stay percentages when both are, otherwise the target becomes the fixed sum. Without it the source budgets are dropped."
+)]
+pub async fn merge_category(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(source_id): Path<i64>,
+    Json(payload): Json<MergeCategory>,
+) -> Result<Json<MergedCategory>, PaymeError> {
+    if source_id == payload.target_id {
+        return Err(PaymeError::BadRequest(
+            "Can't merge a category into itself".to_string(),
+        ));
+    }
+    let owned: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM budget_categories WHERE id IN (?, ?) AND user_id = ?",
+    )
+    .bind(source_id)
+    .bind(payload.target_id)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+    if owned != 2 {
+        return Err(PaymeError::NotFound);
+    }
+
+    let mut tx = pool.begin().await?;
+    let items_moved = sqlx::query("UPDATE items SET category_id = ? WHERE category_id = ?")
+        .bind(payload.target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    sqlx::query("UPDATE item_templates SET category_id = ? WHERE category_id = ? AND user_id = ?")
+        .bind(payload.target_id)
+        .bind(source_id)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut budgets_folded = 0;
+    if payload.fold_budgets {
+        budgets_folded += sqlx::query(
+            r#"
+            UPDATE monthly_budgets AS t SET
+                allocated_amount = CASE
+                    WHEN t.allocation_percent IS NOT NULL AND s.allocation_percent IS NOT NULL THEN t.allocated_amount
+                    ELSE CAST(ROUND(
+                        (CASE WHEN t.allocation_percent IS NULL THEN t.allocated_amount
+                              ELSE t.allocation_percent * (SELECT COALESCE(SUM(monthly_amount), 0) FROM income_entries WHERE month_id = t.month_id) / 100.0 END)
+                        + (CASE WHEN s.allocation_percent IS NULL THEN s.allocated_amount
+                                ELSE s.allocation_percent * (SELECT COALESCE(SUM(monthly_amount), 0) FROM income_entries WHERE month_id = s.month_id) / 100.0 END)
+                        ) AS INTEGER)
+                END,
+                allocation_percent = CASE
+                    WHEN t.allocation_percent IS NOT NULL AND s.allocation_percent IS NOT NULL THEN t.allocation_percent + s.allocation_percent
+                    ELSE NULL
+                END
+            FROM monthly_budgets AS s
+            WHERE t.category_id = ? AND s.category_id = ? AND s.month_id = t.month_id
+            "#,
+        )
+        .bind(payload.target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        // Months where only the source was budgeted keep that budget under the target
+        budgets_folded += sqlx::query(
+            "UPDATE monthly_budgets SET category_id = ?1 WHERE category_id = ?2 AND month_id NOT IN (SELECT month_id FROM monthly_budgets WHERE category_id = ?1)",
+        )
+        .bind(payload.target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    sqlx::query("DELETE FROM monthly_budgets WHERE category_id = ?")
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM budget_categories WHERE id = ? AND user_id = ?")
+        .bind(source_id)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+
+    let category = sqlx::query_as(
+        "SELECT id, user_id, label, default_amount, color, sort_order, icon, enforce_budget FROM budget_categories WHERE id = ?",
+    )
+    .bind(payload.target_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(MergedCategory {
+        category,
+        items_moved,
+        budgets_folded,
+    }))
+}
+
 #[utoipa::path(
     get,
     path = "/api/months/{id}/budgets",
@@ -208,21 +630,29 @@ pub async fn list_monthly_budgets(
     axum::Extension(claims): axum::Extension<Claims>,
     Path(month_id): Path<i64>,
 ) -> Result<Json<Vec<MonthlyBudget>>, PaymeError> {
-    let _month: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-        .bind(month_id)
-        .bind(claims.sub)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(PaymeError::NotFound)?;
+    items::verify_month_access(&pool, claims.sub, month_id).await?;
+
+    Ok(Json(month_budgets(&pool, month_id).await?))
+}
 
-    let budgets: Vec<MonthlyBudget> = sqlx::query_as(
-        "SELECT id, month_id, category_id, allocated_amount FROM monthly_budgets WHERE month_id = ?",
+/// The month's budgets, with percentage allocations worked out from its income.
+pub(crate) async fn month_budgets(
+    pool: &SqlitePool,
+    month_id: i64,
+) -> Result<Vec<MonthlyBudget>, PaymeError> {
+    let rows: Vec<BudgetRow> = sqlx::query_as(
+        r#"
+        SELECT mb.id, mb.month_id, mb.category_id, mb.allocated_amount,
+               mb.rollover, mb.carry_overspend, mb.allocation_percent,
+               (SELECT COALESCE(SUM(monthly_amount), 0) FROM income_entries WHERE month_id = mb.month_id) AS income
+        FROM monthly_budgets mb
+        WHERE mb.month_id = ?
+        "#,
     )
     .bind(month_id)
-    .fetch_all(&pool)
+    .fetch_all(pool)
     .await?;
-
-    Ok(Json(budgets))
+    round_allocations(pool, month_id, rows).await
 }
 
 #[utoipa::path(
@@ -235,11 +665,12 @@ pub async fn list_monthly_budgets(
     request_body = UpdateMonthlyBudget,
     responses(
         (status = 200, body = MonthlyBudget),
+        (status = 400, description = "Both an amount and a percentage given, or percentages over 100"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Budgets",
     summary = "Update monthly allocation",
-    description = "Adjust the amount of money allocated to a specific category for a specific month."
+    description = "Adjust the amount of money allocated to a specific category for a specific month, and whether its leftover rolls into the next month. The allocation is either a fixed `allocated_amount` or an `allocation_percent` of the month's net income, recomputed whenever income changes; percentages across the month's categories can't exceed 100."
 )]
 pub async fn update_monthly_budget(
     State(pool): State<SqlitePool>,
@@ -248,37 +679,276 @@ pub async fn update_monthly_budget(
     Json(payload): Json<UpdateMonthlyBudget>,
 ) -> Result<Json<MonthlyBudget>, PaymeError> {
     payload.validate()?;
-    let month: (bool,) =
-        sqlx::query_as("SELECT is_closed FROM months WHERE id = ? AND user_id = ?")
+    items::verify_month_not_closed(&pool, claims.sub, month_id).await?;
+
+    let existing = find_monthly_budget(&pool, month_id, budget_id).await?;
+
+    let allocation_percent = match (payload.allocated_amount, payload.allocation_percent) {
+        (Some(_), Some(_)) => {
+            return Err(PaymeError::BadRequest(
+                "Set either allocated_amount or allocation_percent, not both".to_string(),
+            ));
+        }
+        (Some(_), None) => None,
+        (None, Some(percent)) => {
+            let others: f64 = sqlx::query_scalar(
+                "SELECT COALESCE(SUM(allocation_percent), 0.0) FROM monthly_budgets WHERE month_id = ? AND id != ?",
+            )
             .bind(month_id)
-            .bind(claims.sub)
-            .fetch_optional(&pool)
-            .await?
-            .ok_or(PaymeError::NotFound)?;
+            .bind(budget_id)
+            .fetch_one(&pool)
+            .await?;
+            if others + percent > 100.0 {
+                return Err(PaymeError::BadRequest(
+                    "Allocation percentages can't exceed 100% of income".to_string(),
+                ));
+            }
+            Some(percent)
+        }
+        (None, None) => existing.allocation_percent,
+    };
+    let rollover = payload.rollover.unwrap_or(existing.rollover);
+    let carry_overspend = payload.carry_overspend.unwrap_or(existing.carry_overspend);
+
+    sqlx::query(
+        "UPDATE monthly_budgets SET allocated_amount = COALESCE(?, allocated_amount), allocation_percent = ?, rollover = ?, carry_overspend = ? WHERE id = ?",
+    )
+    .bind(payload.allocated_amount.map(Money::from_f64))
+    .bind(allocation_percent)
+    .bind(rollover)
+    .bind(carry_overspend)
+    .bind(budget_id)
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(find_monthly_budget(&pool, month_id, budget_id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/months/{id}/budget/bulk",
+    params(("id" = i64, Path, description = "Month ID")),
+    request_body = BulkBudgetUpdate,
+    responses(
+        (status = 200, description = "All of the month's allocations after the update", body = [MonthlyBudget]),
+        (status = 400, description = "An entry is invalid, names another user's category, or percentages exceed 100"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Budgets",
+    summary = "Update several monthly allocations",
+    description = "Sets the allocation of each listed category for the month in one transaction, adding the category to the month if it isn't budgeted yet. Each entry gives either an `allocated_amount` or an `allocation_percent`; percentages across the whole month still can't exceed 100. If any entry is rejected, none are applied."
+)]
+pub async fn bulk_update_monthly_budgets(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+    Json(payload): Json<BulkBudgetUpdate>,
+) -> Result<Json<Vec<MonthlyBudget>>, PaymeError> {
+    payload.validate()?;
+    let owner = items::verify_month_not_closed(&pool, claims.sub, month_id).await?;
+
+    let mut seen = HashSet::new();
+    for entry in &payload.budgets {
+        if entry.allocated_amount.is_some() == entry.allocation_percent.is_some() {
+            return Err(PaymeError::BadRequest(format!(
+                "Category {}: set either allocated_amount or allocation_percent",
+                entry.category_id
+            )));
+        }
+        if !seen.insert(entry.category_id) {
+            return Err(PaymeError::BadRequest(format!(
+                "Category {} is listed more than once",
+                entry.category_id
+            )));
+        }
+        let category: Option<i64> =
+            sqlx::query_scalar("SELECT id FROM budget_categories WHERE id = ? AND user_id = ?")
+                .bind(entry.category_id)
+                .bind(owner)
+                .fetch_optional(&pool)
+                .await?;
+        if category.is_none() {
+            return Err(PaymeError::BadRequest(format!(
+                "Category {} not found",
+                entry.category_id
+            )));
+        }
+    }
 
-    if month.0 {
-        return Err(PaymeError::BadRequest("Month is closed".to_string()));
+    let mut tx = pool.begin().await?;
+    for entry in &payload.budgets {
+        sqlx::query(
+            r#"
+            INSERT INTO monthly_budgets (month_id, category_id, allocated_amount, allocation_percent)
+            VALUES (?1, ?2, COALESCE(?3, 0), ?4)
+            ON CONFLICT(month_id, category_id)
+            DO UPDATE SET allocated_amount = COALESCE(?3, allocated_amount), allocation_percent = ?4
+            "#,
+        )
+        .bind(month_id)
+        .bind(entry.category_id)
+        .bind(entry.allocated_amount.map(Money::from_f64))
+        .bind(entry.allocation_percent)
+        .execute(&mut *tx)
+        .await?;
     }
 
-    let existing: MonthlyBudget = sqlx::query_as(
-        "SELECT id, month_id, category_id, allocated_amount FROM monthly_budgets WHERE id = ? AND month_id = ?",
+    let total_percent: f64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(allocation_percent), 0.0) FROM monthly_budgets WHERE month_id = ?",
     )
-    .bind(budget_id)
     .bind(month_id)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or(PaymeError::NotFound)?;
+    .fetch_one(&mut *tx)
+    .await?;
+    if total_percent > 100.0 {
+        return Err(PaymeError::BadRequest(
+            "Allocation percentages can't exceed 100% of income".to_string(),
+        ));
+    }
+    tx.commit().await?;
 
-    sqlx::query("UPDATE monthly_budgets SET allocated_amount = ? WHERE id = ?")
-        .bind(payload.allocated_amount)
-        .bind(budget_id)
-        .execute(&pool)
+    Ok(Json(month_budgets(&pool, month_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/budget/templates",
+    responses((status = 200, body = [BudgetTemplate])),
+    tag = "Configuration",
+    summary = "List budget templates",
+    description = "Lists the built-in sets of categories a user can start from."
+)]
+pub async fn list_budget_templates() -> Json<Vec<BudgetTemplate>> {
+    Json(budget_templates())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/budget/apply-template",
+    request_body = ApplyBudgetTemplate,
+    responses(
+        (status = 200, body = AppliedBudgetTemplate),
+        (status = 400, description = "Unknown template"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Configuration",
+    summary = "Apply budget template",
+    description = "Creates the template's categories, skipping any whose label (ignoring case) the user already has, so applying a template twice changes nothing. New categories are added to open months; percentage categories are budgeted as a share of income there unless that would take the month's percentages past 100, in which case they start at their default amount. With `replace`, categories outside the template are removed if no item or item template uses them."
+)]
+pub async fn apply_budget_template(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<ApplyBudgetTemplate>,
+) -> Result<Json<AppliedBudgetTemplate>, PaymeError> {
+    let template = budget_templates()
+        .into_iter()
+        .find(|t| t.key == payload.template)
+        .ok_or(PaymeError::BadRequest(
+            "Unknown budget template".to_string(),
+        ))?;
+
+    let mut tx = pool.begin().await?;
+    let mut applied = AppliedBudgetTemplate {
+        created: vec![],
+        existing: vec![],
+        removed: vec![],
+    };
+
+    if payload.replace {
+        let unused: Vec<(i64, String)> = sqlx::query_as(
+            r#"
+            SELECT bc.id, bc.label FROM budget_categories bc
+            WHERE bc.user_id = ?
+              AND NOT EXISTS (SELECT 1 FROM items WHERE category_id = bc.id)
+              AND NOT EXISTS (SELECT 1 FROM item_templates WHERE category_id = bc.id)
+            ORDER BY bc.sort_order, bc.id
+            "#,
+        )
+        .bind(claims.sub)
+        .fetch_all(&mut *tx)
         .await?;
+        for (category_id, label) in unused {
+            if template
+                .categories
+                .iter()
+                .any(|c| c.label.eq_ignore_ascii_case(&label))
+            {
+                continue;
+            }
+            sqlx::query("DELETE FROM budget_categories WHERE id = ?")
+                .bind(category_id)
+                .execute(&mut *tx)
+                .await?;
+            applied.removed.push(label);
+        }
+    }
 
-    Ok(Json(MonthlyBudget {
-        id: budget_id,
-        month_id,
-        category_id: existing.category_id,
-        allocated_amount: payload.allocated_amount,
-    }))
+    let labels: Vec<String> =
+        sqlx::query_scalar("SELECT label FROM budget_categories WHERE user_id = ?")
+            .bind(claims.sub)
+            .fetch_all(&mut *tx)
+            .await?;
+    let open_months: Vec<i64> =
+        sqlx::query_scalar("SELECT id FROM months WHERE user_id = ? AND is_closed = 0")
+            .bind(claims.sub)
+            .fetch_all(&mut *tx)
+            .await?;
+
+    for category in &template.categories {
+        if labels
+            .iter()
+            .any(|l| l.eq_ignore_ascii_case(category.label))
+        {
+            applied.existing.push(category.label.to_string());
+            continue;
+        }
+
+        let (id, sort_order): (i64, i64) = sqlx::query_as(
+            "INSERT INTO budget_categories (user_id, label, default_amount, color, sort_order) VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(sort_order) + 1, 0) FROM budget_categories WHERE user_id = ?1)) RETURNING id, sort_order",
+        )
+        .bind(claims.sub)
+        .bind(category.label)
+        .bind(Money::from_f64(category.default_amount))
+        .bind(category.color)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for &month_id in &open_months {
+            let allocation_percent = match category.allocation_percent {
+                Some(percent) => {
+                    let others: f64 = sqlx::query_scalar(
+                        "SELECT COALESCE(SUM(allocation_percent), 0.0) FROM monthly_budgets WHERE month_id = ?",
+                    )
+                    .bind(month_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    (others + percent <= 100.0).then_some(percent)
+                }
+                None => None,
+            };
+            sqlx::query(
+                "INSERT OR IGNORE INTO monthly_budgets (month_id, category_id, allocated_amount, allocation_percent) VALUES (?, ?, ?, ?)",
+            )
+            .bind(month_id)
+            .bind(id)
+            .bind(Money::from_f64(category.default_amount))
+            .bind(allocation_percent)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        applied.created.push(BudgetCategory {
+            id,
+            user_id: claims.sub,
+            label: category.label.to_string(),
+            default_amount: Money::from_f64(category.default_amount),
+            color: category.color.to_string(),
+            sort_order,
+            icon: None,
+            enforce_budget: false,
+        });
+    }
+    tx.commit().await?;
+
+    Ok(Json(applied))
 }
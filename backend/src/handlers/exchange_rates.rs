@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::currency;
+use crate::error::PaymeError;
+use crate::middleware::auth::Claims;
+use crate::models::ExchangeRate;
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SetExchangeRate {
+    pub from_currency: String,
+    pub to_currency: String,
+    /// Units of `to_currency` per unit of `from_currency`
+    #[validate(range(exclusive_min = 0.0))]
+    pub rate: f64,
+    /// First date the rate applies to; it stays in effect until a later rate is set
+    pub effective_on: NaiveDate,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/exchange-rates",
+    responses(
+        (status = 200, body = [ExchangeRate]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Configuration",
+    summary = "List exchange rates",
+    description = "Lists the rates used to convert foreign-currency items into the base currency, newest first."
+)]
+pub async fn list_exchange_rates(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<ExchangeRate>>, PaymeError> {
+    let rates: Vec<ExchangeRate> = sqlx::query_as(
+        "SELECT id, from_currency, to_currency, rate, effective_on FROM exchange_rates WHERE user_id = ? ORDER BY effective_on DESC, from_currency, to_currency",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(rates))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/exchange-rates",
+    request_body = SetExchangeRate,
+    responses(
+        (status = 200, body = ExchangeRate),
+        (status = 400, description = "Unknown currency or non-positive rate"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Configuration",
+    summary = "Set exchange rate",
+    description = "Records a rate between two currencies from a date onwards, replacing any rate already set for that pair and date. Items are converted at the most recent rate on or before their date; a rate also converts in the opposite direction."
+)]
+pub async fn set_exchange_rate(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<SetExchangeRate>,
+) -> Result<Json<ExchangeRate>, PaymeError> {
+    payload.validate()?;
+    let from_currency = currency::normalize(&payload.from_currency)?;
+    let to_currency = currency::normalize(&payload.to_currency)?;
+    if from_currency == to_currency {
+        return Err(PaymeError::BadRequest(
+            "Exchange rate currencies must differ".to_string(),
+        ));
+    }
+
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO exchange_rates (user_id, from_currency, to_currency, rate, effective_on)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(user_id, from_currency, to_currency, effective_on) DO UPDATE SET rate = excluded.rate
+        RETURNING id
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(&from_currency)
+    .bind(&to_currency)
+    .bind(payload.rate)
+    .bind(payload.effective_on)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(ExchangeRate {
+        id,
+        from_currency,
+        to_currency,
+        rate: payload.rate,
+        effective_on: payload.effective_on,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/exchange-rates/{id}",
+    params(("id" = i64, Path, description = "Exchange rate ID")),
+    responses((status = 204, description = "Deleted")),
+    tag = "Configuration",
+    summary = "Delete exchange rate"
+)]
+pub async fn delete_exchange_rate(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(rate_id): Path<i64>,
+) -> Result<StatusCode, PaymeError> {
+    sqlx::query("DELETE FROM exchange_rates WHERE id = ? AND user_id = ?")
+        .bind(rate_id)
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
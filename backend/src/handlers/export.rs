@@ -1,11 +1,28 @@
-use axum::{extract::State, http::StatusCode, Json};
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::crypto::{self, Sealed};
+use crate::currency;
 use crate::error::PaymeError;
+use crate::handlers::budget::{validate_hex_color, validate_icon};
+use crate::handlers::fixed_expenses::validate_period;
+use crate::handlers::income::INCOME_FREQUENCIES;
+use crate::handlers::items::adjust_savings;
+use crate::handlers::months::find_user_month;
 use crate::middleware::auth::Claims;
-use crate::models::{BudgetCategory, FixedExpense, IncomeEntry, Item, Month};
+use crate::models::{BudgetCategory, FixedExpense, IncomeEntry, Month};
+use crate::money::Money;
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct UserExport {
@@ -21,6 +38,20 @@ pub struct UserExport {
 pub struct FixedExpenseExport {
     pub label: String,
     pub amount: f64,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub due_day: Option<i64>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default)]
+    pub start_month: Option<String>,
+    #[serde(default)]
+    pub end_month: Option<String>,
+}
+
+fn default_active() -> bool {
+    true
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -28,6 +59,8 @@ pub struct CategoryExport {
     pub label: String,
     pub default_amount: f64,
     pub color: String,
+    #[serde(default)]
+    pub icon: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -44,12 +77,28 @@ pub struct MonthExport {
 pub struct IncomeExport {
     pub label: String,
     pub amount: f64,
+    #[serde(default)]
+    pub gross_amount: Option<f64>,
+    #[serde(default)]
+    pub withholding: f64,
+    #[serde(default = "default_frequency")]
+    pub frequency: String,
+}
+
+fn default_frequency() -> String {
+    "monthly".to_string()
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct BudgetExport {
     pub category_label: String,
     pub allocated_amount: f64,
+    #[serde(default)]
+    pub rollover: bool,
+    #[serde(default)]
+    pub carry_overspend: bool,
+    #[serde(default)]
+    pub allocation_percent: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -58,13 +107,114 @@ pub struct ItemExport {
     pub description: String,
     pub amount: f64,
     pub spent_on: String,
+    /// Absent in exports made before items carried a currency; imported as the base currency
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Stable identity matching the item across devices when merging an import
+    #[serde(default)]
+    pub uid: Option<String>,
+    /// When the item was last edited, deleted or restored (`YYYY-MM-DD HH:MM:SS`, UTC)
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Set on tombstones, items that were deleted on the exporting device
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+}
+
+/// An item row as exported, including deleted items still in the trash.
+#[derive(sqlx::FromRow)]
+struct ExportedItem {
+    category_id: i64,
+    description: String,
+    amount: Money,
+    spent_on: NaiveDate,
+    currency: String,
+    uid: Option<String>,
+    updated_at: Option<String>,
+    deleted_at: Option<String>,
+}
+
+/// Timestamp format shared by SQLite's `datetime()` and exported items
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Header carrying the passphrase for encrypted exports and imports
+pub const PASSPHRASE_HEADER: &str = "x-export-passphrase";
+
+/// A [`UserExport`] serialized to JSON and sealed with a passphrase. Binary fields are base64.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EncryptedExport {
+    pub version: u32,
+    pub algorithm: String,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+    pub tag: String,
+}
+
+const ENCRYPTION_ALGORITHM: &str = "argon2id+aes-256-gcm";
+
+/// Body accepted by the import endpoint: either a plain export or an encrypted bundle.
+#[derive(Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum ImportPayload {
+    Encrypted(EncryptedExport),
+    Plain(UserExport),
+}
+
+fn passphrase(headers: &HeaderMap) -> Option<&[u8]> {
+    headers
+        .get(PASSPHRASE_HEADER)
+        .map(|v| v.as_bytes())
+        .filter(|v| !v.is_empty())
+}
+
+fn encrypt_export(passphrase: &[u8], export: &UserExport) -> Result<EncryptedExport, PaymeError> {
+    let plaintext = serde_json::to_vec(export).map_err(|e| PaymeError::Internal(e.to_string()))?;
+    let sealed = crypto::seal(passphrase, &plaintext)?;
+
+    Ok(EncryptedExport {
+        version: 1,
+        algorithm: ENCRYPTION_ALGORITHM.to_string(),
+        salt: BASE64.encode(sealed.salt),
+        nonce: BASE64.encode(sealed.nonce),
+        ciphertext: BASE64.encode(sealed.ciphertext),
+        tag: BASE64.encode(sealed.tag),
+    })
+}
+
+fn decrypt_export(passphrase: &[u8], bundle: &EncryptedExport) -> Result<UserExport, PaymeError> {
+    if bundle.algorithm != ENCRYPTION_ALGORITHM {
+        return Err(PaymeError::BadRequest(format!(
+            "Unsupported encryption algorithm: {}",
+            bundle.algorithm
+        )));
+    }
+
+    let decode = |field: &str| {
+        BASE64
+            .decode(field)
+            .map_err(|_| PaymeError::BadRequest("Malformed encrypted bundle".to_string()))
+    };
+    let sealed = Sealed {
+        salt: decode(&bundle.salt)?,
+        nonce: decode(&bundle.nonce)?,
+        ciphertext: decode(&bundle.ciphertext)?,
+        tag: decode(&bundle.tag)?,
+    };
+
+    let plaintext = crypto::open(passphrase, &sealed)?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| PaymeError::BadRequest(format!("Decrypted bundle is not a valid export: {e}")))
 }
 
 #[utoipa::path(
     get,
     path = "/api/export/json",
+    params(
+        ("x-export-passphrase" = Option<String>, Header, description = "When set, the export is returned as an encrypted bundle")
+    ),
     responses(
-        (status = 200, description = "A complete JSON export of all user data", body = UserExport),
+        (status = 200, description = "A complete JSON export of all user data, or an EncryptedExport when a passphrase is supplied", body = UserExport),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error during database aggregation")
     ),
@@ -75,53 +225,63 @@ pub struct ItemExport {
 pub async fn export_json(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
-) -> Result<Json<UserExport>, PaymeError> {
-    let savings: f64 = sqlx::query_scalar("SELECT savings FROM users WHERE id = ?")
+    headers: HeaderMap,
+) -> Result<Response, PaymeError> {
+    let savings = sqlx::query_scalar("SELECT savings FROM users WHERE id = ?")
         .bind(claims.sub)
         .fetch_one(&pool)
         .await
-        .unwrap_or(0.0);
+        .map_or(0.0, Money::to_f64);
 
-    let retirement_savings: f64 =
+    let retirement_savings =
         sqlx::query_scalar("SELECT retirement_savings FROM users WHERE id = ?")
             .bind(claims.sub)
             .fetch_one(&pool)
             .await
-            .unwrap_or(0.0);
+            .map_or(0.0, Money::to_f64);
 
-    let fixed_expenses: Vec<FixedExpense> =
-        sqlx::query_as("SELECT id, user_id, label, amount FROM fixed_expenses WHERE user_id = ?")
-            .bind(claims.sub)
-            .fetch_all(&pool)
-            .await?;
+    let fixed_expenses: Vec<FixedExpense> = sqlx::query_as(
+        "SELECT id, user_id, label, amount, category, due_day, active, start_month, end_month FROM fixed_expenses WHERE user_id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
 
     let categories: Vec<BudgetCategory> = sqlx::query_as(
-        "SELECT id, user_id, label, default_amount, color FROM budget_categories WHERE user_id = ?",
+        "SELECT id, user_id, label, default_amount, color, sort_order, icon, enforce_budget FROM budget_categories WHERE user_id = ? ORDER BY sort_order, id",
     )
     .bind(claims.sub)
     .fetch_all(&pool)
     .await?;
 
     let months: Vec<Month> = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE user_id = ? ORDER BY year, month",
+        "SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months WHERE user_id = ? ORDER BY year, month",
     )
     .bind(claims.sub)
     .fetch_all(&pool)
     .await?;
 
+    // Items get their identity on first export, so every device sees the same one
+    sqlx::query(
+        "UPDATE items SET uid = lower(hex(randomblob(16))) WHERE uid IS NULL AND month_id IN (SELECT id FROM months WHERE user_id = ?)",
+    )
+    .bind(claims.sub)
+    .execute(&pool)
+    .await?;
+
     let mut month_exports = Vec::new();
 
     for m in &months {
         let income_entries: Vec<IncomeEntry> = sqlx::query_as(
-            "SELECT id, month_id, label, amount FROM income_entries WHERE month_id = ?",
+            "SELECT id, month_id, label, amount, gross_amount, withholding, frequency, monthly_amount FROM income_entries WHERE month_id = ?",
         )
         .bind(m.id)
         .fetch_all(&pool)
         .await?;
 
-        let budgets: Vec<(String, f64)> = sqlx::query_as(
+        let budgets: Vec<(String, Money, bool, bool, Option<f64>)> = sqlx::query_as(
             r#"
-            SELECT bc.label, mb.allocated_amount
+            SELECT bc.label, mb.allocated_amount, mb.rollover, mb.carry_overspend, mb.allocation_percent
             FROM monthly_budgets mb
             JOIN budget_categories bc ON mb.category_id = bc.id
             WHERE mb.month_id = ?
@@ -131,8 +291,8 @@ pub async fn export_json(
         .fetch_all(&pool)
         .await?;
 
-        let items: Vec<Item> = sqlx::query_as(
-            "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination FROM items WHERE month_id = ?",
+        let items: Vec<ExportedItem> = sqlx::query_as(
+            "SELECT category_id, description, amount, spent_on, currency, uid, updated_at, deleted_at FROM items WHERE month_id = ?",
         )
         .bind(m.id)
         .fetch_all(&pool)
@@ -145,8 +305,12 @@ pub async fn export_json(
                 item_exports.push(ItemExport {
                     category_label: cat.label.clone(),
                     description: item.description,
-                    amount: item.amount,
+                    amount: item.amount.to_f64(),
                     spent_on: item.spent_on.to_string(),
+                    currency: Some(item.currency),
+                    uid: item.uid,
+                    updated_at: item.updated_at,
+                    deleted_at: item.deleted_at,
                 });
             }
         }
@@ -159,21 +323,29 @@ pub async fn export_json(
                 .into_iter()
                 .map(|i| IncomeExport {
                     label: i.label,
-                    amount: i.amount,
+                    amount: i.amount.to_f64(),
+                    gross_amount: i.gross_amount.map(Money::to_f64),
+                    withholding: i.withholding.to_f64(),
+                    frequency: i.frequency,
                 })
                 .collect(),
             budgets: budgets
                 .into_iter()
-                .map(|(label, amount)| BudgetExport {
-                    category_label: label,
-                    allocated_amount: amount,
-                })
+                .map(
+                    |(label, amount, rollover, carry_overspend, allocation_percent)| BudgetExport {
+                        category_label: label,
+                        allocated_amount: amount.to_f64(),
+                        rollover,
+                        carry_overspend,
+                        allocation_percent,
+                    },
+                )
                 .collect(),
             items: item_exports,
         });
     }
 
-    Ok(Json(UserExport {
+    let export = UserExport {
         version: 1,
         savings: Some(savings),
         retirement_savings: Some(retirement_savings),
@@ -181,165 +353,1314 @@ pub async fn export_json(
             .into_iter()
             .map(|e| FixedExpenseExport {
                 label: e.label,
-                amount: e.amount,
+                amount: e.amount.to_f64(),
+                category: e.category,
+                due_day: e.due_day,
+                active: e.active,
+                start_month: e.start_month,
+                end_month: e.end_month,
             })
             .collect(),
         categories: categories
             .into_iter()
             .map(|c| CategoryExport {
                 label: c.label,
-                default_amount: c.default_amount,
+                default_amount: c.default_amount.to_f64(),
                 color: c.color,
+                icon: c.icon,
             })
             .collect(),
         months: month_exports,
+    };
+
+    match passphrase(&headers) {
+        Some(passphrase) => Ok(Json(encrypt_export(passphrase, &export)?).into_response()),
+        None => Ok(Json(export).into_response()),
+    }
+}
+
+/// Rough size of each exported entity once serialized, used to estimate an export's size
+/// without building it. Fixed overhead covers the top-level fields.
+const EXPORT_BASE_BYTES: i64 = 80;
+const MONTH_BYTES: i64 = 90;
+const ITEM_BYTES: i64 = 130;
+const INCOME_BYTES: i64 = 120;
+const BUDGET_BYTES: i64 = 140;
+const CATEGORY_BYTES: i64 = 90;
+const FIXED_EXPENSE_BYTES: i64 = 150;
+
+#[derive(Serialize, ToSchema)]
+pub struct ExportEstimate {
+    pub months: i64,
+    /// Includes deleted items still in the trash, which are exported as tombstones
+    pub items: i64,
+    pub income_entries: i64,
+    pub budgets: i64,
+    pub categories: i64,
+    pub fixed_expenses: i64,
+    /// Approximate size of the unencrypted JSON export, in bytes
+    pub approximate_bytes: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export/estimate",
+    responses(
+        (status = 200, description = "Entity counts and approximate size of a full JSON export", body = ExportEstimate),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Data Management",
+    summary = "Estimate export size",
+    description = "Counts what a full JSON export would contain and approximates its size, so clients can warn before exporting large histories."
+)]
+pub async fn get_export_estimate(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<ExportEstimate>, PaymeError> {
+    let months: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM months WHERE user_id = ?")
+        .bind(claims.sub)
+        .fetch_one(&pool)
+        .await?;
+
+    // Deleted items are exported as tombstones for merging imports, so they count too
+    let items: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM items i JOIN months m ON i.month_id = m.id WHERE m.user_id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+
+    let income_entries: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM income_entries ie JOIN months m ON ie.month_id = m.id WHERE m.user_id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+
+    let budgets: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM monthly_budgets mb JOIN months m ON mb.month_id = m.id WHERE m.user_id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+
+    let categories: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM budget_categories WHERE user_id = ?")
+            .bind(claims.sub)
+            .fetch_one(&pool)
+            .await?;
+
+    let fixed_expenses: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM fixed_expenses WHERE user_id = ?")
+            .bind(claims.sub)
+            .fetch_one(&pool)
+            .await?;
+
+    let approximate_bytes = EXPORT_BASE_BYTES
+        + months * MONTH_BYTES
+        + items * ITEM_BYTES
+        + income_entries * INCOME_BYTES
+        + budgets * BUDGET_BYTES
+        + categories * CATEGORY_BYTES
+        + fixed_expenses * FIXED_EXPENSE_BYTES;
+
+    Ok(Json(ExportEstimate {
+        months,
+        items,
+        income_entries,
+        budgets,
+        categories,
+        fixed_expenses,
+        approximate_bytes,
     }))
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// Validate the payload and report what would be created without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Merge the bundle into the existing data instead of replacing it
+    #[serde(default)]
+    pub merge: bool,
+}
+
+/// What a merging import did to the user's items.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct MergeReport {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    /// Items where the local copy was newer or already matched the bundle
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ImportCounts {
+    pub fixed_expenses: usize,
+    pub categories: usize,
+    pub months: usize,
+    pub income_entries: usize,
+    pub budgets: usize,
+    pub items: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ImportIssue {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub valid: bool,
+    pub would_create: ImportCounts,
+    pub errors: Vec<ImportIssue>,
+}
+
+/// Checks an export bundle for internal consistency before anything is written.
+///
+/// Categories and months are keyed by label and (year, month) respectively, so duplicates of
+/// either would make the references from budgets and items ambiguous.
+pub fn validate_import(data: &UserExport) -> ImportReport {
+    let mut errors = Vec::new();
+    let mut issue = |path: String, message: &str| {
+        errors.push(ImportIssue {
+            path,
+            message: message.to_string(),
+        })
+    };
+
+    for (name, value) in [
+        ("savings", data.savings),
+        ("retirement_savings", data.retirement_savings),
+    ] {
+        if value.is_some_and(|v| v < 0.0) {
+            issue(name.to_string(), "must not be negative");
+        }
+    }
+
+    for (i, expense) in data.fixed_expenses.iter().enumerate() {
+        if expense.amount < 0.0 {
+            issue(
+                format!("fixed_expenses[{i}].amount"),
+                "must not be negative",
+            );
+        }
+        for (field, bound) in [
+            ("start_month", &expense.start_month),
+            ("end_month", &expense.end_month),
+        ] {
+            if bound
+                .as_deref()
+                .is_some_and(|m| validate_period(m).is_err())
+            {
+                issue(
+                    format!("fixed_expenses[{i}].{field}"),
+                    "must look like 2024-06",
+                );
+            }
+        }
+    }
+
+    let mut labels = std::collections::HashSet::new();
+    for (i, cat) in data.categories.iter().enumerate() {
+        if !labels.insert(cat.label.as_str()) {
+            issue(format!("categories[{i}].label"), "duplicate category label");
+        }
+        if cat.default_amount < 0.0 {
+            issue(
+                format!("categories[{i}].default_amount"),
+                "must not be negative",
+            );
+        }
+        if validate_hex_color(&cat.color).is_err() {
+            issue(format!("categories[{i}].color"), "must look like #1a2b3c");
+        }
+        if cat
+            .icon
+            .as_deref()
+            .is_some_and(|icon| validate_icon(icon).is_err())
+        {
+            issue(
+                format!("categories[{i}].icon"),
+                "must be up to 32 lowercase letters, digits and dashes",
+            );
+        }
+    }
+
+    let mut uids = std::collections::HashSet::new();
+    let mut periods = std::collections::HashSet::new();
+    let mut counts = ImportCounts {
+        fixed_expenses: data.fixed_expenses.len(),
+        categories: data.categories.len(),
+        months: data.months.len(),
+        ..Default::default()
+    };
+
+    for (m, month) in data.months.iter().enumerate() {
+        if !(1..=12).contains(&month.month) {
+            issue(format!("months[{m}].month"), "must be between 1 and 12");
+        }
+        if !periods.insert((month.year, month.month)) {
+            issue(format!("months[{m}]"), "duplicate month");
+        }
+
+        for (i, income) in month.income_entries.iter().enumerate() {
+            if income.amount < 0.0 {
+                issue(
+                    format!("months[{m}].income_entries[{i}].amount"),
+                    "must not be negative",
+                );
+            }
+            if income.withholding < 0.0 {
+                issue(
+                    format!("months[{m}].income_entries[{i}].withholding"),
+                    "must not be negative",
+                );
+            }
+            if !INCOME_FREQUENCIES.contains(&income.frequency.as_str()) {
+                issue(
+                    format!("months[{m}].income_entries[{i}].frequency"),
+                    "unknown frequency",
+                );
+            }
+        }
+
+        for (i, budget) in month.budgets.iter().enumerate() {
+            if !labels.contains(budget.category_label.as_str()) {
+                issue(
+                    format!("months[{m}].budgets[{i}].category_label"),
+                    "references a missing category",
+                );
+            }
+            if budget.allocated_amount < 0.0 {
+                issue(
+                    format!("months[{m}].budgets[{i}].allocated_amount"),
+                    "must not be negative",
+                );
+            }
+            if budget
+                .allocation_percent
+                .is_some_and(|p| !(0.0..=100.0).contains(&p))
+            {
+                issue(
+                    format!("months[{m}].budgets[{i}].allocation_percent"),
+                    "must be between 0 and 100",
+                );
+            }
+        }
+        let total_percent: f64 = month
+            .budgets
+            .iter()
+            .filter_map(|b| b.allocation_percent)
+            .sum();
+        if total_percent > 100.0 {
+            issue(
+                format!("months[{m}].budgets"),
+                "allocation percentages exceed 100",
+            );
+        }
+
+        for (i, item) in month.items.iter().enumerate() {
+            if !labels.contains(item.category_label.as_str()) {
+                issue(
+                    format!("months[{m}].items[{i}].category_label"),
+                    "references a missing category",
+                );
+            }
+            if item.amount < 0.0 {
+                issue(
+                    format!("months[{m}].items[{i}].amount"),
+                    "must not be negative",
+                );
+            }
+            if NaiveDate::parse_from_str(&item.spent_on, "%Y-%m-%d").is_err() {
+                issue(
+                    format!("months[{m}].items[{i}].spent_on"),
+                    "must be a valid YYYY-MM-DD date",
+                );
+            }
+            if let Some(code) = &item.currency {
+                if currency::normalize(code).is_err() {
+                    issue(
+                        format!("months[{m}].items[{i}].currency"),
+                        "must be an ISO 4217 currency code",
+                    );
+                }
+            }
+            if let Some(uid) = &item.uid {
+                if uid.is_empty() || uid.len() > 64 {
+                    issue(
+                        format!("months[{m}].items[{i}].uid"),
+                        "must be 1-64 characters",
+                    );
+                } else if !uids.insert(uid.as_str()) {
+                    issue(format!("months[{m}].items[{i}].uid"), "duplicate item uid");
+                }
+            }
+            for (field, stamp) in [
+                ("updated_at", &item.updated_at),
+                ("deleted_at", &item.deleted_at),
+            ] {
+                if stamp.as_deref().is_some_and(|s| {
+                    chrono::NaiveDateTime::parse_from_str(s, TIMESTAMP_FORMAT).is_err()
+                }) {
+                    issue(
+                        format!("months[{m}].items[{i}].{field}"),
+                        "must look like 2024-06-01 12:00:00",
+                    );
+                }
+            }
+        }
+
+        counts.income_entries += month.income_entries.len();
+        counts.budgets += month.budgets.len();
+        counts.items += month
+            .items
+            .iter()
+            .filter(|i| i.deleted_at.is_none())
+            .count();
+    }
+
+    ImportReport {
+        valid: errors.is_empty(),
+        would_create: counts,
+        errors,
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/api/import/json",
-    request_body = UserExport,
+    params(
+        ImportQuery,
+        ("x-export-passphrase" = Option<String>, Header, description = "Passphrase used to decrypt an EncryptedExport body")
+    ),
+    request_body = ImportPayload,
     responses(
-        (status = 200, description = "Data imported successfully. Note: This overwrites existing user data."),
+        (status = 200, description = "Data imported successfully. Note: This overwrites existing user data. With dry_run=true, returns a validation report instead; with merge=true, a MergeReport.", body = ImportReport),
+        (status = 400, description = "The payload failed validation"),
+        (status = 422, description = "Wrong passphrase or tampered encrypted bundle"),
         (status = 500, description = "Internal server error during database restoration")
     ),
     tag = "Data Management",
     summary = "Import data from JSON",
-    description = "Overwrites the current user's database records with the provided JSON export. This action is destructive and irreversible."
+    description = "Overwrites the current user's database records with the provided JSON export. This action is destructive and irreversible. With merge=true, items are instead reconciled with the existing data by uid, keeping whichever side changed last, so deletions made on another device carry over."
 )]
 pub async fn import_json(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
-    Json(data): Json<UserExport>,
-) -> Result<StatusCode, PaymeError> {
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    Json(payload): Json<ImportPayload>,
+) -> Result<Response, PaymeError> {
+    let data = match payload {
+        ImportPayload::Plain(data) => data,
+        ImportPayload::Encrypted(bundle) => {
+            let passphrase = passphrase(&headers).ok_or_else(|| {
+                PaymeError::BadRequest("Passphrase required for encrypted import".to_string())
+            })?;
+            decrypt_export(passphrase, &bundle)?
+        }
+    };
+
+    let report = validate_import(&data);
+
+    if query.dry_run {
+        return Ok(Json(report).into_response());
+    }
+
+    if !report.valid {
+        return Err(PaymeError::BadRequest(format!(
+            "Invalid import: {}",
+            summarize(&report.errors)
+        )));
+    }
+
+    let base_currency = currency::base_currency(&pool, claims.sub).await?;
     let mut tx = pool.begin().await?;
 
+    if query.merge {
+        let report = merge_import(&mut tx, claims.sub, &data, &base_currency).await?;
+        tx.commit().await?;
+        return Ok(Json(report).into_response());
+    }
+
+    clear_for_import(&mut tx, claims.sub).await?;
+    let category_map = import_settings(&mut tx, claims.sub, &data).await?;
+    let mut rejected = Vec::new();
+    for (m, month_data) in data.months.iter().enumerate() {
+        rejected.extend(
+            import_month(
+                &mut tx,
+                claims.sub,
+                &format!("months[{m}]"),
+                month_data,
+                &category_map,
+                &base_currency,
+            )
+            .await?,
+        );
+    }
+    if !rejected.is_empty() {
+        return Err(PaymeError::BadRequest(format!(
+            "Invalid import: {}",
+            summarize(&rejected)
+        )));
+    }
+
+    tx.commit().await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Removes everything a replacing import overwrites.
+async fn clear_for_import(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+) -> Result<(), PaymeError> {
     let months: Vec<(i64,)> = sqlx::query_as("SELECT id FROM months WHERE user_id = ?")
-        .bind(claims.sub)
-        .fetch_all(&mut *tx)
+        .bind(user_id)
+        .fetch_all(&mut **tx)
         .await?;
 
     for (month_id,) in &months {
         sqlx::query("DELETE FROM items WHERE month_id = ?")
             .bind(month_id)
-            .execute(&mut *tx)
+            .execute(&mut **tx)
             .await?;
         sqlx::query("DELETE FROM monthly_budgets WHERE month_id = ?")
             .bind(month_id)
-            .execute(&mut *tx)
+            .execute(&mut **tx)
             .await?;
         sqlx::query("DELETE FROM income_entries WHERE month_id = ?")
             .bind(month_id)
-            .execute(&mut *tx)
+            .execute(&mut **tx)
             .await?;
         sqlx::query("DELETE FROM monthly_snapshots WHERE month_id = ?")
             .bind(month_id)
-            .execute(&mut *tx)
+            .execute(&mut **tx)
             .await?;
     }
 
     sqlx::query("DELETE FROM months WHERE user_id = ?")
-        .bind(claims.sub)
-        .execute(&mut *tx)
+        .bind(user_id)
+        .execute(&mut **tx)
         .await?;
     sqlx::query("DELETE FROM budget_categories WHERE user_id = ?")
-        .bind(claims.sub)
-        .execute(&mut *tx)
+        .bind(user_id)
+        .execute(&mut **tx)
         .await?;
+    sqlx::query(
+        "DELETE FROM fixed_expense_amounts WHERE fixed_expense_id IN (SELECT id FROM fixed_expenses WHERE user_id = ?)",
+    )
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
     sqlx::query("DELETE FROM fixed_expenses WHERE user_id = ?")
-        .bind(claims.sub)
-        .execute(&mut *tx)
+        .bind(user_id)
+        .execute(&mut **tx)
         .await?;
 
+    Ok(())
+}
+
+/// Writes a bundle's balances, fixed expenses and categories, returning the new category ids by
+/// label for the months to reference.
+async fn import_settings(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+    data: &UserExport,
+) -> Result<HashMap<String, i64>, PaymeError> {
     if let Some(savings) = data.savings {
         sqlx::query("UPDATE users SET savings = ? WHERE id = ?")
-            .bind(savings)
-            .bind(claims.sub)
-            .execute(&mut *tx)
+            .bind(Money::from_f64(savings))
+            .bind(user_id)
+            .execute(&mut **tx)
             .await?;
     }
 
     if let Some(retirement_savings) = data.retirement_savings {
         sqlx::query("UPDATE users SET retirement_savings = ? WHERE id = ?")
-            .bind(retirement_savings)
-            .bind(claims.sub)
-            .execute(&mut *tx)
+            .bind(Money::from_f64(retirement_savings))
+            .bind(user_id)
+            .execute(&mut **tx)
             .await?;
     }
 
     for expense in &data.fixed_expenses {
-        sqlx::query("INSERT INTO fixed_expenses (user_id, label, amount) VALUES (?, ?, ?)")
+        sqlx::query(
+            "INSERT INTO fixed_expenses (user_id, label, amount, category, due_day, active, start_month, end_month) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(&expense.label)
+        .bind(Money::from_f64(expense.amount))
+        .bind(&expense.category)
+        .bind(expense.due_day.filter(|day| (1..=31).contains(day)))
+        .bind(expense.active)
+        .bind(expense.start_month.as_deref().filter(|m| !m.is_empty()))
+        .bind(expense.end_month.as_deref().filter(|m| !m.is_empty()))
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let mut category_map = HashMap::new();
+    for cat in &data.categories {
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO budget_categories (user_id, label, default_amount, color, icon, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, (SELECT COALESCE(MAX(sort_order) + 1, 0) FROM budget_categories WHERE user_id = ?1)) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(&cat.label)
+        .bind(Money::from_f64(cat.default_amount))
+        .bind(&cat.color)
+        .bind(&cat.icon)
+        .fetch_one(&mut **tx)
+        .await?;
+        category_map.insert(cat.label.clone(), id);
+    }
+
+    sqlx::query(
+        "UPDATE users SET savings_adjustment = savings, retirement_savings_adjustment = retirement_savings WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(category_map)
+}
+
+/// Writes one month of a bundle with its income, budgets and items. Rows the database rejects
+/// are skipped and reported under `path` instead of failing the whole month.
+async fn import_month(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+    path: &str,
+    month_data: &MonthExport,
+    category_map: &HashMap<String, i64>,
+    base_currency: &str,
+) -> Result<Vec<ImportIssue>, PaymeError> {
+    let mut issues = Vec::new();
+    let month_id: i64 = sqlx::query_scalar(
+        "INSERT INTO months (user_id, year, month, is_closed) VALUES (?, ?, ?, ?) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(month_data.year)
+    .bind(month_data.month)
+    .bind(month_data.is_closed)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    for (i, income) in month_data.income_entries.iter().enumerate() {
+        let result = sqlx::query(
+            "INSERT INTO income_entries (month_id, label, amount, gross_amount, withholding, frequency) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(month_id)
+        .bind(&income.label)
+        .bind(Money::from_f64(income.amount))
+        .bind(income.gross_amount.map(Money::from_f64))
+        .bind(Money::from_f64(income.withholding))
+        .bind(&income.frequency)
+        .execute(&mut **tx)
+        .await;
+        record_rejection(result, format!("{path}.income_entries[{i}]"), &mut issues)?;
+    }
+
+    for (i, budget) in month_data.budgets.iter().enumerate() {
+        if let Some(&cat_id) = category_map.get(&budget.category_label) {
+            let result = sqlx::query(
+                "INSERT INTO monthly_budgets (month_id, category_id, allocated_amount, rollover, carry_overspend, allocation_percent) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(month_id)
+            .bind(cat_id)
+            .bind(Money::from_f64(budget.allocated_amount))
+            .bind(budget.rollover)
+            .bind(budget.carry_overspend)
+            .bind(budget.allocation_percent)
+            .execute(&mut **tx)
+            .await;
+            record_rejection(result, format!("{path}.budgets[{i}]"), &mut issues)?;
+        }
+    }
+
+    for (i, item) in month_data.items.iter().enumerate() {
+        if let Some(&cat_id) = category_map.get(&item.category_label) {
+            let item_currency = match &item.currency {
+                Some(code) => currency::normalize(code)?,
+                None => base_currency.to_string(),
+            };
+            let result = sqlx::query(
+                "INSERT INTO items (month_id, category_id, description, amount, spent_on, currency, uid, updated_at, deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(month_id)
+            .bind(cat_id)
+            .bind(&item.description)
+            .bind(Money::from_f64(item.amount))
+            .bind(&item.spent_on)
+            .bind(&item_currency)
+            .bind(&item.uid)
+            .bind(&item.updated_at)
+            .bind(&item.deleted_at)
+            .execute(&mut **tx)
+            .await;
+            record_rejection(result, format!("{path}.items[{i}]"), &mut issues)?;
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Records a row the database refused, such as one breaking a constraint, so the rest of the
+/// chunk can carry on. Anything else, like a lost connection, still fails the chunk.
+fn record_rejection(
+    result: Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error>,
+    path: String,
+    issues: &mut Vec<ImportIssue>,
+) -> Result<(), PaymeError> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) => {
+            issues.push(ImportIssue {
+                path,
+                message: e.message().to_string(),
+            });
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Progress of a background import started through `POST /api/import/jobs`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportJob {
+    pub id: i64,
+    /// pending, running, completed or failed
+    pub status: String,
+    /// Chunks in the bundle: one for balances, fixed expenses and categories, then one per month
+    pub total: i64,
+    /// Chunks committed so far
+    pub processed: i64,
+    /// Rows skipped so far because the database rejected them
+    pub errors: Vec<ImportIssue>,
+    /// Why the job stopped, when it failed
+    pub error: Option<String>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ImportJobRow {
+    id: i64,
+    status: String,
+    total: i64,
+    processed: i64,
+    errors: String,
+    error: Option<String>,
+    created_at: String,
+    finished_at: Option<String>,
+}
+
+impl From<ImportJobRow> for ImportJob {
+    fn from(row: ImportJobRow) -> Self {
+        ImportJob {
+            id: row.id,
+            status: row.status,
+            total: row.total,
+            processed: row.processed,
+            errors: serde_json::from_str(&row.errors).unwrap_or_default(),
+            error: row.error,
+            created_at: row.created_at,
+            finished_at: row.finished_at,
+        }
+    }
+}
+
+async fn find_import_job(
+    pool: &SqlitePool,
+    user_id: i64,
+    id: i64,
+) -> Result<ImportJob, PaymeError> {
+    sqlx::query_as::<_, ImportJobRow>(
+        "SELECT id, status, total, processed, errors, error, created_at, finished_at FROM import_jobs WHERE id = ? AND user_id = ?",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .map(ImportJob::from)
+    .ok_or(PaymeError::NotFound)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/import/jobs",
+    params(
+        ("x-export-passphrase" = Option<String>, Header, description = "Passphrase used to decrypt an EncryptedExport body")
+    ),
+    request_body = ImportPayload,
+    responses(
+        (status = 202, description = "Import started; poll the job for progress", body = ImportJob),
+        (status = 400, description = "The payload failed validation"),
+        (status = 422, description = "Wrong passphrase or tampered encrypted bundle")
+    ),
+    tag = "Data Management",
+    summary = "Start a background import",
+    description = "Replaces the current user's data like POST /api/import/json, but returns straight away and writes the bundle in the background. Each chunk commits on its own, so a job that fails part way leaves the chunks before it in place."
+)]
+pub async fn start_import_job(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<ImportPayload>,
+) -> Result<(StatusCode, Json<ImportJob>), PaymeError> {
+    let data = match payload {
+        ImportPayload::Plain(data) => data,
+        ImportPayload::Encrypted(bundle) => {
+            let passphrase = passphrase(&headers).ok_or_else(|| {
+                PaymeError::BadRequest("Passphrase required for encrypted import".to_string())
+            })?;
+            decrypt_export(passphrase, &bundle)?
+        }
+    };
+
+    let report = validate_import(&data);
+    if !report.valid {
+        return Err(PaymeError::BadRequest(format!(
+            "Invalid import: {}",
+            summarize(&report.errors)
+        )));
+    }
+
+    let base_currency = currency::base_currency(&pool, claims.sub).await?;
+    let id: i64 =
+        sqlx::query_scalar("INSERT INTO import_jobs (user_id, total) VALUES (?, ?) RETURNING id")
             .bind(claims.sub)
-            .bind(&expense.label)
-            .bind(expense.amount)
-            .execute(&mut *tx)
+            .bind(1 + data.months.len() as i64)
+            .fetch_one(&pool)
             .await?;
+    let job = find_import_job(&pool, claims.sub, id).await?;
+
+    let user_id = claims.sub;
+    crate::background::spawn(async move {
+        if let Err(e) = run_import_job(&pool, id, user_id, &data, &base_currency).await {
+            tracing::error!("Import job {} failed: {}", id, e);
+            let _ = sqlx::query(
+                "UPDATE import_jobs SET status = 'failed', error = ?, finished_at = datetime('now') WHERE id = ?",
+            )
+            .bind(e.to_string())
+            .bind(id)
+            .execute(&pool)
+            .await;
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Writes the bundle one chunk per transaction, bumping the job's progress in the same
+/// transaction so what it reports is exactly what has been committed.
+async fn run_import_job(
+    pool: &SqlitePool,
+    job_id: i64,
+    user_id: i64,
+    data: &UserExport,
+    base_currency: &str,
+) -> Result<(), PaymeError> {
+    sqlx::query("UPDATE import_jobs SET status = 'running' WHERE id = ?")
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    clear_for_import(&mut tx, user_id).await?;
+    let category_map = import_settings(&mut tx, user_id, data).await?;
+    record_progress(&mut tx, job_id, &[]).await?;
+    tx.commit().await?;
+
+    let mut errors = Vec::new();
+    for (m, month_data) in data.months.iter().enumerate() {
+        let mut tx = pool.begin().await?;
+        errors.extend(
+            import_month(
+                &mut tx,
+                user_id,
+                &format!("months[{m}]"),
+                month_data,
+                &category_map,
+                base_currency,
+            )
+            .await?,
+        );
+        record_progress(&mut tx, job_id, &errors).await?;
+        tx.commit().await?;
     }
 
-    let mut category_map: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    sqlx::query(
+        "UPDATE import_jobs SET status = 'completed', finished_at = datetime('now') WHERE id = ?",
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn record_progress(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    job_id: i64,
+    errors: &[ImportIssue],
+) -> Result<(), PaymeError> {
+    let errors = serde_json::to_string(errors).map_err(|e| PaymeError::Internal(e.to_string()))?;
+    sqlx::query("UPDATE import_jobs SET processed = processed + 1, errors = ? WHERE id = ?")
+        .bind(errors)
+        .bind(job_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/import/jobs/{id}",
+    params(("id" = i64, Path, description = "Import job ID")),
+    responses(
+        (status = 200, description = "Current progress of the import", body = ImportJob),
+        (status = 404, description = "No such job for this user")
+    ),
+    tag = "Data Management",
+    summary = "Get background import progress"
+)]
+pub async fn get_import_job(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<Json<ImportJob>, PaymeError> {
+    Ok(Json(find_import_job(&pool, claims.sub, id).await?))
+}
+
+fn summarize(issues: &[ImportIssue]) -> String {
+    issues
+        .iter()
+        .map(|e| format!("{}: {}", e.path, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[derive(sqlx::FromRow)]
+struct LocalItem {
+    id: i64,
+    amount: Money,
+    savings_destination: String,
+    savings_account_id: Option<i64>,
+    deleted_at: Option<String>,
+    changed_at: Option<String>,
+}
+
+/// Folds a bundle exported on another device into the user's data instead of replacing it.
+///
+/// Items are matched by `uid` and resolved last-write-wins: whichever side changed the item
+/// last, by `updated_at` (or `deleted_at` for a tombstone without one), is kept, so a newer
+/// deletion removes a local item and a newer edit brings a deleted one back. Ties and missing
+/// timestamps keep the local copy. Tombstones for items unknown here are ignored, and items
+/// without a `uid`, from exports made before items had one, are added as new. Local items keep
+/// their savings destination and their transfers are adjusted to match. Categories and months
+/// missing locally are created; balances, fixed expenses, income and budgets are left as they are.
+async fn merge_import(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+    data: &UserExport,
+    base_currency: &str,
+) -> Result<MergeReport, PaymeError> {
+    let mut report = MergeReport::default();
+
+    let mut category_map: HashMap<String, i64> = sqlx::query_as::<_, (String, i64)>(
+        "SELECT label, id FROM budget_categories WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .collect();
     for cat in &data.categories {
+        if category_map.contains_key(&cat.label) {
+            continue;
+        }
         let id: i64 = sqlx::query_scalar(
-            "INSERT INTO budget_categories (user_id, label, default_amount, color) VALUES (?, ?, ?, ?) RETURNING id",
+            "INSERT INTO budget_categories (user_id, label, default_amount, color, icon, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, (SELECT COALESCE(MAX(sort_order) + 1, 0) FROM budget_categories WHERE user_id = ?1)) RETURNING id",
         )
-        .bind(claims.sub)
+        .bind(user_id)
         .bind(&cat.label)
-        .bind(cat.default_amount)
+        .bind(Money::from_f64(cat.default_amount))
         .bind(&cat.color)
-        .fetch_one(&mut *tx)
+        .bind(&cat.icon)
+        .fetch_one(&mut **tx)
         .await?;
         category_map.insert(cat.label.clone(), id);
     }
 
     for month_data in &data.months {
-        let month_id: i64 = sqlx::query_scalar(
-            "INSERT INTO months (user_id, year, month, is_closed) VALUES (?, ?, ?, ?) RETURNING id",
+        let existing: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM months WHERE user_id = ? AND year = ? AND month = ?",
         )
-        .bind(claims.sub)
+        .bind(user_id)
         .bind(month_data.year)
         .bind(month_data.month)
-        .bind(month_data.is_closed)
-        .fetch_one(&mut *tx)
+        .fetch_optional(&mut **tx)
         .await?;
-
-        for income in &month_data.income_entries {
-            sqlx::query("INSERT INTO income_entries (month_id, label, amount) VALUES (?, ?, ?)")
-                .bind(month_id)
-                .bind(&income.label)
-                .bind(income.amount)
-                .execute(&mut *tx)
-                .await?;
-        }
-
-        for budget in &month_data.budgets {
-            if let Some(&cat_id) = category_map.get(&budget.category_label) {
-                sqlx::query(
-                    "INSERT INTO monthly_budgets (month_id, category_id, allocated_amount) VALUES (?, ?, ?)",
+        let month_id = match existing {
+            Some(id) => id,
+            None => {
+                sqlx::query_scalar(
+                    "INSERT INTO months (user_id, year, month, is_closed) VALUES (?, ?, ?, ?) RETURNING id",
                 )
-                .bind(month_id)
-                .bind(cat_id)
-                .bind(budget.allocated_amount)
-                .execute(&mut *tx)
-                .await?;
+                .bind(user_id)
+                .bind(month_data.year)
+                .bind(month_data.month)
+                .bind(month_data.is_closed)
+                .fetch_one(&mut **tx)
+                .await?
             }
-        }
+        };
 
         for item in &month_data.items {
-            if let Some(&cat_id) = category_map.get(&item.category_label) {
+            let Some(&cat_id) = category_map.get(&item.category_label) else {
+                continue;
+            };
+            let item_currency = match &item.currency {
+                Some(code) => currency::normalize(code)?,
+                None => base_currency.to_string(),
+            };
+            let changed_at = item.updated_at.as_ref().or(item.deleted_at.as_ref());
+
+            let local: Option<LocalItem> = match &item.uid {
+                Some(uid) => {
+                    sqlx::query_as(
+                        "SELECT i.id, i.amount, i.savings_destination, i.savings_account_id, i.deleted_at, COALESCE(i.updated_at, i.deleted_at) AS changed_at FROM items i JOIN months m ON i.month_id = m.id WHERE m.user_id = ? AND i.uid = ?",
+                    )
+                    .bind(user_id)
+                    .bind(uid)
+                    .fetch_optional(&mut **tx)
+                    .await?
+                }
+                None => None,
+            };
+
+            let Some(local) = local else {
+                if item.deleted_at.is_some() {
+                    report.unchanged += 1;
+                    continue;
+                }
                 sqlx::query(
-                    "INSERT INTO items (month_id, category_id, description, amount, spent_on) VALUES (?, ?, ?, ?, ?)",
+                    "INSERT INTO items (month_id, category_id, description, amount, spent_on, currency, uid, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
                 )
                 .bind(month_id)
                 .bind(cat_id)
                 .bind(&item.description)
-                .bind(item.amount)
+                .bind(Money::from_f64(item.amount))
                 .bind(&item.spent_on)
-                .execute(&mut *tx)
+                .bind(&item_currency)
+                .bind(&item.uid)
+                .bind(&item.updated_at)
+                .execute(&mut **tx)
                 .await?;
+                report.created += 1;
+                continue;
+            };
+
+            // Timestamps share one format, so they order as strings
+            let incoming_newer = match (changed_at, &local.changed_at) {
+                (Some(incoming), Some(existing)) => incoming > existing,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if !incoming_newer {
+                report.unchanged += 1;
+                continue;
+            }
+
+            match (&item.deleted_at, &local.deleted_at) {
+                (Some(deleted_at), None) => {
+                    sqlx::query("UPDATE items SET deleted_at = ?, updated_at = ? WHERE id = ?")
+                        .bind(deleted_at)
+                        .bind(changed_at)
+                        .bind(local.id)
+                        .execute(&mut **tx)
+                        .await?;
+                    adjust_savings(
+                        tx,
+                        user_id,
+                        &local.savings_destination,
+                        local.savings_account_id,
+                        -local.amount,
+                    )
+                    .await?;
+                    report.deleted += 1;
+                }
+                (Some(_), Some(_)) => {
+                    report.unchanged += 1;
+                }
+                (None, local_deleted) => {
+                    sqlx::query(
+                        "UPDATE items SET month_id = ?, category_id = ?, description = ?, amount = ?, spent_on = ?, currency = ?, deleted_at = NULL, updated_at = ?, version = version + 1 WHERE id = ?",
+                    )
+                    .bind(month_id)
+                    .bind(cat_id)
+                    .bind(&item.description)
+                    .bind(Money::from_f64(item.amount))
+                    .bind(&item.spent_on)
+                    .bind(&item_currency)
+                    .bind(changed_at)
+                    .bind(local.id)
+                    .execute(&mut **tx)
+                    .await?;
+                    let previous = if local_deleted.is_some() {
+                        Money::ZERO
+                    } else {
+                        local.amount
+                    };
+                    adjust_savings(
+                        tx,
+                        user_id,
+                        &local.savings_destination,
+                        local.savings_account_id,
+                        Money::from_f64(item.amount) - previous,
+                    )
+                    .await?;
+                    report.updated += 1;
+                }
             }
         }
     }
 
-    tx.commit().await?;
-    Ok(StatusCode::OK)
+    Ok(report)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CsvExportQuery {
+    pub year: i32,
+}
+
+const CSV_HEADER: &str = "date,category,description,amount,savings_destination,currency\r\n";
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn items_to_csv(rows: &[(NaiveDate, String, String, Money, String, String)]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    for (spent_on, category, description, amount, savings_destination, currency) in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\r\n",
+            spent_on,
+            csv_field(category),
+            csv_field(description),
+            amount,
+            csv_field(savings_destination),
+            csv_field(currency)
+        ));
+    }
+    csv
+}
+
+fn csv_response(filename: String, body: String) -> impl IntoResponse {
+    attachment("text/csv; charset=utf-8", filename, body)
+}
+
+fn attachment(content_type: &'static str, filename: String, body: String) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+}
+
+/// Transaction rows for a month's export: date, category, description, amount,
+/// savings destination and currency.
+async fn month_item_rows(
+    pool: &SqlitePool,
+    month_id: i64,
+) -> Result<Vec<(NaiveDate, String, String, Money, String, String)>, PaymeError> {
+    Ok(sqlx::query_as(
+        r#"
+        SELECT i.spent_on, bc.label, i.description, i.amount, i.savings_destination, i.currency
+        FROM items i
+        JOIN budget_categories bc ON i.category_id = bc.id
+        WHERE i.month_id = ? AND i.deleted_at IS NULL
+        ORDER BY i.spent_on, i.id
+        "#,
+    )
+    .bind(month_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// QIF fields are line-delimited, so embedded line breaks become spaces.
+fn qif_field(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// Renders items as a QIF bank register. Items leave the account, so amounts are negative,
+/// except withdrawals from savings; savings transfers use QIF's `[Account]` transfer syntax
+/// instead of a category.
+fn items_to_qif(rows: &[(NaiveDate, String, String, Money, String, String)]) -> String {
+    let mut qif = String::from("!Type:Bank\n");
+    for (spent_on, category, description, amount, savings_destination, _currency) in rows {
+        // `/` separates a class and `:` a subcategory in the L field
+        let (category, amount) = match savings_destination.as_str() {
+            "savings" => ("[Savings]".to_string(), -*amount),
+            "savings_withdrawal" => ("[Savings]".to_string(), *amount),
+            "retirement_savings" => ("[Retirement Savings]".to_string(), -*amount),
+            "retirement_savings_withdrawal" => ("[Retirement Savings]".to_string(), *amount),
+            "account_withdrawal" => (qif_field(category).replace(['/', ':'], "-"), *amount),
+            _ => (qif_field(category).replace(['/', ':'], "-"), -*amount),
+        };
+        qif.push_str(&format!(
+            "D{}\nT{}\nP{}\nL{}\n^\n",
+            spent_on.format("%m/%d/%Y"),
+            amount,
+            qif_field(description),
+            category
+        ));
+    }
+    qif
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/export.csv",
+    params(("id" = i64, Path, description = "Month ID")),
+    responses(
+        (status = 200, description = "CSV of the month's transactions", content_type = "text/csv"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Data Management",
+    summary = "Export month to CSV",
+    description = "Exports the month's transactions (date, category, description, amount, savings destination) as a CSV download."
+)]
+pub async fn export_month_csv(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<impl IntoResponse, PaymeError> {
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    let rows = month_item_rows(&pool, month_id).await?;
+
+    let filename = format!("payme-{}-{:02}.csv", month.year, month.month);
+    Ok(csv_response(filename, items_to_csv(&rows)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/export.qif",
+    params(("id" = i64, Path, description = "Month ID")),
+    responses(
+        (status = 200, description = "QIF bank register of the month's transactions", content_type = "application/qif"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Data Management",
+    summary = "Export month to QIF",
+    description = "Exports the month's transactions as a QIF bank register for desktop accounting software. Dates are MM/DD/YYYY, spending is negative and the budget category maps to the QIF category field."
+)]
+pub async fn export_month_qif(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<impl IntoResponse, PaymeError> {
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    let rows = month_item_rows(&pool, month_id).await?;
+
+    let filename = format!("payme-{}-{:02}.qif", month.year, month.month);
+    Ok(attachment("application/qif", filename, items_to_qif(&rows)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export.csv",
+    params(CsvExportQuery),
+    responses(
+        (status = 200, description = "CSV of all transactions in the year", content_type = "text/csv"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Data Management",
+    summary = "Export year to CSV",
+    description = "Exports every transaction across all of the user's months in the given year as a single CSV download."
+)]
+pub async fn export_year_csv(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<CsvExportQuery>,
+) -> Result<impl IntoResponse, PaymeError> {
+    let rows: Vec<(NaiveDate, String, String, Money, String, String)> = sqlx::query_as(
+        r#"
+        SELECT i.spent_on, bc.label, i.description, i.amount, i.savings_destination, i.currency
+        FROM items i
+        JOIN budget_categories bc ON i.category_id = bc.id
+        JOIN months m ON i.month_id = m.id
+        WHERE m.user_id = ? AND m.year = ? AND i.deleted_at IS NULL
+        ORDER BY m.month, i.spent_on, i.id
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(query.year)
+    .fetch_all(&pool)
+    .await?;
+
+    let filename = format!("payme-{}.csv", query.year);
+    Ok(csv_response(filename, items_to_csv(&rows)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_plain() {
+        assert_eq!(csv_field("Groceries"), "Groceries");
+    }
+
+    #[test]
+    fn test_items_to_qif() {
+        let rows = vec![
+            (
+                NaiveDate::from_ymd_opt(2024, 6, 5).unwrap(),
+                "Food/Dining".to_string(),
+                "Groceries\nweekly".to_string(),
+                Money::from_cents(4250),
+                "none".to_string(),
+                "USD".to_string(),
+            ),
+            (
+                NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+                "Savings".to_string(),
+                "Transfer".to_string(),
+                Money::from_cents(10000),
+                "savings".to_string(),
+                "USD".to_string(),
+            ),
+        ];
+        assert_eq!(
+            items_to_qif(&rows),
+            "!Type:Bank\nD06/05/2024\nT-42.50\nPGroceries weekly\nLFood-Dining\n^\n\
+             D06/30/2024\nT-100.00\nPTransfer\nL[Savings]\n^\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_field_escapes() {
+        assert_eq!(csv_field("Milk, eggs"), "\"Milk, eggs\"");
+        assert_eq!(csv_field("12\" pizza"), "\"12\"\" pizza\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
 }
@@ -6,26 +6,147 @@ use axum::{
 use serde::Deserialize;
 use sqlx::SqlitePool;
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use std::collections::BTreeMap;
 
 use crate::error::PaymeError;
 use crate::middleware::auth::Claims;
-use crate::models::FixedExpense;
+use crate::models::{FixedExpense, FixedExpenseGroup};
+use crate::money::{validate_cents, Money};
+
+/// Group name for fixed expenses without a category
+pub const UNCATEGORIZED: &str = "Uncategorized";
+
+/// `(label, amount, category, due_day)` of user `?1`'s active templates whose date range covers
+/// period `?2`, at the amount in effect that month: the latest scheduled change from that month
+/// or earlier, or for months before any change, the amount the first change replaced.
+pub const TEMPLATES_FOR_PERIOD: &str = "SELECT fe.label, COALESCE((SELECT a.amount FROM fixed_expense_amounts a WHERE a.fixed_expense_id = fe.id AND a.effective_from <= ?2 ORDER BY a.effective_from DESC LIMIT 1), (SELECT a.previous_amount FROM fixed_expense_amounts a WHERE a.fixed_expense_id = fe.id ORDER BY a.effective_from LIMIT 1), fe.amount) AS amount, fe.category, fe.due_day FROM fixed_expenses fe WHERE fe.user_id = ?1 AND fe.active = 1 AND (fe.start_month IS NULL OR fe.start_month <= ?2) AND (fe.end_month IS NULL OR fe.end_month >= ?2)";
+
+/// Amount of expense `?1` in effect for period `?2`, resolved as in [`TEMPLATES_FOR_PERIOD`].
+const AMOUNT_FOR_PERIOD: &str = "SELECT COALESCE((SELECT amount FROM fixed_expense_amounts WHERE fixed_expense_id = ?1 AND effective_from <= ?2 ORDER BY effective_from DESC LIMIT 1), (SELECT previous_amount FROM fixed_expense_amounts WHERE fixed_expense_id = ?1 ORDER BY effective_from LIMIT 1), (SELECT amount FROM fixed_expenses WHERE id = ?1))";
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateFixedExpense {
     #[validate(length(min = 1, max = 100))]
     pub label: String,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: f64,
+    #[validate(length(max = 50))]
+    pub category: Option<String>,
+    #[validate(range(min = 1, max = 31))]
+    pub due_day: Option<i64>,
+    /// Defaults to true; a paused expense stays listed but isn't copied into new months
+    pub active: Option<bool>,
+    /// First month (`YYYY-MM`) the expense is copied into
+    #[validate(custom(function = "validate_period"))]
+    pub start_month: Option<String>,
+    /// Last month (`YYYY-MM`) the expense is copied into
+    #[validate(custom(function = "validate_period"))]
+    pub end_month: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateFixedExpense {
     #[validate(length(min = 1, max = 100))]
     pub label: Option<String>,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: Option<f64>,
+    /// First month (`YYYY-MM`) the new `amount` applies to. Earlier months keep materializing the
+    /// amount that was in effect for them. Without it the amount applies to every month.
+    #[validate(custom(function = "validate_period"))]
+    pub effective_from: Option<String>,
+    /// New category; an empty string clears it
+    #[validate(length(max = 50))]
+    pub category: Option<String>,
+    /// New due day; 0 clears it
+    #[validate(range(min = 0, max = 31))]
+    pub due_day: Option<i64>,
+    pub active: Option<bool>,
+    /// New first month; an empty string removes the bound
+    #[validate(custom(function = "validate_period"))]
+    pub start_month: Option<String>,
+    /// New last month; an empty string removes the bound
+    #[validate(custom(function = "validate_period"))]
+    pub end_month: Option<String>,
+}
+
+/// A month as `YYYY-MM`, the form stored in `start_month` and `end_month`. Zero-padded so
+/// periods compare correctly as strings.
+pub fn period(year: i32, month: i32) -> String {
+    format!("{year:04}-{month:02}")
+}
+
+/// `validator` check for `start_month`/`end_month`: `YYYY-MM`, or empty to clear the bound.
+pub fn validate_period(value: &str) -> Result<(), ValidationError> {
+    let valid = value.is_empty()
+        || value.len() == 7
+            && value.as_bytes()[4] == b'-'
+            && value[..4].bytes().all(|b| b.is_ascii_digit())
+            && value[5..]
+                .parse::<u32>()
+                .is_ok_and(|month| (1..=12).contains(&month));
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("period").with_message("Months must look like 2024-06".into()))
+    }
+}
+
+/// Treats a blank bound as none, and rejects a range that ends before it starts.
+fn normalize_range(
+    start_month: Option<String>,
+    end_month: Option<String>,
+) -> Result<(Option<String>, Option<String>), PaymeError> {
+    let start_month = start_month.filter(|m| !m.is_empty());
+    let end_month = end_month.filter(|m| !m.is_empty());
+    if let (Some(start), Some(end)) = (&start_month, &end_month) {
+        if start > end {
+            return Err(PaymeError::BadRequest(
+                "end_month must not be before start_month".to_string(),
+            ));
+        }
+    }
+    Ok((start_month, end_month))
+}
+
+/// Treats a submitted due day of 0 as clearing it.
+pub fn normalize_due_day(due_day: i64) -> Option<i64> {
+    (due_day != 0).then_some(due_day)
+}
+
+/// Trims a submitted category, treating blank input as no category.
+pub fn normalize_category(category: Option<String>) -> Option<String> {
+    category
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+/// Sums `(category, amount)` pairs per category, alphabetically, with "Uncategorized" last.
+pub fn group_by_category<'a>(
+    expenses: impl IntoIterator<Item = (Option<&'a str>, Money)>,
+) -> Vec<FixedExpenseGroup> {
+    let mut groups: BTreeMap<&str, (Money, i64)> = BTreeMap::new();
+    let mut uncategorized: Option<(Money, i64)> = None;
+    for (category, amount) in expenses {
+        let entry = match category {
+            Some(name) => groups.entry(name).or_default(),
+            None => uncategorized.get_or_insert_default(),
+        };
+        entry.0 += amount;
+        entry.1 += 1;
+    }
+
+    groups
+        .into_iter()
+        .map(|(name, totals)| (name.to_string(), totals))
+        .chain(uncategorized.map(|totals| (UNCATEGORIZED.to_string(), totals)))
+        .map(|(category, (total, count))| FixedExpenseGroup {
+            category,
+            total,
+            count,
+        })
+        .collect()
 }
 
 #[utoipa::path(
@@ -43,15 +164,52 @@ pub async fn list_fixed_expenses(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
 ) -> Result<Json<Vec<FixedExpense>>, PaymeError> {
-    let expenses: Vec<FixedExpense> =
-        sqlx::query_as("SELECT id, user_id, label, amount FROM fixed_expenses WHERE user_id = ?")
-            .bind(claims.sub)
-            .fetch_all(&pool)
-            .await?;
+    let expenses: Vec<FixedExpense> = sqlx::query_as(
+        "SELECT id, user_id, label, amount, category, due_day, active, start_month, end_month FROM fixed_expenses WHERE user_id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
 
     Ok(Json(expenses))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/fixed-expenses/by-category",
+    responses(
+        (status = 200, body = [FixedExpenseGroup]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Configuration",
+    summary = "Fixed expense totals by category",
+    description = "Sums the user's active fixed expenses per category. Expenses without a category are grouped under \"Uncategorized\"."
+)]
+pub async fn fixed_expenses_by_category(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<FixedExpenseGroup>>, PaymeError> {
+    Ok(Json(load_groups(&pool, claims.sub).await?))
+}
+
+/// Groups the user's active fixed expense templates by category.
+pub async fn load_groups(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<FixedExpenseGroup>, PaymeError> {
+    let rows: Vec<(Option<String>, Money)> = sqlx::query_as(
+        "SELECT category, amount FROM fixed_expenses WHERE user_id = ? AND active = 1",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(group_by_category(
+        rows.iter()
+            .map(|(category, amount)| (category.as_deref(), *amount)),
+    ))
+}
+
 #[utoipa::path(
     post,
     path = "/api/fixed-expenses",
@@ -62,7 +220,7 @@ pub async fn list_fixed_expenses(
     ),
     tag = "Configuration",
     summary = "Create fixed expense",
-    description = "Adds a new recurring expense (e.g., Rent, Internet) to the user's profile."
+    description = "Adds a new recurring expense (e.g., Rent, Internet) to the user's profile. With `start_month` and/or `end_month` it is only copied into months within that range."
 )]
 pub async fn create_fixed_expense(
     State(pool): State<SqlitePool>,
@@ -70,12 +228,21 @@ pub async fn create_fixed_expense(
     Json(payload): Json<CreateFixedExpense>,
 ) -> Result<Json<FixedExpense>, PaymeError> {
     payload.validate()?;
+    let category = normalize_category(payload.category);
+    let active = payload.active.unwrap_or(true);
+    let (start_month, end_month) = normalize_range(payload.start_month, payload.end_month)?;
+    let amount = Money::from_f64(payload.amount);
     let id: i64 = sqlx::query_scalar(
-        "INSERT INTO fixed_expenses (user_id, label, amount) VALUES (?, ?, ?) RETURNING id",
+        "INSERT INTO fixed_expenses (user_id, label, amount, category, due_day, active, start_month, end_month) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
     )
     .bind(claims.sub)
     .bind(&payload.label)
-    .bind(payload.amount)
+    .bind(amount)
+    .bind(&category)
+    .bind(payload.due_day)
+    .bind(active)
+    .bind(&start_month)
+    .bind(&end_month)
     .fetch_one(&pool)
     .await?;
 
@@ -83,7 +250,12 @@ pub async fn create_fixed_expense(
         id,
         user_id: claims.sub,
         label: payload.label,
-        amount: payload.amount,
+        amount,
+        category,
+        due_day: payload.due_day,
+        active,
+        start_month,
+        end_month,
     }))
 }
 
@@ -99,7 +271,7 @@ pub async fn create_fixed_expense(
     ),
     tag = "Configuration",
     summary = "Update fixed expense",
-    description = "Updates the label or amount of an existing fixed expense by ID."
+    description = "Updates the label, amount, category or due day of an existing fixed expense by ID, or pauses it with `active: false` so new months skip it. Its date range can be changed the same way, with an empty string removing a bound. An amount sent with `effective_from` is scheduled from that month on, keeping earlier months at their old amount; the returned amount is the latest scheduled one. Months already created keep the amounts they were created with."
 )]
 pub async fn update_fixed_expense(
     State(pool): State<SqlitePool>,
@@ -109,7 +281,7 @@ pub async fn update_fixed_expense(
 ) -> Result<Json<FixedExpense>, PaymeError> {
     payload.validate()?;
     let existing: FixedExpense = sqlx::query_as(
-        "SELECT id, user_id, label, amount FROM fixed_expenses WHERE id = ? AND user_id = ?",
+        "SELECT id, user_id, label, amount, category, due_day, active, start_month, end_month FROM fixed_expenses WHERE id = ? AND user_id = ?",
     )
     .bind(expense_id)
     .bind(claims.sub)
@@ -118,23 +290,96 @@ pub async fn update_fixed_expense(
     .ok_or(PaymeError::NotFound)?;
 
     let label = payload.label.unwrap_or(existing.label);
-    let amount = payload.amount.unwrap_or(existing.amount);
+    let category = match payload.category {
+        Some(category) => normalize_category(Some(category)),
+        None => existing.category,
+    };
+    let due_day = payload.due_day.map_or(existing.due_day, normalize_due_day);
+    let active = payload.active.unwrap_or(existing.active);
+    let (start_month, end_month) = normalize_range(
+        payload.start_month.or(existing.start_month),
+        payload.end_month.or(existing.end_month),
+    )?;
 
-    sqlx::query("UPDATE fixed_expenses SET label = ?, amount = ? WHERE id = ?")
-        .bind(&label)
-        .bind(amount)
-        .bind(expense_id)
-        .execute(&pool)
-        .await?;
+    let mut tx = pool.begin().await?;
+    let effective_from = payload.effective_from.filter(|m| !m.is_empty());
+    let amount = match (payload.amount.map(Money::from_f64), effective_from) {
+        (Some(amount), Some(from)) => schedule_amount(&mut tx, expense_id, &from, amount).await?,
+        (Some(amount), None) => {
+            sqlx::query("DELETE FROM fixed_expense_amounts WHERE fixed_expense_id = ?")
+                .bind(expense_id)
+                .execute(&mut *tx)
+                .await?;
+            amount
+        }
+        (None, Some(_)) => {
+            return Err(PaymeError::BadRequest(
+                "effective_from requires an amount".to_string(),
+            ))
+        }
+        (None, None) => existing.amount,
+    };
+
+    sqlx::query(
+        "UPDATE fixed_expenses SET label = ?, amount = ?, category = ?, due_day = ?, active = ?, start_month = ?, end_month = ? WHERE id = ?",
+    )
+    .bind(&label)
+    .bind(amount)
+    .bind(&category)
+    .bind(due_day)
+    .bind(active)
+    .bind(&start_month)
+    .bind(&end_month)
+    .bind(expense_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
 
     Ok(Json(FixedExpense {
         id: expense_id,
         user_id: claims.sub,
         label,
         amount,
+        category,
+        due_day,
+        active,
+        start_month,
+        end_month,
     }))
 }
 
+/// Records `amount` as in effect from month `from` onwards, replacing a change already scheduled
+/// for that month. Returns the latest scheduled amount, which becomes the template's amount.
+async fn schedule_amount(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    expense_id: i64,
+    from: &str,
+    amount: Money,
+) -> Result<Money, PaymeError> {
+    let previous: Money = sqlx::query_scalar(AMOUNT_FOR_PERIOD)
+        .bind(expense_id)
+        .bind(from)
+        .fetch_one(&mut **tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO fixed_expense_amounts (fixed_expense_id, effective_from, amount, previous_amount) VALUES (?, ?, ?, ?) ON CONFLICT(fixed_expense_id, effective_from) DO UPDATE SET amount = excluded.amount",
+    )
+    .bind(expense_id)
+    .bind(from)
+    .bind(amount)
+    .bind(previous)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(sqlx::query_scalar(
+        "SELECT amount FROM fixed_expense_amounts WHERE fixed_expense_id = ? ORDER BY effective_from DESC LIMIT 1",
+    )
+    .bind(expense_id)
+    .fetch_one(&mut **tx)
+    .await?)
+}
+
 #[utoipa::path(
     delete,
     path = "/api/fixed-expenses/{id}",
@@ -149,11 +394,20 @@ pub async fn delete_fixed_expense(
     axum::Extension(claims): axum::Extension<Claims>,
     Path(expense_id): Path<i64>,
 ) -> Result<StatusCode, PaymeError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM fixed_expense_amounts WHERE fixed_expense_id = (SELECT id FROM fixed_expenses WHERE id = ? AND user_id = ?)",
+    )
+    .bind(expense_id)
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await?;
     sqlx::query("DELETE FROM fixed_expenses WHERE id = ? AND user_id = ?")
         .bind(expense_id)
         .bind(claims.sub)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
+    tx.commit().await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use axum::{extract::State, http::StatusCode, Json};
 use serde::Serialize;
 use sqlx::SqlitePool;
@@ -6,23 +8,43 @@ use sqlx::SqlitePool;
 pub struct HealthResponse {
     pub status: &'static str,
     pub database: &'static str,
+    /// Round trip of a `SELECT 1`, including waiting for a connection
+    pub db_latency_ms: f64,
+    pub pool: PoolStats,
 }
 
-pub async fn health_check(
-    State(pool): State<SqlitePool>,
-) -> Result<Json<HealthResponse>, StatusCode> {
-    let db_status = sqlx::query("SELECT 1")
-        .fetch_one(&pool)
-        .await
-        .map(|_| "connected")
-        .unwrap_or("disconnected");
+#[derive(Serialize)]
+pub struct PoolStats {
+    /// Open connections, idle or in use
+    pub size: u32,
+    pub idle: usize,
+    pub max_connections: u32,
+}
 
-    if db_status == "connected" {
-        Ok(Json(HealthResponse {
-            status: "healthy",
-            database: db_status,
-        }))
+/// Reports 503 with the same body when the database can't answer a trivial query, so a
+/// readiness probe fails instead of routing traffic to an instance with a dead pool.
+pub async fn health_check(State(pool): State<SqlitePool>) -> (StatusCode, Json<HealthResponse>) {
+    let started = Instant::now();
+    let connected = sqlx::query("SELECT 1").fetch_one(&pool).await.is_ok();
+    let db_latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let (code, status, database) = if connected {
+        (StatusCode::OK, "healthy", "connected")
     } else {
-        Err(StatusCode::SERVICE_UNAVAILABLE)
-    }
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy", "disconnected")
+    };
+
+    (
+        code,
+        Json(HealthResponse {
+            status,
+            database,
+            db_latency_ms,
+            pool: PoolStats {
+                size: pool.size(),
+                idle: pool.num_idle(),
+                max_connections: pool.options().get_max_connections(),
+            },
+        }),
+    )
 }
@@ -0,0 +1,295 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::error::PaymeError;
+use crate::middleware::auth::Claims;
+use crate::models::{Household, HouseholdMember, Month};
+use crate::timezone;
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateHousehold {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct InviteMember {
+    #[validate(length(min = 1))]
+    pub username: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/households",
+    responses(
+        (status = 200, body = [Household]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Households",
+    summary = "List households",
+    description = "Lists households the user belongs to, including invites that are still pending."
+)]
+pub async fn list_households(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<Household>>, PaymeError> {
+    let households: Vec<Household> = sqlx::query_as(
+        r#"
+        SELECT h.id, h.name, hm.role, hm.accepted_at IS NOT NULL AS accepted
+        FROM households h
+        JOIN household_members hm ON hm.household_id = h.id
+        WHERE hm.user_id = ?
+        ORDER BY h.id
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(households))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/households",
+    request_body = CreateHousehold,
+    responses(
+        (status = 201, body = Household),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Households",
+    summary = "Create household",
+    description = "Creates a household with the caller as its owner."
+)]
+pub async fn create_household(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<CreateHousehold>,
+) -> Result<(StatusCode, Json<Household>), PaymeError> {
+    payload.validate()?;
+
+    let mut tx = pool.begin().await?;
+    let id: i64 = sqlx::query_scalar("INSERT INTO households (name) VALUES (?) RETURNING id")
+        .bind(&payload.name)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO household_members (household_id, user_id, role, accepted_at) VALUES (?, ?, 'owner', datetime('now'))",
+    )
+    .bind(id)
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Household {
+            id,
+            name: payload.name,
+            role: "owner".to_string(),
+            accepted: true,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/households/{id}/members",
+    params(("id" = i64, Path, description = "Household ID")),
+    responses(
+        (status = 200, body = [HouseholdMember]),
+        (status = 404, description = "Household not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Households",
+    summary = "List household members",
+    description = "Lists members and pending invitees. Only visible to members."
+)]
+pub async fn list_members(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(household_id): Path<i64>,
+) -> Result<Json<Vec<HouseholdMember>>, PaymeError> {
+    member_role(&pool, household_id, claims.sub).await?;
+
+    let members: Vec<HouseholdMember> = sqlx::query_as(
+        r#"
+        SELECT hm.user_id, u.username, hm.role, hm.accepted_at IS NOT NULL AS accepted
+        FROM household_members hm
+        JOIN users u ON u.id = hm.user_id
+        WHERE hm.household_id = ?
+        ORDER BY hm.id
+        "#,
+    )
+    .bind(household_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(members))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/households/{id}/invite",
+    params(("id" = i64, Path, description = "Household ID")),
+    request_body = InviteMember,
+    responses(
+        (status = 201, body = HouseholdMember),
+        (status = 403, description = "Only owners can invite"),
+        (status = 404, description = "Household or user not found"),
+        (status = 409, description = "User already invited"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Households",
+    summary = "Invite member",
+    description = "Invites an existing user to the household. They gain access once they accept."
+)]
+pub async fn invite_member(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(household_id): Path<i64>,
+    Json(payload): Json<InviteMember>,
+) -> Result<(StatusCode, Json<HouseholdMember>), PaymeError> {
+    payload.validate()?;
+    if member_role(&pool, household_id, claims.sub).await? != "owner" {
+        return Err(PaymeError::Forbidden);
+    }
+
+    let user_id: i64 = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+        .bind(&payload.username)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+
+    let result = sqlx::query(
+        "INSERT INTO household_members (household_id, user_id, role, invited_by) VALUES (?, ?, 'member', ?) ON CONFLICT(household_id, user_id) DO NOTHING",
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(PaymeError::Conflict(
+            "User is already a member or invited".to_string(),
+        ));
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(HouseholdMember {
+            user_id,
+            username: payload.username,
+            role: "member".to_string(),
+            accepted: false,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/households/{id}/accept",
+    params(("id" = i64, Path, description = "Household ID")),
+    responses(
+        (status = 200, body = Household),
+        (status = 404, description = "No pending invite"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Households",
+    summary = "Accept invite",
+    description = "Accepts a pending invite, granting access to the household's shared months."
+)]
+pub async fn accept_invite(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(household_id): Path<i64>,
+) -> Result<Json<Household>, PaymeError> {
+    let result = sqlx::query(
+        "UPDATE household_members SET accepted_at = datetime('now') WHERE household_id = ? AND user_id = ? AND accepted_at IS NULL",
+    )
+    .bind(household_id)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(PaymeError::NotFound);
+    }
+
+    let household: Household = sqlx::query_as(
+        r#"
+        SELECT h.id, h.name, hm.role, hm.accepted_at IS NOT NULL AS accepted
+        FROM households h
+        JOIN household_members hm ON hm.household_id = h.id
+        WHERE h.id = ? AND hm.user_id = ?
+        "#,
+    )
+    .bind(household_id)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(household))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/households/{id}/months/{month_id}",
+    params(
+        ("id" = i64, Path, description = "Household ID"),
+        ("month_id" = i64, Path, description = "Month ID")
+    ),
+    responses(
+        (status = 200, body = Month),
+        (status = 404, description = "Household or month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Households",
+    summary = "Share month",
+    description = "Shares one of the caller's months with the household so every member can view and edit it."
+)]
+pub async fn share_month(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path((household_id, month_id)): Path<(i64, i64)>,
+) -> Result<Json<Month>, PaymeError> {
+    member_role(&pool, household_id, claims.sub).await?;
+
+    let month: Month = sqlx::query_as(
+        "UPDATE months SET household_id = ? WHERE id = ? AND user_id = ? RETURNING id, user_id, year, month, is_closed, closed_at, is_locked",
+    )
+    .bind(household_id)
+    .bind(month_id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+
+    Ok(Json(month.as_of(timezone::today(&pool, claims.sub).await?)))
+}
+
+/// Role of an accepted member. Non-members get `NotFound` so household ids aren't probeable.
+async fn member_role(
+    pool: &SqlitePool,
+    household_id: i64,
+    user_id: i64,
+) -> Result<String, PaymeError> {
+    sqlx::query_scalar(
+        "SELECT role FROM household_members WHERE household_id = ? AND user_id = ? AND accepted_at IS NOT NULL",
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)
+}
@@ -8,24 +8,88 @@ use sqlx::SqlitePool;
 use utoipa::ToSchema;
 use validator::Validate;
 
+use crate::config::BusyRetry;
+use crate::db;
 use crate::error::PaymeError;
+use crate::handlers::items::{verify_month_access, verify_month_not_closed};
 use crate::middleware::auth::Claims;
 use crate::models::IncomeEntry;
+use crate::money::{validate_cents, Money};
+
+pub const INCOME_FREQUENCIES: [&str; 5] = ["monthly", "biweekly", "weekly", "annual", "one_time"];
+
+fn default_frequency() -> String {
+    "monthly".to_string()
+}
+
+fn verify_frequency(frequency: &str) -> Result<(), PaymeError> {
+    if INCOME_FREQUENCIES.contains(&frequency) {
+        Ok(())
+    } else {
+        Err(PaymeError::BadRequest(format!(
+            "frequency must be one of: {}",
+            INCOME_FREQUENCIES.join(", ")
+        )))
+    }
+}
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateIncome {
     #[validate(length(min = 1, max = 100))]
     pub label: String,
-    #[validate(range(min = 0.0))]
+    /// Net amount
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: f64,
+    /// Pay before withholding; must equal `amount + withholding` when both are given
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
+    pub gross_amount: Option<f64>,
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
+    pub withholding: Option<f64>,
+    /// How often `amount` is paid; defaults to `monthly`
+    #[serde(default = "default_frequency")]
+    pub frequency: String,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateIncome {
     #[validate(length(min = 1, max = 100))]
     pub label: Option<String>,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: Option<f64>,
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
+    pub gross_amount: Option<f64>,
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
+    pub withholding: Option<f64>,
+    pub frequency: Option<String>,
+}
+
+/// Works out `(gross_amount, withholding)` for a net `amount`. Whichever of the two is
+/// missing is derived from the other; with neither, nothing was withheld.
+fn split_gross(
+    amount: Money,
+    gross_amount: Option<Money>,
+    withholding: Option<Money>,
+) -> Result<(Money, Money), PaymeError> {
+    match (gross_amount, withholding) {
+        (Some(gross), Some(withholding)) => {
+            if gross != amount + withholding {
+                return Err(PaymeError::BadRequest(
+                    "gross_amount must equal amount plus withholding".to_string(),
+                ));
+            }
+            Ok((gross, withholding))
+        }
+        (Some(gross), None) => {
+            if gross < amount {
+                return Err(PaymeError::BadRequest(
+                    "gross_amount must not be less than amount".to_string(),
+                ));
+            }
+            Ok((gross, gross - amount))
+        }
+        (None, Some(withholding)) => Ok((amount + withholding, withholding)),
+        (None, None) => Ok((amount, Money::ZERO)),
+    }
 }
 
 #[utoipa::path(
@@ -47,7 +111,7 @@ pub async fn list_income(
     verify_month_access(&pool, claims.sub, month_id).await?;
 
     let entries: Vec<IncomeEntry> =
-        sqlx::query_as("SELECT id, month_id, label, amount FROM income_entries WHERE month_id = ?")
+        sqlx::query_as("SELECT id, month_id, label, amount, gross_amount, withholding, frequency, monthly_amount FROM income_entries WHERE month_id = ?")
             .bind(month_id)
             .fetch_all(&pool)
             .await?;
@@ -61,35 +125,54 @@ pub async fn list_income(
     request_body = CreateIncome,
     responses(
         (status = 200, body = IncomeEntry),
+        (status = 400, description = "Gross amount doesn't match amount plus withholding"),
         (status = 500, description = "Internal server error")   
     ),
     tag = "Income",
     summary = "Add income entry",
-    description = "Records a new income source for the month. Only available if the month is open."
+    description = "Records a new income source for the month. Only available if the month is open. `amount` is the net pay; when only it is given, nothing is treated as withheld. A `frequency` other than `monthly` is converted to a monthly figure for totals and percentage budgets, while the entry keeps the amount as entered."
 )]
 pub async fn create_income(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
     Path(month_id): Path<i64>,
     Json(payload): Json<CreateIncome>,
 ) -> Result<Json<IncomeEntry>, PaymeError> {
     payload.validate()?;
+    verify_frequency(&payload.frequency)?;
     verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    let amount = Money::from_f64(payload.amount);
+    let (gross_amount, withholding) = split_gross(
+        amount,
+        payload.gross_amount.map(Money::from_f64),
+        payload.withholding.map(Money::from_f64),
+    )?;
 
-    let id: i64 = sqlx::query_scalar(
-        "INSERT INTO income_entries (month_id, label, amount) VALUES (?, ?, ?) RETURNING id",
-    )
-    .bind(month_id)
-    .bind(&payload.label)
-    .bind(payload.amount)
-    .fetch_one(&pool)
+    let (id, monthly_amount): (i64, Money) = db::retry_busy(&busy_retry, || async {
+        Ok(sqlx::query_as(
+            "INSERT INTO income_entries (month_id, label, amount, gross_amount, withholding, frequency) VALUES (?, ?, ?, ?, ?, ?) RETURNING id, monthly_amount",
+        )
+        .bind(month_id)
+        .bind(&payload.label)
+        .bind(amount)
+        .bind(gross_amount)
+        .bind(withholding)
+        .bind(&payload.frequency)
+        .fetch_one(&pool)
+        .await?)
+    })
     .await?;
 
     Ok(Json(IncomeEntry {
         id,
         month_id,
         label: payload.label,
-        amount: payload.amount,
+        amount,
+        gross_amount: Some(gross_amount),
+        withholding,
+        frequency: payload.frequency,
+        monthly_amount,
     }))
 }
 
@@ -103,15 +186,17 @@ pub async fn create_income(
     request_body = UpdateIncome,
     responses(
         (status = 200, description = "Income updated successfully", body = IncomeEntry),
+        (status = 400, description = "Gross amount doesn't match amount plus withholding"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Income",
     summary = "Update income entry",
-    description = "Modifies an existing income record's label or amount."
+    description = "Modifies an existing income record's label, net amount, gross amount, withholding or frequency. Withholding is kept unless a new gross amount or withholding is given."
 )]
 pub async fn update_income(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
     Path((month_id, income_id)): Path<(i64, i64)>,
     Json(payload): Json<UpdateIncome>,
 ) -> Result<Json<IncomeEntry>, PaymeError> {
@@ -119,7 +204,7 @@ pub async fn update_income(
     verify_month_not_closed(&pool, claims.sub, month_id).await?;
 
     let existing: IncomeEntry = sqlx::query_as(
-        "SELECT id, month_id, label, amount FROM income_entries WHERE id = ? AND month_id = ?",
+        "SELECT id, month_id, label, amount, gross_amount, withholding, frequency, monthly_amount FROM income_entries WHERE id = ? AND month_id = ?",
     )
     .bind(income_id)
     .bind(month_id)
@@ -128,20 +213,42 @@ pub async fn update_income(
     .ok_or(PaymeError::NotFound)?;
 
     let label = payload.label.unwrap_or(existing.label);
-    let amount = payload.amount.unwrap_or(existing.amount);
+    let amount = payload.amount.map_or(existing.amount, Money::from_f64);
+    let gross_amount = payload.gross_amount.map(Money::from_f64);
+    let withholding = payload.withholding.map(Money::from_f64);
+    // A new gross re-derives withholding; otherwise the existing withholding is kept
+    let withholding = match gross_amount {
+        Some(_) => withholding,
+        None => Some(withholding.unwrap_or(existing.withholding)),
+    };
+    let (gross_amount, withholding) = split_gross(amount, gross_amount, withholding)?;
+    let frequency = payload.frequency.unwrap_or(existing.frequency);
+    verify_frequency(&frequency)?;
 
-    sqlx::query("UPDATE income_entries SET label = ?, amount = ? WHERE id = ?")
+    let monthly_amount: Money = db::retry_busy(&busy_retry, || async {
+        Ok(sqlx::query_scalar(
+            "UPDATE income_entries SET label = ?, amount = ?, gross_amount = ?, withholding = ?, frequency = ? WHERE id = ? RETURNING monthly_amount",
+        )
         .bind(&label)
         .bind(amount)
+        .bind(gross_amount)
+        .bind(withholding)
+        .bind(&frequency)
         .bind(income_id)
-        .execute(&pool)
-        .await?;
+        .fetch_one(&pool)
+        .await?)
+    })
+    .await?;
 
     Ok(Json(IncomeEntry {
         id: income_id,
         month_id,
         label,
         amount,
+        gross_amount: Some(gross_amount),
+        withholding,
+        frequency,
+        monthly_amount,
     }))
 }
 
@@ -163,49 +270,20 @@ pub async fn update_income(
 pub async fn delete_income(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
     Path((month_id, income_id)): Path<(i64, i64)>,
 ) -> Result<StatusCode, PaymeError> {
     verify_month_not_closed(&pool, claims.sub, month_id).await?;
 
-    sqlx::query("DELETE FROM income_entries WHERE id = ? AND month_id = ?")
-        .bind(income_id)
-        .bind(month_id)
-        .execute(&pool)
-        .await?;
-
-    Ok(StatusCode::NO_CONTENT)
-}
-
-async fn verify_month_access(
-    pool: &SqlitePool,
-    user_id: i64,
-    month_id: i64,
-) -> Result<(), PaymeError> {
-    let exists: Option<(i64,)> =
-        sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
+    db::retry_busy(&busy_retry, || async {
+        sqlx::query("DELETE FROM income_entries WHERE id = ? AND month_id = ?")
+            .bind(income_id)
             .bind(month_id)
-            .bind(user_id)
-            .fetch_optional(pool)
-            .await?;
-
-    exists.map(|_| ()).ok_or(PaymeError::NotFound)
-}
-
-async fn verify_month_not_closed(
-    pool: &SqlitePool,
-    user_id: i64,
-    month_id: i64,
-) -> Result<(), PaymeError> {
-    let month: Option<(bool,)> =
-        sqlx::query_as("SELECT is_closed FROM months WHERE id = ? AND user_id = ?")
-            .bind(month_id)
-            .bind(user_id)
-            .fetch_optional(pool)
+            .execute(&pool)
             .await?;
+        Ok(())
+    })
+    .await?;
 
-    match month {
-        Some((true,)) => Err(PaymeError::BadRequest("Month is closed".to_string())),
-        Some((false,)) => Ok(()),
-        None => Err(PaymeError::NotFound),
-    }
+    Ok(StatusCode::NO_CONTENT)
 }
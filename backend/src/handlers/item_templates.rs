@@ -0,0 +1,300 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::config::{BusyRetry, WebhookPolicy};
+use crate::error::PaymeError;
+use crate::handlers::items::{self, CreateItem, CreateItemQuery, SAVINGS_DESTINATIONS};
+use crate::middleware::auth::Claims;
+use crate::models::{Item, ItemTemplate};
+use crate::money::Money;
+use crate::timezone;
+
+fn default_savings_destination() -> String {
+    "none".to_string()
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateItemTemplate {
+    pub category_id: i64,
+    #[validate(length(min = 1, max = 200))]
+    pub description: String,
+    #[validate(range(min = 0.0))]
+    pub default_amount: f64,
+    #[serde(default = "default_savings_destination")]
+    pub savings_destination: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateItemTemplate {
+    pub category_id: Option<i64>,
+    #[validate(length(min = 1, max = 200))]
+    pub description: Option<String>,
+    #[validate(range(min = 0.0))]
+    pub default_amount: Option<f64>,
+    pub savings_destination: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Validate, Default)]
+pub struct ItemFromTemplate {
+    /// Overrides the template's default amount
+    #[validate(range(min = 0.0))]
+    pub amount: Option<f64>,
+    /// Defaults to today
+    pub spent_on: Option<NaiveDate>,
+}
+
+async fn verify_template_fields(
+    pool: &SqlitePool,
+    user_id: i64,
+    category_id: i64,
+    savings_destination: &str,
+) -> Result<(), PaymeError> {
+    if !SAVINGS_DESTINATIONS.contains(&savings_destination) {
+        return Err(PaymeError::BadRequest(
+            "Invalid savings destination".to_string(),
+        ));
+    }
+
+    let _category: (i64,) =
+        sqlx::query_as("SELECT id FROM budget_categories WHERE id = ? AND user_id = ?")
+            .bind(category_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(PaymeError::BadRequest("Invalid category".to_string()))?;
+
+    Ok(())
+}
+
+async fn find_template(
+    pool: &SqlitePool,
+    user_id: i64,
+    template_id: i64,
+) -> Result<ItemTemplate, PaymeError> {
+    sqlx::query_as(
+        "SELECT id, user_id, category_id, description, default_amount, savings_destination FROM item_templates WHERE id = ? AND user_id = ?",
+    )
+    .bind(template_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/item-templates",
+    responses(
+        (status = 200, body = [ItemTemplate]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "List item templates",
+    description = "Retrieves the user's reusable templates for recurring transactions."
+)]
+pub async fn list_item_templates(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<ItemTemplate>>, PaymeError> {
+    let templates: Vec<ItemTemplate> = sqlx::query_as(
+        "SELECT id, user_id, category_id, description, default_amount, savings_destination FROM item_templates WHERE user_id = ? ORDER BY description, id",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(templates))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/item-templates",
+    request_body = CreateItemTemplate,
+    responses(
+        (status = 201, body = ItemTemplate),
+        (status = 400, description = "Invalid category or savings destination"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Create item template",
+    description = "Saves a category, description, default amount and savings destination for transactions that recur with small variations."
+)]
+pub async fn create_item_template(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<CreateItemTemplate>,
+) -> Result<(StatusCode, Json<ItemTemplate>), PaymeError> {
+    payload.validate()?;
+    verify_template_fields(
+        &pool,
+        claims.sub,
+        payload.category_id,
+        &payload.savings_destination,
+    )
+    .await?;
+
+    let default_amount = Money::from_f64(payload.default_amount);
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO item_templates (user_id, category_id, description, default_amount, savings_destination) VALUES (?, ?, ?, ?, ?) RETURNING id",
+    )
+    .bind(claims.sub)
+    .bind(payload.category_id)
+    .bind(&payload.description)
+    .bind(default_amount)
+    .bind(&payload.savings_destination)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ItemTemplate {
+            id,
+            user_id: claims.sub,
+            category_id: payload.category_id,
+            description: payload.description,
+            default_amount,
+            savings_destination: payload.savings_destination,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/item-templates/{id}",
+    params(("id" = i64, Path, description = "Template ID")),
+    request_body = UpdateItemTemplate,
+    responses(
+        (status = 200, body = ItemTemplate),
+        (status = 400, description = "Invalid category or savings destination"),
+        (status = 404, description = "Template not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Update item template"
+)]
+pub async fn update_item_template(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(template_id): Path<i64>,
+    Json(payload): Json<UpdateItemTemplate>,
+) -> Result<Json<ItemTemplate>, PaymeError> {
+    payload.validate()?;
+    let existing = find_template(&pool, claims.sub, template_id).await?;
+
+    let category_id = payload.category_id.unwrap_or(existing.category_id);
+    let description = payload.description.unwrap_or(existing.description);
+    let default_amount = payload
+        .default_amount
+        .map_or(existing.default_amount, Money::from_f64);
+    let savings_destination = payload
+        .savings_destination
+        .unwrap_or(existing.savings_destination);
+    verify_template_fields(&pool, claims.sub, category_id, &savings_destination).await?;
+
+    sqlx::query(
+        "UPDATE item_templates SET category_id = ?, description = ?, default_amount = ?, savings_destination = ? WHERE id = ?",
+    )
+    .bind(category_id)
+    .bind(&description)
+    .bind(default_amount)
+    .bind(&savings_destination)
+    .bind(template_id)
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(ItemTemplate {
+        id: template_id,
+        user_id: claims.sub,
+        category_id,
+        description,
+        default_amount,
+        savings_destination,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/item-templates/{id}",
+    params(("id" = i64, Path, description = "Template ID")),
+    responses((status = 204, description = "Deleted")),
+    tag = "Items",
+    summary = "Delete item template"
+)]
+pub async fn delete_item_template(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(template_id): Path<i64>,
+) -> Result<StatusCode, PaymeError> {
+    sqlx::query("DELETE FROM item_templates WHERE id = ? AND user_id = ?")
+        .bind(template_id)
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{id}/items/from-template/{template_id}",
+    params(
+        ("id" = i64, Path, description = "Month ID"),
+        ("template_id" = i64, Path, description = "Template ID")
+    ),
+    request_body = ItemFromTemplate,
+    responses(
+        (status = 200, body = Item),
+        (status = 400, description = "Month is closed"),
+        (status = 404, description = "Month or template not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Record transaction from template",
+    description = "Creates an item in the month from a template, optionally overriding the amount and date. Savings transfers are applied exactly as for a manually created item."
+)]
+pub async fn create_item_from_template(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    busy_retry: axum::Extension<BusyRetry>,
+    webhook_policy: axum::Extension<WebhookPolicy>,
+    Path((month_id, template_id)): Path<(i64, i64)>,
+    payload: Option<Json<ItemFromTemplate>>,
+) -> Result<Json<Item>, PaymeError> {
+    let Json(payload) = payload.unwrap_or_default();
+    payload.validate()?;
+    let template = find_template(&pool, claims.sub, template_id).await?;
+
+    let item = CreateItem {
+        category_id: template.category_id,
+        description: template.description,
+        amount: payload.amount.unwrap_or(template.default_amount.to_f64()),
+        spent_on: match payload.spent_on {
+            Some(date) => date,
+            None => timezone::today(&pool, claims.sub).await?,
+        },
+        savings_destination: template.savings_destination,
+        savings_account_id: None,
+        currency: None,
+        allow_overdraft: false,
+    };
+
+    let Json(created) = items::create_item(
+        State(pool),
+        axum::Extension(claims),
+        busy_retry,
+        webhook_policy,
+        Path(month_id),
+        Query(CreateItemQuery::default()),
+        Json(item),
+    )
+    .await?;
+    Ok(Json(created.item))
+}
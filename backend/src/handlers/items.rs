@@ -1,32 +1,161 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use chrono::NaiveDate;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use utoipa::ToSchema;
-use validator::Validate;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
 
+use crate::config::{BusyRetry, WebhookPolicy};
+use crate::currency;
+use crate::db;
 use crate::error::PaymeError;
+use crate::handlers::months::find_user_month;
+use crate::handlers::{budget, savings, savings_accounts, stats};
 use crate::middleware::auth::Claims;
 use crate::models::{Item, ItemWithCategory};
+use crate::money::{validate_cents, Money};
+use crate::webhooks;
+
+/// How long a deleted item can be restored before it is purged
+pub const RESTORE_WINDOW_DAYS: i64 = 30;
+
+/// Where an item's amount goes: `none` is spending, the others transfer into or, for the
+/// `_withdrawal` variants, out of a savings balance
+pub const SAVINGS_DESTINATIONS: [&str; 5] = [
+    "none",
+    "savings",
+    "retirement_savings",
+    "savings_withdrawal",
+    "retirement_savings_withdrawal",
+];
+/// Destinations that transfer into or out of the savings account named by `savings_account_id`
+pub const ACCOUNT_DESTINATIONS: [&str; 2] = ["account", "account_withdrawal"];
 
 fn default_savings_destination() -> String {
     "none".to_string()
 }
 
+/// `validator` check that a savings destination is one of the known ones, so a typo is rejected
+/// instead of being recorded as spending.
+pub fn validate_savings_destination(destination: &str) -> Result<(), ValidationError> {
+    if SAVINGS_DESTINATIONS.contains(&destination) || ACCOUNT_DESTINATIONS.contains(&destination) {
+        return Ok(());
+    }
+    let known: Vec<&str> = SAVINGS_DESTINATIONS
+        .iter()
+        .chain(ACCOUNT_DESTINATIONS.iter())
+        .copied()
+        .collect();
+    Err(ValidationError::new("savings_destination")
+        .with_message(format!("Must be one of {}", known.join(", ")).into()))
+}
+
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateItem {
     pub category_id: i64,
     #[validate(length(min = 1, max = 200))]
     pub description: String,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: f64,
     pub spent_on: NaiveDate,
     #[serde(default = "default_savings_destination")]
+    #[validate(custom(function = "validate_savings_destination"))]
     pub savings_destination: String,
+    /// Required for the `account` and `account_withdrawal` destinations
+    pub savings_account_id: Option<i64>,
+    /// ISO 4217 code; defaults to the user's base currency
+    pub currency: Option<String>,
+    /// Records a withdrawal even if it takes the savings balance below zero
+    #[serde(default)]
+    pub allow_overdraft: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MoveItem {
+    pub target_month_id: i64,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct BulkDeleteItems {
+    #[validate(length(min = 1, max = 500))]
+    pub item_ids: Vec<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkDeleteResult {
+    pub deleted: Vec<i64>,
+    /// Requested ids that aren't live items in the month; these are left untouched
+    pub not_found: Vec<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateItemQuery {
+    /// Include `warnings` in the response when the item takes its category over budget
+    #[serde(default)]
+    pub warn: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedItem {
+    #[serde(flatten)]
+    pub item: Item,
+    /// Only present when requested with `?warn=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<BudgetWarning>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BudgetWarning {
+    pub category_id: i64,
+    pub category_overspent: bool,
+    #[schema(value_type = f64)]
+    pub budgeted: Money,
+    /// Category spending for the month in the base currency, including the new item
+    #[schema(value_type = f64)]
+    pub actual: Money,
+    #[schema(value_type = f64)]
+    pub over_by: Money,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchItemsQuery {
+    /// Text to look for in descriptions, ignoring case
+    pub q: Option<String>,
+    /// Earliest `spent_on` date to include
+    pub from: Option<NaiveDate>,
+    /// Latest `spent_on` date to include
+    pub to: Option<NaiveDate>,
+    pub category_id: Option<i64>,
+    /// Results per page, 1-100 (defaults to 50)
+    pub limit: Option<i64>,
+    /// Results to skip, for later pages
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ItemSearchResult {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub item: ItemWithCategory,
+    /// Year and month of the month the item belongs to
+    pub year: i32,
+    pub month: i32,
+    pub is_closed: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ItemSearchPage {
+    pub items: Vec<ItemSearchResult>,
+    /// Matches across all pages
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -34,10 +163,18 @@ pub struct UpdateItem {
     pub category_id: Option<i64>,
     #[validate(length(min = 1, max = 200))]
     pub description: Option<String>,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: Option<f64>,
     pub spent_on: Option<NaiveDate>,
+    #[validate(custom(function = "validate_savings_destination"))]
     pub savings_destination: Option<String>,
+    pub savings_account_id: Option<i64>,
+    pub currency: Option<String>,
+    /// The `version` the edit was based on
+    pub version: i64,
+    /// Records a withdrawal even if it takes the savings balance below zero
+    #[serde(default)]
+    pub allow_overdraft: bool,
 }
 
 #[utoipa::path(
@@ -49,7 +186,7 @@ pub struct UpdateItem {
     ),
     tag = "Items",
     summary = "List transactions",
-    description = "Retrieves all itemized spending for the month, including category labels."
+    description = "Retrieves all itemized spending for the month, including category labels. Items whose category no longer exists are still listed, labelled \"Missing category\"."
 )]
 pub async fn list_items(
     State(pool): State<SqlitePool>,
@@ -60,10 +197,11 @@ pub async fn list_items(
 
     let items: Vec<ItemWithCategory> = sqlx::query_as(
         r#"
-        SELECT i.id, i.month_id, i.category_id, bc.label as category_label, bc.color as category_color, i.description, i.amount, i.spent_on, i.savings_destination
+        SELECT i.id, i.month_id, i.category_id, COALESCE(bc.label, 'Missing category') as category_label, COALESCE(bc.color, '#71717a') as category_color, bc.icon as category_icon, i.description, i.amount, i.spent_on, i.savings_destination, i.savings_account_id, i.currency, i.version,
+               EXISTS(SELECT 1 FROM receipts r WHERE r.item_id = i.id) AS has_receipt
         FROM items i
-        JOIN budget_categories bc ON i.category_id = bc.id
-        WHERE i.month_id = ?
+        LEFT JOIN budget_categories bc ON i.category_id = bc.id
+        WHERE i.month_id = ? AND i.deleted_at IS NULL
         ORDER BY i.spent_on DESC
         "#,
     )
@@ -74,78 +212,467 @@ pub async fn list_items(
     Ok(Json(items))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/items/search",
+    params(SearchItemsQuery),
+    responses(
+        (status = 200, body = ItemSearchPage),
+        (status = 400, description = "Invalid date range or page"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Search transactions",
+    description = "Finds the user's items across all of their months, closed ones included, newest first. Each result carries the year and month it belongs to. Every filter is optional; results come in pages of `limit` starting at `offset`."
+)]
+pub async fn search_items(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<SearchItemsQuery>,
+) -> Result<Json<ItemSearchPage>, PaymeError> {
+    let limit = query.limit.unwrap_or(50);
+    if !(1..=100).contains(&limit) {
+        return Err(PaymeError::BadRequest(
+            "limit must be between 1 and 100".to_string(),
+        ));
+    }
+    let offset = query.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(PaymeError::BadRequest(
+            "offset cannot be negative".to_string(),
+        ));
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(PaymeError::BadRequest(
+                "from must not be after to".to_string(),
+            ));
+        }
+    }
+    // Matched literally, so % and _ in the search text aren't wildcards
+    let pattern = query
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(|q| {
+            let escaped = q
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{escaped}%")
+        });
+
+    let total: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        WHERE m.user_id = ?1 AND i.deleted_at IS NULL
+          AND (?2 IS NULL OR i.description LIKE ?2 ESCAPE '\')
+          AND (?3 IS NULL OR i.spent_on >= ?3)
+          AND (?4 IS NULL OR i.spent_on <= ?4)
+          AND (?5 IS NULL OR i.category_id = ?5)
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(&pattern)
+    .bind(query.from)
+    .bind(query.to)
+    .bind(query.category_id)
+    .fetch_one(&pool)
+    .await?;
+
+    let items: Vec<ItemSearchResult> = sqlx::query_as(
+        r#"
+        SELECT i.id, i.month_id, i.category_id, bc.label as category_label, bc.color as category_color, bc.icon as category_icon, i.description, i.amount, i.spent_on, i.savings_destination, i.savings_account_id, i.currency, i.version,
+               EXISTS(SELECT 1 FROM receipts r WHERE r.item_id = i.id) AS has_receipt,
+               m.year, m.month, m.is_closed
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        JOIN budget_categories bc ON i.category_id = bc.id
+        WHERE m.user_id = ?1 AND i.deleted_at IS NULL
+          AND (?2 IS NULL OR i.description LIKE ?2 ESCAPE '\')
+          AND (?3 IS NULL OR i.spent_on >= ?3)
+          AND (?4 IS NULL OR i.spent_on <= ?4)
+          AND (?5 IS NULL OR i.category_id = ?5)
+        ORDER BY i.spent_on DESC, i.id DESC
+        LIMIT ?6 OFFSET ?7
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(&pattern)
+    .bind(query.from)
+    .bind(query.to)
+    .bind(query.category_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ItemSearchPage {
+        items,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/items/orphaned",
+    responses(
+        (status = 200, body = [ItemSearchResult]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Find items without a category",
+    description = "Lists the items in the user's months whose category is gone or isn't one of the user's, newest first, so they can be moved to a real category."
+)]
+pub async fn list_orphaned_items(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<ItemSearchResult>>, PaymeError> {
+    let items: Vec<ItemSearchResult> = sqlx::query_as(
+        r#"
+        SELECT i.id, i.month_id, i.category_id, 'Missing category' as category_label, '#71717a' as category_color, NULL as category_icon, i.description, i.amount, i.spent_on, i.savings_destination, i.savings_account_id, i.currency, i.version,
+               EXISTS(SELECT 1 FROM receipts r WHERE r.item_id = i.id) AS has_receipt,
+               m.year, m.month, m.is_closed
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        LEFT JOIN budget_categories bc ON i.category_id = bc.id AND bc.user_id = m.user_id
+        WHERE m.user_id = ? AND i.deleted_at IS NULL AND bc.id IS NULL
+        ORDER BY i.spent_on DESC, i.id DESC
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(items))
+}
+
 #[utoipa::path(
     post, path = "/api/months/{id}/items",
-    params(("id" = i64, Path)),
+    params(("id" = i64, Path), CreateItemQuery),
     request_body = CreateItem,
     responses(
-        (status = 200, body = Item),
+        (status = 200, body = CreatedItem),
+        (status = 400, description = "Invalid savings destination, or a withdrawal exceeds the balance"),
+        (status = 409, description = "The category enforces its budget and the expense doesn't fit"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Items",
     summary = "Record transaction",
-    description = "Logs a new expense against a specific budget category."
+    description = "Logs a new expense against a specific budget category. With `?warn=true` the response also lists a warning when the expense leaves its category over its budget for the month; categories without a budget never warn."
 )]
 pub async fn create_item(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
     Path(month_id): Path<i64>,
+    Query(query): Query<CreateItemQuery>,
     Json(payload): Json<CreateItem>,
-) -> Result<Json<Item>, PaymeError> {
+) -> Result<Json<CreatedItem>, PaymeError> {
     payload.validate()?;
-    verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    let savings_account_id = verify_savings_destination(
+        &pool,
+        owner,
+        &payload.savings_destination,
+        payload.savings_account_id,
+    )
+    .await?;
 
     let _category: (i64,) =
         sqlx::query_as("SELECT id FROM budget_categories WHERE id = ? AND user_id = ?")
             .bind(payload.category_id)
-            .bind(claims.sub)
+            .bind(owner)
             .fetch_optional(&pool)
             .await?
             .ok_or(PaymeError::BadRequest("Invalid category".to_string()))?;
 
-    let id: i64 = sqlx::query_scalar(
-        "INSERT INTO items (month_id, category_id, description, amount, spent_on, savings_destination) VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
-    )
-    .bind(month_id)
-    .bind(payload.category_id)
-    .bind(&payload.description)
-    .bind(payload.amount)
-    .bind(payload.spent_on)
-    .bind(&payload.savings_destination)
-    .fetch_one(&pool)
-    .await?;
+    let currency = match &payload.currency {
+        Some(code) => currency::normalize(code)?,
+        None => currency::base_currency(&pool, owner).await?,
+    };
+    let amount = Money::from_f64(payload.amount);
 
-    match payload.savings_destination.as_str() {
-        "savings" => {
-            sqlx::query("UPDATE users SET savings = savings + ? WHERE id = ?")
-                .bind(payload.amount)
-                .bind(claims.sub)
-                .execute(&pool)
-                .await?;
-        }
-        "retirement_savings" => {
-            sqlx::query(
-                "UPDATE users SET retirement_savings = retirement_savings + ? WHERE id = ?",
-            )
-            .bind(payload.amount)
-            .bind(claims.sub)
-            .execute(&pool)
-            .await?;
-        }
-        _ => {}
+    if payload.savings_destination == "none" {
+        enforce_budget(
+            &pool,
+            owner,
+            month_id,
+            payload.category_id,
+            (&currency, amount, payload.spent_on),
+            None,
+        )
+        .await?;
     }
 
-    Ok(Json(Item {
+    let savings_before = savings::current_savings(&pool, owner).await?;
+    let accounts_before = if payload.savings_destination == "account" {
+        savings_accounts::load_accounts(&pool, owner).await?
+    } else {
+        Vec::new()
+    };
+    let spent_before = spent_before(&pool, owner, month_id, payload.category_id).await;
+    let id = db::retry_busy(&busy_retry, || async {
+        let mut tx = pool.begin().await?;
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO items (month_id, category_id, description, amount, spent_on, savings_destination, savings_account_id, currency) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(month_id)
+        .bind(payload.category_id)
+        .bind(&payload.description)
+        .bind(amount)
+        .bind(payload.spent_on)
+        .bind(&payload.savings_destination)
+        .bind(savings_account_id)
+        .bind(&currency)
+        .fetch_one(&mut *tx)
+        .await?;
+        adjust_savings(
+            &mut tx,
+            owner,
+            &payload.savings_destination,
+            savings_account_id,
+            amount,
+        )
+        .await?;
+        check_overdraft(
+            &mut tx,
+            owner,
+            &payload.savings_destination,
+            savings_account_id,
+            payload.allow_overdraft,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(id)
+    })
+    .await?;
+
+    let item = Item {
         id,
         month_id,
         category_id: payload.category_id,
         description: payload.description,
-        amount: payload.amount,
+        amount,
         spent_on: payload.spent_on,
         savings_destination: payload.savings_destination,
+        savings_account_id,
+        currency,
+        version: 1,
+    };
+    if let Ok(data) = serde_json::to_value(&item) {
+        webhooks::enqueue(&pool, webhook_policy, owner, webhooks::ITEM_CREATED, data).await;
+    }
+    if item.savings_destination == "savings" {
+        savings::notify_if_goal_reached(&pool, owner, savings_before).await;
+    }
+    if item.savings_destination == "account" {
+        savings_accounts::notify_goals_reached(&pool, webhook_policy, owner, &accounts_before)
+            .await;
+    }
+    notify_if_threshold_crossed(&pool, webhook_policy, owner, &item, spent_before).await;
+
+    let warnings = if query.warn {
+        // The item is already saved, so a failure here drops the warning rather than the request
+        match overspend_warning(&pool, owner, &item).await {
+            Ok(warning) => Some(warning.into_iter().collect()),
+            Err(e) => {
+                tracing::warn!("Failed to check budget for item {}: {}", item.id, e);
+                Some(vec![])
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(Json(CreatedItem { item, warnings }))
+}
+
+/// A warning when `item`'s category has a budget for the month and spending now exceeds it.
+async fn overspend_warning(
+    pool: &SqlitePool,
+    owner: i64,
+    item: &Item,
+) -> Result<Option<BudgetWarning>, PaymeError> {
+    if item.savings_destination != "none" {
+        return Ok(None);
+    }
+    let Some((budgeted, actual)) =
+        category_budget_use(pool, owner, item.month_id, item.category_id).await?
+    else {
+        return Ok(None);
+    };
+
+    if actual <= budgeted {
+        return Ok(None);
+    }
+    Ok(Some(BudgetWarning {
+        category_id: item.category_id,
+        category_overspent: true,
+        budgeted,
+        actual,
+        over_by: actual - budgeted,
     }))
 }
 
+/// The category's budget for the month and the spending against it in the base currency, or
+/// `None` when the category has no budget that month.
+async fn category_budget_use(
+    pool: &SqlitePool,
+    owner: i64,
+    month_id: i64,
+    category_id: i64,
+) -> Result<Option<(Money, Money)>, PaymeError> {
+    let budgeted = budget::month_budgets(pool, month_id)
+        .await?
+        .into_iter()
+        .find(|b| b.category_id == category_id)
+        .map(|b| b.allocated_amount);
+    let Some(budgeted) = budgeted.filter(|b| *b > Money::ZERO) else {
+        return Ok(None);
+    };
+
+    let items: Vec<(String, Money, NaiveDate)> = sqlx::query_as(
+        "SELECT currency, amount, spent_on FROM items WHERE month_id = ? AND category_id = ? AND savings_destination = 'none' AND deleted_at IS NULL",
+    )
+    .bind(month_id)
+    .bind(category_id)
+    .fetch_all(pool)
+    .await?;
+    let amounts: Vec<_> = items
+        .iter()
+        .map(|(c, amount, on)| (c.as_str(), *amount, *on))
+        .collect();
+    let base_currency = currency::base_currency(pool, owner).await?;
+    let rates = currency::UserRates::new(pool, owner);
+    let actual = currency::sum_in_base(&rates, &base_currency, &amounts).await?;
+
+    Ok(Some((budgeted, actual)))
+}
+
+/// Rejects an `expense` that would take a category with `enforce_budget` set past its budget for
+/// the month, with how much still fits. `replacing` is the item being edited, whose amount is
+/// already part of the category's spending. Categories without a budget are never enforced.
+async fn enforce_budget(
+    pool: &SqlitePool,
+    owner: i64,
+    month_id: i64,
+    category_id: i64,
+    expense: (&str, Money, NaiveDate),
+    replacing: Option<(&str, Money, NaiveDate)>,
+) -> Result<(), PaymeError> {
+    let enforced: bool =
+        sqlx::query_scalar("SELECT enforce_budget FROM budget_categories WHERE id = ?")
+            .bind(category_id)
+            .fetch_one(pool)
+            .await?;
+    if !enforced {
+        return Ok(());
+    }
+    let Some((budgeted, actual)) = category_budget_use(pool, owner, month_id, category_id).await?
+    else {
+        return Ok(());
+    };
+
+    let base_currency = currency::base_currency(pool, owner).await?;
+    let rates = currency::UserRates::new(pool, owner);
+    let amount = currency::sum_in_base(&rates, &base_currency, &[expense]).await?;
+    let replaced = match replacing {
+        Some(item) => currency::sum_in_base(&rates, &base_currency, &[item]).await?,
+        None => Money::ZERO,
+    };
+
+    let remaining = (budgeted - (actual - replaced)).max(Money::ZERO);
+    if amount > remaining {
+        return Err(PaymeError::Conflict(format!(
+            "This expense would exceed the category's budget; {} {} still fits this month",
+            remaining, base_currency
+        )));
+    }
+    Ok(())
+}
+
+/// Spending in the category before a change, for `notify_if_threshold_crossed`. Errors are
+/// logged and give `None`, which skips the notification rather than failing the change.
+async fn spent_before(
+    pool: &SqlitePool,
+    owner: i64,
+    month_id: i64,
+    category_id: i64,
+) -> Option<Money> {
+    match category_budget_use(pool, owner, month_id, category_id).await {
+        Ok(used) => used.map(|(_, actual)| actual),
+        Err(e) => {
+            tracing::warn!("Failed to check budget for category {}: {}", category_id, e);
+            None
+        }
+    }
+}
+
+/// Fires `budget.threshold_crossed` when `item` has just taken its category's spending from
+/// below the owner's critical alert threshold to at or above it. Spending that is already past
+/// the threshold doesn't fire again.
+async fn notify_if_threshold_crossed(
+    pool: &SqlitePool,
+    webhook_policy: WebhookPolicy,
+    owner: i64,
+    item: &Item,
+    spent_before: Option<Money>,
+) {
+    let Some(before) = spent_before else {
+        return;
+    };
+    if item.savings_destination != "none" {
+        return;
+    }
+    let checked = async {
+        let thresholds = stats::load_thresholds(pool, owner).await?;
+        let used = category_budget_use(pool, owner, item.month_id, item.category_id).await?;
+        let label: String = sqlx::query_scalar("SELECT label FROM budget_categories WHERE id = ?")
+            .bind(item.category_id)
+            .fetch_one(pool)
+            .await?;
+        Ok::<_, PaymeError>((thresholds.critical_percent, used, label))
+    };
+    let (threshold, budgeted, actual, label) = match checked.await {
+        Ok((threshold, Some((budgeted, actual)), label)) => (threshold, budgeted, actual, label),
+        Ok((_, None, _)) => return,
+        Err(e) => {
+            tracing::warn!("Failed to check budget for item {}: {}", item.id, e);
+            return;
+        }
+    };
+
+    let percent_before = before.to_f64() / budgeted.to_f64() * 100.0;
+    let percent_used = actual.to_f64() / budgeted.to_f64() * 100.0;
+    if percent_before >= threshold || percent_used < threshold {
+        return;
+    }
+    webhooks::enqueue(
+        pool,
+        webhook_policy,
+        owner,
+        webhooks::BUDGET_THRESHOLD_CROSSED,
+        serde_json::json!({
+            "month_id": item.month_id,
+            "category_id": item.category_id,
+            "category_label": label,
+            "item_id": item.id,
+            "budgeted": budgeted,
+            "actual": actual,
+            "percent_used": percent_used,
+            "threshold_percent": threshold,
+        }),
+    )
+    .await;
+}
+
 #[utoipa::path(
     put,
     path = "/api/months/{month_id}/items/{id}",
@@ -156,109 +683,148 @@ pub async fn create_item(
     request_body = UpdateItem,
     responses(
         (status = 200, description = "Item updated successfully", body = Item),
+        (status = 400, description = "Invalid savings destination, or a withdrawal exceeds the balance"),
         (status = 404, description = "Item not found"),
+        (status = 409, description = "Item was changed since `version`, or the category enforces its budget and the change doesn't fit"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Items",
     summary = "Update transaction details",
-    description = "Updates an existing transaction. Supports partial updates for category, description, amount, or date."
+    description = "Updates an existing transaction. Supports partial updates for category, description, amount, date, or currency. The request must carry the item's current `version`; if it has been updated since, nothing is changed and 409 is returned."
 )]
 pub async fn update_item(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
     Path((month_id, item_id)): Path<(i64, i64)>,
     Json(payload): Json<UpdateItem>,
 ) -> Result<Json<Item>, PaymeError> {
     payload.validate()?;
-    verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
 
     let existing: Item = sqlx::query_as(
-        "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination FROM items WHERE id = ? AND month_id = ?",
+        "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination, savings_account_id, currency, version FROM items WHERE id = ? AND month_id = ? AND deleted_at IS NULL",
     )
     .bind(item_id)
     .bind(month_id)
     .fetch_optional(&pool)
     .await?
     .ok_or(PaymeError::NotFound)?;
+    if existing.version != payload.version {
+        return Err(stale_item());
+    }
 
     let category_id = payload.category_id.unwrap_or(existing.category_id);
+    // Already counted in the category's spending, so an edit only adds the difference
+    let replacing = (existing.savings_destination == "none" && existing.category_id == category_id)
+        .then(|| {
+            (
+                existing.currency.clone(),
+                existing.amount,
+                existing.spent_on,
+            )
+        });
     let description = payload.description.unwrap_or(existing.description);
-    let amount = payload.amount.unwrap_or(existing.amount);
+    let amount = payload.amount.map_or(existing.amount, Money::from_f64);
     let spent_on = payload.spent_on.unwrap_or(existing.spent_on);
     let savings_destination = payload
         .savings_destination
         .unwrap_or(existing.savings_destination.clone());
+    let savings_account_id = verify_savings_destination(
+        &pool,
+        owner,
+        &savings_destination,
+        payload.savings_account_id.or(existing.savings_account_id),
+    )
+    .await?;
+    let currency = match &payload.currency {
+        Some(code) => currency::normalize(code)?,
+        None => existing.currency,
+    };
 
     if payload.category_id.is_some() {
         let _category: (i64,) =
             sqlx::query_as("SELECT id FROM budget_categories WHERE id = ? AND user_id = ?")
                 .bind(category_id)
-                .bind(claims.sub)
+                .bind(owner)
                 .fetch_optional(&pool)
                 .await?
                 .ok_or(PaymeError::BadRequest("Invalid category".to_string()))?;
     }
 
-    // Update the item first to ensure data consistency
-    sqlx::query(
-        "UPDATE items SET category_id = ?, description = ?, amount = ?, spent_on = ?, savings_destination = ? WHERE id = ?",
-    )
-    .bind(category_id)
-    .bind(&description)
-    .bind(amount)
-    .bind(spent_on)
-    .bind(&savings_destination)
-    .bind(item_id)
-    .execute(&pool)
-    .await?;
+    if savings_destination == "none" {
+        enforce_budget(
+            &pool,
+            owner,
+            month_id,
+            category_id,
+            (&currency, amount, spent_on),
+            replacing
+                .as_ref()
+                .map(|(c, amount, on)| (c.as_str(), *amount, *on)),
+        )
+        .await?;
+    }
 
-    let old_dest = existing.savings_destination.as_str();
-    let new_dest = savings_destination.as_str();
-
-    // Adjust balances only after successful item update
-    if old_dest != new_dest || (old_dest != "none" && existing.amount != amount) {
-        match old_dest {
-            "savings" => {
-                sqlx::query("UPDATE users SET savings = savings - ? WHERE id = ?")
-                    .bind(existing.amount)
-                    .bind(claims.sub)
-                    .execute(&pool)
-                    .await?;
-            }
-            "retirement_savings" => {
-                sqlx::query(
-                    "UPDATE users SET retirement_savings = retirement_savings - ? WHERE id = ?",
-                )
-                .bind(existing.amount)
-                .bind(claims.sub)
-                .execute(&pool)
-                .await?;
-            }
-            _ => {}
+    let spent_before = spent_before(&pool, owner, month_id, category_id).await;
+    // The version check makes a concurrent edit lose here, before it can apply its savings
+    // adjustment a second time
+    db::retry_busy(&busy_retry, || async {
+        let mut tx = pool.begin().await?;
+        let updated = sqlx::query(
+            "UPDATE items SET category_id = ?, description = ?, amount = ?, spent_on = ?, savings_destination = ?, savings_account_id = ?, currency = ?, version = version + 1, updated_at = datetime('now') WHERE id = ? AND version = ?",
+        )
+        .bind(category_id)
+        .bind(&description)
+        .bind(amount)
+        .bind(spent_on)
+        .bind(&savings_destination)
+        .bind(savings_account_id)
+        .bind(&currency)
+        .bind(item_id)
+        .bind(payload.version)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(stale_item());
         }
 
-        match new_dest {
-            "savings" => {
-                sqlx::query("UPDATE users SET savings = savings + ? WHERE id = ?")
-                    .bind(amount)
-                    .bind(claims.sub)
-                    .execute(&pool)
-                    .await?;
-            }
-            "retirement_savings" => {
-                sqlx::query(
-                    "UPDATE users SET retirement_savings = retirement_savings + ? WHERE id = ?",
-                )
-                .bind(amount)
-                .bind(claims.sub)
-                .execute(&pool)
-                .await?;
-            }
-            _ => {}
+        if existing.savings_destination != savings_destination
+            || existing.savings_account_id != savings_account_id
+            || existing.amount != amount
+        {
+            adjust_savings(
+                &mut tx,
+                owner,
+                &existing.savings_destination,
+                existing.savings_account_id,
+                -existing.amount,
+            )
+            .await?;
+            adjust_savings(
+                &mut tx,
+                owner,
+                &savings_destination,
+                savings_account_id,
+                amount,
+            )
+            .await?;
+            check_overdraft(
+                &mut tx,
+                owner,
+                &savings_destination,
+                savings_account_id,
+                payload.allow_overdraft,
+            )
+            .await?;
         }
-    }
+        tx.commit().await?;
+        Ok(())
+    })
+    .await?;
 
-    Ok(Json(Item {
+    let item = Item {
         id: item_id,
         month_id,
         category_id,
@@ -266,7 +832,17 @@ pub async fn update_item(
         amount,
         spent_on,
         savings_destination,
-    }))
+        savings_account_id,
+        currency,
+        version: payload.version + 1,
+    };
+    notify_if_threshold_crossed(&pool, webhook_policy, owner, &item, spent_before).await;
+
+    Ok(Json(item))
+}
+
+fn stale_item() -> PaymeError {
+    PaymeError::Conflict("Item was changed by another request; reload it and try again".to_string())
 }
 
 #[utoipa::path(
@@ -282,17 +858,18 @@ pub async fn update_item(
     ),
     tag = "Items",
     summary = "Delete transaction",
-    description = "Permanently removes a transaction from the month's spending list."
+    description = "Removes a transaction from the month's spending list and reverses any transfer to savings. It can be restored for 30 days."
 )]
 pub async fn delete_item(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
     Path((month_id, item_id)): Path<(i64, i64)>,
 ) -> Result<StatusCode, PaymeError> {
-    verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
 
     let item: Item = sqlx::query_as(
-        "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination FROM items WHERE id = ? AND month_id = ?",
+        "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination, savings_account_id, currency, version FROM items WHERE id = ? AND month_id = ? AND deleted_at IS NULL",
     )
     .bind(item_id)
     .bind(month_id)
@@ -300,65 +877,413 @@ pub async fn delete_item(
     .await?
     .ok_or(PaymeError::NotFound)?;
 
-    match item.savings_destination.as_str() {
-        "savings" => {
-            sqlx::query("UPDATE users SET savings = savings - ? WHERE id = ?")
-                .bind(item.amount)
-                .bind(claims.sub)
-                .execute(&pool)
-                .await?;
-        }
-        "retirement_savings" => {
-            sqlx::query(
-                "UPDATE users SET retirement_savings = retirement_savings - ? WHERE id = ?",
-            )
-            .bind(item.amount)
-            .bind(claims.sub)
-            .execute(&pool)
-            .await?;
+    db::retry_busy(&busy_retry, || async {
+        let mut tx = pool.begin().await?;
+        // A concurrent delete may have got there first; only the one that deletes it debits
+        let deleted = sqlx::query(
+            "UPDATE items SET deleted_at = datetime('now'), updated_at = datetime('now') WHERE id = ? AND month_id = ? AND deleted_at IS NULL",
+        )
+        .bind(item_id)
+        .bind(month_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if deleted != 1 {
+            return Err(PaymeError::NotFound);
         }
-        _ => {}
+        adjust_savings(
+            &mut tx,
+            owner,
+            &item.savings_destination,
+            item.savings_account_id,
+            -item.amount,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{month_id}/items/{id}/move",
+    params(
+        ("month_id" = i64, Path, description = "Month ID"),
+        ("id" = i64, Path, description = "Item (Transaction) ID")
+    ),
+    request_body = MoveItem,
+    responses(
+        (status = 200, description = "Item moved", body = Item),
+        (status = 400, description = "Either month is closed, or the months have different owners"),
+        (status = 404, description = "Item or month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Move transaction to another month",
+    description = "Reassigns an item to another open month of the same owner, keeping its id. Savings balances are left as they are since the transfer itself doesn't change."
+)]
+pub async fn move_item(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path((month_id, item_id)): Path<(i64, i64)>,
+    Json(payload): Json<MoveItem>,
+) -> Result<Json<Item>, PaymeError> {
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    let target_owner = verify_month_not_closed(&pool, claims.sub, payload.target_month_id).await?;
+    // Categories and savings balances belong to the month's owner
+    if target_owner != owner {
+        return Err(PaymeError::BadRequest(
+            "Items can only move between months of the same owner".to_string(),
+        ));
     }
 
-    sqlx::query("DELETE FROM items WHERE id = ? AND month_id = ?")
+    let item: Item = sqlx::query_as(
+        "UPDATE items SET month_id = ?, version = version + 1, updated_at = datetime('now') WHERE id = ? AND month_id = ? AND deleted_at IS NULL RETURNING id, month_id, category_id, description, amount, spent_on, savings_destination, savings_account_id, currency, version",
+    )
+    .bind(payload.target_month_id)
+    .bind(item_id)
+    .bind(month_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+
+    Ok(Json(item))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{id}/items/delete",
+    params(("id" = i64, Path, description = "Month ID")),
+    request_body = BulkDeleteItems,
+    responses(
+        (status = 200, body = BulkDeleteResult),
+        (status = 400, description = "Month is closed or no ids given"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Delete several transactions",
+    description = "Deletes the given items from the month and reverses their transfers to savings, all in one transaction. Ids that aren't items of this month are reported in `not_found` and skipped. Deleted items can be restored for 30 days."
+)]
+pub async fn bulk_delete_items(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+    Json(payload): Json<BulkDeleteItems>,
+) -> Result<Json<BulkDeleteResult>, PaymeError> {
+    payload.validate()?;
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
+
+    let mut item_ids = payload.item_ids;
+    item_ids.sort_unstable();
+    item_ids.dedup();
+
+    let mut result = BulkDeleteResult {
+        deleted: vec![],
+        not_found: vec![],
+    };
+    let mut tx = pool.begin().await?;
+    for item_id in item_ids {
+        let item: Option<(Money, String, Option<i64>)> = sqlx::query_as(
+            "SELECT amount, savings_destination, savings_account_id FROM items WHERE id = ? AND month_id = ? AND deleted_at IS NULL",
+        )
         .bind(item_id)
         .bind(month_id)
-        .execute(&pool)
+        .fetch_optional(&mut *tx)
         .await?;
+        let Some((amount, savings_destination, savings_account_id)) = item else {
+            result.not_found.push(item_id);
+            continue;
+        };
 
-    Ok(StatusCode::NO_CONTENT)
+        sqlx::query(
+            "UPDATE items SET deleted_at = datetime('now'), updated_at = datetime('now') WHERE id = ?",
+        )
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        adjust_savings(
+            &mut tx,
+            owner,
+            &savings_destination,
+            savings_account_id,
+            -amount,
+        )
+        .await?;
+        result.deleted.push(item_id);
+    }
+    tx.commit().await?;
+
+    Ok(Json(result))
 }
 
-async fn verify_month_access(
+#[utoipa::path(
+    post,
+    path = "/api/months/{month_id}/items/{id}/restore",
+    params(
+        ("month_id" = i64, Path, description = "Month ID"),
+        ("id" = i64, Path, description = "Item (Transaction) ID")
+    ),
+    responses(
+        (status = 200, description = "Item restored successfully", body = Item),
+        (status = 404, description = "No deleted item within the restore window"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Restore deleted transaction",
+    description = "Undoes a delete made within the last 30 days and re-applies any transfer to savings."
+)]
+pub async fn restore_item(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path((month_id, item_id)): Path<(i64, i64)>,
+) -> Result<Json<Item>, PaymeError> {
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
+
+    let item: Item = sqlx::query_as(
+        "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination, savings_account_id, currency, version FROM items WHERE id = ? AND month_id = ? AND deleted_at >= datetime('now', ?)",
+    )
+    .bind(item_id)
+    .bind(month_id)
+    .bind(format!("-{RESTORE_WINDOW_DAYS} days"))
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+
+    restore(&pool, owner, item).await.map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{id}/items/undo-delete",
+    params(("id" = i64, Path, description = "Month ID")),
+    responses(
+        (status = 200, description = "Item restored successfully", body = Item),
+        (status = 404, description = "Nothing deleted from the month within the restore window"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Undo last delete",
+    description = "Restores the month's most recently deleted item, as long as it was deleted within the last 30 days, and re-applies any transfer to savings. Calling it again restores the delete before that."
+)]
+pub async fn undo_delete_item(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<Json<Item>, PaymeError> {
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
+
+    // Items deleted together share a timestamp; the higher id goes first
+    let item: Item = sqlx::query_as(
+        "SELECT id, month_id, category_id, description, amount, spent_on, savings_destination, savings_account_id, currency, version FROM items WHERE month_id = ? AND deleted_at >= datetime('now', ?) ORDER BY deleted_at DESC, id DESC LIMIT 1",
+    )
+    .bind(month_id)
+    .bind(format!("-{RESTORE_WINDOW_DAYS} days"))
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+
+    restore(&pool, owner, item).await.map(Json)
+}
+
+/// Clears the item's deletion and puts its transfer back into savings.
+async fn restore(pool: &SqlitePool, owner: i64, item: Item) -> Result<Item, PaymeError> {
+    let mut tx = pool.begin().await?;
+    // Only the request that actually brings it back credits savings
+    let restored = sqlx::query(
+        "UPDATE items SET deleted_at = NULL, updated_at = datetime('now') WHERE id = ? AND month_id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(item.id)
+    .bind(item.month_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if restored != 1 {
+        return Err(PaymeError::NotFound);
+    }
+    adjust_savings(
+        &mut tx,
+        owner,
+        &item.savings_destination,
+        item.savings_account_id,
+        item.amount,
+    )
+    .await?;
+    check_overdraft(
+        &mut tx,
+        owner,
+        &item.savings_destination,
+        item.savings_account_id,
+        false,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(item)
+}
+
+/// Checks the destination and, for an account destination, that the account is the owner's.
+/// Returns the account id to store, which is dropped for destinations that don't use one.
+async fn verify_savings_destination(
     pool: &SqlitePool,
-    user_id: i64,
-    month_id: i64,
-) -> Result<(), PaymeError> {
-    let exists: Option<(i64,)> =
-        sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-            .bind(month_id)
-            .bind(user_id)
+    owner: i64,
+    savings_destination: &str,
+    savings_account_id: Option<i64>,
+) -> Result<Option<i64>, PaymeError> {
+    if SAVINGS_DESTINATIONS.contains(&savings_destination) {
+        return Ok(None);
+    }
+    if !ACCOUNT_DESTINATIONS.contains(&savings_destination) {
+        return Err(PaymeError::BadRequest(
+            "Invalid savings destination".to_string(),
+        ));
+    }
+
+    let account_id = savings_account_id.ok_or(PaymeError::BadRequest(
+        "savings_account_id is required for account destinations".to_string(),
+    ))?;
+    let _account: i64 =
+        sqlx::query_scalar("SELECT id FROM savings_accounts WHERE id = ? AND user_id = ?")
+            .bind(account_id)
+            .bind(owner)
             .fetch_optional(pool)
+            .await?
+            .ok_or(PaymeError::BadRequest(
+                "Invalid savings account".to_string(),
+            ))?;
+    Ok(Some(account_id))
+}
+
+/// Moves `delta` into the balance an item's savings destination transfers to, or out of it for
+/// a withdrawal. Runs inside the caller's transaction so the item change and the balance change
+/// commit together.
+pub(crate) async fn adjust_savings(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    owner: i64,
+    savings_destination: &str,
+    savings_account_id: Option<i64>,
+    delta: Money,
+) -> Result<(), PaymeError> {
+    let (query, delta) = match savings_destination {
+        "savings" => ("UPDATE users SET savings = savings + ? WHERE id = ?", delta),
+        "savings_withdrawal" => (
+            "UPDATE users SET savings = savings + ? WHERE id = ?",
+            -delta,
+        ),
+        "retirement_savings" => (
+            "UPDATE users SET retirement_savings = retirement_savings + ? WHERE id = ?",
+            delta,
+        ),
+        "retirement_savings_withdrawal" => (
+            "UPDATE users SET retirement_savings = retirement_savings + ? WHERE id = ?",
+            -delta,
+        ),
+        "account" | "account_withdrawal" => {
+            let delta = if savings_destination == "account" {
+                delta
+            } else {
+                -delta
+            };
+            sqlx::query(
+                "UPDATE savings_accounts SET balance = balance + ? WHERE id = ? AND user_id = ?",
+            )
+            .bind(delta)
+            .bind(savings_account_id)
+            .bind(owner)
+            .execute(&mut **tx)
             .await?;
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+    sqlx::query(query)
+        .bind(delta)
+        .bind(owner)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
 
-    exists.map(|_| ()).ok_or(PaymeError::NotFound)
+/// Fails when a withdrawal has left its balance negative, which rolls back the caller's
+/// transaction. Call after `adjust_savings` so the check sees the new balance.
+async fn check_overdraft(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    owner: i64,
+    savings_destination: &str,
+    savings_account_id: Option<i64>,
+    allow_overdraft: bool,
+) -> Result<(), PaymeError> {
+    if allow_overdraft {
+        return Ok(());
+    }
+
+    let balance: Money = match savings_destination {
+        "savings_withdrawal" => {
+            sqlx::query_scalar("SELECT savings FROM users WHERE id = ?")
+                .bind(owner)
+                .fetch_one(&mut **tx)
+                .await?
+        }
+        "retirement_savings_withdrawal" => {
+            sqlx::query_scalar("SELECT retirement_savings FROM users WHERE id = ?")
+                .bind(owner)
+                .fetch_one(&mut **tx)
+                .await?
+        }
+        "account_withdrawal" => {
+            sqlx::query_scalar("SELECT balance FROM savings_accounts WHERE id = ? AND user_id = ?")
+                .bind(savings_account_id)
+                .bind(owner)
+                .fetch_one(&mut **tx)
+                .await?
+        }
+        _ => return Ok(()),
+    };
+    if balance < Money::ZERO {
+        return Err(PaymeError::BadRequest(
+            "Withdrawal exceeds the savings balance; set allow_overdraft to record it anyway"
+                .to_string(),
+        ));
+    }
+    Ok(())
 }
 
-async fn verify_month_not_closed(
+/// Hard-deletes items whose restore window has passed. Returns the number of rows removed.
+pub async fn purge_deleted_items(pool: &SqlitePool) -> Result<u64, PaymeError> {
+    let result = sqlx::query(
+        "DELETE FROM items WHERE deleted_at IS NOT NULL AND deleted_at < datetime('now', ?)",
+    )
+    .bind(format!("-{RESTORE_WINDOW_DAYS} days"))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Checks the caller can see the month (as owner or household member) and returns its owner.
+pub(crate) async fn verify_month_access(
     pool: &SqlitePool,
     user_id: i64,
     month_id: i64,
-) -> Result<(), PaymeError> {
-    let month: Option<(bool,)> =
-        sqlx::query_as("SELECT is_closed FROM months WHERE id = ? AND user_id = ?")
-            .bind(month_id)
-            .bind(user_id)
-            .fetch_optional(pool)
-            .await?;
+) -> Result<i64, PaymeError> {
+    Ok(find_user_month(pool, user_id, month_id).await?.user_id)
+}
 
-    match month {
-        Some((true,)) => Err(PaymeError::BadRequest("Month is closed".to_string())),
-        Some((false,)) => Ok(()),
-        None => Err(PaymeError::NotFound),
+/// Like [`verify_month_access`], but also rejects closed months.
+pub(crate) async fn verify_month_not_closed(
+    pool: &SqlitePool,
+    user_id: i64,
+    month_id: i64,
+) -> Result<i64, PaymeError> {
+    let month = find_user_month(pool, user_id, month_id).await?;
+    if month.is_closed {
+        return Err(PaymeError::MonthClosed);
+    }
+    if month.is_locked {
+        return Err(PaymeError::MonthLocked);
     }
+    Ok(month.user_id)
 }
@@ -1,13 +1,21 @@
+pub mod api_keys;
 pub mod auth;
 pub mod budget;
+pub mod exchange_rates;
 pub mod export;
 pub mod fixed_expenses;
 pub mod health;
+pub mod households;
 pub mod income;
+pub mod item_templates;
 pub mod items;
 pub mod monthly_data;
 pub mod months;
+pub mod receipts;
+pub mod reminders;
 pub mod retirement_breakdown;
 pub mod savings;
+pub mod savings_accounts;
 pub mod savings_goals;
 pub mod stats;
+pub mod webhooks;
@@ -9,23 +9,38 @@ use utoipa::ToSchema;
 use validator::Validate;
 
 use crate::error::PaymeError;
+use crate::handlers::fixed_expenses::{normalize_category, normalize_due_day};
+use crate::handlers::items::{verify_month_access, verify_month_not_closed};
 use crate::middleware::auth::Claims;
 use crate::models::{MonthlyFixedExpense, MonthlySavings};
+use crate::money::{validate_cents, Money};
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateMonthlyFixedExpense {
     #[validate(length(min = 1, max = 100))]
     pub label: String,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: f64,
+    #[validate(length(max = 50))]
+    pub category: Option<String>,
+    #[validate(range(min = 1, max = 31))]
+    pub due_day: Option<i64>,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateMonthlyFixedExpense {
     #[validate(length(min = 1, max = 100))]
     pub label: Option<String>,
-    #[validate(range(min = 0.0))]
+    #[validate(range(min = 0.0), custom(function = "validate_cents"))]
     pub amount: Option<f64>,
+    /// New category; an empty string clears it
+    #[validate(length(max = 50))]
+    pub category: Option<String>,
+    /// New due day; 0 clears it
+    #[validate(range(min = 0, max = 31))]
+    pub due_day: Option<i64>,
+    /// Whether this month's bill has been paid
+    pub paid: Option<bool>,
 }
 
 #[utoipa::path(
@@ -50,19 +65,18 @@ pub async fn create_monthly_fixed_expense(
 ) -> Result<Json<MonthlyFixedExpense>, PaymeError> {
     payload.validate()?;
 
-    let _: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-        .bind(month_id)
-        .bind(claims.sub)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(PaymeError::NotFound)?;
+    verify_month_access(&pool, claims.sub, month_id).await?;
 
+    let category = normalize_category(payload.category);
+    let amount = Money::from_f64(payload.amount);
     let id: i64 = sqlx::query_scalar(
-        "INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, ?, ?) RETURNING id",
+        "INSERT INTO monthly_fixed_expenses (month_id, label, amount, category, due_day) VALUES (?, ?, ?, ?, ?) RETURNING id",
     )
     .bind(month_id)
     .bind(&payload.label)
-    .bind(payload.amount)
+    .bind(amount)
+    .bind(&category)
+    .bind(payload.due_day)
     .fetch_one(&pool)
     .await?;
 
@@ -70,7 +84,10 @@ pub async fn create_monthly_fixed_expense(
         id,
         month_id,
         label: payload.label,
-        amount: payload.amount,
+        amount,
+        category,
+        due_day: payload.due_day,
+        paid: false,
     }))
 }
 
@@ -89,7 +106,7 @@ pub async fn create_monthly_fixed_expense(
     ),
     tag = "Months",
     summary = "Update monthly fixed expense",
-    description = "Updates a fixed expense for a specific month."
+    description = "Updates a fixed expense for a specific month, including marking it paid."
 )]
 pub async fn update_monthly_fixed_expense(
     State(pool): State<SqlitePool>,
@@ -99,15 +116,10 @@ pub async fn update_monthly_fixed_expense(
 ) -> Result<Json<MonthlyFixedExpense>, PaymeError> {
     payload.validate()?;
 
-    let _: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-        .bind(month_id)
-        .bind(claims.sub)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(PaymeError::NotFound)?;
+    verify_month_access(&pool, claims.sub, month_id).await?;
 
     let existing: MonthlyFixedExpense = sqlx::query_as(
-        "SELECT id, month_id, label, amount FROM monthly_fixed_expenses WHERE id = ? AND month_id = ?",
+        "SELECT id, month_id, label, amount, category, due_day, paid FROM monthly_fixed_expenses WHERE id = ? AND month_id = ?",
     )
     .bind(expense_id)
     .bind(month_id)
@@ -116,20 +128,34 @@ pub async fn update_monthly_fixed_expense(
     .ok_or(PaymeError::NotFound)?;
 
     let label = payload.label.unwrap_or(existing.label);
-    let amount = payload.amount.unwrap_or(existing.amount);
+    let amount = payload.amount.map_or(existing.amount, Money::from_f64);
+    let category = match payload.category {
+        Some(category) => normalize_category(Some(category)),
+        None => existing.category,
+    };
+    let due_day = payload.due_day.map_or(existing.due_day, normalize_due_day);
+    let paid = payload.paid.unwrap_or(existing.paid);
 
-    sqlx::query("UPDATE monthly_fixed_expenses SET label = ?, amount = ? WHERE id = ?")
-        .bind(&label)
-        .bind(amount)
-        .bind(expense_id)
-        .execute(&pool)
-        .await?;
+    sqlx::query(
+        "UPDATE monthly_fixed_expenses SET label = ?, amount = ?, category = ?, due_day = ?, paid = ? WHERE id = ?",
+    )
+    .bind(&label)
+    .bind(amount)
+    .bind(&category)
+    .bind(due_day)
+    .bind(paid)
+    .bind(expense_id)
+    .execute(&pool)
+    .await?;
 
     Ok(Json(MonthlyFixedExpense {
         id: expense_id,
         month_id,
         label,
         amount,
+        category,
+        due_day,
+        paid,
     }))
 }
 
@@ -150,12 +176,7 @@ pub async fn delete_monthly_fixed_expense(
     axum::Extension(claims): axum::Extension<Claims>,
     Path((month_id, expense_id)): Path<(i64, i64)>,
 ) -> Result<StatusCode, PaymeError> {
-    let _: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-        .bind(month_id)
-        .bind(claims.sub)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(PaymeError::NotFound)?;
+    verify_month_access(&pool, claims.sub, month_id).await?;
 
     sqlx::query("DELETE FROM monthly_fixed_expenses WHERE id = ? AND month_id = ?")
         .bind(expense_id)
@@ -194,12 +215,7 @@ pub async fn get_monthly_savings(
     axum::Extension(claims): axum::Extension<Claims>,
     Path(month_id): Path<i64>,
 ) -> Result<Json<MonthlySavings>, PaymeError> {
-    let _: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-        .bind(month_id)
-        .bind(claims.sub)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(PaymeError::NotFound)?;
+    verify_month_access(&pool, claims.sub, month_id).await?;
 
     let existing: Option<MonthlySavings> = sqlx::query_as(
         "SELECT id, month_id, savings, retirement_savings, savings_goal FROM monthly_savings WHERE month_id = ?",
@@ -212,10 +228,10 @@ pub async fn get_monthly_savings(
         Some(savings) => Ok(Json(savings)),
         None => {
             // If no monthly savings exist yet, create one with defaults from user
-            let (savings, retirement_savings, savings_goal): (f64, f64, f64) = sqlx::query_as(
-                "SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?",
+            let (savings, retirement_savings, savings_goal): (Money, Money, Money) = sqlx::query_as(
+                "SELECT savings, retirement_savings, savings_goal FROM users WHERE id = (SELECT user_id FROM months WHERE id = ?)",
             )
-            .bind(claims.sub)
+            .bind(month_id)
             .fetch_one(&pool)
             .await?;
 
@@ -247,12 +263,13 @@ pub async fn get_monthly_savings(
     request_body = UpdateMonthlySavings,
     responses(
         (status = 200, body = MonthlySavings),
+        (status = 400, description = "Month is closed"),
         (status = 404, description = "Month not found"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Months",
     summary = "Update monthly savings snapshot",
-    description = "Updates the savings values for a specific month."
+    description = "Updates the savings values for a specific month. Closed months keep the snapshot taken when they were closed."
 )]
 pub async fn update_monthly_savings(
     State(pool): State<SqlitePool>,
@@ -262,12 +279,8 @@ pub async fn update_monthly_savings(
 ) -> Result<Json<MonthlySavings>, PaymeError> {
     payload.validate()?;
 
-    let _: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
-        .bind(month_id)
-        .bind(claims.sub)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(PaymeError::NotFound)?;
+    // The snapshot taken at close is the month's record; reopen the month to change it
+    verify_month_not_closed(&pool, claims.sub, month_id).await?;
 
     let existing: Option<MonthlySavings> = sqlx::query_as(
         "SELECT id, month_id, savings, retirement_savings, savings_goal FROM monthly_savings WHERE month_id = ?",
@@ -278,14 +291,18 @@ pub async fn update_monthly_savings(
 
     let (savings, retirement_savings, savings_goal) = match existing {
         Some(ref e) => (
-            payload.savings.unwrap_or(e.savings),
-            payload.retirement_savings.unwrap_or(e.retirement_savings),
-            payload.savings_goal.unwrap_or(e.savings_goal),
+            payload.savings.map_or(e.savings, Money::from_f64),
+            payload
+                .retirement_savings
+                .map_or(e.retirement_savings, Money::from_f64),
+            payload.savings_goal.map_or(e.savings_goal, Money::from_f64),
         ),
         None => (
-            payload.savings.unwrap_or(0.0),
-            payload.retirement_savings.unwrap_or(0.0),
-            payload.savings_goal.unwrap_or(0.0),
+            payload.savings.map_or(Money::ZERO, Money::from_f64),
+            payload
+                .retirement_savings
+                .map_or(Money::ZERO, Money::from_f64),
+            payload.savings_goal.map_or(Money::ZERO, Money::from_f64),
         ),
     };
 
@@ -1,19 +1,28 @@
+use std::collections::HashMap;
+
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
-use chrono::{Datelike, Utc};
-use serde::Deserialize;
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::config::WebhookPolicy;
+use crate::currency;
+use crate::email;
 use crate::error::PaymeError;
+use crate::handlers::{budget, fixed_expenses};
 use crate::middleware::auth::Claims;
 use crate::models::{
     IncomeEntry, ItemWithCategory, Month, MonthSummary, MonthlyBudgetWithCategory,
     MonthlyFixedExpense, MonthlySavings,
 };
+use crate::money::{self, Money};
 use crate::pdf;
+use crate::timezone;
+use crate::webhooks;
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateMonthRequest {
@@ -21,6 +30,14 @@ pub struct CreateMonthRequest {
     pub month: i32,
 }
 
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MonthSeedQuery {
+    /// Start the month without any fixed expenses instead of seeding them
+    #[serde(default)]
+    pub skip_fixed_expenses: bool,
+}
+
 #[utoipa::path(
     get,
     path = "/api/months",
@@ -30,25 +47,32 @@ pub struct CreateMonthRequest {
     ),
     tag = "Months",
     summary = "List all budget months",
-    description = "Retrieves a history of all months created by the user, ordered by date."
+    description = "Retrieves a history of all months created by the user or shared with their households, ordered by date."
 )]
 pub async fn list_months(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
 ) -> Result<Json<Vec<Month>>, PaymeError> {
     let months: Vec<Month> = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE user_id = ? ORDER BY year DESC, month DESC",
+        r#"
+        SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months
+        WHERE user_id = ?1
+           OR household_id IN (SELECT household_id FROM household_members WHERE user_id = ?1 AND accepted_at IS NOT NULL)
+        ORDER BY year DESC, month DESC
+        "#,
     )
     .bind(claims.sub)
     .fetch_all(&pool)
     .await?;
 
-    Ok(Json(months))
+    let today = timezone::today(&pool, claims.sub).await?;
+    Ok(Json(months.into_iter().map(|m| m.as_of(today)).collect()))
 }
 
 #[utoipa::path(
     post,
     path = "/api/months",
+    params(MonthSeedQuery),
     request_body = CreateMonthRequest,
     responses(
         (status = 200, description = "Month created or returned if already exists", body = MonthSummary),
@@ -62,213 +86,295 @@ pub async fn list_months(
 pub async fn create_month(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    Query(seed): Query<MonthSeedQuery>,
     Json(payload): Json<CreateMonthRequest>,
 ) -> Result<Json<MonthSummary>, PaymeError> {
-    if payload.month < 1 || payload.month > 12 {
-        return Err(PaymeError::BadRequest(
-            "Month must be between 1 and 12".to_string(),
-        ));
-    }
+    validate_period(payload.year, payload.month)?;
 
-    if payload.year < 2000 || payload.year > 2100 {
-        return Err(PaymeError::BadRequest(
-            "Year must be between 2000 and 2100".to_string(),
-        ));
-    }
-
-    let existing: Option<Month> = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE user_id = ? AND year = ? AND month = ?",
+    let month_id = find_or_create_month(
+        &pool,
+        claims.sub,
+        payload.year,
+        payload.month,
+        !seed.skip_fixed_expenses,
     )
-    .bind(claims.sub)
-    .bind(payload.year)
-    .bind(payload.month)
-    .fetch_optional(&pool)
     .await?;
 
-    let month_record = match existing {
-        Some(m) => m,
-        None => {
-            let id: i64 = sqlx::query_scalar(
-                "INSERT INTO months (user_id, year, month) VALUES (?, ?, ?) RETURNING id",
-            )
-            .bind(claims.sub)
-            .bind(payload.year)
-            .bind(payload.month)
-            .fetch_one(&pool)
-            .await?;
-
-            let categories: Vec<(i64, f64)> = sqlx::query_as(
-                "SELECT id, default_amount FROM budget_categories WHERE user_id = ?",
-            )
-            .bind(claims.sub)
-            .fetch_all(&pool)
-            .await?;
-
-            for (cat_id, default_amount) in categories {
-                sqlx::query(
-                    "INSERT INTO monthly_budgets (month_id, category_id, allocated_amount) VALUES (?, ?, ?)",
-                )
-                .bind(id)
-                .bind(cat_id)
-                .bind(default_amount)
-                .execute(&pool)
-                .await
-                .ok();
-            }
-
-            let fixed_expenses: Vec<(String, f64)> =
-                sqlx::query_as("SELECT label, amount FROM fixed_expenses WHERE user_id = ?")
-                    .bind(claims.sub)
-                    .fetch_all(&pool)
-                    .await?;
-
-            for (label, amount) in fixed_expenses {
-                sqlx::query(
-                    "INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, ?, ?)",
-                )
-                .bind(id)
-                .bind(label)
-                .bind(amount)
-                .execute(&pool)
-                .await?;
-            }
-
-            let (savings, retirement_savings, savings_goal): (f64, f64, f64) = sqlx::query_as(
-                "SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?",
-            )
-            .bind(claims.sub)
-            .fetch_one(&pool)
-            .await?;
-
-            sqlx::query(
-                "INSERT INTO monthly_savings (month_id, savings, retirement_savings, savings_goal) VALUES (?, ?, ?, ?)",
-            )
-            .bind(id)
-            .bind(savings)
-            .bind(retirement_savings)
-            .bind(savings_goal)
-            .execute(&pool)
-            .await?;
-
-            Month {
-                id,
-                user_id: claims.sub,
-                year: payload.year,
-                month: payload.month,
-                is_closed: false,
-                closed_at: None,
-            }
-        }
-    };
-
-    get_month_summary(&pool, claims.sub, month_record.id).await
+    get_month_summary(&pool, claims.sub, month_id).await
 }
 
 #[utoipa::path(
     get,
     path = "/api/months/current",
+    params(MonthSeedQuery),
     responses(
         (status = 200, description = "Get current month or create it if it doesn't exist", body = MonthSummary),
         (status = 500, description = "Internal server error")
     ),
     tag = "Months",
     summary = "Get current month summary",
-    description = "Checks for the current calendar month. If it doesn't exist, it creates it and copies over your default categories."
+    description = "Checks for the current calendar month. If it doesn't exist, it creates it and copies over your default categories and fixed expenses. Categories with rollover enabled in the previous month start with its leftover added to their allocation."
 )]
 pub async fn get_or_create_current_month(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    Query(seed): Query<MonthSeedQuery>,
 ) -> Result<Json<MonthSummary>, PaymeError> {
-    let now = Utc::now();
-    let year = now.year();
-    let month = now.month() as i32;
+    let today = timezone::today(&pool, claims.sub).await?;
+    let year = today.year();
+    let month = today.month() as i32;
+
+    let month_id =
+        find_or_create_month(&pool, claims.sub, year, month, !seed.skip_fixed_expenses).await?;
+
+    get_month_summary(&pool, claims.sub, month_id).await
+}
+
+fn validate_period(year: i32, month: i32) -> Result<(), PaymeError> {
+    if !(1..=12).contains(&month) {
+        return Err(PaymeError::BadRequest(
+            "Month must be between 1 and 12".to_string(),
+        ));
+    }
+
+    if !(2000..=2100).contains(&year) {
+        return Err(PaymeError::BadRequest(
+            "Year must be between 2000 and 2100".to_string(),
+        ));
+    }
+
+    Ok(())
+}
 
-    let existing: Option<Month> = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE user_id = ? AND year = ? AND month = ?",
+/// Returns the id of the user's month for the given period, creating and seeding it if needed.
+///
+/// Seeding only happens for the request that actually inserted the month row, so concurrent or
+/// repeated calls never duplicate budgets, fixed expenses or savings snapshots.
+async fn find_or_create_month(
+    pool: &SqlitePool,
+    user_id: i64,
+    year: i32,
+    month: i32,
+    seed_fixed_expenses: bool,
+) -> Result<i64, PaymeError> {
+    let existing: Option<(i64,)> =
+        sqlx::query_as("SELECT id FROM months WHERE user_id = ? AND year = ? AND month = ?")
+            .bind(user_id)
+            .bind(year)
+            .bind(month)
+            .fetch_optional(pool)
+            .await?;
+
+    if let Some((id,)) = existing {
+        return Ok(id);
+    }
+
+    // Worked out before the month exists so a failure can't leave it created but unseeded
+    let rollovers = previous_month_rollovers(pool, user_id, year, month).await?;
+
+    let inserted: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO months (user_id, year, month) VALUES (?, ?, ?) ON CONFLICT(user_id, year, month) DO NOTHING RETURNING id",
     )
-    .bind(claims.sub)
+    .bind(user_id)
     .bind(year)
     .bind(month)
-    .fetch_optional(&pool)
+    .fetch_optional(pool)
     .await?;
 
-    let month_record = match existing {
-        Some(m) => m,
+    let id = match inserted {
+        Some(id) => id,
         None => {
-            let id: i64 = sqlx::query_scalar(
-                "INSERT INTO months (user_id, year, month) VALUES (?, ?, ?) RETURNING id",
+            return Ok(sqlx::query_scalar(
+                "SELECT id FROM months WHERE user_id = ? AND year = ? AND month = ?",
             )
-            .bind(claims.sub)
+            .bind(user_id)
             .bind(year)
             .bind(month)
-            .fetch_one(&pool)
+            .fetch_one(pool)
+            .await?)
+        }
+    };
+
+    let categories: Vec<(i64, Money)> =
+        sqlx::query_as("SELECT id, default_amount FROM budget_categories WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
             .await?;
 
-            let categories: Vec<(i64, f64)> = sqlx::query_as(
-                "SELECT id, default_amount FROM budget_categories WHERE user_id = ?",
-            )
-            .bind(claims.sub)
-            .fetch_all(&pool)
+    for (cat_id, default_amount) in categories {
+        let rollover = rollovers.get(&cat_id);
+        sqlx::query(
+            "INSERT INTO monthly_budgets (month_id, category_id, allocated_amount, rollover, carry_overspend, allocation_percent) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(cat_id)
+        .bind(default_amount + rollover.map_or(Money::ZERO, |r| r.carry))
+        .bind(rollover.is_some_and(|r| r.rollover))
+        .bind(rollover.is_some_and(|r| r.carry_overspend))
+        .bind(rollover.and_then(|r| r.allocation_percent))
+        .execute(pool)
+        .await
+        .ok();
+    }
+
+    if seed_fixed_expenses {
+        seed_monthly_fixed_expenses(pool, user_id, id, year, month).await?;
+    }
+
+    let (savings, retirement_savings, savings_goal): (Money, Money, Money) =
+        sqlx::query_as("SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(pool)
             .await?;
 
-            for (cat_id, default_amount) in categories {
-                sqlx::query(
-                    "INSERT INTO monthly_budgets (month_id, category_id, allocated_amount) VALUES (?, ?, ?)",
-                )
-                .bind(id)
-                .bind(cat_id)
-                .bind(default_amount)
-                .execute(&pool)
-                .await
-                .ok();
-            }
+    sqlx::query(
+        "INSERT INTO monthly_savings (month_id, savings, retirement_savings, savings_goal) VALUES (?, ?, ?, ?)",
+    )
+    .bind(id)
+    .bind(savings)
+    .bind(retirement_savings)
+    .bind(savings_goal)
+    .execute(pool)
+    .await?;
 
-            let fixed_expenses: Vec<(String, f64)> =
-                sqlx::query_as("SELECT label, amount FROM fixed_expenses WHERE user_id = ?")
-                    .bind(claims.sub)
-                    .fetch_all(&pool)
-                    .await?;
-
-            for (label, amount) in fixed_expenses {
-                sqlx::query(
-                    "INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, ?, ?)",
-                )
-                .bind(id)
-                .bind(label)
-                .bind(amount)
-                .execute(&pool)
-                .await?;
+    Ok(id)
+}
+
+/// A category's budget settings in the previous month and the amount they carry forward.
+struct Rollover {
+    rollover: bool,
+    carry_overspend: bool,
+    allocation_percent: Option<f64>,
+    carry: Money,
+}
+
+/// Budget settings per category from the user's most recent month before `year`/`month`.
+/// Rollover categories carry their unspent allocation, or their overspend as a negative
+/// amount when `carry_overspend` is set. Percentage allocations carry over as percentages.
+async fn previous_month_rollovers(
+    pool: &SqlitePool,
+    user_id: i64,
+    year: i32,
+    month: i32,
+) -> Result<HashMap<i64, Rollover>, PaymeError> {
+    let previous: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM months WHERE user_id = ? AND (year * 12 + month) < (? * 12 + ?) ORDER BY year DESC, month DESC LIMIT 1",
+    )
+    .bind(user_id)
+    .bind(year)
+    .bind(month)
+    .fetch_optional(pool)
+    .await?;
+    let Some(previous_id) = previous else {
+        return Ok(HashMap::new());
+    };
+
+    let budgets = budget::month_budgets(pool, previous_id).await?;
+    let items: Vec<(i64, String, Money, NaiveDate)> = sqlx::query_as(
+        "SELECT category_id, currency, amount, spent_on FROM items WHERE month_id = ? AND savings_destination = 'none' AND deleted_at IS NULL",
+    )
+    .bind(previous_id)
+    .fetch_all(pool)
+    .await?;
+
+    let base_currency = currency::base_currency(pool, user_id).await?;
+    let rates = currency::UserRates::new(pool, user_id);
+    let mut rollovers = HashMap::new();
+    for budget in budgets {
+        let mut carry = Money::ZERO;
+        if budget.rollover {
+            let amounts: Vec<_> = items
+                .iter()
+                .filter(|(cat, ..)| *cat == budget.category_id)
+                .map(|(_, c, amount, on)| (c.as_str(), *amount, *on))
+                .collect();
+            let spent = currency::sum_in_base(&rates, &base_currency, &amounts).await?;
+            carry = budget.allocated_amount - spent;
+            if carry < Money::ZERO && !budget.carry_overspend {
+                carry = Money::ZERO;
             }
+        }
+        rollovers.insert(
+            budget.category_id,
+            Rollover {
+                rollover: budget.rollover,
+                carry_overspend: budget.carry_overspend,
+                allocation_percent: budget.allocation_percent,
+                carry,
+            },
+        );
+    }
 
-            let (savings, retirement_savings, savings_goal): (f64, f64, f64) = sqlx::query_as(
-                "SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?",
-            )
-            .bind(claims.sub)
-            .fetch_one(&pool)
+    Ok(rollovers)
+}
+
+/// Copies the user's active fixed expense templates whose date range covers the month into it,
+/// at the amount in effect for that month.
+/// Users without templates get the entries of their most recent earlier month carried forward
+/// instead.
+async fn seed_monthly_fixed_expenses(
+    pool: &SqlitePool,
+    user_id: i64,
+    month_id: i64,
+    year: i32,
+    month: i32,
+) -> Result<(), PaymeError> {
+    let already_seeded: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM monthly_fixed_expenses WHERE month_id = ?")
+            .bind(month_id)
+            .fetch_one(pool)
             .await?;
 
-            sqlx::query(
-                "INSERT INTO monthly_savings (month_id, savings, retirement_savings, savings_goal) VALUES (?, ?, ?, ?)",
-            )
-            .bind(id)
-            .bind(savings)
-            .bind(retirement_savings)
-            .bind(savings_goal)
-            .execute(&pool)
+    if already_seeded.0 > 0 {
+        return Ok(());
+    }
+
+    let has_templates: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM fixed_expenses WHERE user_id = ?)")
+            .bind(user_id)
+            .fetch_one(pool)
             .await?;
 
-            Month {
-                id,
-                user_id: claims.sub,
-                year,
-                month,
-                is_closed: false,
-                closed_at: None,
-            }
-        }
+    // Paused templates still count as having templates, so they aren't undone by carry-forward
+    let fixed_expenses: Vec<(String, Money, Option<String>, Option<i64>)> = if has_templates {
+        sqlx::query_as(fixed_expenses::TEMPLATES_FOR_PERIOD)
+            .bind(user_id)
+            .bind(fixed_expenses::period(year, month))
+            .fetch_all(pool)
+            .await?
+    } else {
+        sqlx::query_as(
+            r#"
+            SELECT label, amount, category, due_day FROM monthly_fixed_expenses
+            WHERE month_id = (
+                SELECT id FROM months
+                WHERE user_id = ? AND (year * 12 + month) < (? * 12 + ?)
+                ORDER BY year DESC, month DESC
+                LIMIT 1
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(year)
+        .bind(month)
+        .fetch_all(pool)
+        .await?
     };
 
-    get_month_summary(&pool, claims.sub, month_record.id).await
+    for (label, amount, category, due_day) in fixed_expenses {
+        sqlx::query(
+            "INSERT INTO monthly_fixed_expenses (month_id, label, amount, category, due_day) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(month_id)
+        .bind(label)
+        .bind(amount)
+        .bind(category)
+        .bind(due_day)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
 }
 
 #[utoipa::path(
@@ -279,49 +385,47 @@ pub async fn get_or_create_current_month(
     ),
     responses(
         (status = 200, description = "Get full summary for a specific month", body = MonthSummary),
+        (status = 422, description = "A foreign-currency item has no exchange rate for its date"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Months",
     summary = "Get specific month details",
-    description = "Returns a complete financial summary for a given month ID, including income, fixed expenses, and itemized spending."
+    description = "Returns a complete financial summary for a given month ID, including income, fixed expenses, and itemized spending. Spending totals are in the owner's base currency, converting foreign-currency items at the rate for each item's date."
 )]
 pub async fn get_month(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
     Path(month_id): Path<i64>,
 ) -> Result<Json<MonthSummary>, PaymeError> {
-    let month: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ? AND user_id = ?",
-    )
-    .bind(month_id)
-    .bind(claims.sub)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or(PaymeError::NotFound)?;
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
 
     get_month_summary(&pool, claims.sub, month.id).await
 }
 
 async fn get_month_summary(
     pool: &SqlitePool,
-    _user_id: i64,
+    user_id: i64,
     month_id: i64,
 ) -> Result<Json<MonthSummary>, PaymeError> {
-    let month: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ?",
+    let month = sqlx::query_as::<_, Month>(
+        "SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months WHERE id = ?",
     )
     .bind(month_id)
     .fetch_one(pool)
-    .await?;
+    .await?
+    .as_of(timezone::today(pool, user_id).await?);
+    // Shared months are reported in their owner's currency, whoever is viewing
+    let base_currency = currency::base_currency(pool, month.user_id).await?;
+    let rounding_mode = money::rounding_mode(pool, month.user_id).await?;
 
     let income_entries: Vec<IncomeEntry> =
-        sqlx::query_as("SELECT id, month_id, label, amount FROM income_entries WHERE month_id = ?")
+        sqlx::query_as("SELECT id, month_id, label, amount, gross_amount, withholding, frequency, monthly_amount FROM income_entries WHERE month_id = ?")
             .bind(month_id)
             .fetch_all(pool)
             .await?;
 
     let fixed_expenses: Vec<MonthlyFixedExpense> = sqlx::query_as(
-        "SELECT id, month_id, label, amount FROM monthly_fixed_expenses WHERE month_id = ?",
+        "SELECT id, month_id, label, amount, category, due_day, paid FROM monthly_fixed_expenses WHERE month_id = ?",
     )
     .bind(month_id)
     .fetch_all(pool)
@@ -333,40 +437,38 @@ async fn get_month_summary(
             .fetch_optional(pool)
             .await?;
 
-    let budgets: Vec<MonthlyBudgetWithCategory> =
-        sqlx::query_as::<_, (i64, i64, i64, String, String, f64)>(
-            r#"
-        SELECT mb.id, mb.month_id, mb.category_id, bc.label, bc.color, mb.allocated_amount
-        FROM monthly_budgets mb
-        JOIN budget_categories bc ON mb.category_id = bc.id
-        WHERE mb.month_id = ?
-        "#,
-        )
-        .bind(month_id)
-        .fetch_all(pool)
+    let categories: HashMap<i64, (String, String)> =
+        sqlx::query_as("SELECT bc.id, bc.label, bc.color FROM budget_categories bc JOIN monthly_budgets mb ON mb.category_id = bc.id WHERE mb.month_id = ?")
+            .bind(month_id)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|(id, label, color)| (id, (label, color)))
+            .collect();
+    let budgets: Vec<MonthlyBudgetWithCategory> = budget::month_budgets(pool, month_id)
         .await?
         .into_iter()
-        .map(
-            |(id, month_id, category_id, category_label, category_color, allocated_amount)| {
-                MonthlyBudgetWithCategory {
-                    id,
-                    month_id,
-                    category_id,
-                    category_label,
-                    category_color,
-                    allocated_amount,
-                    spent_amount: 0.0,
-                }
-            },
-        )
+        .filter_map(|b| {
+            let (category_label, category_color) = categories.get(&b.category_id)?.clone();
+            Some(MonthlyBudgetWithCategory {
+                id: b.id,
+                month_id: b.month_id,
+                category_id: b.category_id,
+                category_label,
+                category_color,
+                allocated_amount: b.allocated_amount,
+                spent_amount: Money::ZERO,
+            })
+        })
         .collect();
 
     let items: Vec<ItemWithCategory> = sqlx::query_as(
         r#"
-        SELECT i.id, i.month_id, i.category_id, bc.label as category_label, bc.color as category_color, i.description, i.amount, i.spent_on, i.savings_destination
+        SELECT i.id, i.month_id, i.category_id, bc.label as category_label, bc.color as category_color, bc.icon as category_icon, i.description, i.amount, i.spent_on, i.savings_destination, i.savings_account_id, i.currency, i.version,
+               EXISTS(SELECT 1 FROM receipts r WHERE r.item_id = i.id) AS has_receipt
         FROM items i
         JOIN budget_categories bc ON i.category_id = bc.id
-        WHERE i.month_id = ?
+        WHERE i.month_id = ? AND i.deleted_at IS NULL
         ORDER BY i.spent_on DESC
         "#,
     )
@@ -374,33 +476,40 @@ async fn get_month_summary(
     .fetch_all(pool)
     .await?;
 
-    let budgets: Vec<MonthlyBudgetWithCategory> = budgets
-        .into_iter()
-        .map(|mut b| {
-            b.spent_amount = items
-                .iter()
-                .filter(|i| i.category_id == b.category_id && i.savings_destination == "none")
-                .map(|i| i.amount)
-                .sum();
-            b
-        })
-        .collect();
-
-    let total_income: f64 = income_entries.iter().map(|i| i.amount).sum();
-    let total_fixed: f64 = fixed_expenses.iter().map(|e| e.amount).sum();
-    let total_budgeted: f64 = budgets.iter().map(|b| b.allocated_amount).sum();
     // Only count items as "spent" if they're not being transferred to savings
-    let total_spent: f64 = items
-        .iter()
-        .filter(|i| i.savings_destination == "none")
-        .map(|i| i.amount)
-        .sum();
+    let spent_items = || items.iter().filter(|i| i.savings_destination == "none");
+    let rates = currency::UserRates::new(pool, month.user_id);
+
+    let mut budgets = budgets;
+    for b in &mut budgets {
+        let amounts: Vec<_> = spent_items()
+            .filter(|i| i.category_id == b.category_id)
+            .map(|i| (i.currency.as_str(), i.amount, i.spent_on))
+            .collect();
+        b.spent_amount = currency::sum_in_base(&rates, &base_currency, &amounts).await?;
+    }
+
+    let total_income: Money = income_entries.iter().map(|i| i.monthly_amount).sum();
+    let total_fixed: Money = fixed_expenses.iter().map(|e| e.amount).sum();
+    let fixed_by_category = fixed_expenses::group_by_category(
+        fixed_expenses
+            .iter()
+            .map(|e| (e.category.as_deref(), e.amount)),
+    );
+    let total_budgeted: Money = budgets.iter().map(|b| b.allocated_amount).sum();
+    let amounts: Vec<_> = spent_items()
+        .map(|i| (i.currency.as_str(), i.amount, i.spent_on))
+        .collect();
+    let total_spent = currency::sum_in_base(&rates, &base_currency, &amounts).await?;
+    let spent_by_currency =
+        currency::totals_by_currency(spent_items().map(|i| (i.currency.as_str(), i.amount)));
     let remaining = total_income - total_fixed - total_spent;
 
     Ok(Json(MonthSummary {
         month,
         income_entries,
         fixed_expenses,
+        fixed_by_category,
         budgets,
         items,
         savings,
@@ -409,9 +518,111 @@ async fn get_month_summary(
         total_budgeted,
         total_spent,
         remaining,
+        base_currency,
+        spent_by_currency,
+        rounding_mode,
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/months/{id}/duplicate",
+    params(
+        ("id" = i64, Path, description = "Month ID to use as the template")
+    ),
+    request_body = CreateMonthRequest,
+    responses(
+        (status = 200, description = "New month created from the template", body = MonthSummary),
+        (status = 400, description = "Invalid month or year"),
+        (status = 404, description = "Source month not found"),
+        (status = 409, description = "A month already exists for the target period"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Months",
+    summary = "Duplicate a month",
+    description = "Creates a new month for the target year/month using the source month's budget allocations, fixed expenses and savings goal. Transactions and income are not copied."
+)]
+pub async fn duplicate_month(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(source_id): Path<i64>,
+    Json(payload): Json<CreateMonthRequest>,
+) -> Result<Json<MonthSummary>, PaymeError> {
+    validate_period(payload.year, payload.month)?;
+
+    let _source: (i64,) = sqlx::query_as("SELECT id FROM months WHERE id = ? AND user_id = ?")
+        .bind(source_id)
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+
+    let mut tx = pool.begin().await?;
+
+    let new_id: i64 = sqlx::query_scalar(
+        "INSERT INTO months (user_id, year, month) VALUES (?, ?, ?) ON CONFLICT(user_id, year, month) DO NOTHING RETURNING id",
+    )
+    .bind(claims.sub)
+    .bind(payload.year)
+    .bind(payload.month)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        PaymeError::Conflict(format!(
+            "Month {}/{} already exists",
+            payload.month, payload.year
+        ))
+    })?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO monthly_budgets (month_id, category_id, allocated_amount, rollover, carry_overspend, allocation_percent)
+        SELECT ?, category_id, allocated_amount, rollover, carry_overspend, allocation_percent FROM monthly_budgets WHERE month_id = ?
+        "#,
+    )
+    .bind(new_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO monthly_fixed_expenses (month_id, label, amount, category, due_day)
+        SELECT ?, label, amount, category, due_day FROM monthly_fixed_expenses WHERE month_id = ? ORDER BY id
+        "#,
+    )
+    .bind(new_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let (savings, retirement_savings, user_goal): (Money, Money, Money) =
+        sqlx::query_as("SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?")
+            .bind(claims.sub)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    let source_goal: Option<Money> =
+        sqlx::query_scalar("SELECT savings_goal FROM monthly_savings WHERE month_id = ?")
+            .bind(source_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    sqlx::query(
+        "INSERT INTO monthly_savings (month_id, savings, retirement_savings, savings_goal) VALUES (?, ?, ?, ?)",
+    )
+    .bind(new_id)
+    .bind(savings)
+    .bind(retirement_savings)
+    .bind(source_goal.unwrap_or(user_goal))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    get_month_summary(&pool, claims.sub, new_id).await
+}
+
 #[utoipa::path(
     post,
     path = "/api/months/{id}/close",
@@ -419,61 +630,266 @@ async fn get_month_summary(
         ("id" = i64, Path, description = "Month ID")
     ),
     responses(
-        (status = 200, description = "Month closed and PDF snapshot generated", body = Month),
-        (status = 400, description = "Month is already closed"),
+        (status = 200, description = "Month closed, savings snapshotted and PDF generated, or already closed", body = Month),
+        (status = 400, description = "Month can't be closed yet"),
         (status = 404, description = "Month not found"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Months",
     summary = "Close month and generate report",
-    description = "Finalizes the month, prevents further edits, and generates a PDF snapshot for long-term storage."
+    description = "Finalizes the month, prevents further edits, records the current savings balances in the month's savings snapshot, and generates a PDF snapshot for long-term storage. The flag and both snapshots are written together or not at all. Closing a month that is already closed returns it unchanged."
 )]
 pub async fn close_month(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
     Path(month_id): Path<i64>,
 ) -> Result<Json<Month>, PaymeError> {
-    let month: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ? AND user_id = ?",
-    )
-    .bind(month_id)
-    .bind(claims.sub)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or(PaymeError::NotFound)?;
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
 
+    let today = timezone::today(&pool, claims.sub).await?;
     if month.is_closed {
-        return Err(PaymeError::BadRequest(
-            "Month is already closed".to_string(),
-        ));
+        return Ok(Json(month.as_of(today)));
     }
 
     let summary = get_month_summary(&pool, claims.sub, month_id).await?.0;
     let pdf_data = pdf::generate_pdf(&summary).map_err(|e| PaymeError::Internal(e.to_string()))?;
 
-    sqlx::query("INSERT INTO monthly_snapshots (month_id, pdf_data) VALUES (?, ?)")
-        .bind(month_id)
-        .bind(&pdf_data)
-        .execute(&pool)
-        .await?;
+    // A month closed without its snapshot can't get one later, so nothing sticks unless all of
+    // it does. The blockers and balances are read here too, so they match what gets written.
+    let mut tx = pool.begin().await?;
+    let month = sqlx::query_as::<_, Month>(
+        "SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months WHERE id = ?",
+    )
+    .bind(month_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if month.is_closed {
+        return Ok(Json(month.as_of(today)));
+    }
+    if let Some(blocker) = close_blockers(&month).into_iter().next() {
+        return Err(PaymeError::BadRequest(blocker));
+    }
+    let savings = savings_snapshot(&mut tx, month.user_id).await?;
+
+    let closed = sqlx::query(
+        "UPDATE months SET is_closed = 1, is_locked = 0, closed_at = ? WHERE id = ? AND is_closed = 0",
+    )
+    .bind(Utc::now())
+    .bind(month_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
 
-    let now = Utc::now();
-    sqlx::query("UPDATE months SET is_closed = 1, closed_at = ? WHERE id = ?")
-        .bind(now)
+    if closed > 0 {
+        // Freeze the owner's live savings into the month so history reflects them at close
+        sqlx::query(
+            r#"
+            INSERT INTO monthly_savings (month_id, savings, retirement_savings, savings_goal)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(month_id) DO UPDATE SET
+                savings = excluded.savings,
+                retirement_savings = excluded.retirement_savings,
+                savings_goal = excluded.savings_goal
+            "#,
+        )
         .bind(month_id)
-        .execute(&pool)
+        .bind(savings.savings)
+        .bind(savings.retirement_savings)
+        .bind(savings.savings_goal)
+        .execute(&mut *tx)
         .await?;
 
-    let updated: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ?",
+        sqlx::query("INSERT INTO monthly_snapshots (month_id, pdf_data) VALUES (?, ?)")
+            .bind(month_id)
+            .bind(&pdf_data)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let updated = sqlx::query_as::<_, Month>(
+        "SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months WHERE id = ?",
     )
     .bind(month_id)
     .fetch_one(&pool)
-    .await?;
+    .await?
+    .as_of(today);
+    // Another request closed it first and has already sent these
+    if closed == 0 {
+        return Ok(Json(updated));
+    }
+
+    if let Ok(data) = serde_json::to_value(&updated) {
+        webhooks::enqueue(
+            &pool,
+            webhook_policy,
+            updated.user_id,
+            webhooks::MONTH_CLOSED,
+            data,
+        )
+        .await;
+    }
+    let (subject, body) = month_closed_email(&summary);
+    email::enqueue(&pool, updated.user_id, email::MONTH_CLOSED, &subject, &body).await;
 
     Ok(Json(updated))
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct SavingsSnapshot {
+    #[schema(value_type = f64)]
+    pub savings: Money,
+    #[schema(value_type = f64)]
+    pub retirement_savings: Money,
+    #[schema(value_type = f64)]
+    pub savings_goal: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryVariance {
+    pub category_id: i64,
+    pub category_label: String,
+    #[schema(value_type = f64)]
+    pub budgeted: Money,
+    #[schema(value_type = f64)]
+    pub spent: Money,
+    /// `budgeted - spent`; negative when the category is overspent
+    #[schema(value_type = f64)]
+    pub variance: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClosePreview {
+    pub month_id: i64,
+    /// Balances closing would record in the month's savings snapshot
+    pub savings: SavingsSnapshot,
+    #[schema(value_type = f64)]
+    pub total_income: Money,
+    #[schema(value_type = f64)]
+    pub total_fixed: Money,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    #[schema(value_type = f64)]
+    pub remaining: Money,
+    pub categories: Vec<CategoryVariance>,
+    /// Reasons closing would be refused; empty when the month can be closed
+    pub blockers: Vec<String>,
+}
+
+/// The owner's live balances, as closing would freeze them into the month
+async fn savings_snapshot(
+    conn: &mut sqlx::SqliteConnection,
+    user_id: i64,
+) -> Result<SavingsSnapshot, PaymeError> {
+    let (savings, retirement_savings, savings_goal): (Money, Money, Money) =
+        sqlx::query_as("SELECT savings, retirement_savings, savings_goal FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(conn)
+            .await?;
+    Ok(SavingsSnapshot {
+        savings,
+        retirement_savings,
+        savings_goal,
+    })
+}
+
+/// Reasons `close_month` would refuse to close the month
+fn close_blockers(month: &Month) -> Vec<String> {
+    let mut blockers = vec![];
+    if month.is_closed {
+        blockers.push("Month is already closed".to_string());
+    }
+    blockers
+}
+
+/// What closing the month would record, computed without writing anything. `close_month`
+/// snapshots the same values, using the same checks.
+async fn close_preview(pool: &SqlitePool, month: &Month) -> Result<ClosePreview, PaymeError> {
+    let savings = savings_snapshot(&mut *pool.acquire().await?, month.user_id).await?;
+    let Json(summary) = get_month_summary(pool, month.user_id, month.id).await?;
+    let blockers = close_blockers(month);
+
+    let categories = summary
+        .budgets
+        .iter()
+        .map(|b| CategoryVariance {
+            category_id: b.category_id,
+            category_label: b.category_label.clone(),
+            budgeted: b.allocated_amount,
+            spent: b.spent_amount,
+            variance: b.allocated_amount - b.spent_amount,
+        })
+        .collect();
+
+    Ok(ClosePreview {
+        month_id: month.id,
+        savings,
+        total_income: summary.total_income,
+        total_fixed: summary.total_fixed,
+        total_spent: summary.total_spent,
+        remaining: summary.remaining,
+        categories,
+        blockers,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/close-preview",
+    params(
+        ("id" = i64, Path, description = "Month ID")
+    ),
+    responses(
+        (status = 200, body = ClosePreview),
+        (status = 404, description = "Month not found"),
+        (status = 422, description = "Missing exchange rate"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Months",
+    summary = "Preview closing a month",
+    description = "Returns what closing the month would record, without changing anything: the savings snapshot, the month's totals and each budgeted category's variance. `blockers` lists anything that would make the close fail."
+)]
+pub async fn get_close_preview(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<Json<ClosePreview>, PaymeError> {
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    Ok(Json(close_preview(&pool, &month).await?))
+}
+
+fn month_closed_email(summary: &MonthSummary) -> (String, String) {
+    let month = u8::try_from(summary.month.month)
+        .ok()
+        .and_then(|m| chrono::Month::try_from(m).ok())
+        .map_or_else(|| summary.month.month.to_string(), |m| m.name().to_string());
+    let subject = format!("Your {} {} summary", month, summary.month.year);
+
+    let currency = &summary.base_currency;
+    let mut body = format!(
+        "{month} {} is closed.\n\nIncome: {} {currency}\nFixed expenses: {} {currency}\nSpent: {} {currency}\n",
+        summary.month.year, summary.total_income, summary.total_fixed, summary.total_spent
+    );
+    if summary.remaining >= Money::ZERO {
+        body.push_str(&format!("Left over: {} {currency}\n", summary.remaining));
+    } else {
+        body.push_str(&format!("Deficit: {} {currency}\n", -summary.remaining));
+    }
+    let over: Vec<&str> = summary
+        .budgets
+        .iter()
+        .filter(|b| b.spent_amount > b.allocated_amount)
+        .map(|b| b.category_label.as_str())
+        .collect();
+    if !over.is_empty() {
+        body.push_str(&format!("\nOver budget: {}\n", over.join(", ")));
+    }
+    body.push_str("\nThe full report is attached to the month as a PDF.\n");
+
+    (subject, body)
+}
+
 #[utoipa::path(
     post,
     path = "/api/months/{id}/reopen",
@@ -495,14 +911,7 @@ pub async fn reopen_month(
     axum::Extension(claims): axum::Extension<Claims>,
     Path(month_id): Path<i64>,
 ) -> Result<Json<Month>, PaymeError> {
-    let month: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ? AND user_id = ?",
-    )
-    .bind(month_id)
-    .bind(claims.sub)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or(PaymeError::NotFound)?;
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
 
     if !month.is_closed {
         return Err(PaymeError::BadRequest("Month is not closed".to_string()));
@@ -518,16 +927,95 @@ pub async fn reopen_month(
         .execute(&pool)
         .await?;
 
-    let updated: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ?",
+    let updated = sqlx::query_as::<_, Month>(
+        "SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months WHERE id = ?",
     )
     .bind(month_id)
     .fetch_one(&pool)
-    .await?;
+    .await?
+    .as_of(timezone::today(&pool, claims.sub).await?);
 
     Ok(Json(updated))
 }
 
+async fn set_month_locked(
+    pool: &SqlitePool,
+    user_id: i64,
+    month_id: i64,
+    locked: bool,
+) -> Result<Month, PaymeError> {
+    let month = find_user_month(pool, user_id, month_id).await?;
+
+    // A closed month is already read-only; reopen it before locking or unlocking
+    if month.is_closed {
+        return Err(PaymeError::MonthClosed);
+    }
+
+    sqlx::query("UPDATE months SET is_locked = ? WHERE id = ?")
+        .bind(locked)
+        .bind(month_id)
+        .execute(pool)
+        .await?;
+
+    Ok(Month {
+        is_locked: locked,
+        ..month
+    }
+    .as_of(timezone::today(pool, user_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{id}/lock",
+    params(
+        ("id" = i64, Path, description = "Month ID")
+    ),
+    responses(
+        (status = 200, description = "Month locked", body = Month),
+        (status = 400, description = "Month is closed"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Months",
+    summary = "Lock month",
+    description = "Rejects edits to the month's items, income, budgets and savings with a `MONTH_LOCKED` error until it is unlocked. Unlike closing, locking takes no savings snapshot or PDF and can be undone at any time. Locking an already locked month has no effect."
+)]
+pub async fn lock_month(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<Json<Month>, PaymeError> {
+    Ok(Json(
+        set_month_locked(&pool, claims.sub, month_id, true).await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{id}/unlock",
+    params(
+        ("id" = i64, Path, description = "Month ID")
+    ),
+    responses(
+        (status = 200, description = "Month unlocked", body = Month),
+        (status = 400, description = "Month is closed"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Months",
+    summary = "Unlock month",
+    description = "Allows edits to a locked month again."
+)]
+pub async fn unlock_month(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<Json<Month>, PaymeError> {
+    Ok(Json(
+        set_month_locked(&pool, claims.sub, month_id, false).await?,
+    ))
+}
+
 #[utoipa::path(
     get,
     path = "/api/months/{id}/pdf",
@@ -540,21 +1028,14 @@ pub async fn reopen_month(
     ),
     tag = "Months",
     summary = "Download month PDF",
-    description = "Retrieves the binary PDF data for a closed month's financial report."
+    description = "Retrieves the binary PDF data for a closed month's financial report. After the tables, a second page charts spending by category and budget against actual spending."
 )]
 pub async fn get_month_pdf(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
     Path(month_id): Path<i64>,
 ) -> Result<impl axum::response::IntoResponse, PaymeError> {
-    let _month: Month = sqlx::query_as(
-        "SELECT id, user_id, year, month, is_closed, closed_at FROM months WHERE id = ? AND user_id = ?",
-    )
-    .bind(month_id)
-    .bind(claims.sub)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or(PaymeError::NotFound)?;
+    let _month = find_user_month(&pool, claims.sub, month_id).await?;
 
     let snapshot: (Vec<u8>,) =
         sqlx::query_as("SELECT pdf_data FROM monthly_snapshots WHERE month_id = ?")
@@ -571,3 +1052,376 @@ pub async fn get_month_pdf(
         snapshot.0,
     ))
 }
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ForecastQuery {
+    /// Date to project from (defaults to today)
+    pub as_of: Option<NaiveDate>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryForecast {
+    pub category_id: i64,
+    pub category_label: String,
+    #[schema(value_type = f64)]
+    pub budgeted: Money,
+    #[schema(value_type = f64)]
+    pub spent_to_date: Money,
+    #[schema(value_type = f64)]
+    pub projected_spent: Money,
+    pub projected_over_budget: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MonthForecast {
+    pub month_id: i64,
+    pub as_of: NaiveDate,
+    pub days_elapsed: u32,
+    pub days_in_month: u32,
+    #[schema(value_type = f64)]
+    pub total_income: Money,
+    #[schema(value_type = f64)]
+    pub total_fixed: Money,
+    #[schema(value_type = f64)]
+    pub spent_to_date: Money,
+    #[schema(value_type = f64)]
+    pub projected_spent: Money,
+    #[schema(value_type = f64)]
+    pub projected_savings: Money,
+    pub categories: Vec<CategoryForecast>,
+}
+
+pub(crate) fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map_or(30, |d| d.day())
+}
+
+/// Scales spend-to-date up to the full month. With no days elapsed there is nothing to
+/// extrapolate from, so the spend so far is returned as-is.
+fn project(spent: Money, days_elapsed: u32, days_in_month: u32) -> Money {
+    if days_elapsed == 0 {
+        spent
+    } else {
+        spent.scaled(f64::from(days_in_month) / f64::from(days_elapsed))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/forecast",
+    params(
+        ("id" = i64, Path, description = "Month ID"),
+        ForecastQuery
+    ),
+    responses(
+        (status = 200, body = MonthForecast),
+        (status = 404, description = "Month not found")
+    ),
+    tag = "Months",
+    summary = "Forecast end-of-month spending",
+    description = "Linearly projects the month's total spend and resulting savings from the spend to date and the share of the month elapsed, with a per-category breakdown flagging budgets projected to be exceeded."
+)]
+pub async fn get_month_forecast(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+    Query(query): Query<ForecastQuery>,
+) -> Result<Json<MonthForecast>, PaymeError> {
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    let Json(summary) = get_month_summary(&pool, claims.sub, month.id).await?;
+
+    let as_of = match query.as_of {
+        Some(date) => date,
+        None => timezone::today(&pool, claims.sub).await?,
+    };
+    let days_in_month = days_in_month(month.year, month.month as u32);
+    let days_elapsed = match (as_of.year(), as_of.month() as i32) {
+        (y, m) if (y, m) < (month.year, month.month) => 0,
+        (y, m) if (y, m) > (month.year, month.month) => days_in_month,
+        _ => as_of.day(),
+    };
+
+    let projected_spent = project(summary.total_spent, days_elapsed, days_in_month);
+
+    let categories = summary
+        .budgets
+        .iter()
+        .map(|b| {
+            let projected_spent = project(b.spent_amount, days_elapsed, days_in_month);
+            CategoryForecast {
+                category_id: b.category_id,
+                category_label: b.category_label.clone(),
+                budgeted: b.allocated_amount,
+                spent_to_date: b.spent_amount,
+                projected_spent,
+                projected_over_budget: projected_spent > b.allocated_amount,
+            }
+        })
+        .collect();
+
+    Ok(Json(MonthForecast {
+        month_id: month.id,
+        as_of,
+        days_elapsed,
+        days_in_month,
+        total_income: summary.total_income,
+        total_fixed: summary.total_fixed,
+        spent_to_date: summary.total_spent,
+        projected_spent,
+        projected_savings: summary.total_income - summary.total_fixed - projected_spent,
+        categories,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FutureForecastQuery {
+    /// Number of months to project, starting next month (defaults to 6, at most 24)
+    pub count: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProjectedMonth {
+    pub year: i32,
+    pub month: i32,
+    /// Always true: nothing here is recorded, and variable spending is left out
+    pub projected: bool,
+    /// Recurring income from the latest month up to now, at its monthly equivalent
+    #[schema(value_type = f64)]
+    pub expected_income: Money,
+    #[schema(value_type = f64)]
+    pub expected_fixed: Money,
+    /// What the savings goals still open that month need, at today's required pace
+    #[schema(value_type = f64)]
+    pub savings_contributions: Money,
+    #[schema(value_type = f64)]
+    pub projected_net: Money,
+}
+
+/// Savings goals with a date, as the per-month amount they need and the last month it's due.
+async fn goal_contributions(
+    pool: &SqlitePool,
+    user_id: i64,
+    today: NaiveDate,
+) -> Result<Vec<(Money, (i32, u32))>, PaymeError> {
+    let goals: Vec<(Money, Money, NaiveDate)> = sqlx::query_as(
+        "SELECT balance, goal, goal_date FROM savings_accounts WHERE user_id = ? AND goal IS NOT NULL AND goal_date IS NOT NULL",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(goals
+        .into_iter()
+        .filter_map(|(balance, goal, goal_date)| {
+            let remaining = goal - balance;
+            crate::handlers::savings_accounts::required_monthly(remaining, today, goal_date)
+                .map(|amount| (amount, (goal_date.year(), goal_date.month())))
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/forecast/months",
+    params(FutureForecastQuery),
+    responses(
+        (status = 200, body = Vec<ProjectedMonth>),
+        (status = 400, description = "Count out of range")
+    ),
+    tag = "Months",
+    summary = "Project future months",
+    description = "Projects the months after the current one without creating them: recurring income from the latest month so far, fixed expenses as scheduled for each month, and the contributions dated savings goals need. Variable spending is not projected, so the net is what's left before it."
+)]
+pub async fn forecast_future_months(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<FutureForecastQuery>,
+) -> Result<Json<Vec<ProjectedMonth>>, PaymeError> {
+    let count = query.count.unwrap_or(6);
+    if !(1..=24).contains(&count) {
+        return Err(PaymeError::BadRequest(
+            "count must be between 1 and 24".to_string(),
+        ));
+    }
+
+    let today = timezone::today(&pool, claims.sub).await?;
+    let expected_income: Money = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(monthly_amount), 0) FROM income_entries WHERE frequency != 'one_time' AND month_id = (SELECT id FROM months WHERE user_id = ? AND year * 12 + month <= ? ORDER BY year DESC, month DESC LIMIT 1)",
+    )
+    .bind(claims.sub)
+    .bind(today.year() * 12 + today.month() as i32)
+    .fetch_one(&pool)
+    .await?;
+    let goals = goal_contributions(&pool, claims.sub, today).await?;
+
+    let mut projections = Vec::with_capacity(count as usize);
+    let (mut year, mut month) = (today.year(), today.month());
+    for _ in 0..count {
+        (year, month) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+
+        let templates: Vec<(String, Money, Option<String>, Option<i64>)> =
+            sqlx::query_as(fixed_expenses::TEMPLATES_FOR_PERIOD)
+                .bind(claims.sub)
+                .bind(format!("{year:04}-{month:02}"))
+                .fetch_all(&pool)
+                .await?;
+        let expected_fixed: Money = templates.iter().map(|(_, amount, _, _)| *amount).sum();
+        let savings_contributions: Money = goals
+            .iter()
+            .filter(|(_, due)| *due >= (year, month))
+            .map(|(amount, _)| *amount)
+            .sum();
+
+        projections.push(ProjectedMonth {
+            year,
+            month: month as i32,
+            projected: true,
+            expected_income,
+            expected_fixed,
+            savings_contributions,
+            projected_net: expected_income - expected_fixed - savings_contributions,
+        });
+    }
+
+    Ok(Json(projections))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SafeToSpendQuery {
+    /// Date to compute from (defaults to today)
+    pub as_of: Option<NaiveDate>,
+    /// Amount to set aside for savings this month (defaults to 0)
+    pub savings_target: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SafeToSpend {
+    pub month_id: i64,
+    pub as_of: NaiveDate,
+    #[schema(value_type = f64)]
+    pub total_income: Money,
+    #[schema(value_type = f64)]
+    pub total_fixed: Money,
+    /// The larger of the savings target and the net amount already moved to savings this month
+    #[schema(value_type = f64)]
+    pub savings_contribution: Money,
+    #[schema(value_type = f64)]
+    pub spent_to_date: Money,
+    /// Negative when the month is already over
+    #[schema(value_type = f64)]
+    pub safe_to_spend: Money,
+    /// Days left in the month, counting `as_of`
+    pub days_remaining: u32,
+    /// `safe_to_spend` spread over the remaining days. `None` once the month has ended
+    #[schema(value_type = Option<f64>)]
+    pub per_day: Option<Money>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/safe-to-spend",
+    params(
+        ("id" = i64, Path, description = "Month ID"),
+        SafeToSpendQuery
+    ),
+    responses(
+        (status = 200, body = SafeToSpend),
+        (status = 400, description = "Invalid savings target"),
+        (status = 404, description = "Month not found"),
+        (status = 422, description = "Missing exchange rate")
+    ),
+    tag = "Months",
+    summary = "Get the amount safe to spend",
+    description = "Income minus fixed expenses, the month's savings contribution and the variable spend so far, with a per-day allowance for the rest of the month. The savings contribution is the larger of `savings_target` and the net of this month's transfers to savings, so money already set aside is not counted twice. A negative result means the month is overspent."
+)]
+pub async fn get_safe_to_spend(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+    Query(query): Query<SafeToSpendQuery>,
+) -> Result<Json<SafeToSpend>, PaymeError> {
+    let savings_target = query.savings_target.unwrap_or(0.0);
+    if !savings_target.is_finite() || savings_target < 0.0 {
+        return Err(PaymeError::BadRequest(
+            "savings_target must be zero or more".to_string(),
+        ));
+    }
+
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    let Json(summary) = get_month_summary(&pool, claims.sub, month.id).await?;
+
+    let rates = currency::UserRates::new(&pool, month.user_id);
+    let transfers: Vec<_> = summary
+        .items
+        .iter()
+        .filter(|i| i.savings_destination != "none")
+        .map(|i| {
+            let amount = if i.savings_destination.ends_with("withdrawal") {
+                -i.amount
+            } else {
+                i.amount
+            };
+            (i.currency.as_str(), amount, i.spent_on)
+        })
+        .collect();
+    let transferred = currency::sum_in_base(&rates, &summary.base_currency, &transfers).await?;
+    let savings_contribution = Money::from_f64(savings_target).max(transferred);
+
+    let safe_to_spend =
+        summary.total_income - summary.total_fixed - savings_contribution - summary.total_spent;
+
+    let as_of = match query.as_of {
+        Some(date) => date,
+        None => timezone::today(&pool, claims.sub).await?,
+    };
+    let month = month.as_of(as_of);
+    let days_remaining = month.days_remaining;
+    let per_day = (days_remaining > 0).then(|| {
+        Money::round(
+            safe_to_spend.to_f64() / f64::from(days_remaining),
+            summary.rounding_mode,
+        )
+    });
+
+    Ok(Json(SafeToSpend {
+        month_id: month.id,
+        as_of,
+        total_income: summary.total_income,
+        total_fixed: summary.total_fixed,
+        savings_contribution,
+        spent_to_date: summary.total_spent,
+        safe_to_spend,
+        days_remaining,
+        per_day,
+    }))
+}
+
+/// Loads a month the user owns or shares through a household, returning `NotFound` for missing
+/// or foreign months.
+pub(crate) async fn find_user_month(
+    pool: &SqlitePool,
+    user_id: i64,
+    month_id: i64,
+) -> Result<Month, PaymeError> {
+    sqlx::query_as(
+        "SELECT id, user_id, year, month, is_closed, closed_at, is_locked FROM months WHERE id = ?1 AND (user_id = ?2 OR household_id IN (SELECT household_id FROM household_members WHERE user_id = ?2 AND accepted_at IS NOT NULL))",
+    )
+    .bind(month_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)
+}
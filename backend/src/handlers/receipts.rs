@@ -0,0 +1,154 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use sqlx::SqlitePool;
+
+use crate::error::PaymeError;
+use crate::handlers::items::{verify_month_access, verify_month_not_closed};
+use crate::middleware::auth::Claims;
+
+/// Largest receipt accepted in one upload
+pub const MAX_RECEIPT_BYTES: usize = 5 * 1024 * 1024;
+/// Total size of all receipts one user can store
+pub const RECEIPT_QUOTA_BYTES: i64 = 100 * 1024 * 1024;
+
+/// Accepted content types and the leading bytes their files start with
+const RECEIPT_TYPES: [(&str, &[u8]); 3] = [
+    ("image/png", b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", b"\xff\xd8\xff"),
+    ("application/pdf", b"%PDF-"),
+];
+
+async fn verify_item(pool: &SqlitePool, month_id: i64, item_id: i64) -> Result<(), PaymeError> {
+    let _item: i64 = sqlx::query_scalar(
+        "SELECT id FROM items WHERE id = ? AND month_id = ? AND deleted_at IS NULL",
+    )
+    .bind(item_id)
+    .bind(month_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/months/{month_id}/items/{id}/receipt",
+    params(
+        ("month_id" = i64, Path, description = "Month ID"),
+        ("id" = i64, Path, description = "Item (Transaction) ID")
+    ),
+    request_body(content = Vec<u8>, content_type = "image/png", description = "The file itself, sent as `image/png`, `image/jpeg` or `application/pdf`"),
+    responses(
+        (status = 204, description = "Receipt stored"),
+        (status = 400, description = "Unsupported type, file too large, or storage quota exceeded"),
+        (status = 404, description = "Item not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Upload receipt",
+    description = "Stores a receipt for the item, replacing any earlier one. Files are limited to 5 MB each and 100 MB per owner. The receipt is removed with the item once the item can no longer be restored."
+)]
+pub async fn upload_receipt(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path((month_id, item_id)): Path<(i64, i64)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, PaymeError> {
+    let owner = verify_month_not_closed(&pool, claims.sub, month_id).await?;
+    verify_item(&pool, month_id, item_id).await?;
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let Some((content_type, magic)) = RECEIPT_TYPES
+        .iter()
+        .find(|(accepted, _)| content_type.eq_ignore_ascii_case(accepted))
+    else {
+        return Err(PaymeError::BadRequest(
+            "Receipts must be image/png, image/jpeg or application/pdf".to_string(),
+        ));
+    };
+    if !body.starts_with(magic) {
+        return Err(PaymeError::BadRequest(format!(
+            "File is not a valid {content_type}"
+        )));
+    }
+    if body.len() > MAX_RECEIPT_BYTES {
+        return Err(PaymeError::BadRequest(format!(
+            "Receipts can be at most {} MB",
+            MAX_RECEIPT_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+    // A replaced receipt doesn't count against the quota
+    let used: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(size), 0) FROM receipts WHERE user_id = ? AND item_id != ?",
+    )
+    .bind(owner)
+    .bind(item_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if used + body.len() as i64 > RECEIPT_QUOTA_BYTES {
+        return Err(PaymeError::BadRequest(format!(
+            "Receipt storage is limited to {} MB; delete old items to make room",
+            RECEIPT_QUOTA_BYTES / (1024 * 1024)
+        )));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO receipts (item_id, user_id, content_type, size, data) VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(item_id) DO UPDATE SET content_type = excluded.content_type, size = excluded.size, data = excluded.data, uploaded_at = datetime('now')
+        "#,
+    )
+    .bind(item_id)
+    .bind(owner)
+    .bind(*content_type)
+    .bind(body.len() as i64)
+    .bind(body.as_ref())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{month_id}/items/{id}/receipt",
+    params(
+        ("month_id" = i64, Path, description = "Month ID"),
+        ("id" = i64, Path, description = "Item (Transaction) ID")
+    ),
+    responses(
+        (status = 200, description = "The receipt as uploaded", content_type = "application/octet-stream"),
+        (status = 404, description = "Item or receipt not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Items",
+    summary = "Download receipt"
+)]
+pub async fn get_receipt(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path((month_id, item_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, PaymeError> {
+    verify_month_access(&pool, claims.sub, month_id).await?;
+    verify_item(&pool, month_id, item_id).await?;
+
+    let (content_type, data): (String, Vec<u8>) =
+        sqlx::query_as("SELECT content_type, data FROM receipts WHERE item_id = ?")
+            .bind(item_id)
+            .fetch_optional(&pool)
+            .await?
+            .ok_or(PaymeError::NotFound)?;
+
+    Ok(([(header::CONTENT_TYPE, content_type)], data))
+}
@@ -0,0 +1,155 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::PaymeError;
+use crate::handlers::months::days_in_month;
+use crate::middleware::auth::Claims;
+use crate::money::Money;
+use crate::timezone;
+
+const DEFAULT_WINDOW_DAYS: u32 = 7;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpcomingQuery {
+    /// How many days ahead to look (defaults to 7, at most 31)
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpcomingBill {
+    /// Monthly fixed expense ID, or the fixed expense template's ID when `month_id` is absent
+    pub id: i64,
+    /// The current month, unless it hasn't been created yet
+    pub month_id: Option<i64>,
+    pub label: String,
+    #[schema(value_type = f64)]
+    pub amount: Money,
+    pub category: Option<String>,
+    pub due_on: NaiveDate,
+    pub paid: bool,
+}
+
+/// When a bill due on `due_day` falls in `today`'s month, if that is between `today` and
+/// `days` days later. Due days past the end of a short month land on its last day.
+pub fn due_within(due_day: i64, today: NaiveDate, days: u32) -> Option<NaiveDate> {
+    let last_day = days_in_month(today.year(), today.month());
+    let day = u32::try_from(due_day).ok()?.clamp(1, last_day);
+    let due_on = today.with_day(day)?;
+    (due_on >= today && (due_on - today).num_days() <= i64::from(days)).then_some(due_on)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/reminders/upcoming",
+    params(UpcomingQuery),
+    responses(
+        (status = 200, body = [UpcomingBill]),
+        (status = 400, description = "Window out of range"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Upcoming bills",
+    description = "Lists fixed expenses with a due day between today and the given number of days ahead, within the current month, soonest first. Bills come from the current month with their paid status; if that month hasn't been created yet, the fixed expense templates are used and every bill is unpaid."
+)]
+pub async fn upcoming_bills(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<UpcomingQuery>,
+) -> Result<Json<Vec<UpcomingBill>>, PaymeError> {
+    let days = query.days.unwrap_or(DEFAULT_WINDOW_DAYS);
+    if days > 31 {
+        return Err(PaymeError::BadRequest(
+            "days must be between 0 and 31".to_string(),
+        ));
+    }
+
+    let today = timezone::today(&pool, claims.sub).await?;
+    let month_id: Option<i64> =
+        sqlx::query_scalar("SELECT id FROM months WHERE user_id = ? AND year = ? AND month = ?")
+            .bind(claims.sub)
+            .bind(today.year())
+            .bind(today.month() as i32)
+            .fetch_optional(&pool)
+            .await?;
+
+    let bills: Vec<(i64, String, Money, Option<String>, i64, bool)> = match month_id {
+        Some(month_id) => {
+            sqlx::query_as(
+                "SELECT id, label, amount, category, due_day, paid FROM monthly_fixed_expenses WHERE month_id = ? AND due_day IS NOT NULL",
+            )
+            .bind(month_id)
+            .fetch_all(&pool)
+            .await?
+        }
+        None => {
+            sqlx::query_as(
+                "SELECT id, label, amount, category, due_day, 0 FROM fixed_expenses WHERE user_id = ? AND due_day IS NOT NULL AND active = 1",
+            )
+            .bind(claims.sub)
+            .fetch_all(&pool)
+            .await?
+        }
+    };
+
+    let mut upcoming: Vec<UpcomingBill> = bills
+        .into_iter()
+        .filter_map(|(id, label, amount, category, due_day, paid)| {
+            due_within(due_day, today, days).map(|due_on| UpcomingBill {
+                id,
+                month_id,
+                label,
+                amount,
+                category,
+                due_on,
+                paid,
+            })
+        })
+        .collect();
+    upcoming.sort_by(|a, b| a.due_on.cmp(&b.due_on).then_with(|| a.label.cmp(&b.label)));
+
+    Ok(Json(upcoming))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_due_within_window() {
+        assert_eq!(
+            due_within(28, date(2024, 6, 25), 7),
+            Some(date(2024, 6, 28))
+        );
+        assert_eq!(
+            due_within(25, date(2024, 6, 25), 0),
+            Some(date(2024, 6, 25))
+        );
+        assert_eq!(due_within(10, date(2024, 6, 25), 7), None);
+        assert_eq!(due_within(28, date(2024, 6, 15), 7), None);
+    }
+
+    #[test]
+    fn test_due_within_clamps_to_month_end() {
+        assert_eq!(
+            due_within(31, date(2025, 2, 25), 7),
+            Some(date(2025, 2, 28))
+        );
+        assert_eq!(
+            due_within(31, date(2024, 2, 25), 7),
+            Some(date(2024, 2, 29))
+        );
+        // The window never crosses into the next month
+        assert_eq!(due_within(2, date(2024, 6, 28), 7), None);
+    }
+}
@@ -11,6 +11,7 @@ use validator::Validate;
 use crate::error::PaymeError;
 use crate::middleware::auth::Claims;
 use crate::models::RetirementBreakdownItem;
+use crate::money::Money;
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateRetirementBreakdownItem {
@@ -48,12 +49,13 @@ pub async fn create_retirement_breakdown_item(
     Json(payload): Json<CreateRetirementBreakdownItem>,
 ) -> Result<(StatusCode, Json<RetirementBreakdownItem>), PaymeError> {
     payload.validate()?;
+    let amount = Money::from_f64(payload.amount);
     let id: i64 = sqlx::query_scalar(
         "INSERT INTO retirement_breakdown_items (user_id, label, amount) VALUES (?, ?, ?) RETURNING id",
     )
     .bind(claims.sub)
     .bind(&payload.label)
-    .bind(payload.amount)
+    .bind(amount)
     .fetch_one(&pool)
     .await?;
 
@@ -63,7 +65,7 @@ pub async fn create_retirement_breakdown_item(
             id,
             user_id: claims.sub,
             label: payload.label,
-            amount: payload.amount,
+            amount,
         }),
     ))
 }
@@ -85,7 +87,7 @@ pub async fn update_retirement_breakdown_item(
     .ok_or(PaymeError::NotFound)?;
 
     let label = payload.label.unwrap_or(existing.label);
-    let amount = payload.amount.unwrap_or(existing.amount);
+    let amount = payload.amount.map_or(existing.amount, Money::from_f64);
 
     sqlx::query("UPDATE retirement_breakdown_items SET label = ?, amount = ? WHERE id = ?")
         .bind(&label)
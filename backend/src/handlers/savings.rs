@@ -1,16 +1,29 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use validator::Validate;
 
+use crate::config::BusyRetry;
+use crate::db;
+use crate::email;
 use crate::error::PaymeError;
+use crate::handlers::savings_accounts;
 use crate::middleware::auth::Claims;
+use crate::models::SavingsAccount;
+use crate::money::Money;
 
 #[derive(Serialize, ToSchema)]
 pub struct SavingsResponse {
-    pub savings: f64,
-    pub savings_goal: f64,
+    #[schema(value_type = f64)]
+    pub savings: Money,
+    #[schema(value_type = f64)]
+    pub savings_goal: Money,
+    /// Named accounts with their balances and goals, besides the built-in savings balance
+    pub accounts: Vec<SavingsAccount>,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -27,7 +40,8 @@ pub struct UpdateSavingsGoal {
 
 #[derive(Serialize, ToSchema)]
 pub struct RetirementSavingsResponse {
-    pub retirement_savings: f64,
+    #[schema(value_type = f64)]
+    pub retirement_savings: Money,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -36,6 +50,27 @@ pub struct UpdateRetirementSavings {
     pub retirement_savings: f64,
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SavingsHistoryQuery {
+    /// Number of most recent months to include (defaults to 12)
+    pub months: Option<i64>,
+    /// Fill months without a snapshot with the previous month's values instead of omitting them
+    #[serde(default)]
+    pub carry_forward: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SavingsHistoryPoint {
+    pub year: i32,
+    pub month: i32,
+    #[schema(value_type = f64)]
+    pub savings: Money,
+    #[schema(value_type = f64)]
+    pub retirement_savings: Money,
+    pub carried_forward: bool,
+}
+
 #[utoipa::path(
     get,
     path = "/api/savings",
@@ -45,22 +80,62 @@ pub struct UpdateRetirementSavings {
     ),
     tag = "Wealth",
     summary = "Get savings balance",
-    description = "Retrieves the user's total liquid savings amount stored in their profile."
+    description = "Retrieves the user's total liquid savings amount stored in their profile, and the progress of each named savings account."
 )]
 pub async fn get_savings(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
 ) -> Result<Json<SavingsResponse>, PaymeError> {
-    let (savings, savings_goal): (f64, f64) =
+    Ok(Json(savings_response(&pool, claims.sub).await?))
+}
+
+async fn savings_response(pool: &SqlitePool, user_id: i64) -> Result<SavingsResponse, PaymeError> {
+    let (savings, savings_goal): (Money, Money) =
         sqlx::query_as("SELECT savings, savings_goal FROM users WHERE id = ?")
-            .bind(claims.sub)
-            .fetch_one(&pool)
+            .bind(user_id)
+            .fetch_one(pool)
             .await?;
+    let accounts = savings_accounts::load_accounts(pool, user_id).await?;
 
-    Ok(Json(SavingsResponse {
+    Ok(SavingsResponse {
         savings,
         savings_goal,
-    }))
+        accounts,
+    })
+}
+
+pub(crate) async fn current_savings(pool: &SqlitePool, user_id: i64) -> Result<Money, PaymeError> {
+    let savings = sqlx::query_scalar("SELECT savings FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(savings)
+}
+
+/// Emails the user when their savings have just crossed the goal, starting from `before`.
+pub(crate) async fn notify_if_goal_reached(pool: &SqlitePool, user_id: i64, before: Money) {
+    let balances: Result<(Money, Money), sqlx::Error> =
+        sqlx::query_as("SELECT savings, savings_goal FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await;
+    match balances {
+        Ok((savings, goal)) if goal > Money::ZERO && before < goal && savings >= goal => {
+            let body = format!(
+                "Congratulations! Your savings have reached {savings}, meeting your goal of {goal}.\n"
+            );
+            email::enqueue(
+                pool,
+                user_id,
+                email::SAVINGS_GOAL_REACHED,
+                "You reached your savings goal",
+                &body,
+            )
+            .await;
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to check savings goal for user {}: {}", user_id, e),
+    }
 }
 
 #[utoipa::path(
@@ -78,24 +153,28 @@ pub async fn get_savings(
 pub async fn update_savings(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
     Json(payload): Json<UpdateSavings>,
 ) -> Result<Json<SavingsResponse>, PaymeError> {
     payload.validate()?;
-    sqlx::query("UPDATE users SET savings = ? WHERE id = ?")
-        .bind(payload.savings)
-        .bind(claims.sub)
-        .execute(&pool)
-        .await?;
-
-    let savings_goal: f64 = sqlx::query_scalar("SELECT savings_goal FROM users WHERE id = ?")
-        .bind(claims.sub)
-        .fetch_one(&pool)
-        .await?;
+    let before = current_savings(&pool, claims.sub).await?;
+    db::retry_busy(&busy_retry, || async {
+        let mut tx = pool.begin().await?;
+        let (from_items, _) = item_transfer_totals(&mut tx, claims.sub).await?;
+        let savings = Money::from_f64(payload.savings);
+        sqlx::query("UPDATE users SET savings = ?, savings_adjustment = ? WHERE id = ?")
+            .bind(savings)
+            .bind(savings - from_items)
+            .bind(claims.sub)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    })
+    .await?;
+    notify_if_goal_reached(&pool, claims.sub, before).await;
 
-    Ok(Json(SavingsResponse {
-        savings: payload.savings,
-        savings_goal,
-    }))
+    Ok(Json(savings_response(&pool, claims.sub).await?))
 }
 
 #[utoipa::path(
@@ -117,20 +196,12 @@ pub async fn update_savings_goal(
 ) -> Result<Json<SavingsResponse>, PaymeError> {
     payload.validate()?;
     sqlx::query("UPDATE users SET savings_goal = ? WHERE id = ?")
-        .bind(payload.savings_goal)
+        .bind(Money::from_f64(payload.savings_goal))
         .bind(claims.sub)
         .execute(&pool)
         .await?;
 
-    let savings: f64 = sqlx::query_scalar("SELECT savings FROM users WHERE id = ?")
-        .bind(claims.sub)
-        .fetch_one(&pool)
-        .await?;
-
-    Ok(Json(SavingsResponse {
-        savings,
-        savings_goal: payload.savings_goal,
-    }))
+    Ok(Json(savings_response(&pool, claims.sub).await?))
 }
 
 #[utoipa::path(
@@ -148,12 +219,12 @@ pub async fn get_retirement_savings(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
 ) -> Result<Json<RetirementSavingsResponse>, PaymeError> {
-    let retirement_savings: f64 =
+    let retirement_savings: Money =
         sqlx::query_scalar("SELECT retirement_savings FROM users WHERE id = ?")
             .bind(claims.sub)
             .fetch_one(&pool)
             .await
-            .unwrap_or(0.0);
+            .unwrap_or(Money::ZERO);
 
     Ok(Json(RetirementSavingsResponse { retirement_savings }))
 }
@@ -173,16 +244,221 @@ pub async fn get_retirement_savings(
 pub async fn update_retirement_savings(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(busy_retry): axum::Extension<BusyRetry>,
     Json(payload): Json<UpdateRetirementSavings>,
 ) -> Result<Json<RetirementSavingsResponse>, PaymeError> {
     payload.validate()?;
-    sqlx::query("UPDATE users SET retirement_savings = ? WHERE id = ?")
-        .bind(payload.retirement_savings)
+    let retirement_savings = Money::from_f64(payload.retirement_savings);
+    db::retry_busy(&busy_retry, || async {
+        let mut tx = pool.begin().await?;
+        let (_, from_items) = item_transfer_totals(&mut tx, claims.sub).await?;
+        sqlx::query(
+            "UPDATE users SET retirement_savings = ?, retirement_savings_adjustment = ? WHERE id = ?",
+        )
+        .bind(retirement_savings)
+        .bind(retirement_savings - from_items)
         .bind(claims.sub)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
+        tx.commit().await?;
+        Ok(())
+    })
+    .await?;
 
-    Ok(Json(RetirementSavingsResponse {
-        retirement_savings: payload.retirement_savings,
+    Ok(Json(RetirementSavingsResponse { retirement_savings }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/savings/history",
+    params(SavingsHistoryQuery),
+    responses(
+        (status = 200, body = [SavingsHistoryPoint]),
+        (status = 400, description = "Invalid window size"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Get savings history",
+    description = "Returns month-end savings and retirement savings from the monthly snapshots, ordered chronologically."
+)]
+pub async fn get_savings_history(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<SavingsHistoryQuery>,
+) -> Result<Json<Vec<SavingsHistoryPoint>>, PaymeError> {
+    let window = query.months.unwrap_or(12);
+    if !(1..=240).contains(&window) {
+        return Err(PaymeError::BadRequest(
+            "months must be between 1 and 240".to_string(),
+        ));
+    }
+
+    let mut rows: Vec<(i32, i32, Option<Money>, Option<Money>)> = sqlx::query_as(
+        r#"
+        SELECT m.year, m.month, ms.savings, ms.retirement_savings
+        FROM months m
+        LEFT JOIN monthly_savings ms ON ms.month_id = m.id
+        WHERE m.user_id = ?
+        ORDER BY m.year DESC, m.month DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(window)
+    .fetch_all(&pool)
+    .await?;
+    rows.reverse();
+
+    let mut history = Vec::with_capacity(rows.len());
+    let mut last: Option<(Money, Money)> = None;
+
+    for (year, month, savings, retirement_savings) in rows {
+        match (savings, retirement_savings) {
+            (Some(savings), Some(retirement_savings)) => {
+                last = Some((savings, retirement_savings));
+                history.push(SavingsHistoryPoint {
+                    year,
+                    month,
+                    savings,
+                    retirement_savings,
+                    carried_forward: false,
+                });
+            }
+            _ => {
+                if let (true, Some((savings, retirement_savings))) = (query.carry_forward, last) {
+                    history.push(SavingsHistoryPoint {
+                        year,
+                        month,
+                        savings,
+                        retirement_savings,
+                        carried_forward: true,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(Json(history))
+}
+
+/// Net amount the user's live items have moved into savings and retirement savings. Together
+/// with the adjustments recorded when a balance is set by hand, this is what the balances
+/// should be.
+async fn item_transfer_totals(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: i64,
+) -> Result<(Money, Money), PaymeError> {
+    Ok(sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM(CASE i.savings_destination WHEN 'savings' THEN i.amount WHEN 'savings_withdrawal' THEN -i.amount ELSE 0 END), 0),
+            COALESCE(SUM(CASE i.savings_destination WHEN 'retirement_savings' THEN i.amount WHEN 'retirement_savings_withdrawal' THEN -i.amount ELSE 0 END), 0)
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        WHERE m.user_id = ? AND i.deleted_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut **tx)
+    .await?)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReconcileQuery {
+    /// Overwrite the stored balances with the recomputed ones instead of only reporting
+    #[serde(default)]
+    pub fix: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BalanceReconciliation {
+    #[schema(value_type = f64)]
+    pub stored: Money,
+    /// Manual adjustments plus the net of all savings items
+    #[schema(value_type = f64)]
+    pub expected: Money,
+    /// `stored - expected`; zero when the balance is consistent
+    #[schema(value_type = f64)]
+    pub discrepancy: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SavingsReconciliation {
+    pub savings: BalanceReconciliation,
+    pub retirement_savings: BalanceReconciliation,
+    /// Whether the stored balances were overwritten
+    pub corrected: bool,
+}
+
+fn reconcile(stored: Money, adjustment: Money, from_items: Money) -> BalanceReconciliation {
+    let expected = adjustment + from_items;
+    BalanceReconciliation {
+        stored,
+        expected,
+        discrepancy: stored - expected,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/savings/reconcile",
+    params(ReconcileQuery),
+    responses(
+        (status = 200, body = SavingsReconciliation),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Reconcile savings balances",
+    description = "Recomputes savings and retirement savings from the balances last set by hand plus every live item transferring to or withdrawing from them, and reports how far the stored balances have drifted. With fix=true, drifted balances are overwritten with the recomputed ones."
+)]
+pub async fn reconcile_savings(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<ReconcileQuery>,
+) -> Result<Json<SavingsReconciliation>, PaymeError> {
+    let mut tx = pool.begin().await?;
+    let (savings, retirement_savings, savings_adjustment, retirement_savings_adjustment): (
+        Money,
+        Money,
+        Money,
+        Money,
+    ) = sqlx::query_as(
+        "SELECT savings, retirement_savings, savings_adjustment, retirement_savings_adjustment FROM users WHERE id = ?",
+    )
+    .bind(claims.sub)
+    .fetch_one(&mut *tx)
+    .await?;
+    let (to_savings, to_retirement) = item_transfer_totals(&mut tx, claims.sub).await?;
+
+    let savings = reconcile(savings, savings_adjustment, to_savings);
+    let retirement_savings = reconcile(
+        retirement_savings,
+        retirement_savings_adjustment,
+        to_retirement,
+    );
+
+    let corrected = query.fix
+        && (savings.discrepancy != Money::ZERO || retirement_savings.discrepancy != Money::ZERO);
+    if corrected {
+        sqlx::query("UPDATE users SET savings = ?, retirement_savings = ? WHERE id = ?")
+            .bind(savings.expected)
+            .bind(retirement_savings.expected)
+            .bind(claims.sub)
+            .execute(&mut *tx)
+            .await?;
+        tracing::info!(
+            "Reconciled savings for user {}: savings off by {}, retirement savings off by {}",
+            claims.sub,
+            savings.discrepancy,
+            retirement_savings.discrepancy
+        );
+    }
+    tx.commit().await?;
+
+    Ok(Json(SavingsReconciliation {
+        savings,
+        retirement_savings,
+        corrected,
     }))
 }
@@ -0,0 +1,711 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::config::WebhookPolicy;
+use crate::error::PaymeError;
+use crate::middleware::auth::Claims;
+use crate::models::SavingsAccount;
+use crate::money::{self, validate_cents, Money, RoundingMode};
+use crate::timezone;
+use crate::webhooks;
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateSavingsAccount {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// Opening balance; defaults to zero
+    #[validate(custom(function = "validate_cents"))]
+    pub balance: Option<f64>,
+    #[validate(range(min = 0.01))]
+    pub goal: Option<f64>,
+    /// Date the goal should be reached by
+    pub goal_date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateSavingsAccount {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(custom(function = "validate_cents"))]
+    pub balance: Option<f64>,
+    #[validate(range(min = 0.01))]
+    pub goal: Option<f64>,
+    pub goal_date: Option<NaiveDate>,
+}
+
+pub(crate) async fn load_accounts(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<SavingsAccount>, PaymeError> {
+    let accounts = sqlx::query_as(
+        "SELECT id, user_id, name, balance, goal, goal_date FROM savings_accounts WHERE user_id = ? ORDER BY name, id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(accounts)
+}
+
+async fn find_account(
+    pool: &SqlitePool,
+    user_id: i64,
+    account_id: i64,
+) -> Result<SavingsAccount, PaymeError> {
+    sqlx::query_as(
+        "SELECT id, user_id, name, balance, goal, goal_date FROM savings_accounts WHERE id = ? AND user_id = ?",
+    )
+    .bind(account_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)
+}
+
+/// Fires `savings.goal_reached` for each account in `before` whose balance has since reached its
+/// goal. Accounts already at their goal beforehand don't fire again.
+pub(crate) async fn notify_goals_reached(
+    pool: &SqlitePool,
+    webhook_policy: WebhookPolicy,
+    user_id: i64,
+    before: &[SavingsAccount],
+) {
+    let accounts = match load_accounts(pool, user_id).await {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            tracing::error!("Failed to check savings goals for user {}: {}", user_id, e);
+            return;
+        }
+    };
+    for account in accounts {
+        let Some(goal) = account.goal else {
+            continue;
+        };
+        let was_reached = before
+            .iter()
+            .find(|b| b.id == account.id)
+            .is_none_or(|b| b.goal.is_some_and(|g| b.balance >= g));
+        if was_reached || account.balance < goal {
+            continue;
+        }
+        if let Ok(data) = serde_json::to_value(&account) {
+            webhooks::enqueue(
+                pool,
+                webhook_policy,
+                user_id,
+                webhooks::SAVINGS_GOAL_REACHED,
+                data,
+            )
+            .await;
+        }
+    }
+}
+
+async fn verify_name_free(
+    pool: &SqlitePool,
+    user_id: i64,
+    name: &str,
+    except_id: i64,
+) -> Result<(), PaymeError> {
+    let taken: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM savings_accounts WHERE user_id = ? AND name = ? AND id != ?",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(except_id)
+    .fetch_optional(pool)
+    .await?;
+    if taken.is_some() {
+        return Err(PaymeError::Conflict(
+            "A savings account with this name already exists".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/savings-accounts",
+    responses(
+        (status = 200, body = [SavingsAccount]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "List savings accounts",
+    description = "Lists the user's named savings accounts with their balances and goals, alongside the built-in savings and retirement balances."
+)]
+pub async fn list_savings_accounts(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<SavingsAccount>>, PaymeError> {
+    Ok(Json(load_accounts(&pool, claims.sub).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/savings-accounts",
+    request_body = CreateSavingsAccount,
+    responses(
+        (status = 201, body = SavingsAccount),
+        (status = 409, description = "An account with this name already exists"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Create savings account",
+    description = "Adds a named savings account. Items with the `account` destination and this account's id transfer into it; `account_withdrawal` takes money out."
+)]
+pub async fn create_savings_account(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<CreateSavingsAccount>,
+) -> Result<(StatusCode, Json<SavingsAccount>), PaymeError> {
+    payload.validate()?;
+    verify_name_free(&pool, claims.sub, &payload.name, 0).await?;
+
+    let balance = payload.balance.map_or(Money::ZERO, Money::from_f64);
+    let goal = payload.goal.map(Money::from_f64);
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO savings_accounts (user_id, name, balance, goal, goal_date) VALUES (?, ?, ?, ?, ?) RETURNING id",
+    )
+    .bind(claims.sub)
+    .bind(&payload.name)
+    .bind(balance)
+    .bind(goal)
+    .bind(payload.goal_date)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SavingsAccount {
+            id,
+            user_id: claims.sub,
+            name: payload.name,
+            balance,
+            goal,
+            goal_date: payload.goal_date,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/savings-accounts/{id}",
+    params(("id" = i64, Path, description = "Savings account ID")),
+    request_body = UpdateSavingsAccount,
+    responses(
+        (status = 200, body = SavingsAccount),
+        (status = 404, description = "Account not found"),
+        (status = 409, description = "An account with this name already exists"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Update savings account",
+    description = "Renames an account, corrects its balance or changes its goal and target date. Reaching the goal fires `savings.goal_reached`."
+)]
+pub async fn update_savings_account(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
+    Path(account_id): Path<i64>,
+    Json(payload): Json<UpdateSavingsAccount>,
+) -> Result<Json<SavingsAccount>, PaymeError> {
+    payload.validate()?;
+    let existing = find_account(&pool, claims.sub, account_id).await?;
+    let before = [existing.clone()];
+
+    let name = payload.name.unwrap_or(existing.name);
+    let balance = payload.balance.map_or(existing.balance, Money::from_f64);
+    let goal = payload.goal.map(Money::from_f64).or(existing.goal);
+    let goal_date = payload.goal_date.or(existing.goal_date);
+    verify_name_free(&pool, claims.sub, &name, account_id).await?;
+
+    sqlx::query(
+        "UPDATE savings_accounts SET name = ?, balance = ?, goal = ?, goal_date = ? WHERE id = ?",
+    )
+    .bind(&name)
+    .bind(balance)
+    .bind(goal)
+    .bind(goal_date)
+    .bind(account_id)
+    .execute(&pool)
+    .await?;
+    notify_goals_reached(&pool, webhook_policy, claims.sub, &before).await;
+
+    Ok(Json(SavingsAccount {
+        id: account_id,
+        user_id: claims.sub,
+        name,
+        balance,
+        goal,
+        goal_date,
+    }))
+}
+
+/// Progress of a savings account towards its goal
+#[derive(Serialize, ToSchema)]
+pub struct AccountGoal {
+    pub savings_account_id: i64,
+    pub name: String,
+    #[schema(value_type = f64)]
+    pub balance: Money,
+    #[schema(value_type = f64)]
+    pub target: Money,
+    pub target_date: Option<NaiveDate>,
+    /// Capped at 100
+    pub percent_complete: f64,
+    #[schema(value_type = f64)]
+    pub remaining: Money,
+    /// Monthly contribution that reaches the target by its date, counting the current month;
+    /// absent without a date or once the date has passed
+    #[schema(value_type = Option<f64>)]
+    pub required_monthly: Option<Money>,
+}
+
+/// What must be saved each month from `today` to put `remaining` away by `target_date`. Months
+/// are counted from the current one up to the target's, and a target later this month needs it
+/// all now.
+pub fn required_monthly(
+    remaining: Money,
+    today: NaiveDate,
+    target_date: NaiveDate,
+) -> Option<Money> {
+    if target_date < today {
+        return None;
+    }
+    if remaining <= Money::ZERO {
+        return Some(Money::ZERO);
+    }
+    let months = (target_date.year() * 12 + target_date.month0() as i32)
+        - (today.year() * 12 + today.month0() as i32);
+    let months = i64::from(months.max(1));
+    // Rounded up to the cent so the contributions add up to at least the remainder
+    Some(Money::from_cents((remaining.cents() + months - 1) / months))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/savings/goals",
+    responses(
+        (status = 200, body = [AccountGoal]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Savings account goals",
+    description = "Lists each savings account that has a goal with its balance, percent complete and, when it has a target date, the monthly contribution needed to reach the goal by then."
+)]
+pub async fn list_account_goals(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<AccountGoal>>, PaymeError> {
+    let today = timezone::today(&pool, claims.sub).await?;
+    let goals = load_accounts(&pool, claims.sub)
+        .await?
+        .into_iter()
+        .filter_map(|account| {
+            let target = account.goal?;
+            let remaining = (target - account.balance).max(Money::ZERO);
+            Some(AccountGoal {
+                savings_account_id: account.id,
+                name: account.name,
+                balance: account.balance,
+                target,
+                target_date: account.goal_date,
+                percent_complete: (account.balance.to_f64() / target.to_f64() * 100.0)
+                    .clamp(0.0, 100.0),
+                remaining,
+                required_monthly: account
+                    .goal_date
+                    .and_then(|date| required_monthly(remaining, today, date)),
+            })
+        })
+        .collect();
+
+    Ok(Json(goals))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/savings-accounts/{id}",
+    params(("id" = i64, Path, description = "Savings account ID")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 409, description = "Items still transfer to or from the account"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Delete savings account"
+)]
+pub async fn delete_savings_account(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(account_id): Path<i64>,
+) -> Result<StatusCode, PaymeError> {
+    // Deleted items still count: restoring one re-applies its transfer to the account
+    let referenced: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM items i
+            JOIN savings_accounts sa ON sa.id = i.savings_account_id
+            WHERE sa.id = ? AND sa.user_id = ?
+        )
+        "#,
+    )
+    .bind(account_id)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+    if referenced {
+        return Err(PaymeError::Conflict(
+            "Items still transfer to or from this account; move them to another destination first"
+                .to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    // Leaves the remaining rules short of 100%, so contributions are refused until they're fixed
+    sqlx::query(
+        "DELETE FROM savings_allocation_rules WHERE savings_account_id = ? AND user_id = ?",
+    )
+    .bind(account_id)
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "DELETE FROM savings_transfers WHERE (from_account_id = ?1 OR to_account_id = ?1) AND user_id = ?2",
+    )
+    .bind(account_id)
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM savings_accounts WHERE id = ? AND user_id = ?")
+        .bind(account_id)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow, ToSchema, Validate)]
+pub struct AllocationRule {
+    pub savings_account_id: i64,
+    /// Share of each contribution, e.g. 60 for 60%
+    #[validate(range(min = 0.01, max = 100.0))]
+    pub percent: f64,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateAllocationRules {
+    /// Replaces the current rules; the percentages must add up to 100, or the list be empty
+    #[validate(nested)]
+    pub rules: Vec<AllocationRule>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct Contribute {
+    #[validate(range(min = 0.01), custom(function = "validate_cents"))]
+    pub amount: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ContributionShare {
+    pub savings_account_id: i64,
+    pub name: String,
+    /// Amount credited by this contribution
+    #[schema(value_type = f64)]
+    pub amount: Money,
+    /// Account balance afterwards
+    #[schema(value_type = f64)]
+    pub balance: Money,
+}
+
+/// Percentages in hundredths of a percent, so 100% is checked exactly.
+fn basis_points(percent: f64) -> i64 {
+    (percent * 100.0).round() as i64
+}
+
+fn verify_total(rules: &[AllocationRule]) -> Result<(), PaymeError> {
+    let total: i64 = rules.iter().map(|r| basis_points(r.percent)).sum();
+    if total != 10_000 {
+        return Err(PaymeError::BadRequest(format!(
+            "Allocation rules must add up to 100%, not {}%",
+            total as f64 / 100.0
+        )));
+    }
+    Ok(())
+}
+
+async fn load_rules(pool: &SqlitePool, user_id: i64) -> Result<Vec<AllocationRule>, PaymeError> {
+    let rules = sqlx::query_as(
+        "SELECT savings_account_id, percent FROM savings_allocation_rules WHERE user_id = ? ORDER BY id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rules)
+}
+
+/// Splits `amount` by the rules' percentages with the user's rounding mode, keeping the shares'
+/// total exactly equal to the amount.
+fn split(amount: Money, rules: &[AllocationRule], mode: RoundingMode) -> Vec<Money> {
+    let weights: Vec<i64> = rules.iter().map(|r| basis_points(r.percent)).collect();
+    amount.allocate(&weights, mode)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/savings/allocation-rules",
+    responses(
+        (status = 200, body = [AllocationRule]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "List savings allocation rules",
+    description = "Returns how contributions are split across savings accounts, in the order they are applied."
+)]
+pub async fn list_allocation_rules(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<AllocationRule>>, PaymeError> {
+    Ok(Json(load_rules(&pool, claims.sub).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/savings/allocation-rules",
+    request_body = UpdateAllocationRules,
+    responses(
+        (status = 200, body = [AllocationRule]),
+        (status = 400, description = "Percentages don't add up to 100, or an account is repeated"),
+        (status = 404, description = "Savings account not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Set savings allocation rules",
+    description = "Replaces the rules for splitting contributions across savings accounts. Contributions are split with the user's rounding mode and the shares always add up to the amount contributed."
+)]
+pub async fn update_allocation_rules(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<UpdateAllocationRules>,
+) -> Result<Json<Vec<AllocationRule>>, PaymeError> {
+    payload.validate()?;
+    if !payload.rules.is_empty() {
+        verify_total(&payload.rules)?;
+    }
+    for (i, rule) in payload.rules.iter().enumerate() {
+        if payload.rules[..i]
+            .iter()
+            .any(|r| r.savings_account_id == rule.savings_account_id)
+        {
+            return Err(PaymeError::BadRequest(
+                "Each savings account can appear in only one rule".to_string(),
+            ));
+        }
+        find_account(&pool, claims.sub, rule.savings_account_id).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM savings_allocation_rules WHERE user_id = ?")
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    for rule in &payload.rules {
+        sqlx::query(
+            "INSERT INTO savings_allocation_rules (user_id, savings_account_id, percent) VALUES (?, ?, ?)",
+        )
+        .bind(claims.sub)
+        .bind(rule.savings_account_id)
+        .bind(rule.percent)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Json(payload.rules))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/savings/contribute",
+    request_body = Contribute,
+    responses(
+        (status = 200, body = [ContributionShare]),
+        (status = 400, description = "No allocation rules, or they don't add up to 100%"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Contribute to savings accounts",
+    description = "Credits the amount to the savings accounts according to the allocation rules, all at once. Shares are rounded to the cent with the user's rounding mode; if that leaves them a cent or two off, the shares rounding moved furthest give or take the difference, so they always add up to the amount."
+)]
+pub async fn contribute(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
+    Json(payload): Json<Contribute>,
+) -> Result<Json<Vec<ContributionShare>>, PaymeError> {
+    payload.validate()?;
+    let rules = load_rules(&pool, claims.sub).await?;
+    if rules.is_empty() {
+        return Err(PaymeError::BadRequest(
+            "Set up allocation rules before contributing".to_string(),
+        ));
+    }
+    verify_total(&rules)?;
+
+    let before = load_accounts(&pool, claims.sub).await?;
+    let mode = money::rounding_mode(&pool, claims.sub).await?;
+    let shares = split(Money::from_f64(payload.amount), &rules, mode);
+    let mut tx = pool.begin().await?;
+    let mut credited = Vec::with_capacity(rules.len());
+    for (rule, share) in rules.iter().zip(shares) {
+        let (name, balance): (String, Money) = sqlx::query_as(
+            "UPDATE savings_accounts SET balance = balance + ? WHERE id = ? AND user_id = ? RETURNING name, balance",
+        )
+        .bind(share)
+        .bind(rule.savings_account_id)
+        .bind(claims.sub)
+        .fetch_one(&mut *tx)
+        .await?;
+        credited.push(ContributionShare {
+            savings_account_id: rule.savings_account_id,
+            name,
+            amount: share,
+            balance,
+        });
+    }
+    tx.commit().await?;
+    notify_goals_reached(&pool, webhook_policy, claims.sub, &before).await;
+
+    Ok(Json(credited))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TransferSavings {
+    pub from_account: i64,
+    pub to_account: i64,
+    #[validate(range(min = 0.01), custom(function = "validate_cents"))]
+    pub amount: f64,
+}
+
+/// A record of money moved from one savings account to another
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct SavingsTransfer {
+    pub id: i64,
+    pub from_account_id: i64,
+    pub to_account_id: i64,
+    #[schema(value_type = f64)]
+    pub amount: Money,
+    pub created_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SavingsTransferReceipt {
+    pub transfer: SavingsTransfer,
+    /// The source account after the transfer
+    pub from: SavingsAccount,
+    /// The destination account after the transfer
+    pub to: SavingsAccount,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/savings/transfer",
+    request_body = TransferSavings,
+    responses(
+        (status = 200, body = SavingsTransferReceipt),
+        (status = 400, description = "Same account on both sides, or the amount exceeds the source balance"),
+        (status = 404, description = "Savings account not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "Transfer between savings accounts",
+    description = "Moves the amount from one savings account to another in a single transaction and records the transfer in the account history. A transfer may not take the source account below zero."
+)]
+pub async fn transfer_savings(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
+    Json(payload): Json<TransferSavings>,
+) -> Result<Json<SavingsTransferReceipt>, PaymeError> {
+    payload.validate()?;
+    if payload.from_account == payload.to_account {
+        return Err(PaymeError::BadRequest(
+            "Can't transfer from an account to itself".to_string(),
+        ));
+    }
+    find_account(&pool, claims.sub, payload.from_account).await?;
+    let to_before = find_account(&pool, claims.sub, payload.to_account).await?;
+
+    let amount = Money::from_f64(payload.amount);
+    let mut tx = pool.begin().await?;
+    let from: SavingsAccount = sqlx::query_as(
+        "UPDATE savings_accounts SET balance = balance - ? WHERE id = ? AND user_id = ? RETURNING id, user_id, name, balance, goal, goal_date",
+    )
+    .bind(amount)
+    .bind(payload.from_account)
+    .bind(claims.sub)
+    .fetch_one(&mut *tx)
+    .await?;
+    if from.balance < Money::ZERO {
+        return Err(PaymeError::BadRequest(format!(
+            "Transfer exceeds the balance of {}",
+            from.name
+        )));
+    }
+    let to: SavingsAccount = sqlx::query_as(
+        "UPDATE savings_accounts SET balance = balance + ? WHERE id = ? AND user_id = ? RETURNING id, user_id, name, balance, goal, goal_date",
+    )
+    .bind(amount)
+    .bind(payload.to_account)
+    .bind(claims.sub)
+    .fetch_one(&mut *tx)
+    .await?;
+    let transfer: SavingsTransfer = sqlx::query_as(
+        "INSERT INTO savings_transfers (user_id, from_account_id, to_account_id, amount) VALUES (?, ?, ?, ?) RETURNING id, from_account_id, to_account_id, amount, created_at",
+    )
+    .bind(claims.sub)
+    .bind(payload.from_account)
+    .bind(payload.to_account)
+    .bind(amount)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    notify_goals_reached(&pool, webhook_policy, claims.sub, &[to_before]).await;
+
+    Ok(Json(SavingsTransferReceipt { transfer, from, to }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/savings/transfers",
+    responses(
+        (status = 200, body = [SavingsTransfer]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Wealth",
+    summary = "List savings transfers",
+    description = "Returns the transfers between the user's savings accounts, newest first."
+)]
+pub async fn list_savings_transfers(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<SavingsTransfer>>, PaymeError> {
+    let transfers = sqlx::query_as(
+        "SELECT id, from_account_id, to_account_id, amount, created_at FROM savings_transfers WHERE user_id = ? ORDER BY id DESC",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(transfers))
+}
@@ -8,9 +8,12 @@ use sqlx::SqlitePool;
 use utoipa::ToSchema;
 use validator::Validate;
 
+use crate::config::WebhookPolicy;
 use crate::error::PaymeError;
 use crate::middleware::auth::Claims;
 use crate::models::CustomSavingsGoal;
+use crate::money::Money;
+use crate::webhooks;
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateSavingsGoal {
@@ -52,14 +55,15 @@ pub async fn create_savings_goal(
     Json(payload): Json<CreateSavingsGoal>,
 ) -> Result<(StatusCode, Json<CustomSavingsGoal>), PaymeError> {
     payload.validate()?;
-    let current_amount = payload.current_amount.unwrap_or(0.0);
+    let current_amount = payload.current_amount.map_or(Money::ZERO, Money::from_f64);
+    let target_amount = Money::from_f64(payload.target_amount);
     let id: i64 = sqlx::query_scalar(
         "INSERT INTO custom_savings_goals (user_id, name, current_amount, target_amount) VALUES (?, ?, ?, ?) RETURNING id",
     )
     .bind(claims.sub)
     .bind(&payload.name)
     .bind(current_amount)
-    .bind(payload.target_amount)
+    .bind(target_amount)
     .fetch_one(&pool)
     .await?;
 
@@ -70,7 +74,7 @@ pub async fn create_savings_goal(
             user_id: claims.sub,
             name: payload.name,
             current_amount,
-            target_amount: payload.target_amount,
+            target_amount,
         }),
     ))
 }
@@ -78,6 +82,7 @@ pub async fn create_savings_goal(
 pub async fn update_savings_goal(
     State(pool): State<SqlitePool>,
     axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(webhook_policy): axum::Extension<WebhookPolicy>,
     Path(goal_id): Path<i64>,
     Json(payload): Json<UpdateSavingsGoal>,
 ) -> Result<Json<CustomSavingsGoal>, PaymeError> {
@@ -92,8 +97,12 @@ pub async fn update_savings_goal(
     .ok_or(PaymeError::NotFound)?;
 
     let name = payload.name.unwrap_or(existing.name);
-    let current_amount = payload.current_amount.unwrap_or(existing.current_amount);
-    let target_amount = payload.target_amount.unwrap_or(existing.target_amount);
+    let current_amount = payload
+        .current_amount
+        .map_or(existing.current_amount, Money::from_f64);
+    let target_amount = payload
+        .target_amount
+        .map_or(existing.target_amount, Money::from_f64);
 
     sqlx::query(
         "UPDATE custom_savings_goals SET name = ?, current_amount = ?, target_amount = ? WHERE id = ?",
@@ -105,13 +114,28 @@ pub async fn update_savings_goal(
     .execute(&pool)
     .await?;
 
-    Ok(Json(CustomSavingsGoal {
+    let goal = CustomSavingsGoal {
         id: goal_id,
         user_id: claims.sub,
         name,
         current_amount,
         target_amount,
-    }))
+    };
+    let was_reached = existing.current_amount >= existing.target_amount;
+    if !was_reached && goal.current_amount >= goal.target_amount {
+        if let Ok(data) = serde_json::to_value(&goal) {
+            webhooks::enqueue(
+                &pool,
+                webhook_policy,
+                claims.sub,
+                webhooks::SAVINGS_GOAL_REACHED,
+                data,
+            )
+            .await;
+        }
+    }
+
+    Ok(Json(goal))
 }
 
 pub async fn delete_savings_goal(
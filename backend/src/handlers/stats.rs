@@ -1,9 +1,23 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::currency;
+use crate::email;
 use crate::error::PaymeError;
+use crate::handlers::months::{days_in_month, find_user_month};
+use crate::handlers::{budget, fixed_expenses};
 use crate::middleware::auth::Claims;
-use crate::models::{CategoryStats, MonthlyStats, StatsResponse};
+use crate::models::{
+    AlertSeverity, AlertThresholds, BudgetAlert, CategoryStats, MonthlyStats, StatsResponse,
+};
+use crate::money::Money;
+use crate::timezone;
 
 #[utoipa::path(
     get,
@@ -14,7 +28,7 @@ use crate::models::{CategoryStats, MonthlyStats, StatsResponse};
     ),
     tag = "Insights",
     summary = "Generate financial statistics",
-    description = "Calculates average monthly spending/income, monthly trends (Net income), and month-over-month category performance comparisons."
+    description = "Calculates average monthly spending/income, monthly trends (Net income), month-over-month category performance comparisons, and alerts for categories past the user's budget thresholds. Totals are in the base currency, with foreign-currency items converted at the user's exchange rate for each item's date; each trend point also lists spending per currency unconverted. Fails with 422 if a needed rate is missing."
 )]
 pub async fn get_stats(
     State(pool): State<SqlitePool>,
@@ -27,61 +41,40 @@ pub async fn get_stats(
     .fetch_all(&pool)
     .await?;
 
+    let fixed_by_category = fixed_expenses::load_groups(&pool, claims.sub).await?;
+
     if months.is_empty() {
         return Ok(Json(StatsResponse {
             category_comparisons: vec![],
             monthly_trends: vec![],
             average_monthly_spending: 0.0,
             average_monthly_income: 0.0,
+            alerts: vec![],
+            fixed_by_category,
         }));
     }
 
+    let base_currency = currency::base_currency(&pool, claims.sub).await?;
     let mut monthly_trends: Vec<MonthlyStats> = vec![];
-    let mut total_spending = 0.0;
-    let mut total_income_all = 0.0;
+    let mut total_spending = Money::ZERO;
+    let mut total_income_all = Money::ZERO;
 
     for (month_id, year, month) in &months {
-        let income: (f64,) = sqlx::query_as(
-            "SELECT COALESCE(SUM(amount), 0.0) FROM income_entries WHERE month_id = ?",
-        )
-        .bind(month_id)
-        .fetch_one(&pool)
-        .await?;
-
-        let spent: (f64,) =
-            sqlx::query_as("SELECT COALESCE(SUM(amount), 0.0) FROM items WHERE month_id = ? AND savings_destination = 'none'")
-                .bind(month_id)
-                .fetch_one(&pool)
-                .await?;
-
-        let fixed: (f64,) = sqlx::query_as(
-            "SELECT COALESCE(SUM(amount), 0.0) FROM fixed_expenses WHERE user_id = ?",
-        )
-        .bind(claims.sub)
-        .fetch_one(&pool)
-        .await?;
-
-        total_spending += spent.0;
-        total_income_all += income.0;
-
-        monthly_trends.push(MonthlyStats {
-            year: *year,
-            month: *month,
-            total_income: income.0,
-            total_spent: spent.0,
-            total_fixed: fixed.0,
-            net: income.0 - fixed.0 - spent.0,
-        });
+        let stats =
+            monthly_stats(&pool, claims.sub, &base_currency, *month_id, *year, *month).await?;
+        total_spending += stats.total_spent;
+        total_income_all += stats.total_income;
+        monthly_trends.push(stats);
     }
 
     let month_count = months.len() as f64;
     let average_monthly_spending = if month_count > 0.0 {
-        total_spending / month_count
+        total_spending.to_f64() / month_count
     } else {
         0.0
     };
     let average_monthly_income = if month_count > 0.0 {
-        total_income_all / month_count
+        total_income_all.to_f64() / month_count
     } else {
         0.0
     };
@@ -92,37 +85,35 @@ pub async fn get_stats(
         let current_month_id = months[0].0;
         let previous_month_id = months.get(1).map(|m| m.0);
 
-        let categories: Vec<(i64, String, String)> =
-            sqlx::query_as("SELECT id, label, color FROM budget_categories WHERE user_id = ?")
-                .bind(claims.sub)
-                .fetch_all(&pool)
-                .await?;
+        let categories =
+            category_budget_use(&pool, claims.sub, &base_currency, current_month_id).await?;
 
-        for (cat_id, cat_label, cat_color) in categories {
-            let current_spent: (f64,) = sqlx::query_as(
-                "SELECT COALESCE(SUM(amount), 0.0) FROM items WHERE month_id = ? AND category_id = ? AND savings_destination = 'none'",
-            )
-            .bind(current_month_id)
-            .bind(cat_id)
-            .fetch_one(&pool)
-            .await?;
-
-            let previous_spent: f64 = if let Some(prev_id) = previous_month_id {
-                let result: (f64,) = sqlx::query_as(
-                    "SELECT COALESCE(SUM(amount), 0.0) FROM items WHERE month_id = ? AND category_id = ? AND savings_destination = 'none'",
+        for category in categories {
+            let variance = category.variance();
+            let CategoryBudgetUse {
+                category_id: cat_id,
+                category_label: cat_label,
+                category_color: cat_color,
+                budgeted,
+                spent: current_spent,
+            } = category;
+            let previous_spent = if let Some(prev_id) = previous_month_id {
+                let result: (Money,) = sqlx::query_as(
+                    "SELECT COALESCE(SUM(amount), 0) FROM items WHERE month_id = ? AND category_id = ? AND savings_destination = 'none' AND currency = ? AND deleted_at IS NULL",
                 )
                 .bind(prev_id)
                 .bind(cat_id)
+                .bind(&base_currency)
                 .fetch_one(&pool)
                 .await?;
                 result.0
             } else {
-                0.0
+                Money::ZERO
             };
 
-            let change_amount = current_spent.0 - previous_spent;
-            let change_percent = if previous_spent > 0.0 {
-                Some((change_amount / previous_spent) * 100.0)
+            let change_amount = current_spent - previous_spent;
+            let change_percent = if previous_spent > Money::ZERO {
+                Some((change_amount.to_f64() / previous_spent.to_f64()) * 100.0)
             } else {
                 None
             };
@@ -131,18 +122,1137 @@ pub async fn get_stats(
                 category_id: cat_id,
                 category_label: cat_label,
                 category_color: cat_color,
-                current_month_spent: current_spent.0,
+                current_month_spent: current_spent,
                 previous_month_spent: previous_spent,
                 change_amount,
                 change_percent,
+                budgeted,
+                actual: current_spent,
+                variance,
+                percent_used: if budgeted > Money::ZERO {
+                    Some((current_spent.to_f64() / budgeted.to_f64()) * 100.0)
+                } else {
+                    None
+                },
             });
         }
     }
 
+    let thresholds = load_thresholds(&pool, claims.sub).await?;
+    let alerts = category_comparisons
+        .iter()
+        .filter_map(|c| {
+            let percent_used = c.percent_used?;
+            let severity = if percent_used >= thresholds.critical_percent {
+                AlertSeverity::Critical
+            } else if percent_used >= thresholds.warning_percent {
+                AlertSeverity::Warning
+            } else {
+                return None;
+            };
+            Some(BudgetAlert {
+                category_id: c.category_id,
+                category_label: c.category_label.clone(),
+                percent_used,
+                severity,
+            })
+        })
+        .collect();
+
     Ok(Json(StatsResponse {
         category_comparisons,
         monthly_trends,
         average_monthly_spending,
         average_monthly_income,
+        alerts,
+        fixed_by_category,
+    }))
+}
+
+/// Budgeted and spent amounts in one month for each of the user's categories. Spending only
+/// counts items in `base_currency`.
+#[derive(sqlx::FromRow)]
+struct CategoryBudgetUse {
+    category_id: i64,
+    category_label: String,
+    category_color: String,
+    budgeted: Money,
+    spent: Money,
+}
+
+impl CategoryBudgetUse {
+    /// Negative when the category is overspent
+    fn variance(&self) -> Money {
+        self.budgeted - self.spent
+    }
+}
+
+async fn category_budget_use(
+    pool: &SqlitePool,
+    user_id: i64,
+    base_currency: &str,
+    month_id: i64,
+) -> Result<Vec<CategoryBudgetUse>, PaymeError> {
+    let mut categories: Vec<CategoryBudgetUse> = sqlx::query_as(
+        r#"
+        SELECT bc.id AS category_id, bc.label AS category_label, bc.color AS category_color,
+               COALESCE(mb.allocated_amount, 0) AS budgeted,
+               COALESCE(SUM(i.amount), 0) AS spent
+        FROM budget_categories bc
+        LEFT JOIN monthly_budgets mb ON mb.category_id = bc.id AND mb.month_id = ?
+        LEFT JOIN items i ON i.category_id = bc.id AND i.month_id = ? AND i.savings_destination = 'none' AND i.currency = ?
+            AND i.deleted_at IS NULL
+        WHERE bc.user_id = ?
+        GROUP BY bc.id
+        "#,
+    )
+    .bind(month_id)
+    .bind(month_id)
+    .bind(base_currency)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    for budget in budget::month_budgets(pool, month_id).await? {
+        if let Some(category) = categories
+            .iter_mut()
+            .find(|c| c.category_id == budget.category_id)
+        {
+            category.budgeted = budget.allocated_amount;
+        }
+    }
+
+    Ok(categories)
+}
+
+/// Totals for one of the user's months, converted into `base_currency`.
+async fn monthly_stats(
+    pool: &SqlitePool,
+    user_id: i64,
+    base_currency: &str,
+    month_id: i64,
+    year: i32,
+    month: i32,
+) -> Result<MonthlyStats, PaymeError> {
+    let income: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(monthly_amount), 0) FROM income_entries WHERE month_id = ?",
+    )
+    .bind(month_id)
+    .fetch_one(pool)
+    .await?;
+
+    let rates = currency::UserRates::new(pool, user_id);
+    let spent_rows: Vec<(String, Money, NaiveDate)> = sqlx::query_as(
+        "SELECT currency, amount, spent_on FROM items WHERE month_id = ? AND savings_destination = 'none' AND deleted_at IS NULL",
+    )
+    .bind(month_id)
+    .fetch_all(pool)
+    .await?;
+    let spent_by_currency = currency::totals_by_currency(
+        spent_rows
+            .iter()
+            .map(|(c, amount, _)| (c.as_str(), *amount)),
+    );
+    let amounts: Vec<_> = spent_rows
+        .iter()
+        .map(|(c, amount, on)| (c.as_str(), *amount, *on))
+        .collect();
+    let spent = currency::sum_in_base(&rates, base_currency, &amounts).await?;
+
+    let fixed: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM fixed_expenses WHERE user_id = ? AND active = 1",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    let transfer_rows: Vec<(String, Money, NaiveDate)> = sqlx::query_as(
+        "SELECT currency, CASE WHEN savings_destination LIKE '%withdrawal' THEN -amount ELSE amount END, spent_on FROM items WHERE month_id = ? AND savings_destination != 'none' AND deleted_at IS NULL",
+    )
+    .bind(month_id)
+    .fetch_all(pool)
+    .await?;
+    let amounts: Vec<_> = transfer_rows
+        .iter()
+        .map(|(c, amount, on)| (c.as_str(), *amount, *on))
+        .collect();
+    let transferred = currency::sum_in_base(&rates, base_currency, &amounts).await?;
+
+    let snapshot: Option<Money> = sqlx::query_scalar(
+        "SELECT savings + retirement_savings FROM monthly_savings WHERE month_id = ?",
+    )
+    .bind(month_id)
+    .fetch_optional(pool)
+    .await?;
+    let previous_snapshot: Option<Money> = sqlx::query_scalar(
+        r#"
+        SELECT ms.savings + ms.retirement_savings
+        FROM monthly_savings ms
+        JOIN months m ON ms.month_id = m.id
+        WHERE m.user_id = ? AND (m.year * 12 + m.month) < (? * 12 + ?)
+        ORDER BY m.year DESC, m.month DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(year)
+    .bind(month)
+    .fetch_optional(pool)
+    .await?;
+    let snapshot_delta = match (snapshot, previous_snapshot) {
+        (Some(current), Some(previous)) => current - previous,
+        _ => Money::ZERO,
+    };
+
+    let saved = transferred + snapshot_delta;
+    let savings_rate = if income.0 > Money::ZERO {
+        saved.to_f64() / income.0.to_f64()
+    } else {
+        0.0
+    };
+
+    Ok(MonthlyStats {
+        year,
+        month,
+        total_income: income.0,
+        total_spent: spent,
+        total_fixed: fixed.0,
+        net: income.0 - fixed.0 - spent,
+        spent_by_currency,
+        saved,
+        savings_rate,
+    })
+}
+
+/// Queues the digest of the month before `today` for every user who opted in and has that
+/// month. Each user's digest is claimed before it is queued, so running this again, say after a
+/// restart, doesn't send a second one. Returns the number queued.
+pub async fn queue_monthly_digests(pool: &SqlitePool, today: NaiveDate) -> Result<u64, PaymeError> {
+    let previous = today
+        .with_day(1)
+        .and_then(|d| d.pred_opt())
+        .ok_or_else(|| PaymeError::Internal(format!("No month before {today}")))?;
+    let (year, month) = (previous.year(), previous.month() as i32);
+    let period = format!("{year:04}-{month:02}");
+
+    let due: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT u.id, m.id
+        FROM users u
+        JOIN months m ON m.user_id = u.id AND m.year = ? AND m.month = ?
+        WHERE u.notify_monthly_digest = 1 AND u.email IS NOT NULL
+          AND (u.last_digest_month IS NULL OR u.last_digest_month < ?)
+        "#,
+    )
+    .bind(year)
+    .bind(month)
+    .bind(&period)
+    .fetch_all(pool)
+    .await?;
+
+    let mut queued = 0;
+    for (user_id, month_id) in due {
+        let (subject, body) = match monthly_digest(pool, user_id, month_id, year, month).await {
+            Ok(digest) => digest,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to build {} digest for user {}: {}",
+                    period,
+                    user_id,
+                    e
+                );
+                continue;
+            }
+        };
+        let claimed = sqlx::query(
+            "UPDATE users SET last_digest_month = ?1 WHERE id = ?2 AND (last_digest_month IS NULL OR last_digest_month < ?1)",
+        )
+        .bind(&period)
+        .bind(user_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        if claimed == 1 {
+            email::enqueue(pool, user_id, email::MONTHLY_DIGEST, &subject, &body).await;
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+/// Subject and body of the digest for one of the user's months.
+async fn monthly_digest(
+    pool: &SqlitePool,
+    user_id: i64,
+    month_id: i64,
+    year: i32,
+    month: i32,
+) -> Result<(String, String), PaymeError> {
+    let base_currency = currency::base_currency(pool, user_id).await?;
+    let stats = monthly_stats(pool, user_id, &base_currency, month_id, year, month).await?;
+    let categories = category_budget_use(pool, user_id, &base_currency, month_id).await?;
+    let (savings, savings_goal): (Money, Money) =
+        sqlx::query_as("SELECT savings, savings_goal FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+
+    let name = u8::try_from(month)
+        .ok()
+        .and_then(|m| chrono::Month::try_from(m).ok())
+        .map_or_else(|| month.to_string(), |m| m.name().to_string());
+    let subject = format!("Your {name} {year} recap");
+
+    let currency = &base_currency;
+    let mut body = format!(
+        "Here is how {name} {year} went.\n\nIncome: {} {currency}\nFixed expenses: {} {currency}\nSpent: {} {currency}\nAdded to savings: {} {currency}\n",
+        stats.total_income, stats.total_fixed, stats.total_spent, stats.saved
+    );
+
+    let budgeted: Vec<_> = categories
+        .iter()
+        .filter(|c| c.budgeted > Money::ZERO || c.spent > Money::ZERO)
+        .collect();
+    if !budgeted.is_empty() {
+        body.push_str("\nBudgets:\n");
+        for category in budgeted {
+            let status = if category.variance() < Money::ZERO {
+                format!("over by {}", -category.variance())
+            } else {
+                format!("{} left", category.variance())
+            };
+            body.push_str(&format!(
+                "{}: {} of {} {currency} ({status})\n",
+                category.category_label, category.spent, category.budgeted
+            ));
+        }
+    }
+
+    if savings_goal > Money::ZERO {
+        body.push_str(&format!(
+            "\nSavings goal: {} of {} {currency} ({:.0}%)\n",
+            savings,
+            savings_goal,
+            (savings.to_f64() / savings_goal.to_f64() * 100.0).min(100.0)
+        ));
+    }
+
+    Ok((subject, body))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SavingsRateQuery {
+    /// Number of most recent months to include (defaults to 12)
+    pub months: Option<i32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/savings-rate",
+    params(SavingsRateQuery),
+    responses(
+        (status = 200, body = [MonthlyStats]),
+        (status = 400, description = "Invalid window size"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get savings rate over time",
+    description = "Returns the user's most recent N months, oldest first, with income, amount saved and savings rate (saved / income). Amount saved is transfers to savings plus the change in the month's savings snapshot since the previous one. Months without income report a rate of zero."
+)]
+pub async fn get_savings_rate(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<SavingsRateQuery>,
+) -> Result<Json<Vec<MonthlyStats>>, PaymeError> {
+    let window = query.months.unwrap_or(12);
+    if !(1..=120).contains(&window) {
+        return Err(PaymeError::BadRequest(
+            "months must be between 1 and 120".to_string(),
+        ));
+    }
+
+    let mut months: Vec<(i64, i32, i32)> = sqlx::query_as(
+        "SELECT id, year, month FROM months WHERE user_id = ? ORDER BY year DESC, month DESC LIMIT ?",
+    )
+    .bind(claims.sub)
+    .bind(window)
+    .fetch_all(&pool)
+    .await?;
+    months.reverse();
+
+    let base_currency = currency::base_currency(&pool, claims.sub).await?;
+    let mut points = Vec::with_capacity(months.len());
+    for (month_id, year, month) in months {
+        points.push(monthly_stats(&pool, claims.sub, &base_currency, month_id, year, month).await?);
+    }
+
+    Ok(Json(points))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnnualStatsQuery {
+    /// Calendar year (defaults to the current year)
+    pub year: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AnnualCategoryStats {
+    pub category_id: i64,
+    pub category_label: String,
+    pub category_color: String,
+    /// Sum of the category's monthly allocations over the year
+    #[schema(value_type = f64)]
+    pub budgeted: Money,
+    #[schema(value_type = f64)]
+    pub spent: Money,
+    /// `budgeted - spent`; negative when the category is overspent for the year
+    #[schema(value_type = f64)]
+    pub variance: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AnnualStats {
+    pub year: i32,
+    /// Number of months of the year the user has created
+    pub months: i64,
+    pub categories: Vec<AnnualCategoryStats>,
+    #[schema(value_type = f64)]
+    pub total_budgeted: Money,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    #[schema(value_type = f64)]
+    pub total_variance: Money,
+    #[schema(value_type = f64)]
+    pub total_income: Money,
+    #[schema(value_type = f64)]
+    pub total_saved: Money,
+    /// `total_saved / total_income`, or zero without income
+    pub savings_rate: f64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/annual",
+    params(AnnualStatsQuery),
+    responses(
+        (status = 200, body = AnnualStats),
+        (status = 400, description = "Invalid year"),
+        (status = 422, description = "Missing exchange rate"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get a year-end budget review",
+    description = "Sums each category's monthly budgeted and spent amounts across the year, with the cumulative variance, plus overall totals and the year's savings rate (saved / income). Months of the year that don't exist contribute zero."
+)]
+pub async fn get_annual_stats(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<AnnualStatsQuery>,
+) -> Result<Json<AnnualStats>, PaymeError> {
+    let year = match query.year {
+        Some(year) => year,
+        None => timezone::today(&pool, claims.sub).await?.year(),
+    };
+    if NaiveDate::from_ymd_opt(year, 1, 1).is_none() {
+        return Err(PaymeError::BadRequest("Invalid year".to_string()));
+    }
+
+    let months: Vec<(i64, i32)> = sqlx::query_as(
+        "SELECT id, month FROM months WHERE user_id = ? AND year = ? ORDER BY month",
+    )
+    .bind(claims.sub)
+    .bind(year)
+    .fetch_all(&pool)
+    .await?;
+
+    let labels: Vec<(i64, String, String)> = sqlx::query_as(
+        "SELECT id, label, color FROM budget_categories WHERE user_id = ? ORDER BY id",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+    let mut categories: Vec<AnnualCategoryStats> = labels
+        .into_iter()
+        .map(
+            |(category_id, category_label, category_color)| AnnualCategoryStats {
+                category_id,
+                category_label,
+                category_color,
+                budgeted: Money::ZERO,
+                spent: Money::ZERO,
+                variance: Money::ZERO,
+            },
+        )
+        .collect();
+
+    let base_currency = currency::base_currency(&pool, claims.sub).await?;
+    let mut total_income = Money::ZERO;
+    let mut total_saved = Money::ZERO;
+    for (month_id, month) in &months {
+        for usage in category_budget_use(&pool, claims.sub, &base_currency, *month_id).await? {
+            if let Some(category) = categories
+                .iter_mut()
+                .find(|c| c.category_id == usage.category_id)
+            {
+                category.budgeted += usage.budgeted;
+                category.spent += usage.spent;
+                category.variance += usage.variance();
+            }
+        }
+
+        let stats =
+            monthly_stats(&pool, claims.sub, &base_currency, *month_id, year, *month).await?;
+        total_income += stats.total_income;
+        total_saved += stats.saved;
+    }
+
+    let total_budgeted = categories.iter().map(|c| c.budgeted).sum();
+    let total_spent = categories.iter().map(|c| c.spent).sum();
+    let total_variance = categories.iter().map(|c| c.variance).sum();
+    let savings_rate = if total_income > Money::ZERO {
+        total_saved.to_f64() / total_income.to_f64()
+    } else {
+        0.0
+    };
+
+    Ok(Json(AnnualStats {
+        year,
+        months: months.len() as i64,
+        categories,
+        total_budgeted,
+        total_spent,
+        total_variance,
+        total_income,
+        total_saved,
+        savings_rate,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RangeStatsQuery {
+    /// First day of the range, inclusive
+    pub from: NaiveDate,
+    /// Last day of the range, inclusive
+    pub to: NaiveDate,
+    /// Only count spending in this category
+    pub category_id: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RangeMonthSpend {
+    pub year: i32,
+    pub month: i32,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RangeCategoryStats {
+    pub category_id: i64,
+    pub category_label: String,
+    pub category_color: String,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    /// The category's spending in each month that has some within the range, oldest first
+    pub months: Vec<RangeMonthSpend>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RangeStats {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    pub categories: Vec<RangeCategoryStats>,
+    /// Spending across the listed categories in each month, oldest first
+    pub months: Vec<RangeMonthSpend>,
+}
+
+/// A spending item in a range, with the category and month it was recorded under.
+#[derive(sqlx::FromRow)]
+struct RangeItem {
+    category_id: i64,
+    category_label: String,
+    category_color: String,
+    year: i32,
+    month: i32,
+    currency: String,
+    amount: Money,
+    spent_on: NaiveDate,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/range",
+    params(RangeStatsQuery),
+    responses(
+        (status = 200, body = RangeStats),
+        (status = 400, description = "`from` is after `to`"),
+        (status = 404, description = "Category not found"),
+        (status = 422, description = "Missing exchange rate"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get spending for a date range",
+    description = "Totals spending dated from `from` through `to` across all of the user's months, closed ones included, so the range can start and end mid-month. Spending is broken down by category and, within each, by the month it was recorded in. Amounts are in the base currency, converted at the rate for each item's date."
+)]
+pub async fn get_range_stats(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<RangeStatsQuery>,
+) -> Result<Json<RangeStats>, PaymeError> {
+    if query.from > query.to {
+        return Err(PaymeError::BadRequest(
+            "from must not be after to".to_string(),
+        ));
+    }
+    if let Some(category_id) = query.category_id {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM budget_categories WHERE id = ? AND user_id = ?",
+        )
+        .bind(category_id)
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+    }
+
+    let items: Vec<RangeItem> = sqlx::query_as(
+        r#"
+        SELECT bc.id AS category_id, bc.label AS category_label, bc.color AS category_color,
+               m.year, m.month, i.currency, i.amount, i.spent_on
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        JOIN budget_categories bc ON i.category_id = bc.id
+        WHERE m.user_id = ?1 AND i.spent_on BETWEEN ?2 AND ?3
+          AND (?4 IS NULL OR i.category_id = ?4)
+          AND i.savings_destination = 'none' AND i.deleted_at IS NULL
+        ORDER BY bc.label, bc.id, m.year, m.month
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(query.from)
+    .bind(query.to)
+    .bind(query.category_id)
+    .fetch_all(&pool)
+    .await?;
+
+    let base_currency = currency::base_currency(&pool, claims.sub).await?;
+    let rates = currency::UserRates::new(&pool, claims.sub);
+    let mut categories: Vec<RangeCategoryStats> = Vec::new();
+    let mut months: Vec<RangeMonthSpend> = Vec::new();
+    // Rows are ordered by category then month, so each chunk is one category's month
+    for chunk in items
+        .chunk_by(|a, b| a.category_id == b.category_id && (a.year, a.month) == (b.year, b.month))
+    {
+        let first = &chunk[0];
+        let amounts: Vec<_> = chunk
+            .iter()
+            .map(|i| (i.currency.as_str(), i.amount, i.spent_on))
+            .collect();
+        let total_spent = currency::sum_in_base(&rates, &base_currency, &amounts).await?;
+
+        if categories
+            .last()
+            .is_none_or(|c| c.category_id != first.category_id)
+        {
+            categories.push(RangeCategoryStats {
+                category_id: first.category_id,
+                category_label: first.category_label.clone(),
+                category_color: first.category_color.clone(),
+                total_spent: Money::ZERO,
+                months: Vec::new(),
+            });
+        }
+        let category = categories.last_mut().expect("pushed above");
+        category.total_spent += total_spent;
+        category.months.push(RangeMonthSpend {
+            year: first.year,
+            month: first.month,
+            total_spent,
+        });
+
+        match months
+            .iter_mut()
+            .find(|m| (m.year, m.month) == (first.year, first.month))
+        {
+            Some(existing) => existing.total_spent += total_spent,
+            None => months.push(RangeMonthSpend {
+                year: first.year,
+                month: first.month,
+                total_spent,
+            }),
+        }
+    }
+    months.sort_by_key(|m| (m.year, m.month));
+
+    Ok(Json(RangeStats {
+        from: query.from,
+        to: query.to,
+        total_spent: categories.iter().map(|c| c.total_spent).sum(),
+        categories,
+        months,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TopCategoriesQuery {
+    /// Maximum number of categories to return (defaults to 5)
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TopCategory {
+    pub category_id: i64,
+    pub category_label: String,
+    pub category_color: String,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    /// Share of the month's total spending, 0-100
+    pub percent_of_total: f64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/stats/top-categories",
+    params(("id" = i64, Path, description = "Month ID"), TopCategoriesQuery),
+    responses(
+        (status = 200, body = [TopCategory]),
+        (status = 400, description = "Invalid limit"),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get top spending categories",
+    description = "Returns the month's highest-spending categories in the base currency, largest first, with ties ordered by label. Categories without spending are left out."
+)]
+pub async fn get_top_categories(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+    Query(query): Query<TopCategoriesQuery>,
+) -> Result<Json<Vec<TopCategory>>, PaymeError> {
+    let limit = query.limit.unwrap_or(5);
+    if !(1..=100).contains(&limit) {
+        return Err(PaymeError::BadRequest(
+            "limit must be between 1 and 100".to_string(),
+        ));
+    }
+
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    let base_currency = currency::base_currency(&pool, month.user_id).await?;
+
+    let totals: Vec<(i64, String, String, Money)> = sqlx::query_as(
+        r#"
+        SELECT bc.id, bc.label, bc.color, SUM(i.amount) AS total
+        FROM items i
+        JOIN budget_categories bc ON i.category_id = bc.id
+        WHERE i.month_id = ? AND i.savings_destination = 'none' AND i.currency = ?
+          AND i.deleted_at IS NULL
+        GROUP BY bc.id
+        ORDER BY total DESC, bc.label, bc.id
+        "#,
+    )
+    .bind(month_id)
+    .bind(&base_currency)
+    .fetch_all(&pool)
+    .await?;
+
+    let total_spent: Money = totals.iter().map(|(_, _, _, total)| *total).sum();
+    let top = totals
+        .into_iter()
+        .take(limit as usize)
+        .map(
+            |(category_id, category_label, category_color, spent)| TopCategory {
+                category_id,
+                category_label,
+                category_color,
+                total_spent: spent,
+                percent_of_total: if total_spent > Money::ZERO {
+                    spent.to_f64() / total_spent.to_f64() * 100.0
+                } else {
+                    0.0
+                },
+            },
+        )
+        .collect();
+
+    Ok(Json(top))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct IncomeTotals {
+    #[schema(value_type = f64)]
+    pub gross: Money,
+    #[schema(value_type = f64)]
+    pub withholding: Money,
+    #[schema(value_type = f64)]
+    pub net: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct IncomeSummary {
+    pub month: IncomeTotals,
+    /// From January through this month of the same year
+    pub year_to_date: IncomeTotals,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/stats/income",
+    params(("id" = i64, Path, description = "Month ID")),
+    responses(
+        (status = 200, body = IncomeSummary),
+        (status = 404, description = "Month not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get gross and net income",
+    description = "Totals gross pay, withholding and net income for the month and for the year to date. Entries recorded without a gross amount count as fully net."
+)]
+pub async fn get_income_summary(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<Json<IncomeSummary>, PaymeError> {
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+
+    let (gross, withholding, net): (Money, Money, Money) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(COALESCE(gross_amount, amount + withholding)), 0),
+               COALESCE(SUM(withholding), 0),
+               COALESCE(SUM(amount), 0)
+        FROM income_entries
+        WHERE month_id = ?
+        "#,
+    )
+    .bind(month_id)
+    .fetch_one(&pool)
+    .await?;
+
+    let (ytd_gross, ytd_withholding, ytd_net): (Money, Money, Money) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(COALESCE(ie.gross_amount, ie.amount + ie.withholding)), 0),
+               COALESCE(SUM(ie.withholding), 0),
+               COALESCE(SUM(ie.amount), 0)
+        FROM income_entries ie
+        JOIN months m ON ie.month_id = m.id
+        WHERE m.user_id = ? AND m.year = ? AND m.month <= ?
+        "#,
+    )
+    .bind(month.user_id)
+    .bind(month.year)
+    .bind(month.month)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(IncomeSummary {
+        month: IncomeTotals {
+            gross,
+            withholding,
+            net,
+        },
+        year_to_date: IncomeTotals {
+            gross: ytd_gross,
+            withholding: ytd_withholding,
+            net: ytd_net,
+        },
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DailySpend {
+    pub date: NaiveDate,
+    /// Spending on the day in the base currency
+    #[schema(value_type = f64)]
+    pub total: Money,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DailySpendQuery {
+    /// Calendar year (defaults to the current year)
+    pub year: Option<i32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/months/{id}/stats/daily",
+    params(("id" = i64, Path, description = "Month ID")),
+    responses(
+        (status = 200, body = [DailySpend]),
+        (status = 404, description = "Month not found"),
+        (status = 422, description = "Missing exchange rate"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get daily spending for a month",
+    description = "Returns one entry per calendar day of the month, oldest first, with the day's spending by `spent_on` in the base currency. Days without spending are included with a total of 0. Savings transfers are not spending."
+)]
+pub async fn get_month_daily_spending(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(month_id): Path<i64>,
+) -> Result<Json<Vec<DailySpend>>, PaymeError> {
+    let month = find_user_month(&pool, claims.sub, month_id).await?;
+    let first = NaiveDate::from_ymd_opt(month.year, month.month as u32, 1)
+        .ok_or_else(|| PaymeError::Internal("Invalid month".to_string()))?;
+    let last =
+        first + chrono::Days::new(u64::from(days_in_month(month.year, month.month as u32)) - 1);
+
+    let items: Vec<(String, Money, NaiveDate)> = sqlx::query_as(
+        "SELECT currency, amount, spent_on FROM items WHERE month_id = ? AND savings_destination = 'none' AND deleted_at IS NULL",
+    )
+    .bind(month_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(
+        daily_totals(&pool, month.user_id, &items, first, last).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/daily",
+    params(DailySpendQuery),
+    responses(
+        (status = 200, body = [DailySpend]),
+        (status = 400, description = "Invalid year"),
+        (status = 422, description = "Missing exchange rate"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get daily spending for a year",
+    description = "Returns one entry per calendar day of the year across all of the user's months, oldest first, for a full-year heatmap. Days without spending are included with a total of 0."
+)]
+pub async fn get_year_daily_spending(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<DailySpendQuery>,
+) -> Result<Json<Vec<DailySpend>>, PaymeError> {
+    let year = match query.year {
+        Some(year) => year,
+        None => timezone::today(&pool, claims.sub).await?.year(),
+    };
+    let (Some(first), Some(last)) = (
+        NaiveDate::from_ymd_opt(year, 1, 1),
+        NaiveDate::from_ymd_opt(year, 12, 31),
+    ) else {
+        return Err(PaymeError::BadRequest("Invalid year".to_string()));
+    };
+
+    let items: Vec<(String, Money, NaiveDate)> = sqlx::query_as(
+        r#"
+        SELECT i.currency, i.amount, i.spent_on
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        WHERE m.user_id = ? AND i.spent_on BETWEEN ? AND ?
+          AND i.savings_destination = 'none' AND i.deleted_at IS NULL
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(first)
+    .bind(last)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(
+        daily_totals(&pool, claims.sub, &items, first, last).await?,
+    ))
+}
+
+/// Spending per day from `first` through `last`, with zero for days that have no items.
+async fn daily_totals(
+    pool: &SqlitePool,
+    user_id: i64,
+    items: &[(String, Money, NaiveDate)],
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Result<Vec<DailySpend>, PaymeError> {
+    let base_currency = currency::base_currency(pool, user_id).await?;
+    let rates = currency::UserRates::new(pool, user_id);
+
+    let mut days = Vec::new();
+    for date in first.iter_days().take_while(|d| *d <= last) {
+        let amounts: Vec<_> = items
+            .iter()
+            .filter(|(.., on)| *on == date)
+            .map(|(c, amount, on)| (c.as_str(), *amount, *on))
+            .collect();
+        let total = currency::sum_in_base(&rates, &base_currency, &amounts).await?;
+        days.push(DailySpend { date, total });
+    }
+    Ok(days)
+}
+
+pub(crate) async fn load_thresholds(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<AlertThresholds, PaymeError> {
+    let thresholds: AlertThresholds = sqlx::query_as(
+        "SELECT alert_warning_percent AS warning_percent, alert_critical_percent AS critical_percent FROM users WHERE id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::NotFound)?;
+
+    Ok(thresholds)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/alert-thresholds",
+    responses(
+        (status = 200, body = AlertThresholds),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get budget alert thresholds",
+    description = "Returns the percentages of a category budget at which warning and critical alerts are raised."
+)]
+pub async fn get_alert_thresholds(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<AlertThresholds>, PaymeError> {
+    Ok(Json(load_thresholds(&pool, claims.sub).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/stats/alert-thresholds",
+    request_body = AlertThresholds,
+    responses(
+        (status = 200, body = AlertThresholds),
+        (status = 400, description = "Thresholds out of order or not positive"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Update budget alert thresholds",
+    description = "Sets the warning and critical percentages. The warning threshold must be positive and below the critical one."
+)]
+pub async fn update_alert_thresholds(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Json(payload): Json<AlertThresholds>,
+) -> Result<Json<AlertThresholds>, PaymeError> {
+    if !(payload.warning_percent > 0.0 && payload.warning_percent < payload.critical_percent) {
+        return Err(PaymeError::BadRequest(
+            "warning_percent must be positive and below critical_percent".to_string(),
+        ));
+    }
+
+    sqlx::query(
+        "UPDATE users SET alert_warning_percent = ?, alert_critical_percent = ? WHERE id = ?",
+    )
+    .bind(payload.warning_percent)
+    .bind(payload.critical_percent)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(payload))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoryTrendQuery {
+    pub category_id: i64,
+    /// Number of trailing calendar months to include, ending with the current one (defaults to 6)
+    pub months: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryTrendPoint {
+    pub year: i32,
+    pub month: i32,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryTrend {
+    pub category_id: i64,
+    pub category_label: String,
+    pub points: Vec<CategoryTrendPoint>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/trends",
+    params(CategoryTrendQuery),
+    responses(
+        (status = 200, body = CategoryTrend),
+        (status = 400, description = "Invalid window size"),
+        (status = 404, description = "Category not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Insights",
+    summary = "Get category spending trend",
+    description = "Returns total spend in one category for each of the trailing N calendar months, oldest first, in the base currency. Months without spending are reported as zero."
+)]
+pub async fn get_category_trend(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Query(query): Query<CategoryTrendQuery>,
+) -> Result<Json<CategoryTrend>, PaymeError> {
+    let window = query.months.unwrap_or(6);
+    if !(1..=120).contains(&window) {
+        return Err(PaymeError::BadRequest(
+            "months must be between 1 and 120".to_string(),
+        ));
+    }
+
+    let category_label: String =
+        sqlx::query_scalar("SELECT label FROM budget_categories WHERE id = ? AND user_id = ?")
+            .bind(query.category_id)
+            .bind(claims.sub)
+            .fetch_optional(&pool)
+            .await?
+            .ok_or(PaymeError::NotFound)?;
+
+    let spending: Vec<(i32, i32, Money)> = sqlx::query_as(
+        r#"
+        SELECT m.year, m.month, COALESCE(SUM(i.amount), 0)
+        FROM items i
+        JOIN months m ON i.month_id = m.id
+        WHERE m.user_id = ? AND i.category_id = ? AND i.savings_destination = 'none'
+          AND i.currency = ? AND i.deleted_at IS NULL
+        GROUP BY m.year, m.month
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(query.category_id)
+    .bind(currency::base_currency(&pool, claims.sub).await?)
+    .fetch_all(&pool)
+    .await?;
+
+    let today = timezone::today(&pool, claims.sub).await?;
+    let current = today.year() * 12 + today.month0() as i32;
+
+    let points = (current - window + 1..=current)
+        .map(|index| {
+            let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+            let total_spent = spending
+                .iter()
+                .find(|(y, m, _)| *y == year && *m == month)
+                .map_or(Money::ZERO, |(_, _, total)| *total);
+            CategoryTrendPoint {
+                year,
+                month,
+                total_spent,
+            }
+        })
+        .collect();
+
+    Ok(Json(CategoryTrend {
+        category_id: query.category_id,
+        category_label,
+        points,
     }))
 }
@@ -0,0 +1,223 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::config::WebhookPolicy;
+use crate::error::PaymeError;
+use crate::middleware::auth::Claims;
+use crate::webhooks::{self, EVENTS};
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateWebhook {
+    #[validate(length(min = 1, max = 2048))]
+    pub url: String,
+    /// Event types to receive, e.g. `item.created`
+    #[validate(length(min = 1))]
+    pub events: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_at: String,
+    /// Signing secret; only returned when the webhook is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub event: String,
+    /// "pending", "sending", "delivered" or "failed"
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/webhooks",
+    responses(
+        (status = 200, body = [Webhook]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Webhooks",
+    summary = "List webhooks",
+    description = "Lists registered webhooks. Secrets are not included."
+)]
+pub async fn list_webhooks(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+) -> Result<Json<Vec<Webhook>>, PaymeError> {
+    let rows: Vec<(i64, String, String, String)> = sqlx::query_as(
+        "SELECT id, url, events, created_at FROM webhooks WHERE user_id = ? ORDER BY id",
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|(id, url, events, created_at)| Webhook {
+                id,
+                url,
+                events: events.split(',').map(str::to_string).collect(),
+                created_at,
+                secret: None,
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/webhooks",
+    request_body = CreateWebhook,
+    responses(
+        (status = 201, body = Webhook),
+        (status = 400, description = "Invalid URL or unknown event type"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Webhooks",
+    summary = "Register webhook",
+    description = "Registers an https URL on a public host to receive signed POSTs for the given event types. The response includes the signing secret, which is not shown again. Each request carries an `x-payme-signature: sha256=<hex>` header, an HMAC-SHA256 of the raw body keyed by the secret."
+)]
+pub async fn create_webhook(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    axum::Extension(policy): axum::Extension<WebhookPolicy>,
+    Json(payload): Json<CreateWebhook>,
+) -> Result<(StatusCode, Json<Webhook>), PaymeError> {
+    payload.validate()?;
+
+    let url = url::Url::parse(&payload.url)
+        .map_err(|_| PaymeError::BadRequest("Invalid webhook URL".to_string()))?;
+    webhooks::check_url(&url, &policy)
+        .map_err(|e| PaymeError::BadRequest(format!("Invalid webhook URL: {e}")))?;
+    if let Some(unknown) = payload
+        .events
+        .iter()
+        .find(|e| !EVENTS.contains(&e.as_str()))
+    {
+        return Err(PaymeError::BadRequest(format!(
+            "Unknown event type: {unknown}"
+        )));
+    }
+
+    let mut events = payload.events;
+    events.sort();
+    events.dedup();
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let secret: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+    let (id, created_at): (i64, String) = sqlx::query_as(
+        "INSERT INTO webhooks (user_id, url, secret, events) VALUES (?, ?, ?, ?) RETURNING id, created_at",
+    )
+    .bind(claims.sub)
+    .bind(url.as_str())
+    .bind(&secret)
+    .bind(events.join(","))
+    .fetch_one(&pool)
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Webhook {
+            id,
+            url: url.to_string(),
+            events,
+            created_at,
+            secret: Some(secret),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/webhooks/{id}",
+    params(("id" = i64, Path, description = "Webhook ID")),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Webhooks",
+    summary = "Delete webhook",
+    description = "Removes a webhook along with its delivery history."
+)]
+pub async fn delete_webhook(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(webhook_id): Path<i64>,
+) -> Result<StatusCode, PaymeError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE id = ? AND user_id = ?)",
+    )
+    .bind(webhook_id)
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM webhooks WHERE id = ? AND user_id = ?")
+        .bind(webhook_id)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}/deliveries",
+    params(("id" = i64, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, body = [WebhookDelivery]),
+        (status = 404, description = "Webhook not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Webhooks",
+    summary = "List deliveries",
+    description = "Shows the 100 most recent delivery attempts for a webhook, newest first."
+)]
+pub async fn list_deliveries(
+    State(pool): State<SqlitePool>,
+    axum::Extension(claims): axum::Extension<Claims>,
+    Path(webhook_id): Path<i64>,
+) -> Result<Json<Vec<WebhookDelivery>>, PaymeError> {
+    let _webhook: i64 = sqlx::query_scalar("SELECT id FROM webhooks WHERE id = ? AND user_id = ?")
+        .bind(webhook_id)
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(PaymeError::NotFound)?;
+
+    let deliveries: Vec<WebhookDelivery> = sqlx::query_as(
+        r#"
+        SELECT id, event, status, attempts, last_error, created_at, delivered_at
+        FROM webhook_deliveries
+        WHERE webhook_id = ?
+        ORDER BY id DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(webhook_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(deliveries))
+}
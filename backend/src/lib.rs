@@ -1,54 +1,206 @@
+pub mod background;
 pub mod config;
+pub mod crypto;
+pub mod currency;
 pub mod db;
+pub mod email;
 pub mod error;
 pub mod handlers;
 pub mod middleware;
 pub mod models;
+pub mod money;
 pub mod openapi;
 pub mod pdf;
+pub mod timezone;
+pub mod tls;
+pub mod totp;
+pub mod webhooks;
 
 use axum::{
-    middleware::from_fn,
+    extract::DefaultBodyLimit,
+    middleware::{from_fn, from_fn_with_state},
     routing::{delete, get, post, put},
-    Router,
+    Extension, Router,
 };
 use sqlx::SqlitePool;
-use tower_http::cors::{Any, CorsLayer};
+use std::sync::Arc;
 
+use config::AppOptions;
 use handlers::{
-    auth, budget, export, fixed_expenses, health, income, items, monthly_data, months,
-    retirement_breakdown, savings, savings_goals, stats,
+    api_keys, auth, budget, exchange_rates, export, fixed_expenses, health, households, income,
+    item_templates, items, monthly_data, months, receipts, reminders, retirement_breakdown,
+    savings, savings_accounts, savings_goals, stats,
 };
-use middleware::auth::auth_middleware;
+use middleware::auth::{auth_middleware, session_only_middleware};
+use middleware::body_limit::body_limit_middleware;
+use middleware::compression::compression_layer;
+use middleware::cors::{cors_layer, strip_unmatched_cors_headers};
+use middleware::idempotency::idempotency_middleware;
+use middleware::request_id::request_id_middleware;
 
-/// Create the application router with all routes
+/// Create the application router with all routes, configured from the environment
 pub fn create_app(pool: SqlitePool) -> Router {
+    create_app_with(pool, AppOptions::from_env())
+}
+
+/// Create the application router with explicit settings
+pub fn create_app_with(pool: SqlitePool, options: AppOptions) -> Router {
     let public_routes = Router::new()
         .route("/health", get(health::health_check))
         .route("/api/auth/register", post(auth::register))
-        .route("/api/auth/login", post(auth::login));
+        .route("/api/auth/login", post(auth::login))
+        .route("/api/auth/forgot-password", post(auth::forgot_password))
+        .route("/api/auth/reset-password", post(auth::reset_password))
+        .route("/api/auth/refresh", post(auth::refresh))
+        .layer(from_fn_with_state(
+            options.body_limits.default,
+            body_limit_middleware,
+        ));
 
-    let protected_routes = Router::new()
+    let bulk_routes = Router::new()
+        .route(
+            "/api/months/{id}/items/delete",
+            post(items::bulk_delete_items),
+        )
+        .merge(
+            Router::new()
+                .route("/api/import/json", post(export::import_json))
+                .route("/api/import/jobs", post(export::start_import_job))
+                .route_layer(from_fn_with_state(pool.clone(), idempotency_middleware)),
+        )
+        .layer(from_fn_with_state(
+            options.body_limits.bulk,
+            body_limit_middleware,
+        ));
+
+    let upload_routes = Router::new()
+        .route(
+            "/api/months/{month_id}/items/{id}/receipt",
+            get(receipts::get_receipt).post(receipts::upload_receipt),
+        )
+        .layer(from_fn_with_state(
+            // Room for the size check in the handler to report oversized files itself
+            receipts::MAX_RECEIPT_BYTES + 1024 * 1024,
+            body_limit_middleware,
+        ));
+
+    // Everything under /api/auth but `me` manages the account itself and needs a login session
+    let account_routes = Router::new()
         .route("/api/auth/logout", post(auth::logout))
-        .route("/api/auth/me", get(auth::me))
         .route("/api/auth/change-username", put(auth::change_username))
-        .route("/api/auth/change-password", put(auth::change_password))
+        .route(
+            "/api/auth/change-password",
+            put(auth::change_password).post(auth::change_password),
+        )
+        .route("/api/auth/change-email", put(auth::change_email))
+        .route("/api/auth/base-currency", put(auth::change_base_currency))
+        .route("/api/auth/rounding-mode", put(auth::change_rounding_mode))
+        .route("/api/auth/timezone", put(auth::change_timezone))
+        .route(
+            "/api/auth/notifications",
+            get(auth::get_notification_preferences).put(auth::update_notification_preferences),
+        )
+        .route("/api/auth/2fa/setup", post(auth::setup_two_factor))
+        .route("/api/auth/2fa/verify", post(auth::verify_two_factor))
+        .route(
+            "/api/auth/2fa/backup-codes",
+            post(auth::regenerate_two_factor_backup_codes),
+        )
+        .route(
+            "/api/auth/api-keys",
+            get(api_keys::list_api_keys).post(api_keys::create_api_key),
+        )
+        .route("/api/auth/api-keys/{id}", delete(api_keys::revoke_api_key))
         .route("/api/auth/clear-data", delete(auth::clear_all_data))
+        .route("/api/auth/account", delete(auth::delete_account))
+        .route_layer(from_fn(session_only_middleware));
+
+    // Creating routes replay the response to a retried request instead of creating a duplicate.
+    // Only these, so secrets in other responses, such as a new API key, are never stored.
+    let idempotent_routes = Router::new()
+        .route("/api/months", post(months::create_month))
+        .route("/api/months/{id}/duplicate", post(months::duplicate_month))
+        .route(
+            "/api/months/{month_id}/fixed-expenses",
+            post(monthly_data::create_monthly_fixed_expense),
+        )
+        .route(
+            "/api/fixed-expenses",
+            post(fixed_expenses::create_fixed_expense),
+        )
+        .route("/api/categories", post(budget::create_category))
+        .route("/api/months/{id}/income", post(income::create_income))
+        .route("/api/months/{id}/items", post(items::create_item))
+        .route(
+            "/api/months/{id}/items/from-template/{template_id}",
+            post(item_templates::create_item_from_template),
+        )
+        .route(
+            "/api/item-templates",
+            post(item_templates::create_item_template),
+        )
+        .route(
+            "/api/savings/contribute",
+            post(savings_accounts::contribute),
+        )
+        .route(
+            "/api/savings/transfer",
+            post(savings_accounts::transfer_savings),
+        )
+        .route(
+            "/api/savings-accounts",
+            post(savings_accounts::create_savings_account),
+        )
+        .route(
+            "/api/savings-goals",
+            post(savings_goals::create_savings_goal),
+        )
+        .route(
+            "/api/retirement-breakdown",
+            post(retirement_breakdown::create_retirement_breakdown_item),
+        )
+        .route_layer(from_fn_with_state(pool.clone(), idempotency_middleware));
+
+    let protected_routes = Router::new()
+        .merge(account_routes)
+        .merge(idempotent_routes)
+        .route("/api/auth/me", get(auth::me))
         .route("/api/export", get(auth::export_db))
         .route("/api/months", get(months::list_months))
-        .route("/api/months", post(months::create_month))
         .route(
             "/api/months/current",
             get(months::get_or_create_current_month),
         )
         .route("/api/months/{id}", get(months::get_month))
         .route("/api/months/{id}/close", post(months::close_month))
+        .route(
+            "/api/months/{id}/close-preview",
+            get(months::get_close_preview),
+        )
         .route("/api/months/{id}/reopen", post(months::reopen_month))
+        .route("/api/months/{id}/lock", post(months::lock_month))
+        .route("/api/months/{id}/unlock", post(months::unlock_month))
         .route("/api/months/{id}/pdf", get(months::get_month_pdf))
+        .route("/api/months/{id}/forecast", get(months::get_month_forecast))
+        .route("/api/forecast/months", get(months::forecast_future_months))
         .route(
-            "/api/months/{month_id}/fixed-expenses",
-            post(monthly_data::create_monthly_fixed_expense),
+            "/api/months/{id}/safe-to-spend",
+            get(months::get_safe_to_spend),
+        )
+        .route(
+            "/api/months/{id}/stats/top-categories",
+            get(stats::get_top_categories),
+        )
+        .route(
+            "/api/months/{id}/stats/income",
+            get(stats::get_income_summary),
+        )
+        .route(
+            "/api/months/{id}/stats/daily",
+            get(stats::get_month_daily_spending),
         )
+        .route("/api/months/{id}/export.csv", get(export::export_month_csv))
+        .route("/api/months/{id}/export.qif", get(export::export_month_qif))
         .route(
             "/api/months/{month_id}/fixed-expenses/{id}",
             put(monthly_data::update_monthly_fixed_expense),
@@ -70,8 +222,8 @@ pub fn create_app(pool: SqlitePool) -> Router {
             get(fixed_expenses::list_fixed_expenses),
         )
         .route(
-            "/api/fixed-expenses",
-            post(fixed_expenses::create_fixed_expense),
+            "/api/fixed-expenses/by-category",
+            get(fixed_expenses::fixed_expenses_by_category),
         )
         .route(
             "/api/fixed-expenses/{id}",
@@ -82,9 +234,15 @@ pub fn create_app(pool: SqlitePool) -> Router {
             delete(fixed_expenses::delete_fixed_expense),
         )
         .route("/api/categories", get(budget::list_categories))
-        .route("/api/categories", post(budget::create_category))
+        .route("/api/categories/reorder", post(budget::reorder_categories))
+        .route("/api/budget/templates", get(budget::list_budget_templates))
+        .route(
+            "/api/budget/apply-template",
+            post(budget::apply_budget_template),
+        )
         .route("/api/categories/{id}", put(budget::update_category))
         .route("/api/categories/{id}", delete(budget::delete_category))
+        .route("/api/categories/{id}/merge", post(budget::merge_category))
         .route(
             "/api/months/{id}/budgets",
             get(budget::list_monthly_budgets),
@@ -93,8 +251,11 @@ pub fn create_app(pool: SqlitePool) -> Router {
             "/api/months/{month_id}/budgets/{id}",
             put(budget::update_monthly_budget),
         )
+        .route(
+            "/api/months/{id}/budget/bulk",
+            put(budget::bulk_update_monthly_budgets),
+        )
         .route("/api/months/{id}/income", get(income::list_income))
-        .route("/api/months/{id}/income", post(income::create_income))
         .route(
             "/api/months/{month_id}/income/{id}",
             put(income::update_income),
@@ -103,17 +264,103 @@ pub fn create_app(pool: SqlitePool) -> Router {
             "/api/months/{month_id}/income/{id}",
             delete(income::delete_income),
         )
+        .route("/api/items/search", get(items::search_items))
+        .route("/api/items/orphaned", get(items::list_orphaned_items))
         .route("/api/months/{id}/items", get(items::list_items))
-        .route("/api/months/{id}/items", post(items::create_item))
+        .route(
+            "/api/months/{id}/items/undo-delete",
+            post(items::undo_delete_item),
+        )
         .route("/api/months/{month_id}/items/{id}", put(items::update_item))
         .route(
             "/api/months/{month_id}/items/{id}",
             delete(items::delete_item),
         )
+        .route(
+            "/api/months/{month_id}/items/{id}/move",
+            post(items::move_item),
+        )
+        .route(
+            "/api/months/{month_id}/items/{id}/restore",
+            post(items::restore_item),
+        )
+        .route(
+            "/api/item-templates",
+            get(item_templates::list_item_templates),
+        )
+        .route(
+            "/api/item-templates/{id}",
+            put(item_templates::update_item_template).delete(item_templates::delete_item_template),
+        )
+        .route(
+            "/api/households",
+            get(households::list_households).post(households::create_household),
+        )
+        .route(
+            "/api/households/{id}/members",
+            get(households::list_members),
+        )
+        .route(
+            "/api/households/{id}/invite",
+            post(households::invite_member),
+        )
+        .route(
+            "/api/households/{id}/accept",
+            post(households::accept_invite),
+        )
+        .route(
+            "/api/households/{id}/months/{month_id}",
+            post(households::share_month),
+        )
+        .route(
+            "/api/webhooks",
+            get(handlers::webhooks::list_webhooks).post(handlers::webhooks::create_webhook),
+        )
+        .route(
+            "/api/webhooks/{id}",
+            delete(handlers::webhooks::delete_webhook),
+        )
+        .route(
+            "/api/webhooks/{id}/deliveries",
+            get(handlers::webhooks::list_deliveries),
+        )
+        .route(
+            "/api/exchange-rates",
+            get(exchange_rates::list_exchange_rates).put(exchange_rates::set_exchange_rate),
+        )
+        .route(
+            "/api/exchange-rates/{id}",
+            delete(exchange_rates::delete_exchange_rate),
+        )
+        .route("/api/reminders/upcoming", get(reminders::upcoming_bills))
         .route("/api/stats", get(stats::get_stats))
+        .route("/api/stats/trends", get(stats::get_category_trend))
+        .route("/api/stats/daily", get(stats::get_year_daily_spending))
+        .route("/api/stats/savings-rate", get(stats::get_savings_rate))
+        .route("/api/stats/annual", get(stats::get_annual_stats))
+        .route("/api/stats/range", get(stats::get_range_stats))
+        .route(
+            "/api/stats/alert-thresholds",
+            get(stats::get_alert_thresholds).put(stats::update_alert_thresholds),
+        )
         .route("/api/savings", get(savings::get_savings))
         .route("/api/savings", put(savings::update_savings))
         .route("/api/savings/goal", put(savings::update_savings_goal))
+        .route("/api/savings/history", get(savings::get_savings_history))
+        .route("/api/savings/reconcile", post(savings::reconcile_savings))
+        .route(
+            "/api/savings/allocation-rules",
+            get(savings_accounts::list_allocation_rules)
+                .put(savings_accounts::update_allocation_rules),
+        )
+        .route(
+            "/api/savings/transfers",
+            get(savings_accounts::list_savings_transfers),
+        )
+        .route(
+            "/api/savings/goals",
+            get(savings_accounts::list_account_goals),
+        )
         .route(
             "/api/retirement-savings",
             get(savings::get_retirement_savings),
@@ -122,13 +369,20 @@ pub fn create_app(pool: SqlitePool) -> Router {
             "/api/retirement-savings",
             put(savings::update_retirement_savings),
         )
+        .route("/api/export/estimate", get(export::get_export_estimate))
+        .route("/api/import/jobs/{id}", get(export::get_import_job))
         .route("/api/export/json", get(export::export_json))
-        .route("/api/import/json", post(export::import_json))
-        .route("/api/savings-goals", get(savings_goals::list_savings_goals))
+        .route("/api/export.csv", get(export::export_year_csv))
         .route(
-            "/api/savings-goals",
-            post(savings_goals::create_savings_goal),
+            "/api/savings-accounts",
+            get(savings_accounts::list_savings_accounts),
         )
+        .route(
+            "/api/savings-accounts/{id}",
+            put(savings_accounts::update_savings_account)
+                .delete(savings_accounts::delete_savings_account),
+        )
+        .route("/api/savings-goals", get(savings_goals::list_savings_goals))
         .route(
             "/api/savings-goals/{id}",
             put(savings_goals::update_savings_goal),
@@ -141,10 +395,6 @@ pub fn create_app(pool: SqlitePool) -> Router {
             "/api/retirement-breakdown",
             get(retirement_breakdown::list_retirement_breakdown),
         )
-        .route(
-            "/api/retirement-breakdown",
-            post(retirement_breakdown::create_retirement_breakdown_item),
-        )
         .route(
             "/api/retirement-breakdown/{id}",
             put(retirement_breakdown::update_retirement_breakdown_item),
@@ -153,17 +403,29 @@ pub fn create_app(pool: SqlitePool) -> Router {
             "/api/retirement-breakdown/{id}",
             delete(retirement_breakdown::delete_retirement_breakdown_item),
         )
-        .layer(from_fn(auth_middleware));
-
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any)
-        .allow_credentials(false);
+        .layer(from_fn_with_state(
+            options.body_limits.default,
+            body_limit_middleware,
+        ))
+        .merge(bulk_routes)
+        .merge(upload_routes)
+        .layer(from_fn_with_state(pool.clone(), auth_middleware));
 
     Router::new()
         .merge(public_routes)
         .merge(protected_routes)
-        .layer(cors)
+        .layer(compression_layer())
+        // Bodies are already bounded per route group by body_limit_middleware
+        .layer(DefaultBodyLimit::disable())
+        .layer(Extension(Arc::new(options.password_policy)))
+        .layer(Extension(options.busy_retry))
+        .layer(Extension(options.session))
+        .layer(Extension(options.webhooks))
+        .layer(Extension(Arc::new(options.jwt_keys)))
+        .layer(Extension(Arc::new(options.default_categories)))
+        .layer(Extension(Arc::new(options.two_factor)))
+        .layer(cors_layer(&options.cors))
+        .layer(from_fn(strip_unmatched_cors_headers))
+        .layer(from_fn(request_id_middleware))
         .with_state(pool)
 }
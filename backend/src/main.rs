@@ -1,9 +1,14 @@
 use tower_http::services::ServeDir;
 
-use payme::config::Config;
-use payme::create_app;
+use payme::background;
+use payme::config::{AppOptions, Config, TwoFactorConfig, WebhookPolicy};
+use payme::create_app_with;
 use payme::db;
+use payme::email;
+use payme::handlers::{items, stats};
+use payme::middleware::idempotency;
 use payme::openapi::ApiDoc;
+use payme::webhooks;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -19,7 +24,16 @@ async fn main() {
         .await
         .expect("Failed to run migrations");
 
-    let app = create_app(pool)
+    tokio::spawn(purge_deleted_items(pool.clone()));
+    let options = AppOptions::from_env();
+    require_two_factor_key(&pool, &options.two_factor).await;
+
+    tokio::spawn(retry_webhooks(pool.clone(), options.webhooks));
+    tokio::spawn(send_queued_emails(pool.clone()));
+    tokio::spawn(purge_idempotency_keys(pool.clone()));
+    tokio::spawn(send_monthly_digests(pool.clone()));
+
+    let app = create_app_with(pool, options)
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .fallback_service(ServeDir::new("/app/static"));
 
@@ -34,6 +48,90 @@ async fn main() {
         .with_graceful_shutdown(shutdown_signal())
         .await
         .expect("Server error");
+
+    // In-flight requests are done; give the sends they started a chance to finish
+    if !background::drain(std::time::Duration::from_secs(SHUTDOWN_DRAIN_SECS)).await {
+        tracing::warn!("Background work still running after shutdown; it resumes on next start");
+    }
+}
+
+/// Refuses to start when accounts have two-factor secrets but no key to decrypt them, rather than
+/// failing each of their logins.
+async fn require_two_factor_key(pool: &sqlx::SqlitePool, two_factor: &TwoFactorConfig) {
+    if two_factor.encryption_key.is_some() {
+        return;
+    }
+    let enrolled: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE totp_secret IS NOT NULL")
+            .fetch_one(pool)
+            .await
+            .expect("Failed to check for two-factor accounts");
+    if enrolled > 0 {
+        panic!("TOTP_ENCRYPTION_KEY must be set: {enrolled} accounts have two-factor secrets");
+    }
+    tracing::warn!("TOTP_ENCRYPTION_KEY is not set; two-factor auth can't be set up");
+}
+
+/// How long shutdown waits for webhook and email sends started by requests
+const SHUTDOWN_DRAIN_SECS: u64 = 10;
+
+/// Periodically hard-deletes items soft-deleted longer ago than the restore window.
+async fn purge_deleted_items(pool: sqlx::SqlitePool) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match items::purge_deleted_items(&pool).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Purged {} deleted items", purged),
+            Err(e) => tracing::error!("Failed to purge deleted items: {}", e),
+        }
+    }
+}
+
+/// Periodically drops idempotency keys older than their replay window.
+async fn purge_idempotency_keys(pool: sqlx::SqlitePool) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match idempotency::purge_expired_keys(&pool).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Purged {} expired idempotency keys", purged),
+            Err(e) => tracing::error!("Failed to purge idempotency keys: {}", e),
+        }
+    }
+}
+
+/// Queues last month's digest for opted-in users. Runs hourly, but each month's digest is only
+/// queued once per user, so it goes out shortly after the month turns over.
+async fn send_monthly_digests(pool: sqlx::SqlitePool) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match stats::queue_monthly_digests(&pool, chrono::Utc::now().date_naive()).await {
+            Ok(0) => {}
+            Ok(queued) => tracing::info!("Queued {} monthly digests", queued),
+            Err(e) => tracing::error!("Failed to queue monthly digests: {}", e),
+        }
+    }
+}
+
+/// Picks up webhook deliveries waiting on a retry or left behind by a restart.
+async fn retry_webhooks(pool: sqlx::SqlitePool, policy: WebhookPolicy) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+    loop {
+        interval.tick().await;
+        webhooks::dispatch_due(&pool, policy).await;
+    }
+}
+
+/// Picks up emails that weren't sent when they were queued, e.g. because of a restart.
+async fn send_queued_emails(pool: sqlx::SqlitePool) {
+    let sender = email::sender_from_env();
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        email::dispatch_due(&pool, sender.as_ref()).await;
+    }
 }
 
 async fn shutdown_signal() {
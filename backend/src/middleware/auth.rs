@@ -1,18 +1,39 @@
-use axum::{extract::Request, middleware::Next, response::Response};
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
 use axum_extra::extract::CookieJar;
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
 
+use std::sync::Arc;
+
+use crate::config::{JwtKeys, SessionPolicy, MAX_TOUCH_INTERVAL_SECS};
 use crate::error::PaymeError;
+use crate::handlers::auth::hash_token;
+
+/// Bearer tokens starting with this are API keys rather than JWTs
+pub const API_KEY_PREFIX: &str = "pk_";
+
+/// Request extension marking a request authenticated with an API key rather than a login session
+#[derive(Debug, Clone, Copy)]
+pub struct ApiKeyAuth;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: i64,
     pub username: String,
     pub exp: usize,
+    /// Login session the token was issued for; absent on API keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 pub async fn auth_middleware(
+    State(pool): State<SqlitePool>,
     jar: CookieJar,
     mut request: Request,
     next: Next,
@@ -30,16 +51,126 @@ pub async fn auth_middleware(
         })
         .ok_or(PaymeError::Unauthorized)?;
 
-    let secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "payme-secret-key-change-in-production".to_string());
+    let claims = if token.starts_with(API_KEY_PREFIX) {
+        let claims = api_key_claims(&pool, &token, request.method()).await?;
+        request.extensions_mut().insert(ApiKeyAuth);
+        claims
+    } else {
+        let keys = request
+            .extensions()
+            .get::<Arc<JwtKeys>>()
+            .cloned()
+            .unwrap_or_default();
+        let kid = decode_header(&token)
+            .map_err(|_| PaymeError::Unauthorized)?
+            .kid;
 
-    let token_data = decode::<Claims>(
-        &token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|_| PaymeError::Unauthorized)?;
+        keys.verification_keys(kid.as_deref())
+            .into_iter()
+            .find_map(|key| {
+                decode::<Claims>(
+                    &token,
+                    &DecodingKey::from_secret(key.secret.as_bytes()),
+                    &Validation::default(),
+                )
+                .ok()
+            })
+            .ok_or(PaymeError::Unauthorized)?
+            .claims
+    };
+    if let Some(session_id) = &claims.sid {
+        let policy = request
+            .extensions()
+            .get::<SessionPolicy>()
+            .copied()
+            .unwrap_or_default();
+        touch_session(&pool, claims.sub, session_id, &policy).await?;
+    }
 
-    request.extensions_mut().insert(token_data.claims);
+    request.extensions_mut().insert(claims);
     Ok(next.run(request).await)
 }
+
+/// Refuses API keys on account routes, so a leaked key can't mint more keys, change the account's
+/// credentials or delete it. Layer it inside `auth_middleware`.
+pub async fn session_only_middleware(request: Request, next: Next) -> Result<Response, PaymeError> {
+    if request.extensions().get::<ApiKeyAuth>().is_some() {
+        return Err(PaymeError::Forbidden);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Records a request on the token's session, refusing it once the session has been idle past
+/// the timeout or has ended. The use is only written when the recorded one is getting stale, so
+/// most requests, reads included, don't take SQLite's write lock.
+async fn touch_session(
+    pool: &SqlitePool,
+    user_id: i64,
+    session_id: &str,
+    policy: &SessionPolicy,
+) -> Result<(), PaymeError> {
+    let touch_cutoff = policy.touch_cutoff();
+    let stale: bool = sqlx::query_scalar(
+        "SELECT last_used_at < datetime('now', ?4) FROM sessions WHERE family_id = ?1 AND user_id = ?2 AND (?3 IS NULL OR last_used_at > datetime('now', ?3))",
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(policy.idle_cutoff())
+    .bind(&touch_cutoff)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PaymeError::Unauthorized)?;
+
+    if stale {
+        sqlx::query(
+            "UPDATE sessions SET last_used_at = datetime('now') WHERE family_id = ? AND last_used_at < datetime('now', ?)",
+        )
+        .bind(session_id)
+        .bind(&touch_cutoff)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Resolves an API key to its owner, recording the use on the key at most once a minute.
+async fn api_key_claims(
+    pool: &SqlitePool,
+    key: &str,
+    method: &Method,
+) -> Result<Claims, PaymeError> {
+    let (key_id, user_id, username, scopes, expires): (i64, i64, String, String, Option<i64>) =
+        sqlx::query_as(
+            r#"
+            SELECT k.id, k.user_id, u.username, k.scopes, CAST(strftime('%s', k.expires_at) AS INTEGER)
+            FROM api_keys k
+            JOIN users u ON u.id = k.user_id
+            WHERE k.key_hash = ? AND k.revoked_at IS NULL
+              AND (k.expires_at IS NULL OR k.expires_at > datetime('now'))
+            "#,
+        )
+        .bind(hash_token(key))
+        .fetch_optional(pool)
+        .await?
+        .ok_or(PaymeError::Unauthorized)?;
+
+    let scope = if method.is_safe() { "read" } else { "write" };
+    if !scopes.split(',').any(|s| s == scope) {
+        return Err(PaymeError::Forbidden);
+    }
+
+    sqlx::query(
+        "UPDATE api_keys SET last_used_at = datetime('now') WHERE id = ? AND (last_used_at IS NULL OR last_used_at < datetime('now', ?))",
+    )
+    .bind(key_id)
+    .bind(format!("-{MAX_TOUCH_INTERVAL_SECS} seconds"))
+    .execute(pool)
+    .await?;
+
+    Ok(Claims {
+        sub: user_id,
+        username,
+        exp: expires.map_or(usize::MAX, |exp| exp as usize),
+        sid: None,
+    })
+}
@@ -0,0 +1,32 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::error::PaymeError;
+
+/// Buffers the request body, rejecting it with 413 once it passes `limit` bytes. Routes are
+/// grouped by how large a body they take, each group layered with its own limit; extractors
+/// then read the buffered body without a limit of their own.
+pub async fn body_limit_middleware(
+    State(limit): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let declared = request
+        .headers()
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared.is_some_and(|len| len > limit) {
+        return PaymeError::PayloadTooLarge { limit }.into_response();
+    }
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, limit).await else {
+        return PaymeError::PayloadTooLarge { limit }.into_response();
+    };
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
@@ -0,0 +1,20 @@
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
+
+/// Bodies smaller than this are sent as-is; the encoding framing would outweigh the savings.
+pub const MIN_COMPRESS_BYTES: u64 = 1024;
+
+/// Compresses responses with brotli or gzip, whichever the client prefers in `Accept-Encoding`.
+/// Bodies are encoded as they stream. Responses that already carry a `Content-Encoding` are left
+/// alone, as are formats that are compressed themselves.
+pub fn compression_layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        SizeAbove::new(MIN_COMPRESS_BYTES)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::const_new("application/pdf"))
+            .and(NotForContentType::const_new("application/zip"))
+            .and(NotForContentType::const_new("application/octet-stream")),
+    )
+}
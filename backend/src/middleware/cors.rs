@@ -0,0 +1,81 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::config::CorsConfig;
+use crate::middleware::idempotency::IDEMPOTENT_REPLAYED_HEADER;
+use crate::middleware::request_id::REQUEST_ID_HEADER;
+
+/// Without an origin allowlist any origin may call the API, but without credentials. With one,
+/// only those origins get CORS headers and credentials (cookies) are allowed.
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let layer = CorsLayer::new().expose_headers([REQUEST_ID_HEADER, IDEMPOTENT_REPLAYED_HEADER]);
+
+    let origins: Vec<HeaderValue> = config
+        .allowed_origins
+        .iter()
+        .filter_map(|o| match HeaderValue::from_str(o) {
+            Ok(origin) => Some(origin),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin {:?}", o);
+                None
+            }
+        })
+        .collect();
+    if origins.is_empty() {
+        return layer
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .allow_credentials(false);
+    }
+
+    let methods: Vec<Method> = config
+        .allowed_methods
+        .iter()
+        .filter_map(|m| Method::from_bytes(m.to_uppercase().as_bytes()).ok())
+        .collect();
+    let headers: Vec<HeaderName> = config
+        .allowed_headers
+        .iter()
+        .filter_map(|h| HeaderName::from_bytes(h.as_bytes()).ok())
+        .collect();
+
+    // Wildcards can't be combined with credentials, so an unset list mirrors the preflight
+    layer
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(if methods.is_empty() {
+            AllowMethods::mirror_request()
+        } else {
+            AllowMethods::list(methods)
+        })
+        .allow_headers(if headers.is_empty() {
+            AllowHeaders::mirror_request()
+        } else {
+            AllowHeaders::list(headers)
+        })
+        .allow_credentials(true)
+}
+
+/// `CorsLayer` still sends its other `Access-Control-*` headers when the origin isn't on the
+/// allowlist. Drop them so a rejected origin gets no CORS headers at all.
+pub async fn strip_unmatched_cors_headers(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    if !headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN) {
+        for name in [
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            header::ACCESS_CONTROL_MAX_AGE,
+        ] {
+            headers.remove(name);
+        }
+    }
+    response
+}
@@ -0,0 +1,212 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use crate::error::PaymeError;
+use crate::middleware::auth::Claims;
+
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+/// Set on responses replayed from an earlier request with the same key
+pub const IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+/// How long a key's response is kept for replay
+pub const KEY_RETENTION_HOURS: i64 = 24;
+const MAX_KEY_LEN: usize = 255;
+
+#[derive(sqlx::FromRow)]
+struct StoredKey {
+    request_hash: String,
+    status: Option<u16>,
+    content_type: Option<String>,
+    body: Option<Vec<u8>>,
+}
+
+/// Makes POST requests carrying an `Idempotency-Key` header safe to retry. The first request
+/// with a key runs as usual and its successful response is stored; repeats with the same key
+/// and body get that response back without running the handler again. Keys are scoped to the
+/// user and endpoint. Reusing a key with a different body, or while the first request is still
+/// running, is a conflict. Failed responses aren't stored, so those can be retried.
+///
+/// Responses are stored as sent, so layer this only on routes that create records, and inside a
+/// body limit layer, since the request body is buffered whole to fingerprint it.
+pub async fn idempotency_middleware(
+    State(pool): State<SqlitePool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(&IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let Some(key) = key
+        .to_str()
+        .ok()
+        .filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LEN)
+        .map(str::to_string)
+    else {
+        return PaymeError::BadRequest(format!(
+            "Idempotency-Key must be 1-{MAX_KEY_LEN} visible ASCII characters"
+        ))
+        .into_response();
+    };
+    let Some(user_id) = request.extensions().get::<Claims>().map(|c| c.sub) else {
+        return next.run(request).await;
+    };
+
+    match replay_or_run(&pool, user_id, key, request, next).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn replay_or_run(
+    pool: &SqlitePool,
+    user_id: i64,
+    key: String,
+    request: Request,
+    next: Next,
+) -> Result<Response, PaymeError> {
+    let endpoint = format!("{} {}", request.method(), request.uri().path());
+    let (parts, body) = request.into_parts();
+    // The body limit layered outside has already buffered the body within its limit
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| PaymeError::BadRequest(format!("Failed to read request body: {e}")))?;
+    let request_hash: String = Sha256::digest(&body)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+
+    // An expired key is free to be used again
+    sqlx::query(
+        "DELETE FROM idempotency_keys WHERE user_id = ? AND endpoint = ? AND idempotency_key = ? AND created_at < datetime('now', ?)",
+    )
+    .bind(user_id)
+    .bind(&endpoint)
+    .bind(&key)
+    .bind(format!("-{KEY_RETENTION_HOURS} hours"))
+    .execute(pool)
+    .await?;
+
+    let claimed = sqlx::query(
+        "INSERT INTO idempotency_keys (user_id, endpoint, idempotency_key, request_hash) VALUES (?, ?, ?, ?) ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(&endpoint)
+    .bind(&key)
+    .bind(&request_hash)
+    .execute(pool)
+    .await?
+    .rows_affected()
+        == 1;
+    if !claimed {
+        return replay(pool, user_id, &endpoint, &key, &request_hash).await;
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !response.status().is_success() {
+        release(pool, user_id, &endpoint, &key).await;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            release(pool, user_id, &endpoint, &key).await;
+            return Err(PaymeError::Internal(format!(
+                "Failed to buffer response for idempotency: {e}"
+            )));
+        }
+    };
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    sqlx::query(
+        "UPDATE idempotency_keys SET status = ?, content_type = ?, body = ? WHERE user_id = ? AND endpoint = ? AND idempotency_key = ?",
+    )
+    .bind(parts.status.as_u16())
+    .bind(content_type)
+    .bind(body.as_ref())
+    .bind(user_id)
+    .bind(&endpoint)
+    .bind(&key)
+    .execute(pool)
+    .await?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// The stored response for a key that was used before.
+async fn replay(
+    pool: &SqlitePool,
+    user_id: i64,
+    endpoint: &str,
+    key: &str,
+    request_hash: &str,
+) -> Result<Response, PaymeError> {
+    let stored: StoredKey = sqlx::query_as(
+        "SELECT request_hash, status, content_type, body FROM idempotency_keys WHERE user_id = ? AND endpoint = ? AND idempotency_key = ?",
+    )
+    .bind(user_id)
+    .bind(endpoint)
+    .bind(key)
+    .fetch_one(pool)
+    .await?;
+
+    if stored.request_hash != request_hash {
+        return Err(PaymeError::Conflict(
+            "Idempotency-Key was already used with a different request body".to_string(),
+        ));
+    }
+    let Some(status) = stored.status.and_then(|s| StatusCode::from_u16(s).ok()) else {
+        return Err(PaymeError::Conflict(
+            "A request with this Idempotency-Key is still in progress".to_string(),
+        ));
+    };
+
+    let mut response = (status, stored.body.unwrap_or_default()).into_response();
+    if let Some(value) = stored
+        .content_type
+        .and_then(|c| HeaderValue::from_str(&c).ok())
+    {
+        response.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    Ok(response)
+}
+
+/// Drops the claim on a key whose request failed, so a retry runs again.
+async fn release(pool: &SqlitePool, user_id: i64, endpoint: &str, key: &str) {
+    let released = sqlx::query(
+        "DELETE FROM idempotency_keys WHERE user_id = ? AND endpoint = ? AND idempotency_key = ?",
+    )
+    .bind(user_id)
+    .bind(endpoint)
+    .bind(key)
+    .execute(pool)
+    .await;
+    if let Err(e) = released {
+        tracing::error!("Failed to release idempotency key: {}", e);
+    }
+}
+
+/// Deletes keys older than the retention window. Returns the number of rows removed.
+pub async fn purge_expired_keys(pool: &SqlitePool) -> Result<u64, PaymeError> {
+    let result = sqlx::query("DELETE FROM idempotency_keys WHERE created_at < datetime('now', ?)")
+        .bind(format!("-{KEY_RETENTION_HOURS} hours"))
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
@@ -1 +1,6 @@
 pub mod auth;
+pub mod body_limit;
+pub mod compression;
+pub mod cors;
+pub mod idempotency;
+pub mod request_id;
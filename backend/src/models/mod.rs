@@ -1,13 +1,27 @@
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::currency::CurrencyTotal;
+use crate::money::{Money, RoundingMode};
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct FixedExpense {
     pub id: i64,
     pub user_id: i64,
     pub label: String,
-    pub amount: f64,
+    #[schema(value_type = f64)]
+    pub amount: Money,
+    /// Optional grouping such as "Housing" or "Subscriptions"
+    pub category: Option<String>,
+    /// Day of the month the bill is due (1-31); clamped to shorter months' last day
+    pub due_day: Option<i64>,
+    /// Paused expenses aren't copied into new months
+    pub active: bool,
+    /// First month the expense applies to, as `YYYY-MM`; unbounded when absent
+    pub start_month: Option<String>,
+    /// Last month the expense applies to, as `YYYY-MM`; unbounded when absent
+    pub end_month: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -15,16 +29,32 @@ pub struct MonthlyFixedExpense {
     pub id: i64,
     pub month_id: i64,
     pub label: String,
-    pub amount: f64,
+    #[schema(value_type = f64)]
+    pub amount: Money,
+    pub category: Option<String>,
+    pub due_day: Option<i64>,
+    pub paid: bool,
+}
+
+/// Fixed expenses summed by their category; ungrouped ones fall under "Uncategorized"
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct FixedExpenseGroup {
+    pub category: String,
+    #[schema(value_type = f64)]
+    pub total: Money,
+    pub count: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct MonthlySavings {
     pub id: i64,
     pub month_id: i64,
-    pub savings: f64,
-    pub retirement_savings: f64,
-    pub savings_goal: f64,
+    #[schema(value_type = f64)]
+    pub savings: Money,
+    #[schema(value_type = f64)]
+    pub retirement_savings: Money,
+    #[schema(value_type = f64)]
+    pub savings_goal: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -32,8 +62,15 @@ pub struct BudgetCategory {
     pub id: i64,
     pub user_id: i64,
     pub label: String,
-    pub default_amount: f64,
+    #[schema(value_type = f64)]
+    pub default_amount: Money,
     pub color: String,
+    /// Display position; lower values come first
+    pub sort_order: i64,
+    /// Icon name for the UI, e.g. `shopping-cart`
+    pub icon: Option<String>,
+    /// Reject expenses that would take the category past its monthly budget
+    pub enforce_budget: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -44,6 +81,35 @@ pub struct Month {
     pub month: i32,
     pub is_closed: bool,
     pub closed_at: Option<DateTime<Utc>>,
+    /// Locked months reject edits like closed ones but keep no snapshot and unlock freely
+    pub is_locked: bool,
+    /// Whether this is the calendar month it is now in the viewer's timezone
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub is_current: bool,
+    /// Days left in the month counting today; 0 once it has passed and every day of the month
+    /// while it is still ahead
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub days_remaining: u32,
+}
+
+impl Month {
+    /// Fills in `is_current` and `days_remaining` relative to `today`.
+    pub fn as_of(self, today: NaiveDate) -> Self {
+        let days_in_month = crate::handlers::months::days_in_month(self.year, self.month as u32);
+        let position = (self.year, self.month).cmp(&(today.year(), today.month() as i32));
+        let days_remaining = match position {
+            std::cmp::Ordering::Less => 0,
+            std::cmp::Ordering::Equal => days_in_month - today.day() + 1,
+            std::cmp::Ordering::Greater => days_in_month,
+        };
+        Month {
+            is_current: position.is_eq(),
+            days_remaining,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -51,7 +117,20 @@ pub struct IncomeEntry {
     pub id: i64,
     pub month_id: i64,
     pub label: String,
-    pub amount: f64,
+    /// Net amount, which is what budgets and totals use
+    #[schema(value_type = f64)]
+    pub amount: Money,
+    /// Pay before withholding; absent for entries recorded as net only
+    #[schema(value_type = Option<f64>)]
+    pub gross_amount: Option<Money>,
+    #[schema(value_type = f64)]
+    pub withholding: Money,
+    /// How often `amount` is paid: `monthly`, `biweekly`, `weekly`, `annual` or `one_time`
+    pub frequency: String,
+    /// `amount` as a monthly figure, which totals and percentage budgets use; a biweekly
+    /// amount counts 26/12 times, for example. `one_time` entries count once.
+    #[schema(value_type = f64)]
+    pub monthly_amount: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -59,7 +138,15 @@ pub struct MonthlyBudget {
     pub id: i64,
     pub month_id: i64,
     pub category_id: i64,
-    pub allocated_amount: f64,
+    /// Budgeted amount; derived from the month's income when `allocation_percent` is set
+    #[schema(value_type = f64)]
+    pub allocated_amount: Money,
+    /// Carry what's left of this allocation into the next month created
+    pub rollover: bool,
+    /// With `rollover`, also carry overspending forward as a reduced allocation
+    pub carry_overspend: bool,
+    /// Share of the month's net income to budget, 0-100, instead of a fixed amount
+    pub allocation_percent: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -68,9 +155,15 @@ pub struct Item {
     pub month_id: i64,
     pub category_id: i64,
     pub description: String,
-    pub amount: f64,
+    #[schema(value_type = f64)]
+    pub amount: Money,
     pub spent_on: NaiveDate,
     pub savings_destination: String,
+    /// Set when `savings_destination` is `account` or `account_withdrawal`
+    pub savings_account_id: Option<i64>,
+    pub currency: String,
+    /// Incremented on every update; updates must send the version they were based on
+    pub version: i64,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -80,8 +173,10 @@ pub struct MonthlyBudgetWithCategory {
     pub category_id: i64,
     pub category_label: String,
     pub category_color: String,
-    pub allocated_amount: f64,
-    pub spent_amount: f64,
+    #[schema(value_type = f64)]
+    pub allocated_amount: Money,
+    #[schema(value_type = f64)]
+    pub spent_amount: Money,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -89,14 +184,25 @@ pub struct MonthSummary {
     pub month: Month,
     pub income_entries: Vec<IncomeEntry>,
     pub fixed_expenses: Vec<MonthlyFixedExpense>,
+    pub fixed_by_category: Vec<FixedExpenseGroup>,
     pub budgets: Vec<MonthlyBudgetWithCategory>,
     pub items: Vec<ItemWithCategory>,
     pub savings: Option<MonthlySavings>,
-    pub total_income: f64,
-    pub total_fixed: f64,
-    pub total_budgeted: f64,
-    pub total_spent: f64,
-    pub remaining: f64,
+    #[schema(value_type = f64)]
+    pub total_income: Money,
+    #[schema(value_type = f64)]
+    pub total_fixed: Money,
+    #[schema(value_type = f64)]
+    pub total_budgeted: Money,
+    /// Spending converted into `base_currency`; `spent_by_currency` has the unconverted totals
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    #[schema(value_type = f64)]
+    pub remaining: Money,
+    pub base_currency: String,
+    pub spent_by_currency: Vec<CurrencyTotal>,
+    /// How the owner's derived amounts, like percentage budgets, were rounded to the cent
+    pub rounding_mode: RoundingMode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -106,10 +212,18 @@ pub struct ItemWithCategory {
     pub category_id: i64,
     pub category_label: String,
     pub category_color: String,
+    pub category_icon: Option<String>,
     pub description: String,
-    pub amount: f64,
+    #[schema(value_type = f64)]
+    pub amount: Money,
     pub spent_on: NaiveDate,
     pub savings_destination: String,
+    /// Set when `savings_destination` is `account` or `account_withdrawal`
+    pub savings_account_id: Option<i64>,
+    pub currency: String,
+    /// Incremented on every update; updates must send the version they were based on
+    pub version: i64,
+    pub has_receipt: bool,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -117,20 +231,79 @@ pub struct CategoryStats {
     pub category_id: i64,
     pub category_label: String,
     pub category_color: String,
-    pub current_month_spent: f64,
-    pub previous_month_spent: f64,
-    pub change_amount: f64,
+    #[schema(value_type = f64)]
+    pub current_month_spent: Money,
+    #[schema(value_type = f64)]
+    pub previous_month_spent: Money,
+    #[schema(value_type = f64)]
+    pub change_amount: Money,
     pub change_percent: Option<f64>,
+    /// Amount allocated to the category in the current month
+    #[schema(value_type = f64)]
+    pub budgeted: Money,
+    /// Amount spent in the category in the current month
+    #[schema(value_type = f64)]
+    pub actual: Money,
+    /// `budgeted - actual`; negative when the category is overspent
+    #[schema(value_type = f64)]
+    pub variance: Money,
+    /// Share of the budget spent, in percent. `None` when nothing was budgeted
+    pub percent_used: Option<f64>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct MonthlyStats {
     pub year: i32,
     pub month: i32,
-    pub total_income: f64,
-    pub total_spent: f64,
-    pub total_fixed: f64,
-    pub net: f64,
+    #[schema(value_type = f64)]
+    pub total_income: Money,
+    #[schema(value_type = f64)]
+    pub total_spent: Money,
+    #[schema(value_type = f64)]
+    pub total_fixed: Money,
+    #[schema(value_type = f64)]
+    pub net: Money,
+    pub spent_by_currency: Vec<CurrencyTotal>,
+    /// Transfers to savings plus the growth of the savings snapshot since the previous month
+    #[schema(value_type = f64)]
+    pub saved: Money,
+    /// `saved / total_income`, or zero for months without income
+    pub savings_rate: f64,
+}
+
+/// A reusable starting point for a recurring transaction
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ItemTemplate {
+    pub id: i64,
+    pub user_id: i64,
+    pub category_id: i64,
+    pub description: String,
+    #[schema(value_type = f64)]
+    pub default_amount: Money,
+    pub savings_destination: String,
+}
+
+/// Units of `to_currency` per unit of `from_currency` from `effective_on` onwards
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ExchangeRate {
+    pub id: i64,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+    pub effective_on: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct SavingsAccount {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    #[schema(value_type = f64)]
+    pub balance: Money,
+    #[schema(value_type = Option<f64>)]
+    pub goal: Option<Money>,
+    /// Date the goal should be reached by
+    pub goal_date: Option<NaiveDate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -138,8 +311,10 @@ pub struct CustomSavingsGoal {
     pub id: i64,
     pub user_id: i64,
     pub name: String,
-    pub current_amount: f64,
-    pub target_amount: f64,
+    #[schema(value_type = f64)]
+    pub current_amount: Money,
+    #[schema(value_type = f64)]
+    pub target_amount: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -147,7 +322,8 @@ pub struct RetirementBreakdownItem {
     pub id: i64,
     pub user_id: i64,
     pub label: String,
-    pub amount: f64,
+    #[schema(value_type = f64)]
+    pub amount: Money,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -156,4 +332,49 @@ pub struct StatsResponse {
     pub monthly_trends: Vec<MonthlyStats>,
     pub average_monthly_spending: f64,
     pub average_monthly_income: f64,
+    pub alerts: Vec<BudgetAlert>,
+    /// Current fixed expense templates grouped by category
+    pub fixed_by_category: Vec<FixedExpenseGroup>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Warning,
+    Critical,
+}
+
+/// A category in the latest month whose spend has crossed one of the user's thresholds
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BudgetAlert {
+    pub category_id: i64,
+    pub category_label: String,
+    pub percent_used: f64,
+    pub severity: AlertSeverity,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct AlertThresholds {
+    /// Percent of the allocated budget at which a warning is raised
+    pub warning_percent: f64,
+    /// Percent of the allocated budget at which a critical alert is raised
+    pub critical_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Household {
+    pub id: i64,
+    pub name: String,
+    /// Caller's role: "owner" or "member"
+    pub role: String,
+    /// False while the caller's invite has not been accepted
+    pub accepted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct HouseholdMember {
+    pub user_id: i64,
+    pub username: String,
+    pub role: String,
+    pub accepted: bool,
 }
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::ValidationError;
+
+use crate::error::PaymeError;
+
+/// How a derived amount that lands exactly between two cents is settled, such as a percentage
+/// budget or a share of a split contribution. Each user picks one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Halves round away from zero, like SQLite's `ROUND`
+    #[default]
+    HalfUp,
+    /// Halves round to the even cent (banker's rounding), so they don't all lean the same way
+    HalfEven,
+}
+
+impl RoundingMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "half_up",
+            RoundingMode::HalfEven => "half_even",
+        }
+    }
+
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "half_up" => Some(RoundingMode::HalfUp),
+            "half_even" => Some(RoundingMode::HalfEven),
+            _ => None,
+        }
+    }
+
+    /// `numerator / denominator` rounded to a whole number; `denominator` must be positive.
+    fn divide(self, numerator: i64, denominator: i64) -> i64 {
+        let quotient = numerator.div_euclid(denominator);
+        let twice_remainder = 2 * numerator.rem_euclid(denominator);
+        match twice_remainder.cmp(&denominator) {
+            std::cmp::Ordering::Less => quotient,
+            std::cmp::Ordering::Greater => quotient + 1,
+            std::cmp::Ordering::Equal => match self {
+                // The quotient is the floor, which is already away from zero for negatives
+                RoundingMode::HalfUp if numerator < 0 => quotient,
+                RoundingMode::HalfUp => quotient + 1,
+                RoundingMode::HalfEven => quotient + quotient.rem_euclid(2),
+            },
+        }
+    }
+}
+
+/// The user's rounding mode, or the default for an unset or unknown value.
+pub async fn rounding_mode(
+    pool: &sqlx::SqlitePool,
+    user_id: i64,
+) -> Result<RoundingMode, PaymeError> {
+    let mode: Option<String> = sqlx::query_scalar("SELECT rounding_mode FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(mode
+        .as_deref()
+        .and_then(RoundingMode::parse)
+        .unwrap_or_default())
+}
+
+/// An amount in whole cents. Sums and balance changes go through this type so that adding and
+/// subtracting amounts like 0.10 many times stays exact instead of drifting the way `f64` does.
+/// Balances are stored as INTEGER cents through this type and exchanged as decimals in the API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub fn from_cents(cents: i64) -> Self {
+        Money(cents)
+    }
+
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+
+    /// Rounds to the nearest cent. Use for values already in storage or computed, such as a
+    /// converted foreign-currency amount.
+    pub fn from_f64(amount: f64) -> Self {
+        Money((amount * 100.0).round() as i64)
+    }
+
+    /// Parses an amount given by a client, rejecting fractions of a cent.
+    pub fn from_decimal(amount: f64) -> Option<Self> {
+        if !amount.is_finite() {
+            return None;
+        }
+        let scaled = amount * 100.0;
+        let cents = scaled.round();
+        // Allows for decimals like 0.29 that have no exact binary representation
+        ((scaled - cents).abs() <= 1e-9 * scaled.abs().max(1.0)).then_some(Money(cents as i64))
+    }
+
+    /// Rounds a derived amount to the cent with `mode`. Amounts within a hair of a half cent
+    /// count as one, since results like `2.675 * 100` come out as 267.49999... in `f64`.
+    pub fn round(amount: f64, mode: RoundingMode) -> Self {
+        let scaled = amount * 100.0;
+        let floor = scaled.floor();
+        if (scaled - floor - 0.5).abs() > 1e-6 {
+            return Money(scaled.round() as i64);
+        }
+        // Twice the amount is a whole number of cents, so it can be halved exactly
+        Money(mode.divide(2 * floor as i64 + 1, 2))
+    }
+
+    /// `percent` of the amount, rounded to the cent with `mode`.
+    pub fn percent(self, percent: f64, mode: RoundingMode) -> Self {
+        Money::round(self.to_f64() * percent / 100.0, mode)
+    }
+
+    /// Splits the amount in proportion to `weights`, rounding each share with `mode`. When that
+    /// leaves the shares a cent or two away from the whole, the shares rounding moved furthest are
+    /// nudged back one cent at a time, so they always add up to exactly the amount.
+    pub fn allocate(self, weights: &[i64], mode: RoundingMode) -> Vec<Money> {
+        let total: i64 = weights.iter().sum();
+        if total <= 0 {
+            return vec![Money::ZERO; weights.len()];
+        }
+        // Each share with how far rounding moved it, in 1/total of a cent
+        let mut shares: Vec<(i64, i64)> = weights
+            .iter()
+            .map(|&weight| {
+                let exact = self.0 * weight;
+                let cents = mode.divide(exact, total);
+                (cents, cents * total - exact)
+            })
+            .collect();
+
+        let mut gap = self.0 - shares.iter().map(|&(cents, _)| cents).sum::<i64>();
+        while gap != 0 {
+            let step = gap.signum();
+            // Ties favour earlier shares, so the same split always comes out the same way
+            let (cents, error) = if step > 0 {
+                shares.iter_mut().min_by_key(|(_, error)| *error)
+            } else {
+                shares.iter_mut().max_by_key(|(_, error)| *error)
+            }
+            .expect("a positive total has at least one weight");
+            *cents += step;
+            *error += step * total;
+            gap -= step;
+        }
+
+        shares.into_iter().map(|(cents, _)| Money(cents)).collect()
+    }
+
+    /// The amount times `factor`, such as an exchange rate, rounded to the nearest cent.
+    pub fn scaled(self, factor: f64) -> Self {
+        Money((self.0 as f64 * factor).round() as i64)
+    }
+
+    pub fn abs(self) -> Self {
+        Money(self.0.abs())
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+/// Two decimal places, as in `-1234.50`.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:02}", cents / 100, cents % 100)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        f64::deserialize(deserializer).map(Money::from_f64)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+/// `validator` check for amount fields: at most two decimal places.
+pub fn validate_cents(amount: f64) -> Result<(), ValidationError> {
+    match Money::from_decimal(amount) {
+        Some(_) => Ok(()),
+        None => Err(ValidationError::new("cents")
+            .with_message("Amounts can have at most two decimal places".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_add_and_subtract_stays_exact() {
+        let mut float = 19.99;
+        let mut money = Money::from_decimal(19.99).unwrap();
+        let step = Money::from_decimal(0.1).unwrap();
+        for _ in 0..1000 {
+            float += 0.1;
+            money += step;
+        }
+        for _ in 0..1000 {
+            float -= 0.1;
+            money = money - step;
+        }
+
+        assert_ne!(float, 19.99);
+        assert_eq!(money, Money::from_cents(1999));
+        assert_eq!(money.to_f64(), 19.99);
+    }
+
+    #[test]
+    fn test_serializes_as_decimal() {
+        let money = Money::from_cents(123456);
+        assert_eq!(serde_json::to_string(&money).unwrap(), "1234.56");
+        assert_eq!(serde_json::from_str::<Money>("1234.56").unwrap(), money);
+    }
+
+    #[test]
+    fn test_displays_two_decimal_places() {
+        assert_eq!(Money::from_cents(123450).to_string(), "1234.50");
+        assert_eq!(Money::from_cents(-5).to_string(), "-0.05");
+        assert_eq!(Money::ZERO.to_string(), "0.00");
+    }
+
+    #[test]
+    fn test_from_decimal() {
+        assert_eq!(Money::from_decimal(0.29), Some(Money::from_cents(29)));
+        assert_eq!(Money::from_decimal(-12.5), Some(Money::from_cents(-1250)));
+        assert_eq!(
+            Money::from_decimal(1234567.89),
+            Some(Money::from_cents(123456789))
+        );
+        assert_eq!(Money::from_decimal(0.125), None);
+        assert_eq!(Money::from_decimal(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_round_settles_halves_by_mode() {
+        let up = RoundingMode::HalfUp;
+        let even = RoundingMode::HalfEven;
+        assert_eq!(Money::round(0.125, up), Money::from_cents(13));
+        assert_eq!(Money::round(0.125, even), Money::from_cents(12));
+        assert_eq!(Money::round(0.135, even), Money::from_cents(14));
+        assert_eq!(Money::round(2.675, even), Money::from_cents(268));
+        assert_eq!(Money::round(-0.125, up), Money::from_cents(-13));
+        assert_eq!(Money::round(-0.125, even), Money::from_cents(-12));
+        assert_eq!(Money::round(1.004, up), Money::from_cents(100));
+    }
+
+    #[test]
+    fn test_allocate_odd_amount_three_ways_sums_to_whole() {
+        for mode in [RoundingMode::HalfUp, RoundingMode::HalfEven] {
+            let amount = Money::from_cents(10001);
+            let shares = amount.allocate(&[1, 1, 1], mode);
+            assert_eq!(shares.iter().copied().sum::<Money>(), amount, "{mode:?}");
+            assert_eq!(
+                shares,
+                vec![
+                    Money::from_cents(3334),
+                    Money::from_cents(3334),
+                    Money::from_cents(3333)
+                ]
+            );
+        }
+
+        // Three shares of 0.5 cents each round up under half-up and must be pulled back
+        let shares = Money::from_cents(3).allocate(&[1, 1, 1, 3], RoundingMode::HalfUp);
+        assert_eq!(shares.iter().copied().sum::<Money>(), Money::from_cents(3));
+        assert!(shares.iter().all(|&share| share >= Money::ZERO));
+
+        // Half-even leaves 2.5 at 2 and gives the spare cent to the first tied share
+        let shares = Money::from_cents(5).allocate(&[1, 1], RoundingMode::HalfEven);
+        assert_eq!(shares, vec![Money::from_cents(3), Money::from_cents(2)]);
+    }
+}
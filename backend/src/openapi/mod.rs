@@ -1,22 +1,62 @@
 use utoipa::OpenApi;
 
+use crate::currency::CurrencyTotal;
 use crate::handlers::{
-    auth::{AuthRequest, AuthResponse},
-    budget::{CreateCategory, UpdateCategory, UpdateMonthlyBudget},
+    api_keys::{ApiKey, CreateApiKey},
+    auth::{
+        AuthRequest, AuthResponse, BackupCodesResponse, ChangeBaseCurrencyRequest,
+        ChangeEmailRequest, ChangePasswordRequest, ChangeRoundingModeRequest,
+        ChangeTimezoneRequest, DeleteAccountRequest, ForgotPasswordRequest,
+        NotificationPreferences, RefreshRequest, ResetPasswordRequest, TwoFactorSetupResponse,
+        TwoFactorVerifyRequest,
+    },
+    budget::{
+        AppliedBudgetTemplate, ApplyBudgetTemplate, BudgetTemplate, BulkBudgetEntry,
+        BulkBudgetUpdate, CreateCategory, MergeCategory, MergedCategory, ReorderCategories,
+        TemplateCategory, UpdateCategory, UpdateMonthlyBudget,
+    },
+    exchange_rates::SetExchangeRate,
     export::{
-        BudgetExport, CategoryExport, FixedExpenseExport, IncomeExport, ItemExport, MonthExport,
-        UserExport,
+        BudgetExport, CategoryExport, EncryptedExport, ExportEstimate, FixedExpenseExport,
+        ImportCounts, ImportIssue, ImportJob, ImportPayload, ImportReport, IncomeExport,
+        ItemExport, MergeReport, MonthExport, UserExport,
     },
     fixed_expenses::{CreateFixedExpense, UpdateFixedExpense},
+    households::{CreateHousehold, InviteMember},
     income::{CreateIncome, UpdateIncome},
-    items::{CreateItem, UpdateItem},
+    item_templates::{CreateItemTemplate, ItemFromTemplate, UpdateItemTemplate},
+    items::{
+        BudgetWarning, BulkDeleteItems, BulkDeleteResult, CreateItem, CreatedItem, ItemSearchPage,
+        ItemSearchResult, MoveItem, UpdateItem,
+    },
     monthly_data::{CreateMonthlyFixedExpense, UpdateMonthlyFixedExpense, UpdateMonthlySavings},
-    savings::{RetirementSavingsResponse, SavingsResponse, UpdateRetirementSavings, UpdateSavings},
+    months::{
+        CategoryForecast, CategoryVariance, ClosePreview, CreateMonthRequest, MonthForecast,
+        ProjectedMonth, SafeToSpend, SavingsSnapshot,
+    },
+    reminders::UpcomingBill,
+    savings::{
+        BalanceReconciliation, RetirementSavingsResponse, SavingsHistoryPoint,
+        SavingsReconciliation, SavingsResponse, UpdateRetirementSavings, UpdateSavings,
+    },
+    savings_accounts::{
+        AccountGoal, AllocationRule, Contribute, ContributionShare, CreateSavingsAccount,
+        SavingsTransfer, SavingsTransferReceipt, TransferSavings, UpdateAllocationRules,
+        UpdateSavingsAccount,
+    },
+    stats::{
+        AnnualCategoryStats, AnnualStats, CategoryTrend, CategoryTrendPoint, DailySpend,
+        IncomeSummary, IncomeTotals, RangeCategoryStats, RangeMonthSpend, RangeStats, TopCategory,
+    },
+    webhooks::{CreateWebhook, Webhook, WebhookDelivery},
 };
 use crate::models::{
-    BudgetCategory, CategoryStats, FixedExpense, IncomeEntry, Item, ItemWithCategory, Month,
-    MonthSummary, MonthlyBudget, MonthlyFixedExpense, MonthlySavings, MonthlyStats, StatsResponse,
+    AlertSeverity, AlertThresholds, BudgetAlert, BudgetCategory, CategoryStats, ExchangeRate,
+    FixedExpense, FixedExpenseGroup, Household, HouseholdMember, IncomeEntry, Item, ItemTemplate,
+    ItemWithCategory, Month, MonthSummary, MonthlyBudget, MonthlyFixedExpense, MonthlySavings,
+    MonthlyStats, SavingsAccount, StatsResponse,
 };
+use crate::money::RoundingMode;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -25,19 +65,55 @@ use crate::models::{
         crate::handlers::auth::login,
         crate::handlers::auth::logout,
         crate::handlers::auth::me,
+        crate::handlers::auth::change_email,
+        crate::handlers::auth::change_base_currency,
+        crate::handlers::auth::change_rounding_mode,
+        crate::handlers::auth::change_timezone,
+        crate::handlers::auth::get_notification_preferences,
+        crate::handlers::auth::update_notification_preferences,
+        crate::handlers::auth::change_password,
+        crate::handlers::auth::forgot_password,
+        crate::handlers::auth::reset_password,
+        crate::handlers::auth::setup_two_factor,
+        crate::handlers::auth::verify_two_factor,
+        crate::handlers::auth::regenerate_two_factor_backup_codes,
+        crate::handlers::auth::refresh,
+        crate::handlers::auth::delete_account,
+        crate::handlers::api_keys::list_api_keys,
+        crate::handlers::api_keys::create_api_key,
+        crate::handlers::api_keys::revoke_api_key,
+        crate::handlers::export::get_export_estimate,
         crate::handlers::export::export_json,
         crate::handlers::export::import_json,
+        crate::handlers::export::start_import_job,
+        crate::handlers::export::get_import_job,
+        crate::handlers::export::export_month_csv,
+        crate::handlers::export::export_month_qif,
+        crate::handlers::export::export_year_csv,
         crate::handlers::budget::list_monthly_budgets,
         crate::handlers::budget::update_monthly_budget,
+        crate::handlers::budget::bulk_update_monthly_budgets,
         crate::handlers::income::list_income,
         crate::handlers::income::create_income,
         crate::handlers::income::update_income,
         crate::handlers::income::delete_income,
         crate::handlers::items::list_items,
+        crate::handlers::items::search_items,
+        crate::handlers::items::list_orphaned_items,
         crate::handlers::items::create_item,
         crate::handlers::items::update_item,
         crate::handlers::items::delete_item,
+        crate::handlers::items::bulk_delete_items,
+        crate::handlers::items::move_item,
+        crate::handlers::items::restore_item,
+        crate::handlers::items::undo_delete_item,
+        crate::handlers::item_templates::list_item_templates,
+        crate::handlers::item_templates::create_item_template,
+        crate::handlers::item_templates::update_item_template,
+        crate::handlers::item_templates::delete_item_template,
+        crate::handlers::item_templates::create_item_from_template,
         crate::handlers::fixed_expenses::list_fixed_expenses,
+        crate::handlers::fixed_expenses::fixed_expenses_by_category,
         crate::handlers::fixed_expenses::create_fixed_expense,
         crate::handlers::fixed_expenses::update_fixed_expense,
         crate::handlers::fixed_expenses::delete_fixed_expense,
@@ -45,26 +121,93 @@ use crate::models::{
         crate::handlers::budget::create_category,
         crate::handlers::budget::update_category,
         crate::handlers::budget::delete_category,
+        crate::handlers::budget::reorder_categories,
+        crate::handlers::budget::merge_category,
+        crate::handlers::budget::list_budget_templates,
+        crate::handlers::budget::apply_budget_template,
         crate::handlers::months::list_months,
         crate::handlers::months::get_or_create_current_month,
         crate::handlers::months::get_month,
+        crate::handlers::months::duplicate_month,
         crate::handlers::months::close_month,
+        crate::handlers::months::get_close_preview,
+        crate::handlers::months::lock_month,
+        crate::handlers::months::unlock_month,
         crate::handlers::months::get_month_pdf,
+        crate::handlers::months::get_month_forecast,
+        crate::handlers::months::forecast_future_months,
+        crate::handlers::months::get_safe_to_spend,
         crate::handlers::monthly_data::create_monthly_fixed_expense,
         crate::handlers::monthly_data::update_monthly_fixed_expense,
         crate::handlers::monthly_data::delete_monthly_fixed_expense,
         crate::handlers::monthly_data::update_monthly_savings,
         crate::handlers::savings::get_savings,
         crate::handlers::savings::update_savings,
+        crate::handlers::savings::get_savings_history,
+        crate::handlers::savings::reconcile_savings,
         crate::handlers::savings::get_retirement_savings,
         crate::handlers::savings::update_retirement_savings,
-        crate::handlers::stats::get_stats
+        crate::handlers::savings_accounts::list_savings_accounts,
+        crate::handlers::savings_accounts::create_savings_account,
+        crate::handlers::savings_accounts::update_savings_account,
+        crate::handlers::savings_accounts::delete_savings_account,
+        crate::handlers::savings_accounts::list_allocation_rules,
+        crate::handlers::savings_accounts::update_allocation_rules,
+        crate::handlers::savings_accounts::contribute,
+        crate::handlers::savings_accounts::transfer_savings,
+        crate::handlers::savings_accounts::list_savings_transfers,
+        crate::handlers::savings_accounts::list_account_goals,
+        crate::handlers::receipts::upload_receipt,
+        crate::handlers::receipts::get_receipt,
+        crate::handlers::exchange_rates::list_exchange_rates,
+        crate::handlers::exchange_rates::set_exchange_rate,
+        crate::handlers::exchange_rates::delete_exchange_rate,
+        crate::handlers::reminders::upcoming_bills,
+        crate::handlers::stats::get_stats,
+        crate::handlers::stats::get_category_trend,
+        crate::handlers::stats::get_savings_rate,
+        crate::handlers::stats::get_annual_stats,
+        crate::handlers::stats::get_range_stats,
+        crate::handlers::stats::get_top_categories,
+        crate::handlers::stats::get_income_summary,
+        crate::handlers::stats::get_month_daily_spending,
+        crate::handlers::stats::get_year_daily_spending,
+        crate::handlers::stats::get_alert_thresholds,
+        crate::handlers::stats::update_alert_thresholds,
+        crate::handlers::households::list_households,
+        crate::handlers::households::create_household,
+        crate::handlers::households::list_members,
+        crate::handlers::households::invite_member,
+        crate::handlers::households::accept_invite,
+        crate::handlers::households::share_month,
+        crate::handlers::webhooks::list_webhooks,
+        crate::handlers::webhooks::create_webhook,
+        crate::handlers::webhooks::delete_webhook,
+        crate::handlers::webhooks::list_deliveries
     ),
     components(schemas(
         AuthRequest,
         AuthResponse,
+        ApiKey,
+        CreateApiKey,
+        ChangeEmailRequest,
+        ChangeBaseCurrencyRequest,
+        ChangeRoundingModeRequest,
+        ChangeTimezoneRequest,
+        RoundingMode,
+        NotificationPreferences,
+        ChangePasswordRequest,
+        ForgotPasswordRequest,
+        ResetPasswordRequest,
+        TwoFactorSetupResponse,
+        TwoFactorVerifyRequest,
+        BackupCodesResponse,
+        RefreshRequest,
+        DeleteAccountRequest,
         MonthlyBudget,
         UpdateMonthlyBudget,
+        BulkBudgetUpdate,
+        BulkBudgetEntry,
         IncomeEntry,
         CreateIncome,
         UpdateIncome,
@@ -72,9 +215,21 @@ use crate::models::{
         ItemWithCategory,
         CreateItem,
         UpdateItem,
+        CreatedItem,
+        BudgetWarning,
+        BulkDeleteItems,
+        BulkDeleteResult,
+        ItemSearchResult,
+        ItemSearchPage,
+        MoveItem,
+        ItemTemplate,
+        CreateItemTemplate,
+        UpdateItemTemplate,
+        ItemFromTemplate,
         FixedExpense,
         CreateFixedExpense,
         UpdateFixedExpense,
+        FixedExpenseGroup,
         MonthlyFixedExpense,
         CreateMonthlyFixedExpense,
         UpdateMonthlyFixedExpense,
@@ -83,22 +238,84 @@ use crate::models::{
         BudgetCategory,
         CreateCategory,
         UpdateCategory,
+        ReorderCategories,
+        MergeCategory,
+        MergedCategory,
+        BudgetTemplate,
+        TemplateCategory,
+        ApplyBudgetTemplate,
+        AppliedBudgetTemplate,
         Month,
+        CreateMonthRequest,
         MonthSummary,
+        MonthForecast,
+        CategoryForecast,
+        ProjectedMonth,
+        SafeToSpend,
+        ClosePreview,
+        SavingsSnapshot,
+        CategoryVariance,
         StatsResponse,
         CategoryStats,
+        BudgetAlert,
+        AlertSeverity,
+        AlertThresholds,
         MonthlyStats,
+        CategoryTrend,
+        CurrencyTotal,
+        ExchangeRate,
+        SetExchangeRate,
+        CategoryTrendPoint,
+        TopCategory,
+        IncomeTotals,
+        IncomeSummary,
+        DailySpend,
+        AnnualStats,
+        RangeStats,
+        RangeCategoryStats,
+        RangeMonthSpend,
+        AnnualCategoryStats,
+        UpcomingBill,
         RetirementSavingsResponse,
         SavingsResponse,
+        SavingsAccount,
+        CreateSavingsAccount,
+        AllocationRule,
+        UpdateAllocationRules,
+        Contribute,
+        ContributionShare,
+        TransferSavings,
+        SavingsTransfer,
+        SavingsTransferReceipt,
+        AccountGoal,
+        UpdateSavingsAccount,
+        SavingsHistoryPoint,
+        SavingsReconciliation,
+        BalanceReconciliation,
         UpdateSavings,
         UpdateRetirementSavings,
         UserExport,
+        ExportEstimate,
         CategoryExport,
         MonthExport,
         FixedExpenseExport,
         IncomeExport,
         BudgetExport,
-        ItemExport
+        ItemExport,
+        ImportReport,
+        ImportJob,
+        MergeReport,
+        ImportCounts,
+        ImportIssue,
+        EncryptedExport,
+        ImportPayload,
+        Household,
+        HouseholdMember,
+        CreateHousehold,
+        InviteMember,
+        Webhook,
+        CreateWebhook,
+        WebhookDelivery
     ))
 )]
 pub struct ApiDoc;
@@ -1,9 +1,22 @@
 use printpdf::*;
 use std::io::BufWriter;
 
+use crate::handlers::fixed_expenses::UNCATEGORIZED;
 use crate::models::MonthSummary;
+use crate::money::Money;
+
+/// Bars per chart; further categories are folded into one "Other" bar so the chart fits the page
+const MAX_CHART_ROWS: usize = 12;
+const CHART_LABEL_CHARS: usize = 24;
 
 pub fn generate_pdf(summary: &MonthSummary) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    render_pdf(summary, true)
+}
+
+fn render_pdf(
+    summary: &MonthSummary,
+    with_charts: bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let title = format!(
         "Financial Summary - {}/{}",
         summary.month.month, summary.month.year
@@ -25,25 +38,55 @@ pub fn generate_pdf(summary: &MonthSummary) -> Result<Vec<u8>, Box<dyn std::erro
     y -= line_height;
 
     for entry in &summary.income_entries {
-        let text = format!("  {} - ${:.2}", entry.label, entry.amount);
+        let mut text = format!("  {} - ${}", entry.label, entry.amount);
+        // The total uses the monthly figure, so say when an entry's amount isn't monthly
+        if entry.frequency != "monthly" {
+            text.push_str(&format!(
+                " ({}, ${}/month)",
+                entry.frequency, entry.monthly_amount
+            ));
+        }
         layer.use_text(&text, 10.0, Mm(left_margin), Mm(y), &font);
         y -= line_height;
     }
 
-    let total_income_text = format!("Total Income: ${:.2}", summary.total_income);
+    let total_income_text = format!("Total Income: ${}", summary.total_income);
     layer.use_text(&total_income_text, 10.0, Mm(left_margin), Mm(y), &font_bold);
     y -= line_height * 2.0;
 
     layer.use_text("FIXED EXPENSES", 12.0, Mm(left_margin), Mm(y), &font_bold);
     y -= line_height;
 
-    for expense in &summary.fixed_expenses {
-        let text = format!("  {} - ${:.2}", expense.label, expense.amount);
-        layer.use_text(&text, 10.0, Mm(left_margin), Mm(y), &font);
-        y -= line_height;
+    // Only break the list into groups once the user has categorized something
+    let grouped = summary
+        .fixed_by_category
+        .iter()
+        .any(|g| g.category != UNCATEGORIZED);
+    if grouped {
+        for group in &summary.fixed_by_category {
+            let text = format!("  {} - ${}", group.category, group.total);
+            layer.use_text(&text, 10.0, Mm(left_margin), Mm(y), &font_bold);
+            y -= line_height;
+
+            let in_group = summary
+                .fixed_expenses
+                .iter()
+                .filter(|e| e.category.as_deref().unwrap_or(UNCATEGORIZED) == group.category);
+            for expense in in_group {
+                let text = format!("    {} - ${}", expense.label, expense.amount);
+                layer.use_text(&text, 10.0, Mm(left_margin), Mm(y), &font);
+                y -= line_height;
+            }
+        }
+    } else {
+        for expense in &summary.fixed_expenses {
+            let text = format!("  {} - ${}", expense.label, expense.amount);
+            layer.use_text(&text, 10.0, Mm(left_margin), Mm(y), &font);
+            y -= line_height;
+        }
     }
 
-    let total_fixed_text = format!("Total Fixed: ${:.2}", summary.total_fixed);
+    let total_fixed_text = format!("Total Fixed: ${}", summary.total_fixed);
     layer.use_text(&total_fixed_text, 10.0, Mm(left_margin), Mm(y), &font_bold);
     y -= line_height * 2.0;
 
@@ -52,19 +95,16 @@ pub fn generate_pdf(summary: &MonthSummary) -> Result<Vec<u8>, Box<dyn std::erro
 
     for budget in &summary.budgets {
         let status = if budget.spent_amount > budget.allocated_amount {
-            format!(
-                "OVER by ${:.2}",
-                budget.spent_amount - budget.allocated_amount
-            )
+            format!("OVER by ${}", budget.spent_amount - budget.allocated_amount)
         } else {
             format!(
-                "${:.2} remaining",
+                "${} remaining",
                 budget.allocated_amount - budget.spent_amount
             )
         };
 
         let text = format!(
-            "  {}: ${:.2} / ${:.2} ({})",
+            "  {}: ${} / ${} ({})",
             budget.category_label, budget.spent_amount, budget.allocated_amount, status
         );
         layer.use_text(&text, 10.0, Mm(left_margin), Mm(y), &font);
@@ -80,10 +120,17 @@ pub fn generate_pdf(summary: &MonthSummary) -> Result<Vec<u8>, Box<dyn std::erro
         if y < 20.0 {
             break;
         }
-        let text = format!(
-            "  {} - {} - ${:.2} ({})",
-            item.spent_on, item.description, item.amount, item.category_label
-        );
+        let text = if item.currency == summary.base_currency {
+            format!(
+                "  {} - {} - ${} ({})",
+                item.spent_on, item.description, item.amount, item.category_label
+            )
+        } else {
+            format!(
+                "  {} - {} - {} {} ({})",
+                item.spent_on, item.description, item.amount, item.currency, item.category_label
+            )
+        };
         layer.use_text(&text, 9.0, Mm(left_margin), Mm(y), &font);
         y -= line_height;
     }
@@ -93,30 +140,254 @@ pub fn generate_pdf(summary: &MonthSummary) -> Result<Vec<u8>, Box<dyn std::erro
     layer.use_text("SUMMARY", 12.0, Mm(left_margin), Mm(y), &font_bold);
     y -= line_height;
 
-    let total_spent_text = format!("Total Spent: ${:.2}", summary.total_spent);
+    let total_spent_text = format!("Total Spent: ${}", summary.total_spent);
     layer.use_text(&total_spent_text, 10.0, Mm(left_margin), Mm(y), &font);
     y -= line_height;
 
-    let remaining_text = if summary.remaining >= 0.0 {
-        format!("Remaining: ${:.2}", summary.remaining)
+    let remaining_text = if summary.remaining >= Money::ZERO {
+        format!("Remaining: ${}", summary.remaining)
     } else {
-        format!("Deficit: -${:.2}", summary.remaining.abs())
+        format!("Deficit: -${}", summary.remaining.abs())
     };
 
     layer.use_text(&remaining_text, 10.0, Mm(left_margin), Mm(y), &font_bold);
 
+    if with_charts {
+        // The tables above carry the same figures, so a month whose data can't be charted
+        // still gets a complete report
+        if let Some(charts) = chart_data(summary) {
+            let (page, layer) = doc.add_page(Mm(210.0), Mm(297.0), "Charts");
+            let layer = doc.get_page(page).get_layer(layer);
+            draw_charts(&layer, &font, &font_bold, &charts);
+        }
+    }
+
     let mut buffer = BufWriter::new(Vec::new());
     doc.save(&mut buffer)?;
     Ok(buffer.into_inner()?)
 }
 
+struct Charts {
+    spending: Vec<ChartRow>,
+    budgets: Vec<ChartRow>,
+}
+
+#[derive(Clone)]
+struct ChartRow {
+    label: String,
+    color: Color,
+    spent: f64,
+    allocated: f64,
+}
+
+/// Bars for the spending and budget-vs-actual charts, or `None` when there is nothing to chart.
+fn chart_data(summary: &MonthSummary) -> Option<Charts> {
+    let rows: Vec<ChartRow> = summary
+        .budgets
+        .iter()
+        .map(|b| ChartRow {
+            label: b.category_label.clone(),
+            color: hex_color(&b.category_color),
+            spent: b.spent_amount.to_f64().max(0.0),
+            allocated: b.allocated_amount.to_f64().max(0.0),
+        })
+        .collect();
+
+    let mut spending: Vec<ChartRow> = rows.iter().filter(|r| r.spent > 0.0).cloned().collect();
+    spending.sort_by(|a, b| b.spent.total_cmp(&a.spent).then(a.label.cmp(&b.label)));
+    let budgets: Vec<ChartRow> = rows
+        .into_iter()
+        .filter(|r| r.spent > 0.0 || r.allocated > 0.0)
+        .collect();
+
+    if spending.is_empty() && budgets.is_empty() {
+        return None;
+    }
+    Some(Charts {
+        spending: fold_rows(spending),
+        budgets: fold_rows(budgets),
+    })
+}
+
+fn fold_rows(mut rows: Vec<ChartRow>) -> Vec<ChartRow> {
+    if rows.len() <= MAX_CHART_ROWS {
+        return rows;
+    }
+    let rest = rows.split_off(MAX_CHART_ROWS - 1);
+    rows.push(ChartRow {
+        label: format!("Other ({} more)", rest.len()),
+        color: grey(0.6),
+        spent: rest.iter().map(|r| r.spent).sum(),
+        allocated: rest.iter().map(|r| r.allocated).sum(),
+    });
+    rows
+}
+
+fn hex_color(hex: &str) -> Color {
+    let channels = hex
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6)
+        .and_then(|h| {
+            let channel = |i: usize| u8::from_str_radix(&h[i..i + 2], 16).ok();
+            Some((channel(0)?, channel(2)?, channel(4)?))
+        });
+    match channels {
+        Some((r, g, b)) => Color::Rgb(Rgb::new(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            None,
+        )),
+        None => grey(0.45),
+    }
+}
+
+fn grey(level: f32) -> Color {
+    Color::Rgb(Rgb::new(level, level, level, None))
+}
+
+fn truncate_label(label: &str) -> String {
+    if label.chars().count() <= CHART_LABEL_CHARS {
+        return label.to_string();
+    }
+    let mut short: String = label.chars().take(CHART_LABEL_CHARS - 3).collect();
+    short.push_str("...");
+    short
+}
+
+fn draw_charts(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    font_bold: &IndirectFontRef,
+    charts: &Charts,
+) {
+    let left_margin = 20.0;
+
+    layer.use_text(
+        "SPENDING BY CATEGORY",
+        12.0,
+        Mm(left_margin),
+        Mm(270.0),
+        font_bold,
+    );
+    draw_bar_chart(layer, font, &charts.spending, 262.0, 160.0, false);
+
+    layer.use_text(
+        "BUDGET VS ACTUAL",
+        12.0,
+        Mm(left_margin),
+        Mm(145.0),
+        font_bold,
+    );
+    layer.use_text(
+        "Grey bars show the budget, coloured bars what was spent; red means over budget.",
+        8.0,
+        Mm(left_margin),
+        Mm(139.0),
+        font,
+    );
+    draw_bar_chart(layer, font, &charts.budgets, 133.0, 30.0, true);
+}
+
+/// Horizontal bars between `top` and `bottom` (in mm), one row per category, scaled to the
+/// largest value. Rows shrink to fit rather than running past `bottom`.
+fn draw_bar_chart(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    rows: &[ChartRow],
+    top: f32,
+    bottom: f32,
+    with_budget: bool,
+) {
+    let (label_x, bar_x, bar_end, value_x) = (20.0, 72.0, 168.0, 171.0);
+
+    if rows.is_empty() {
+        layer.use_text("No data", 9.0, Mm(label_x), Mm(top - 6.0), font);
+        return;
+    }
+
+    let scale_max = rows
+        .iter()
+        .map(|r| {
+            if with_budget {
+                r.spent.max(r.allocated)
+            } else {
+                r.spent
+            }
+        })
+        .fold(0.0, f64::max);
+    let row_height = ((top - bottom) / rows.len() as f32).min(8.0);
+    let bar_height = row_height * 0.6;
+    let width = |amount: f64| {
+        if scale_max > 0.0 {
+            (bar_end - bar_x) * (amount / scale_max) as f32
+        } else {
+            0.0
+        }
+    };
+
+    for (i, row) in rows.iter().enumerate() {
+        let base = top - row_height * (i as f32 + 1.0) + (row_height - bar_height) / 2.0;
+
+        if with_budget && row.allocated > 0.0 {
+            layer.set_fill_color(grey(0.85));
+            layer.add_rect(
+                Rect::new(
+                    Mm(bar_x),
+                    Mm(base),
+                    Mm(bar_x + width(row.allocated)),
+                    Mm(base + bar_height),
+                )
+                .with_mode(path::PaintMode::Fill),
+            );
+        }
+        if row.spent > 0.0 {
+            let over = with_budget && row.spent > row.allocated;
+            layer.set_fill_color(if over {
+                Color::Rgb(Rgb::new(0.8, 0.15, 0.15, None))
+            } else {
+                row.color.clone()
+            });
+            // Spending sits inside the budget bar so both stay readable
+            let inset = if with_budget { bar_height * 0.25 } else { 0.0 };
+            layer.add_rect(
+                Rect::new(
+                    Mm(bar_x),
+                    Mm(base + inset),
+                    Mm(bar_x + width(row.spent)),
+                    Mm(base + bar_height - inset),
+                )
+                .with_mode(path::PaintMode::Fill),
+            );
+        }
+
+        layer.set_fill_color(grey(0.0));
+        let font_size = (row_height * 1.6).clamp(5.0, 9.0);
+        let text_y = Mm(base + bar_height / 2.0 - font_size * 0.12);
+        layer.use_text(
+            truncate_label(&row.label),
+            font_size,
+            Mm(label_x),
+            text_y,
+            font,
+        );
+        let value = if with_budget {
+            format!("${:.0} / ${:.0}", row.spent, row.allocated)
+        } else {
+            format!("${:.2}", row.spent)
+        };
+        layer.use_text(value, font_size, Mm(value_x), text_y, font);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::{
-        IncomeEntry, ItemWithCategory, Month, MonthlyBudgetWithCategory, MonthlyFixedExpense,
-        MonthlySavings,
+        FixedExpenseGroup, IncomeEntry, ItemWithCategory, Month, MonthlyBudgetWithCategory,
+        MonthlyFixedExpense, MonthlySavings,
     };
+    use crate::money::RoundingMode;
     use chrono::NaiveDate;
 
     fn create_test_summary() -> MonthSummary {
@@ -128,18 +399,33 @@ mod tests {
                 month: 6,
                 is_closed: false,
                 closed_at: None,
+                is_locked: false,
+                is_current: false,
+                days_remaining: 0,
             },
             income_entries: vec![IncomeEntry {
                 id: 1,
                 month_id: 1,
                 label: "Salary".to_string(),
-                amount: 5000.0,
+                amount: Money::from_cents(500000),
+                gross_amount: None,
+                withholding: Money::from_cents(0),
+                frequency: "monthly".to_string(),
+                monthly_amount: Money::from_cents(500000),
             }],
             fixed_expenses: vec![MonthlyFixedExpense {
                 id: 1,
                 month_id: 1,
                 label: "Rent".to_string(),
-                amount: 1500.0,
+                amount: Money::from_cents(150000),
+                category: Some("Housing".to_string()),
+                due_day: None,
+                paid: false,
+            }],
+            fixed_by_category: vec![FixedExpenseGroup {
+                category: "Housing".to_string(),
+                total: Money::from_cents(150000),
+                count: 1,
             }],
             budgets: vec![MonthlyBudgetWithCategory {
                 id: 1,
@@ -147,8 +433,8 @@ mod tests {
                 category_id: 1,
                 category_label: "Food".to_string(),
                 category_color: "#71717a".to_string(),
-                allocated_amount: 500.0,
-                spent_amount: 300.0,
+                allocated_amount: Money::from_cents(50000),
+                spent_amount: Money::from_cents(30000),
             }],
             items: vec![ItemWithCategory {
                 id: 1,
@@ -156,23 +442,31 @@ mod tests {
                 category_id: 1,
                 category_label: "Food".to_string(),
                 category_color: "#71717a".to_string(),
+                category_icon: None,
                 description: "Groceries".to_string(),
-                amount: 150.0,
+                amount: Money::from_cents(15000),
                 spent_on: NaiveDate::from_ymd_opt(2024, 6, 15).unwrap(),
                 savings_destination: "none".to_string(),
+                savings_account_id: None,
+                currency: "USD".to_string(),
+                version: 1,
+                has_receipt: false,
             }],
             savings: Some(MonthlySavings {
                 id: 1,
                 month_id: 1,
-                savings: 10000.0,
-                retirement_savings: 50000.0,
-                savings_goal: 20000.0,
+                savings: Money::from_cents(1000000),
+                retirement_savings: Money::from_cents(5000000),
+                savings_goal: Money::from_cents(2000000),
             }),
-            total_income: 5000.0,
-            total_fixed: 1500.0,
-            total_budgeted: 500.0,
-            total_spent: 300.0,
-            remaining: 3200.0,
+            total_income: Money::from_cents(500000),
+            total_fixed: Money::from_cents(150000),
+            total_budgeted: Money::from_cents(50000),
+            total_spent: Money::from_cents(30000),
+            remaining: Money::from_cents(320000),
+            base_currency: "USD".to_string(),
+            spent_by_currency: vec![],
+            rounding_mode: RoundingMode::HalfUp,
         }
     }
 
@@ -201,17 +495,24 @@ mod tests {
                 month: 6,
                 is_closed: false,
                 closed_at: None,
+                is_locked: false,
+                is_current: false,
+                days_remaining: 0,
             },
             income_entries: vec![],
             fixed_expenses: vec![],
+            fixed_by_category: vec![],
             budgets: vec![],
             items: vec![],
             savings: None,
-            total_income: 0.0,
-            total_fixed: 0.0,
-            total_budgeted: 0.0,
-            total_spent: 0.0,
-            remaining: 0.0,
+            total_income: Money::from_cents(0),
+            total_fixed: Money::from_cents(0),
+            total_budgeted: Money::from_cents(0),
+            total_spent: Money::from_cents(0),
+            remaining: Money::from_cents(0),
+            base_currency: "USD".to_string(),
+            spent_by_currency: vec![],
+            rounding_mode: RoundingMode::HalfUp,
         };
 
         let result = generate_pdf(&summary);
@@ -221,16 +522,59 @@ mod tests {
     #[test]
     fn test_generate_pdf_with_deficit() {
         let mut summary = create_test_summary();
-        summary.remaining = -500.0;
+        summary.remaining = Money::from_cents(-50000);
 
         let result = generate_pdf(&summary);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_generate_pdf_includes_charts() {
+        let summary = create_test_summary();
+
+        let text_only = render_pdf(&summary, false).unwrap();
+        let with_charts = generate_pdf(&summary).unwrap();
+
+        assert!(!with_charts.is_empty());
+        assert!(with_charts.len() > text_only.len());
+    }
+
+    #[test]
+    fn test_generate_pdf_charts_fit_many_categories() {
+        let mut summary = create_test_summary();
+        summary.budgets = (0..40)
+            .map(|i| MonthlyBudgetWithCategory {
+                id: i,
+                month_id: 1,
+                category_id: i,
+                category_label: format!("Category with a fairly long name {i}"),
+                category_color: "#71717a".to_string(),
+                allocated_amount: Money::from_cents(20000),
+                spent_amount: Money::from_cents(i * 1000),
+            })
+            .collect();
+
+        let charts = chart_data(&summary).unwrap();
+        assert_eq!(charts.spending.len(), MAX_CHART_ROWS);
+        assert_eq!(charts.budgets.len(), MAX_CHART_ROWS);
+        assert_eq!(charts.spending[0].spent, 390.0);
+        assert_eq!(charts.spending[MAX_CHART_ROWS - 1].label, "Other (28 more)");
+        assert!(generate_pdf(&summary).is_ok());
+    }
+
+    #[test]
+    fn test_generate_pdf_without_chart_data() {
+        let mut summary = create_test_summary();
+        summary.budgets.clear();
+        assert!(chart_data(&summary).is_none());
+        // Falls back to the tables alone
+        assert!(generate_pdf(&summary).is_ok());
+    }
+
     #[test]
     fn test_generate_pdf_over_budget() {
         let mut summary = create_test_summary();
-        summary.budgets[0].spent_amount = 600.0; // Over the 500 allocated
+        summary.budgets[0].spent_amount = Money::from_cents(60000); // Over the 500 allocated
 
         let result = generate_pdf(&summary);
         assert!(result.is_ok());
@@ -0,0 +1,59 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use sqlx::SqlitePool;
+
+use crate::error::PaymeError;
+
+/// Timezone of users who haven't picked one
+pub const DEFAULT_TIMEZONE: Tz = Tz::UTC;
+
+/// Parses an IANA timezone name such as "Pacific/Auckland".
+pub fn parse(name: &str) -> Result<Tz, PaymeError> {
+    name.trim()
+        .parse()
+        .map_err(|_| PaymeError::BadRequest(format!("Unknown timezone: {name}")))
+}
+
+/// The user's timezone. Timestamps are stored in UTC; this only decides where their days and
+/// months begin.
+pub async fn user_timezone(pool: &SqlitePool, user_id: i64) -> Result<Tz, PaymeError> {
+    let name: Option<String> = sqlx::query_scalar("SELECT timezone FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(name
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(DEFAULT_TIMEZONE))
+}
+
+/// The calendar date it is at `now` in `tz`.
+pub fn local_date(tz: Tz, now: DateTime<Utc>) -> NaiveDate {
+    now.with_timezone(&tz).date_naive()
+}
+
+/// The user's calendar date right now.
+pub async fn today(pool: &SqlitePool, user_id: i64) -> Result<NaiveDate, PaymeError> {
+    Ok(local_date(user_timezone(pool, user_id).await?, Utc::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_local_date_crosses_midnight_ahead_of_utc() {
+        // Late morning on 31 January in UTC is just past midnight on 1 February in Tonga (UTC+13)
+        let now = Utc.with_ymd_and_hms(2024, 1, 31, 11, 30, 0).unwrap();
+        let tonga = parse("Pacific/Tongatapu").unwrap();
+        assert_eq!(
+            local_date(tonga, now),
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()
+        );
+        assert_eq!(
+            local_date(DEFAULT_TIMEZONE, now),
+            NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()
+        );
+        assert!(parse("Mars/Olympus_Mons").is_err());
+    }
+}
@@ -0,0 +1,13 @@
+use std::sync::{Arc, LazyLock};
+
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+
+/// Client TLS trusting the Mozilla root certificates, for outbound webhooks and SMTP.
+pub(crate) static CLIENT_CONFIG: LazyLock<Arc<ClientConfig>> = LazyLock::new(|| {
+    let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    Arc::new(
+        ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    )
+});
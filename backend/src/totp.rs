@@ -0,0 +1,134 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use hmac::{Hmac, KeyInit, Mac};
+use sha1::Sha1;
+
+/// Seconds per TOTP time step (RFC 6238 default)
+pub const STEP_SECONDS: u64 = 30;
+/// Number of steps either side of the current one accepted to tolerate clock drift
+pub const DRIFT_STEPS: u64 = 1;
+const DIGITS: u32 = 6;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generates a random 160-bit secret, base32-encoded as authenticator apps expect.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+/// Builds the `otpauth://` URI that authenticator apps import (usually via QR code).
+pub fn otpauth_url(secret: &str, account: &str) -> String {
+    let label = url::form_urlencoded::byte_serialize(format!("payme:{account}").as_bytes())
+        .collect::<String>();
+    format!(
+        "otpauth://totp/{label}?secret={secret}&issuer=payme&algorithm=SHA1&digits={DIGITS}&period={STEP_SECONDS}"
+    )
+}
+
+/// Computes the code for the step containing `unix_time`.
+pub fn code_at(secret: &str, unix_time: u64) -> Option<String> {
+    let key = base32_decode(secret)?;
+    Some(hotp(&key, unix_time / STEP_SECONDS))
+}
+
+/// Checks `code` against the current step and [`DRIFT_STEPS`] neighbours on either side.
+pub fn verify(secret: &str, code: &str, unix_time: u64) -> bool {
+    let Some(key) = base32_decode(secret) else {
+        return false;
+    };
+    let code = code.trim();
+    if code.len() != DIGITS as usize {
+        return false;
+    }
+
+    let step = unix_time / STEP_SECONDS;
+    (step.saturating_sub(DRIFT_STEPS)..=step + DRIFT_STEPS).any(|s| hotp(&key, s) == code)
+}
+
+fn hotp(key: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase() as u8)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B seed "12345678901234567890", truncated to 6 digits
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        assert_eq!(code_at(RFC_SECRET, 59).unwrap(), "287082");
+        assert_eq!(code_at(RFC_SECRET, 1111111109).unwrap(), "081804");
+        assert_eq!(code_at(RFC_SECRET, 1234567890).unwrap(), "005924");
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        assert_eq!(base32_encode(b"12345678901234567890"), RFC_SECRET);
+        assert_eq!(base32_decode(RFC_SECRET).unwrap(), b"12345678901234567890");
+    }
+
+    #[test]
+    fn test_verify_drift_window() {
+        let now = 1_700_000_000;
+        let previous = code_at(RFC_SECRET, now - STEP_SECONDS).unwrap();
+        let stale = code_at(RFC_SECRET, now - 3 * STEP_SECONDS).unwrap();
+        assert!(verify(RFC_SECRET, &previous, now));
+        assert!(!verify(RFC_SECRET, &stale, now));
+        assert!(!verify(RFC_SECRET, "12345", now));
+    }
+}
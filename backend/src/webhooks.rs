@@ -0,0 +1,467 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::http::{header, Request, Response};
+use chrono::Utc;
+use hmac::{Hmac, KeyInit, Mac};
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{lookup_host, TcpStream};
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use url::{Host, Url};
+
+use crate::background;
+use crate::config::WebhookPolicy;
+use crate::tls;
+
+pub const ITEM_CREATED: &str = "item.created";
+pub const MONTH_CLOSED: &str = "month.closed";
+pub const SAVINGS_GOAL_REACHED: &str = "savings.goal_reached";
+pub const BUDGET_THRESHOLD_CROSSED: &str = "budget.threshold_crossed";
+/// Event types a webhook can subscribe to
+pub const EVENTS: [&str; 4] = [
+    ITEM_CREATED,
+    MONTH_CLOSED,
+    SAVINGS_GOAL_REACHED,
+    BUDGET_THRESHOLD_CROSSED,
+];
+
+pub const SIGNATURE_HEADER: &str = "x-payme-signature";
+pub const EVENT_HEADER: &str = "x-payme-event";
+pub const DELIVERY_HEADER: &str = "x-payme-delivery";
+
+/// Deliveries still failing after this many attempts are marked failed
+pub const MAX_ATTEMPTS: i64 = 6;
+/// Delay before the first retry; doubles on each subsequent failure
+const BASE_BACKOFF_SECS: i64 = 30;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 50;
+
+/// `sha256=<hex>` HMAC of the raw request body, keyed by the webhook's secret.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+/// Queues `event` for every webhook of `user_id` subscribed to it and kicks off delivery.
+/// Failures are logged rather than returned so they never fail the request that fired the event.
+pub async fn enqueue(
+    pool: &SqlitePool,
+    policy: WebhookPolicy,
+    user_id: i64,
+    event: &str,
+    data: serde_json::Value,
+) {
+    if let Err(e) = try_enqueue(pool, policy, user_id, event, data).await {
+        tracing::error!("Failed to enqueue {} webhook: {}", event, e);
+    }
+}
+
+async fn try_enqueue(
+    pool: &SqlitePool,
+    policy: WebhookPolicy,
+    user_id: i64,
+    event: &str,
+    data: serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    let hooks: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, events FROM webhooks WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+
+    let mut queued = false;
+    for (webhook_id, events) in hooks {
+        if !events.split(',').any(|e| e == event) {
+            continue;
+        }
+
+        let payload = json!({
+            "event": event,
+            "created_at": Utc::now(),
+            "data": data,
+        });
+        sqlx::query("INSERT INTO webhook_deliveries (webhook_id, event, payload) VALUES (?, ?, ?)")
+            .bind(webhook_id)
+            .bind(event)
+            .bind(payload.to_string())
+            .execute(pool)
+            .await?;
+        queued = true;
+    }
+
+    if queued {
+        let pool = pool.clone();
+        background::spawn(async move { dispatch_due(&pool, policy).await });
+    }
+    Ok(())
+}
+
+/// Attempts every delivery that is due. Safe to run concurrently: each delivery is claimed
+/// before it is sent, and claims older than a few minutes are treated as abandoned.
+pub async fn dispatch_due(pool: &SqlitePool, policy: WebhookPolicy) {
+    let due: Vec<i64> = match sqlx::query_scalar(
+        r#"
+        SELECT id FROM webhook_deliveries
+        WHERE (status = 'pending' AND next_attempt_at <= datetime('now'))
+           OR (status = 'sending' AND claimed_at < datetime('now', '-5 minutes'))
+        ORDER BY id
+        LIMIT ?
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("Failed to load due webhook deliveries: {}", e);
+            return;
+        }
+    };
+
+    for delivery_id in due {
+        if let Err(e) = attempt(pool, policy, delivery_id).await {
+            tracing::error!("Webhook delivery {} errored: {}", delivery_id, e);
+        }
+    }
+}
+
+async fn attempt(
+    pool: &SqlitePool,
+    policy: WebhookPolicy,
+    delivery_id: i64,
+) -> Result<(), sqlx::Error> {
+    let claimed = sqlx::query(
+        r#"
+        UPDATE webhook_deliveries SET status = 'sending', claimed_at = datetime('now')
+        WHERE id = ?
+          AND ((status = 'pending' AND next_attempt_at <= datetime('now'))
+           OR (status = 'sending' AND claimed_at < datetime('now', '-5 minutes')))
+        "#,
+    )
+    .bind(delivery_id)
+    .execute(pool)
+    .await?;
+    if claimed.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    let (event, payload, attempts, url, secret): (String, String, i64, String, String) =
+        sqlx::query_as(
+            r#"
+            SELECT d.event, d.payload, d.attempts, w.url, w.secret
+            FROM webhook_deliveries d
+            JOIN webhooks w ON w.id = d.webhook_id
+            WHERE d.id = ?
+            "#,
+        )
+        .bind(delivery_id)
+        .fetch_one(pool)
+        .await?;
+
+    let attempts = attempts + 1;
+    match send(
+        &url,
+        &policy,
+        &secret,
+        &event,
+        delivery_id,
+        payload.into_bytes(),
+    )
+    .await
+    {
+        Ok(()) => {
+            sqlx::query(
+                "UPDATE webhook_deliveries SET status = 'delivered', attempts = ?, last_error = NULL, delivered_at = datetime('now') WHERE id = ?",
+            )
+            .bind(attempts)
+            .bind(delivery_id)
+            .execute(pool)
+            .await?;
+        }
+        Err(error) if attempts >= MAX_ATTEMPTS => {
+            tracing::warn!(
+                "Webhook delivery {} failed permanently: {}",
+                delivery_id,
+                error
+            );
+            sqlx::query(
+                "UPDATE webhook_deliveries SET status = 'failed', attempts = ?, last_error = ? WHERE id = ?",
+            )
+            .bind(attempts)
+            .bind(&error)
+            .bind(delivery_id)
+            .execute(pool)
+            .await?;
+        }
+        Err(error) => {
+            let backoff = BASE_BACKOFF_SECS << (attempts - 1);
+            sqlx::query(
+                "UPDATE webhook_deliveries SET status = 'pending', attempts = ?, last_error = ?, next_attempt_at = datetime('now', ?) WHERE id = ?",
+            )
+            .bind(attempts)
+            .bind(&error)
+            .bind(format!("+{backoff} seconds"))
+            .bind(delivery_id)
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks a webhook URL's scheme and, for IP literals, its address. Hostnames are checked
+/// again after resolution on every delivery.
+pub(crate) fn check_url(url: &Url, policy: &WebhookPolicy) -> Result<(), String> {
+    match url.scheme() {
+        "https" => {}
+        "http" if policy.allow_http => {}
+        "http" => return Err("url must use https".to_string()),
+        _ => return Err("url must use https or http".to_string()),
+    }
+
+    let ip = match url.host() {
+        Some(Host::Domain(_)) => return Ok(()),
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        None => return Err("url has no host".to_string()),
+    };
+    if !policy.allow_private_hosts && !is_public(ip) {
+        return Err(format!("{ip} is not a public address"));
+    }
+    Ok(())
+}
+
+/// Whether `ip` is routable on the public internet, so a webhook can't be aimed at the
+/// server's own network.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public(IpAddr::V4(v4)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7
+                    || (first & 0xfe00) == 0xfc00
+                    // Link-local, fe80::/10
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves the URL's host to the addresses it's allowed to connect to. Connecting to these
+/// rather than the hostname keeps a second lookup from swapping in a private address.
+async fn resolve(url: &Url, policy: &WebhookPolicy) -> Result<Vec<SocketAddr>, String> {
+    let port = url.port_or_known_default().ok_or("url has no port")?;
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(Host::Domain(domain)) => lookup_host((domain, port))
+            .await
+            .map_err(|e| format!("lookup failed: {e}"))?
+            .collect(),
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+        None => return Err("url has no host".to_string()),
+    };
+
+    if addrs.is_empty() {
+        return Err("host did not resolve".to_string());
+    }
+    if !policy.allow_private_hosts {
+        if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+            return Err(format!(
+                "host resolves to {}, which is not public",
+                addr.ip()
+            ));
+        }
+    }
+    Ok(addrs)
+}
+
+/// POSTs the payload over HTTP/1.1, with TLS for https URLs; any 2xx response counts as
+/// delivered. Redirects are not followed.
+async fn send(
+    url: &str,
+    policy: &WebhookPolicy,
+    secret: &str,
+    event: &str,
+    delivery_id: i64,
+    body: Vec<u8>,
+) -> Result<(), String> {
+    let url = Url::parse(url).map_err(|e| format!("invalid url: {e}"))?;
+    check_url(&url, policy)?;
+    let host = url.host_str().ok_or("url has no host")?;
+    let port = url.port_or_known_default().ok_or("url has no port")?;
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    let request = Request::post(path)
+        .header(header::HOST, format!("{host}:{port}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::USER_AGENT, "payme-webhooks")
+        .header(SIGNATURE_HEADER, sign(secret, &body))
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, delivery_id.to_string())
+        .body(Full::new(Bytes::from(body)))
+        .map_err(|e| e.to_string())?;
+
+    let exchange = async {
+        let addrs = resolve(&url, policy).await?;
+        let stream = TcpStream::connect(&addrs[..])
+            .await
+            .map_err(|e| format!("connect failed: {e}"))?;
+        if url.scheme() != "https" {
+            return post(stream, request).await;
+        }
+
+        let server_name = match url.host() {
+            Some(Host::Domain(domain)) => ServerName::try_from(domain.to_string())
+                .map_err(|e| format!("invalid server name: {e}"))?,
+            Some(Host::Ipv4(ip)) => ServerName::from(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => ServerName::from(IpAddr::V6(ip)),
+            None => return Err("url has no host".to_string()),
+        };
+        let stream = TlsConnector::from(tls::CLIENT_CONFIG.clone())
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| format!("tls handshake failed: {e}"))?;
+        post(stream, request).await
+    };
+
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| "timed out".to_string())??;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("endpoint returned {}", response.status()))
+    }
+}
+
+async fn post<S>(stream: S, request: Request<Full<Bytes>>) -> Result<Response<Incoming>, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| format!("handshake failed: {e}"))?;
+    tokio::spawn(connection);
+    sender
+        .send_request(request)
+        .await
+        .map_err(|e| format!("request failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_known_vector() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    fn check(url: &str, policy: WebhookPolicy) -> Result<(), String> {
+        check_url(&Url::parse(url).unwrap(), &policy)
+    }
+
+    #[test]
+    fn test_check_url_requires_https_unless_allowed() {
+        let strict = WebhookPolicy::default();
+        assert!(check("https://hooks.example.com/payme", strict).is_ok());
+        assert!(check("http://hooks.example.com/payme", strict).is_err());
+        assert!(check("ftp://hooks.example.com/payme", strict).is_err());
+
+        let http = WebhookPolicy {
+            allow_http: true,
+            ..strict
+        };
+        assert!(check("http://hooks.example.com/payme", http).is_ok());
+        assert!(check("ftp://hooks.example.com/payme", http).is_err());
+    }
+
+    #[test]
+    fn test_check_url_rejects_private_addresses() {
+        let strict = WebhookPolicy::default();
+        for url in [
+            "https://127.0.0.1/hook",
+            "https://10.1.2.3/hook",
+            "https://172.16.0.1/hook",
+            "https://192.168.1.1/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/hook",
+            "https://0.0.0.0/hook",
+            "https://[::1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[fe80::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(check(url, strict).is_err(), "{url}");
+        }
+        assert!(check("https://93.184.216.34/hook", strict).is_ok());
+        assert!(check("https://[2606:4700::1111]/hook", strict).is_ok());
+
+        let private = WebhookPolicy {
+            allow_private_hosts: true,
+            ..strict
+        };
+        assert!(check("https://127.0.0.1/hook", private).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_refuses_hostnames_resolving_to_loopback() {
+        let policy = WebhookPolicy {
+            allow_http: true,
+            ..WebhookPolicy::default()
+        };
+        let error = send(
+            "http://localhost:9/hook",
+            &policy,
+            "secret",
+            ITEM_CREATED,
+            1,
+            vec![],
+        )
+        .await
+        .unwrap_err();
+        assert!(error.contains("not public"), "{error}");
+    }
+}
@@ -1,9 +1,17 @@
 mod common;
 
 use common::{
-    auth_name, auth_value, create_test_pool, create_test_server, create_test_user, generate_token,
+    auth_name, auth_value, create_test_budget, create_test_category, create_test_fixed_expense,
+    create_test_income, create_test_item, create_test_month, create_test_monthly_savings,
+    create_test_pool, create_test_server, create_test_user, generate_token,
 };
-use payme::create_app;
+use payme::config::{
+    AppOptions, DefaultCategories, JwtKeys, PasswordPolicy, SessionPolicy, SigningKey,
+    TwoFactorConfig,
+};
+use payme::handlers::auth::hash_token;
+use payme::totp;
+use payme::{create_app, create_app_with};
 use serde_json::json;
 
 async fn setup() -> axum_test::TestServer {
@@ -21,6 +29,41 @@ async fn setup_with_user() -> (axum_test::TestServer, i64, String) {
     (server, user_id, token)
 }
 
+async fn setup_with_pool() -> (axum_test::TestServer, sqlx::SqlitePool, i64, String) {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let app = create_app(pool.clone());
+    let server = create_test_server(app);
+    (server, pool, user_id, token)
+}
+
+async fn setup_with_two_factor() -> (axum_test::TestServer, sqlx::SqlitePool, i64, String) {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let options = AppOptions {
+        two_factor: TwoFactorConfig {
+            encryption_key: Some("test totp key".to_string()),
+        },
+        ..Default::default()
+    };
+    let server = create_test_server(create_app_with(pool.clone(), options));
+    (server, pool, user_id, token)
+}
+
+async fn insert_reset_token(pool: &sqlx::SqlitePool, user_id: i64, token: &str, expires_in: &str) {
+    sqlx::query(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES (?, ?, datetime('now', ?))",
+    )
+    .bind(user_id)
+    .bind(hash_token(token))
+    .bind(expires_in)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
 #[tokio::test]
 async fn test_register_success() {
     let server = setup().await;
@@ -29,7 +72,7 @@ async fn test_register_success() {
         .post("/api/auth/register")
         .json(&json!({
             "username": "newuser",
-            "password": "password123"
+            "password": "tulip-harbor-42"
         }))
         .await;
 
@@ -39,6 +82,59 @@ async fn test_register_success() {
     assert!(body["id"].as_i64().is_some());
 }
 
+#[tokio::test]
+async fn test_register_seeds_default_categories() {
+    let pool = create_test_pool().await;
+    let options = AppOptions {
+        default_categories: DefaultCategories {
+            labels: vec!["Rent".to_string(), "Food".to_string(), "Food".to_string()],
+        },
+        ..Default::default()
+    };
+    let server = create_test_server(create_app_with(pool.clone(), options));
+
+    let labels = |user_id: i64| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, String>(
+                "SELECT label FROM budget_categories WHERE user_id = ? ORDER BY sort_order",
+            )
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+        }
+    };
+
+    let response = server
+        .post("/api/auth/register")
+        .json(&json!({"username": "newuser", "password": "tulip-harbor-42"}))
+        .await;
+    response.assert_status_ok();
+    let user_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+    assert_eq!(labels(user_id).await, vec!["Rent", "Food"]);
+
+    // A retried registration fails on the username and seeds nothing more
+    server
+        .post("/api/auth/register")
+        .json(&json!({"username": "newuser", "password": "tulip-harbor-42"}))
+        .await
+        .assert_status_internal_server_error();
+    assert_eq!(labels(user_id).await, vec!["Rent", "Food"]);
+
+    let response = server
+        .post("/api/auth/register")
+        .json(&json!({
+            "username": "emptyuser",
+            "password": "tulip-harbor-42",
+            "skip_default_categories": true
+        }))
+        .await;
+    response.assert_status_ok();
+    let user_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+    assert!(labels(user_id).await.is_empty());
+}
+
 #[tokio::test]
 async fn test_register_duplicate_username() {
     let pool = create_test_pool().await;
@@ -87,6 +183,72 @@ async fn test_register_validation_short_password() {
     response.assert_status_bad_request();
 }
 
+#[tokio::test]
+async fn test_register_reports_failed_password_rules() {
+    let server = setup().await;
+
+    let response = server
+        .post("/api/auth/register")
+        .json(&json!({"username": "validuser", "password": "passwords"}))
+        .await;
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "VALIDATION");
+    assert_eq!(
+        body["error"]["fields"]["password"],
+        json!(["Must contain a digit"])
+    );
+
+    let response = server
+        .post("/api/auth/register")
+        .json(&json!({"username": "validuser", "password": "Password123"}))
+        .await;
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["error"]["fields"]["password"],
+        json!(["Is too common"])
+    );
+
+    server
+        .post("/api/auth/register")
+        .json(&json!({"username": "validuser", "password": "tulip-harbor-42"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_password_policy_is_configurable() {
+    let pool = create_test_pool().await;
+    let options = AppOptions {
+        password_policy: PasswordPolicy {
+            min_length: 12,
+            require_uppercase: true,
+            require_symbol: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let server = create_test_server(create_app_with(pool, options));
+
+    let response = server
+        .post("/api/auth/register")
+        .json(&json!({"username": "validuser", "password": "tulipharbor42"}))
+        .await;
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["error"]["fields"]["password"],
+        json!(["Must contain an uppercase letter", "Must contain a symbol"])
+    );
+
+    server
+        .post("/api/auth/register")
+        .json(&json!({"username": "validuser", "password": "Tulip-Harbor-42"}))
+        .await
+        .assert_status_ok();
+}
+
 #[tokio::test]
 async fn test_login_success() {
     let pool = create_test_pool().await;
@@ -294,3 +456,925 @@ async fn test_clear_all_data_wrong_password() {
 
     response.assert_status_unauthorized();
 }
+
+#[tokio::test]
+async fn test_forgot_password_issues_token() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    server
+        .put("/api/auth/change-email")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"email": "Test@Example.com", "current_password": "password123"}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/api/auth/forgot-password")
+        .json(&json!({"email": "test@example.com"}))
+        .await;
+
+    response.assert_status_ok();
+    let issued: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM password_reset_tokens WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(issued, 1);
+
+    // The token goes out by email, even with notifications off
+    let (recipient, body): (String, String) = sqlx::query_as(
+        "SELECT recipient, body FROM email_outbox WHERE user_id = ? AND kind = 'password_reset'",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(recipient, "test@example.com");
+    let stored: String =
+        sqlx::query_scalar("SELECT token_hash FROM password_reset_tokens WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(body
+        .lines()
+        .any(|line| !line.is_empty() && hash_token(line) == stored));
+}
+
+#[tokio::test]
+async fn test_forgot_password_unknown_email() {
+    let (server, pool, _user_id, _token) = setup_with_pool().await;
+
+    let response = server
+        .post("/api/auth/forgot-password")
+        .json(&json!({"email": "nobody@example.com"}))
+        .await;
+
+    response.assert_status_ok();
+    let issued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM password_reset_tokens")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(issued, 0);
+}
+
+#[tokio::test]
+async fn test_reset_password_success() {
+    let (server, pool, user_id, _token) = setup_with_pool().await;
+
+    insert_reset_token(&pool, user_id, "reset-token", "+30 minutes").await;
+
+    let response = server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": "reset-token", "new_password": "newpassword9"}))
+        .await;
+
+    response.assert_status_ok();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "newpassword9"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_reset_password_ends_sessions() {
+    let (server, pool, user_id, _token) = setup_with_pool().await;
+    let login = server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await;
+    login.assert_status_ok();
+    let session_token = login.cookie("token").value().to_string();
+
+    insert_reset_token(&pool, user_id, "reset-token", "+30 minutes").await;
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": "reset-token", "new_password": "newpassword9"}))
+        .await
+        .assert_status_ok();
+
+    server
+        .get("/api/auth/me")
+        .add_header(auth_name(), auth_value(&session_token))
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_change_email_requires_current_password() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    server
+        .put("/api/auth/change-email")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"email": "attacker@example.com", "current_password": "wrongpass1"}))
+        .await
+        .assert_status_unauthorized();
+
+    let email: Option<String> = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(email.is_none());
+}
+
+#[tokio::test]
+async fn test_reset_password_expired_token() {
+    let (server, pool, user_id, _token) = setup_with_pool().await;
+
+    insert_reset_token(&pool, user_id, "stale-token", "-1 minutes").await;
+
+    let response = server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": "stale-token", "new_password": "newpassword9"}))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_reset_password_token_reused() {
+    let (server, pool, user_id, _token) = setup_with_pool().await;
+
+    insert_reset_token(&pool, user_id, "reset-token", "+30 minutes").await;
+
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": "reset-token", "new_password": "newpassword9"}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": "reset-token", "new_password": "another1234"}))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_reset_password_weak_password() {
+    let (server, pool, user_id, _token) = setup_with_pool().await;
+
+    insert_reset_token(&pool, user_id, "reset-token", "+30 minutes").await;
+
+    let response = server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": "reset-token", "new_password": "abcdefgh"}))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+fn current_code(secret: &str) -> String {
+    totp::code_at(secret, chrono::Utc::now().timestamp() as u64).unwrap()
+}
+
+#[tokio::test]
+async fn test_enable_two_factor() {
+    let (server, pool, user_id, token) = setup_with_two_factor().await;
+
+    let response = server
+        .post("/api/auth/2fa/setup")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let secret = body["secret"].as_str().unwrap().to_string();
+    assert!(body["otpauth_url"]
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://totp/"));
+
+    let stored: String = sqlx::query_scalar("SELECT totp_secret FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(!stored.contains(&secret));
+
+    server
+        .post("/api/auth/2fa/verify")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"code": "000000"}))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .post("/api/auth/2fa/verify")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"code": current_code(&secret)}))
+        .await
+        .assert_status_ok();
+
+    let enabled: bool = sqlx::query_scalar("SELECT totp_enabled FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(enabled);
+}
+
+#[tokio::test]
+async fn test_two_factor_setup_requires_encryption_key() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    server
+        .post("/api/auth/2fa/setup")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_bad_request();
+
+    let stored: Option<String> = sqlx::query_scalar("SELECT totp_secret FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(stored.is_none());
+}
+
+#[tokio::test]
+async fn test_login_requires_totp_when_enabled() {
+    let (server, _pool, _user_id, token) = setup_with_two_factor().await;
+
+    let response = server
+        .post("/api/auth/2fa/setup")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    let body: serde_json::Value = response.json();
+    let secret = body["secret"].as_str().unwrap().to_string();
+
+    server
+        .post("/api/auth/2fa/verify")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"code": current_code(&secret)}))
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123", "totp_code": "000000"}))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({
+            "username": "testuser",
+            "password": "password123",
+            "totp_code": current_code(&secret)
+        }))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_backup_code_logs_in_once() {
+    let (server, _pool, _user_id, token) = setup_with_two_factor().await;
+
+    let response = server
+        .post("/api/auth/2fa/setup")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    let body: serde_json::Value = response.json();
+    let secret = body["secret"].as_str().unwrap().to_string();
+    let codes = body["backup_codes"].as_array().unwrap();
+    assert_eq!(codes.len(), 10);
+    let code = codes[0].as_str().unwrap().to_string();
+
+    server
+        .post("/api/auth/2fa/verify")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"code": current_code(&secret)}))
+        .await
+        .assert_status_ok();
+
+    let login = |backup_code: String| {
+        server.post("/api/auth/login").json(&json!({
+            "username": "testuser",
+            "password": "password123",
+            "backup_code": backup_code
+        }))
+    };
+    login(code.to_uppercase()).await.assert_status_ok();
+    login(code).await.assert_status_unauthorized();
+
+    // Regenerating invalidates the codes from setup
+    let response = server
+        .post("/api/auth/2fa/backup-codes")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let fresh = body["backup_codes"][0].as_str().unwrap().to_string();
+
+    login(codes[1].as_str().unwrap().to_string())
+        .await
+        .assert_status_unauthorized();
+    login(fresh).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_backup_codes_require_two_factor() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    server
+        .post("/api/auth/2fa/backup-codes")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_bad_request();
+}
+
+async fn login_refresh_token(server: &axum_test::TestServer) -> String {
+    let response = server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await;
+    response.assert_status_ok();
+    response.cookie("refresh_token").value().to_string()
+}
+
+async fn refresh_with(
+    server: &axum_test::TestServer,
+    refresh_token: &str,
+) -> axum_test::TestResponse {
+    server
+        .post("/api/auth/refresh")
+        .json(&json!({"refresh_token": refresh_token}))
+        .await
+}
+
+#[tokio::test]
+async fn test_refresh_success() {
+    let (server, _pool, _user_id, _token) = setup_with_pool().await;
+
+    let refresh_token = login_refresh_token(&server).await;
+
+    let response = refresh_with(&server, &refresh_token).await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["username"], "testuser");
+    assert!(!response.cookie("token").value().is_empty());
+    assert_ne!(response.cookie("refresh_token").value(), refresh_token);
+}
+
+#[tokio::test]
+async fn test_refresh_rotation_invalidates_old_token() {
+    let (server, _pool, _user_id, _token) = setup_with_pool().await;
+
+    let first = login_refresh_token(&server).await;
+    let response = refresh_with(&server, &first).await;
+    response.assert_status_ok();
+    let second = response.cookie("refresh_token").value().to_string();
+
+    refresh_with(&server, &first)
+        .await
+        .assert_status_unauthorized();
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn test_refresh_reuse_revokes_family() {
+    let (server, pool, user_id, _token) = setup_with_pool().await;
+
+    let first = login_refresh_token(&server).await;
+    let other_session = login_refresh_token(&server).await;
+
+    let response = refresh_with(&server, &first).await;
+    let second = response.cookie("refresh_token").value().to_string();
+
+    refresh_with(&server, &first)
+        .await
+        .assert_status_unauthorized();
+    refresh_with(&server, &second)
+        .await
+        .assert_status_unauthorized();
+
+    let revoked: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM refresh_tokens WHERE user_id = ? AND revoked_at IS NOT NULL",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(revoked, 2);
+
+    refresh_with(&server, &other_session)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_recent_session_use_is_not_rewritten() {
+    let (server, pool, _user_id, _token) = setup_with_pool().await;
+    let login = server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await;
+    login.assert_status_ok();
+    let token = login.cookie("token").value().to_string();
+    let last_used = || async {
+        sqlx::query_scalar::<_, String>("SELECT last_used_at FROM sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+
+    sqlx::query("UPDATE sessions SET last_used_at = datetime('now', '-30 seconds')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let recent = last_used().await;
+    server
+        .get("/api/auth/me")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+    assert_eq!(last_used().await, recent);
+
+    sqlx::query("UPDATE sessions SET last_used_at = datetime('now', '-5 minutes')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let stale = last_used().await;
+    server
+        .get("/api/auth/me")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+    assert!(last_used().await > stale);
+}
+
+#[tokio::test]
+async fn test_idle_session_is_rejected() {
+    let pool = create_test_pool().await;
+    create_test_user(&pool, "testuser", "password123").await;
+    let options = AppOptions {
+        session: SessionPolicy {
+            idle_timeout: Some(std::time::Duration::from_secs(30 * 60)),
+        },
+        ..Default::default()
+    };
+    let server = create_test_server(create_app_with(pool.clone(), options));
+    let login = || async {
+        let response = server
+            .post("/api/auth/login")
+            .json(&json!({"username": "testuser", "password": "password123"}))
+            .await;
+        response.assert_status_ok();
+        (
+            response.cookie("token").value().to_string(),
+            response.cookie("refresh_token").value().to_string(),
+        )
+    };
+    let (idle_token, idle_refresh) = login().await;
+    let (active_token, _) = login().await;
+
+    // Both sessions were last used 40 minutes ago; only the active one is used again in time
+    sqlx::query("UPDATE sessions SET last_used_at = datetime('now', '-40 minutes')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "UPDATE sessions SET last_used_at = datetime('now', '-20 minutes') WHERE family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash != ? ORDER BY id DESC LIMIT 1)",
+    )
+    .bind(hash_token(&idle_refresh))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    server
+        .get("/api/months")
+        .add_header(auth_name(), auth_value(&idle_token))
+        .await
+        .assert_status_unauthorized();
+    refresh_with(&server, &idle_refresh)
+        .await
+        .assert_status_unauthorized();
+
+    for _ in 0..2 {
+        server
+            .get("/api/months")
+            .add_header(auth_name(), auth_value(&active_token))
+            .await
+            .assert_status_ok();
+    }
+    let recent: bool =
+        sqlx::query_scalar("SELECT MAX(last_used_at) > datetime('now', '-1 minute') FROM sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(recent);
+}
+
+#[tokio::test]
+async fn test_refresh_invalid_token() {
+    let server = setup().await;
+
+    refresh_with(&server, "not-a-token")
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_delete_account_removes_all_rows() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_budget(&pool, month_id, cat_id, 500.0).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 50.0, "2024-06-10").await;
+    create_test_income(&pool, month_id, "Salary", 3000.0).await;
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+    create_test_monthly_savings(&pool, month_id, 100.0, 0.0).await;
+    login_refresh_token(&server).await;
+
+    server
+        .delete("/api/auth/account")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"password": "wrongpassword"}))
+        .await
+        .assert_status_unauthorized();
+
+    let response = server
+        .delete("/api/auth/account")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"password": "password123"}))
+        .await;
+
+    response.assert_status_ok();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await
+        .assert_status_unauthorized();
+
+    for (query, id) in [
+        ("SELECT COUNT(*) FROM users WHERE id = ?", user_id),
+        ("SELECT COUNT(*) FROM months WHERE user_id = ?", user_id),
+        (
+            "SELECT COUNT(*) FROM fixed_expenses WHERE user_id = ?",
+            user_id,
+        ),
+        (
+            "SELECT COUNT(*) FROM budget_categories WHERE user_id = ?",
+            user_id,
+        ),
+        (
+            "SELECT COUNT(*) FROM refresh_tokens WHERE user_id = ?",
+            user_id,
+        ),
+        ("SELECT COUNT(*) FROM items WHERE month_id = ?", month_id),
+        (
+            "SELECT COUNT(*) FROM income_entries WHERE month_id = ?",
+            month_id,
+        ),
+        (
+            "SELECT COUNT(*) FROM monthly_budgets WHERE month_id = ?",
+            month_id,
+        ),
+        (
+            "SELECT COUNT(*) FROM monthly_savings WHERE month_id = ?",
+            month_id,
+        ),
+    ] {
+        let remaining: i64 = sqlx::query_scalar(query)
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0, "{query}");
+    }
+
+    server
+        .delete("/api/auth/account")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"password": "password123"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_change_password_post_then_login() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    let response = server
+        .post("/api/auth/change-password")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "current_password": "password123",
+            "new_password": "newpassword456"
+        }))
+        .await;
+
+    response.assert_status_ok();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "newpassword456"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_change_password_post_wrong_current() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    let response = server
+        .post("/api/auth/change-password")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "current_password": "wrongpassword",
+            "new_password": "newpassword456"
+        }))
+        .await;
+
+    response.assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_change_password_rejects_same_or_weak() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    server
+        .post("/api/auth/change-password")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "current_password": "password123",
+            "new_password": "password123"
+        }))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .post("/api/auth/change-password")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "current_password": "password123",
+            "new_password": "onlyletters"
+        }))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_change_password_revokes_other_sessions() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    let current = login_refresh_token(&server).await;
+    let other = login_refresh_token(&server).await;
+
+    server
+        .post("/api/auth/change-password")
+        .add_header(auth_name(), auth_value(&token))
+        .add_cookie(axum_extra::extract::cookie::Cookie::new(
+            "refresh_token",
+            current.clone(),
+        ))
+        .json(&json!({
+            "current_password": "password123",
+            "new_password": "newpassword456",
+            "revoke_other_sessions": true
+        }))
+        .await
+        .assert_status_ok();
+
+    refresh_with(&server, &other)
+        .await
+        .assert_status_unauthorized();
+    refresh_with(&server, &current).await.assert_status_ok();
+}
+
+async fn create_api_key(
+    server: &axum_test::TestServer,
+    token: &str,
+    body: serde_json::Value,
+) -> (i64, String) {
+    let response = server
+        .post("/api/auth/api-keys")
+        .add_header(auth_name(), auth_value(token))
+        .json(&body)
+        .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    (
+        body["id"].as_i64().unwrap(),
+        body["key"].as_str().unwrap().to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_api_key_authenticates_until_revoked() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    let (key_id, key) = create_api_key(&server, &token, json!({"name": "cron"})).await;
+    assert!(key.starts_with("pk_"));
+    let stored: String = sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE id = ?")
+        .bind(key_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, hash_token(&key));
+
+    let response = server
+        .get("/api/auth/me")
+        .add_header(auth_name(), auth_value(&key))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["id"], user_id);
+
+    let response = server
+        .get("/api/auth/api-keys")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    let body: serde_json::Value = response.json();
+    assert!(body[0].get("key").is_none());
+    assert!(body[0]["last_used_at"].as_str().is_some());
+
+    server
+        .delete(&format!("/api/auth/api-keys/{key_id}"))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    server
+        .get("/api/auth/me")
+        .add_header(auth_name(), auth_value(&key))
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_api_key_cannot_manage_account() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+    let (_, key) = create_api_key(&server, &token, json!({"name": "cron"})).await;
+
+    server
+        .post("/api/auth/api-keys")
+        .add_header(auth_name(), auth_value(&key))
+        .json(&json!({"name": "escalated"}))
+        .await
+        .assert_status_forbidden();
+    let keys: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_keys WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(keys, 1);
+
+    server
+        .post("/api/auth/2fa/setup")
+        .add_header(auth_name(), auth_value(&key))
+        .await
+        .assert_status_forbidden();
+    server
+        .put("/api/auth/change-email")
+        .add_header(auth_name(), auth_value(&key))
+        .json(&json!({"email": "attacker@example.com", "current_password": "password123"}))
+        .await
+        .assert_status_forbidden();
+    server
+        .delete("/api/auth/account")
+        .add_header(auth_name(), auth_value(&key))
+        .json(&json!({"password": "password123"}))
+        .await
+        .assert_status_forbidden();
+
+    // Data routes still take the key
+    server
+        .get("/api/months")
+        .add_header(auth_name(), auth_value(&key))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_api_key_scopes_and_expiry() {
+    let (server, pool, _user_id, token) = setup_with_pool().await;
+
+    let (_, read_only) = create_api_key(
+        &server,
+        &token,
+        json!({"name": "reports", "scopes": ["read"]}),
+    )
+    .await;
+    server
+        .get("/api/months")
+        .add_header(auth_name(), auth_value(&read_only))
+        .await
+        .assert_status_ok();
+    server
+        .post("/api/months")
+        .add_header(auth_name(), auth_value(&read_only))
+        .json(&json!({"year": 2024, "month": 6}))
+        .await
+        .assert_status_forbidden();
+
+    let (expired_id, expired) = create_api_key(
+        &server,
+        &token,
+        json!({"name": "old", "expires_in_days": 30}),
+    )
+    .await;
+    sqlx::query("UPDATE api_keys SET expires_at = datetime('now', '-1 day') WHERE id = ?")
+        .bind(expired_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    server
+        .get("/api/months")
+        .add_header(auth_name(), auth_value(&expired))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .post("/api/auth/api-keys")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"name": "bad", "scopes": ["admin"]}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_rotated_signing_keys() {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let key = |id: &str| SigningKey {
+        id: id.to_string(),
+        secret: format!("{id}-secret"),
+    };
+    let options = AppOptions {
+        jwt_keys: JwtKeys {
+            current: key("v3"),
+            previous: vec![key("v2")],
+        },
+        ..Default::default()
+    };
+    let server = create_test_server(create_app_with(pool, options));
+
+    let sign = |key: SigningKey| {
+        let header = jsonwebtoken::Header {
+            kid: Some(key.id),
+            ..Default::default()
+        };
+        let claims = json!({
+            "sub": user_id,
+            "username": "testuser",
+            "exp": jsonwebtoken::get_current_timestamp() + 600
+        });
+        jsonwebtoken::encode(
+            &header,
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(key.secret.as_bytes()),
+        )
+        .unwrap()
+    };
+    let me = |token: String| {
+        server
+            .get("/api/auth/me")
+            .add_header(auth_name(), auth_value(&token))
+    };
+
+    me(sign(key("v3"))).await.assert_status_ok();
+    me(sign(key("v2"))).await.assert_status_ok();
+    // v1 has been retired from the key set
+    me(sign(key("v1"))).await.assert_status_unauthorized();
+    // A retained key id with the wrong secret is still a forgery
+    me(sign(SigningKey {
+        id: "v2".to_string(),
+        secret: "v3-secret".to_string(),
+    }))
+    .await
+    .assert_status_unauthorized();
+
+    let response = server
+        .post("/api/auth/login")
+        .json(&json!({"username": "testuser", "password": "password123"}))
+        .await;
+    response.assert_status_ok();
+    let token = response.cookie("token").value().to_string();
+    assert_eq!(
+        jsonwebtoken::decode_header(&token).unwrap().kid.as_deref(),
+        Some("v3")
+    );
+}
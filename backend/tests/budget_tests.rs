@@ -2,7 +2,8 @@ mod common;
 
 use common::{
     auth_name, auth_value, close_test_month, create_test_budget, create_test_category,
-    create_test_month, create_test_pool, create_test_server, create_test_user, generate_token,
+    create_test_income, create_test_item, create_test_month, create_test_pool, create_test_server,
+    create_test_user, generate_token,
 };
 use payme::create_app;
 use serde_json::json;
@@ -71,6 +72,71 @@ async fn test_create_category_validation() {
     response.assert_status_bad_request();
 }
 
+#[tokio::test]
+async fn test_category_color_and_icon() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    for (color, icon) in [("red", "cart"), ("#12345g", "cart"), ("#1a2b3c", "Cart!")] {
+        server
+            .post("/api/categories")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "label": "Groceries",
+                "default_amount": 400.0,
+                "color": color,
+                "icon": icon
+            }))
+            .await
+            .assert_status_bad_request();
+    }
+
+    let response = server
+        .post("/api/categories")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "label": "Groceries",
+            "default_amount": 400.0,
+            "color": "#1A2b3c",
+            "icon": "shopping-cart"
+        }))
+        .await;
+    response.assert_status_ok();
+    let category: serde_json::Value = response.json();
+    assert_eq!(category["color"], "#1A2b3c");
+    assert_eq!(category["icon"], "shopping-cart");
+    let cat_id = category["id"].as_i64().unwrap();
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Weekly shop",
+            "amount": 60.0,
+            "spent_on": "2024-06-15"
+        }))
+        .await
+        .assert_status_ok();
+
+    let items: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(items[0]["category_color"], "#1A2b3c");
+    assert_eq!(items[0]["category_icon"], "shopping-cart");
+
+    // Updating other fields keeps the icon
+    let body: serde_json::Value = server
+        .put(&format!("/api/categories/{}", cat_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Food"}))
+        .await
+        .json();
+    assert_eq!(body["icon"], "shopping-cart");
+}
+
 #[tokio::test]
 async fn test_update_category() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -129,6 +195,51 @@ async fn test_delete_category() {
     assert!(body.is_empty());
 }
 
+#[tokio::test]
+async fn test_reorder_categories() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let food = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let rent = create_test_category(&pool, user_id, "Rent", 1200.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 100.0).await;
+
+    let response = server
+        .post("/api/categories/reorder")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"category_ids": [rent, fun, food]}))
+        .await;
+    response.assert_status_ok();
+
+    let body: Vec<serde_json::Value> = server
+        .get("/api/categories")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let labels: Vec<&str> = body.iter().map(|c| c["label"].as_str().unwrap()).collect();
+    assert_eq!(labels, vec!["Rent", "Fun", "Food"]);
+
+    // New categories go to the end
+    server
+        .post("/api/categories")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Travel", "default_amount": 50.0}))
+        .await
+        .assert_status_ok();
+    let body: Vec<serde_json::Value> = server
+        .get("/api/categories")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body[3]["label"], "Travel");
+
+    let response = server
+        .post("/api/categories/reorder")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"category_ids": [rent, food]}))
+        .await;
+    response.assert_status_bad_request();
+}
+
 #[tokio::test]
 async fn test_list_monthly_budgets() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -169,6 +280,144 @@ async fn test_update_monthly_budget() {
     assert_eq!(body["allocated_amount"], 750.0);
 }
 
+#[tokio::test]
+async fn test_percentage_budget_follows_income() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Needs", 500.0).await;
+    let budget_id = create_test_budget(&pool, month_id, cat_id, 500.0).await;
+    create_test_income(&pool, month_id, "Salary", 4000.0).await;
+
+    let response = server
+        .put(&format!("/api/months/{}/budgets/{}", month_id, budget_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "allocation_percent": 50.0
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["allocation_percent"], 50.0);
+    assert_eq!(body["allocated_amount"], 2000.0);
+
+    create_test_income(&pool, month_id, "Bonus", 1000.0).await;
+
+    let response = server
+        .get(&format!("/api/months/{}/budgets", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body[0]["allocated_amount"], 2500.0);
+}
+
+#[tokio::test]
+async fn test_percentage_budgets_capped_at_100() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let needs = create_test_category(&pool, user_id, "Needs", 0.0).await;
+    let wants = create_test_category(&pool, user_id, "Wants", 0.0).await;
+    let needs_budget = create_test_budget(&pool, month_id, needs, 0.0).await;
+    let wants_budget = create_test_budget(&pool, month_id, wants, 0.0).await;
+
+    server
+        .put(&format!(
+            "/api/months/{}/budgets/{}",
+            month_id, needs_budget
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"allocation_percent": 70.0}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .put(&format!(
+            "/api/months/{}/budgets/{}",
+            month_id, wants_budget
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"allocation_percent": 40.0}))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_bulk_budget_update_applies_all_or_nothing() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    create_test_income(&pool, month_id, "Salary", 4000.0).await;
+    let rent = create_test_category(&pool, user_id, "Rent", 0.0).await;
+    let food = create_test_category(&pool, user_id, "Food", 0.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 0.0).await;
+    create_test_budget(&pool, month_id, rent, 1000.0).await;
+    create_test_budget(&pool, month_id, food, 300.0).await;
+
+    let other_user = create_test_user(&pool, "other", "password123").await;
+    let foreign = create_test_category(&pool, other_user, "Theirs", 0.0).await;
+
+    let budgets = || async {
+        let body: Vec<serde_json::Value> = server
+            .get(&format!("/api/months/{month_id}/budgets"))
+            .add_header(auth_name(), auth_value(&token))
+            .await
+            .json();
+        let mut amounts: Vec<(i64, f64)> = body
+            .iter()
+            .map(|b| {
+                (
+                    b["category_id"].as_i64().unwrap(),
+                    b["allocated_amount"].as_f64().unwrap(),
+                )
+            })
+            .collect();
+        amounts.sort_by_key(|(category, _)| *category);
+        amounts
+    };
+
+    // Fun has no budget this month yet, so it is added
+    let response = server
+        .put(&format!("/api/months/{month_id}/budget/bulk"))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"budgets": [
+            {"category_id": rent, "allocated_amount": 1200.0},
+            {"category_id": food, "allocation_percent": 10.0},
+            {"category_id": fun, "allocated_amount": 150.0}
+        ]}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Vec<serde_json::Value>>().len(), 3);
+    let applied = vec![(rent, 1200.0), (food, 400.0), (fun, 150.0)];
+    assert_eq!(budgets().await, applied);
+
+    for entries in [
+        json!([
+            {"category_id": rent, "allocated_amount": 900.0},
+            {"category_id": foreign, "allocated_amount": 50.0}
+        ]),
+        json!([
+            {"category_id": rent, "allocated_amount": 900.0},
+            {"category_id": 999_999, "allocated_amount": 50.0}
+        ]),
+        json!([
+            {"category_id": rent, "allocation_percent": 60.0},
+            {"category_id": fun, "allocation_percent": 50.0}
+        ]),
+    ] {
+        server
+            .put(&format!("/api/months/{month_id}/budget/bulk"))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({ "budgets": entries }))
+            .await
+            .assert_status_bad_request();
+        assert_eq!(budgets().await, applied);
+    }
+}
+
 #[tokio::test]
 async fn test_update_monthly_budget_closed_month() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -188,3 +437,116 @@ async fn test_update_monthly_budget_closed_month() {
 
     response.assert_status_bad_request();
 }
+
+#[tokio::test]
+async fn test_apply_50_30_20_template() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    create_test_income(&pool, month_id, "Salary", 4000.0).await;
+    create_test_category(&pool, user_id, "savings", 0.0).await;
+
+    let response = server
+        .post("/api/budget/apply-template")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"template": "50-30-20"}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let created: Vec<&str> = body["created"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(created, ["Needs", "Wants"]);
+    assert_eq!(body["existing"], json!(["Savings"]));
+
+    let budgets: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/budgets", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let mut allocated: Vec<f64> = budgets
+        .iter()
+        .filter(|b| !b["allocation_percent"].is_null())
+        .map(|b| b["allocated_amount"].as_f64().unwrap())
+        .collect();
+    allocated.sort_by(f64::total_cmp);
+    assert_eq!(allocated, [1200.0, 2000.0]);
+
+    let again: serde_json::Value = server
+        .post("/api/budget/apply-template")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"template": "50-30-20"}))
+        .await
+        .json();
+    assert_eq!(again["created"], json!([]));
+
+    let categories: Vec<serde_json::Value> = server
+        .get("/api/categories")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(categories.len(), 3);
+
+    server
+        .post("/api/budget/apply-template")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"template": "unknown"}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_merge_category_moves_items_and_folds_budgets() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let source = create_test_category(&pool, user_id, "Grocery", 200.0).await;
+    let target = create_test_category(&pool, user_id, "Groceries", 300.0).await;
+    create_test_budget(&pool, month_id, source, 200.0).await;
+    create_test_budget(&pool, month_id, target, 300.0).await;
+    for amount in [10.0, 20.0, 30.0] {
+        create_test_item(&pool, month_id, source, "Market", amount, "2024-06-05").await;
+    }
+
+    let response = server
+        .post(&format!("/api/categories/{}/merge", source))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"target_id": target}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["category"]["id"], target);
+    assert_eq!(body["items_moved"], 3);
+    assert_eq!(body["budgets_folded"], 1);
+
+    let moved: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items WHERE category_id = ?")
+        .bind(target)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(moved, 3);
+    let budget: i64 = sqlx::query_scalar(
+        "SELECT allocated_amount FROM monthly_budgets WHERE month_id = ? AND category_id = ?",
+    )
+    .bind(month_id)
+    .bind(target)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(budget, 50000);
+
+    let categories: Vec<serde_json::Value> = server
+        .get("/api/categories")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert!(categories.iter().all(|c| c["id"] != source));
+
+    server
+        .post(&format!("/api/categories/{}/merge", target))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"target_id": target}))
+        .await
+        .assert_status_bad_request();
+}
@@ -8,6 +8,7 @@ use axum::Router;
 use axum_test::TestServer;
 use chrono::{Duration, Utc};
 use jsonwebtoken::{encode, EncodingKey, Header};
+use payme::money::Money;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
@@ -38,9 +39,23 @@ async fn run_migrations(pool: &SqlitePool) {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             username TEXT NOT NULL UNIQUE,
             password_hash TEXT NOT NULL,
-            savings REAL NOT NULL DEFAULT 0,
-            savings_goal REAL NOT NULL DEFAULT 0,
-            retirement_savings REAL NOT NULL DEFAULT 0,
+            savings INTEGER NOT NULL DEFAULT 0,
+            savings_goal INTEGER NOT NULL DEFAULT 0,
+            retirement_savings INTEGER NOT NULL DEFAULT 0,
+            email TEXT,
+            totp_secret TEXT,
+            totp_enabled INTEGER NOT NULL DEFAULT 0,
+            base_currency TEXT NOT NULL DEFAULT 'USD',
+            alert_warning_percent REAL NOT NULL DEFAULT 80,
+            alert_critical_percent REAL NOT NULL DEFAULT 100,
+            notify_month_closed INTEGER NOT NULL DEFAULT 0,
+            notify_savings_goal INTEGER NOT NULL DEFAULT 0,
+            notify_monthly_digest INTEGER NOT NULL DEFAULT 0,
+            last_digest_month TEXT,
+            rounding_mode TEXT NOT NULL DEFAULT 'half_up',
+            timezone TEXT NOT NULL DEFAULT 'UTC',
+            savings_adjustment INTEGER NOT NULL DEFAULT 0,
+            retirement_savings_adjustment INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL DEFAULT (datetime('now'))
         )
         "#,
@@ -49,13 +64,23 @@ async fn run_migrations(pool: &SqlitePool) {
     .await
     .expect("Failed to create users table");
 
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email ON users(email)")
+        .execute(pool)
+        .await
+        .expect("Failed to create users email index");
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS fixed_expenses (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             user_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
+            category TEXT,
+            due_day INTEGER,
+            active INTEGER NOT NULL DEFAULT 1,
+            start_month TEXT,
+            end_month TEXT,
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
         )
         "#,
@@ -64,14 +89,34 @@ async fn run_migrations(pool: &SqlitePool) {
     .await
     .expect("Failed to create fixed_expenses table");
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS fixed_expense_amounts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fixed_expense_id INTEGER NOT NULL,
+            effective_from TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            previous_amount INTEGER NOT NULL,
+            UNIQUE(fixed_expense_id, effective_from),
+            FOREIGN KEY (fixed_expense_id) REFERENCES fixed_expenses(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create fixed_expense_amounts table");
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS budget_categories (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             user_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            default_amount REAL NOT NULL,
+            default_amount INTEGER NOT NULL,
             color TEXT NOT NULL DEFAULT '#71717a',
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            icon TEXT,
+            enforce_budget INTEGER NOT NULL DEFAULT 0,
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
         )
         "#,
@@ -80,6 +125,131 @@ async fn run_migrations(pool: &SqlitePool) {
     .await
     .expect("Failed to create budget_categories table");
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS households (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create households table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS household_members (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            household_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            role TEXT NOT NULL DEFAULT 'member',
+            invited_by INTEGER,
+            accepted_at TEXT,
+            FOREIGN KEY (household_id) REFERENCES households(id) ON DELETE CASCADE,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            UNIQUE(household_id, user_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create household_members table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            events TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create webhooks table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            next_attempt_at TEXT NOT NULL DEFAULT (datetime('now')),
+            claimed_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            delivered_at TEXT,
+            FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create webhook_deliveries table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS item_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            category_id INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            default_amount INTEGER NOT NULL,
+            savings_destination TEXT NOT NULL DEFAULT 'none',
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES budget_categories(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create item_templates table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS receipts (
+            item_id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            content_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            uploaded_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create receipts table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS exchange_rates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate REAL NOT NULL,
+            effective_on DATE NOT NULL,
+            UNIQUE (user_id, from_currency, to_currency, effective_on),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create exchange_rates table");
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS months (
@@ -89,6 +259,8 @@ async fn run_migrations(pool: &SqlitePool) {
             month INTEGER NOT NULL,
             is_closed INTEGER NOT NULL DEFAULT 0,
             closed_at TEXT,
+            is_locked INTEGER NOT NULL DEFAULT 0,
+            household_id INTEGER REFERENCES households(id) ON DELETE SET NULL,
             FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
             UNIQUE(user_id, year, month)
         )
@@ -104,7 +276,11 @@ async fn run_migrations(pool: &SqlitePool) {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
+            gross_amount INTEGER,
+            withholding INTEGER NOT NULL DEFAULT 0,
+            frequency TEXT NOT NULL DEFAULT 'monthly',
+            monthly_amount INTEGER GENERATED ALWAYS AS (CAST(ROUND(CASE frequency WHEN 'biweekly' THEN amount * 26 / 12.0 WHEN 'weekly' THEN amount * 52 / 12.0 WHEN 'annual' THEN amount / 12.0 ELSE amount END) AS INTEGER)) VIRTUAL,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE
         )
         "#,
@@ -119,7 +295,10 @@ async fn run_migrations(pool: &SqlitePool) {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL,
             category_id INTEGER NOT NULL,
-            allocated_amount REAL NOT NULL,
+            allocated_amount INTEGER NOT NULL,
+            rollover INTEGER NOT NULL DEFAULT 0,
+            carry_overspend INTEGER NOT NULL DEFAULT 0,
+            allocation_percent REAL,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE,
             FOREIGN KEY (category_id) REFERENCES budget_categories(id) ON DELETE CASCADE,
             UNIQUE(month_id, category_id)
@@ -137,9 +316,15 @@ async fn run_migrations(pool: &SqlitePool) {
             month_id INTEGER NOT NULL,
             category_id INTEGER NOT NULL,
             description TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
             spent_on TEXT NOT NULL,
             savings_destination TEXT NOT NULL DEFAULT 'none',
+            currency TEXT NOT NULL DEFAULT 'USD',
+            deleted_at TEXT,
+            version INTEGER NOT NULL DEFAULT 1,
+            savings_account_id INTEGER REFERENCES savings_accounts(id),
+            uid TEXT,
+            updated_at TEXT,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE,
             FOREIGN KEY (category_id) REFERENCES budget_categories(id) ON DELETE CASCADE
         )
@@ -170,7 +355,10 @@ async fn run_migrations(pool: &SqlitePool) {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL,
             label TEXT NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
+            category TEXT,
+            due_day INTEGER,
+            paid INTEGER NOT NULL DEFAULT 0,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE
         )
         "#,
@@ -184,9 +372,9 @@ async fn run_migrations(pool: &SqlitePool) {
         CREATE TABLE IF NOT EXISTS monthly_savings (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             month_id INTEGER NOT NULL UNIQUE,
-            savings REAL NOT NULL DEFAULT 0,
-            retirement_savings REAL NOT NULL DEFAULT 0,
-            savings_goal REAL NOT NULL DEFAULT 0,
+            savings INTEGER NOT NULL DEFAULT 0,
+            retirement_savings INTEGER NOT NULL DEFAULT 0,
+            savings_goal INTEGER NOT NULL DEFAULT 0,
             FOREIGN KEY (month_id) REFERENCES months(id) ON DELETE CASCADE
         )
         "#,
@@ -194,6 +382,241 @@ async fn run_migrations(pool: &SqlitePool) {
     .execute(pool)
     .await
     .expect("Failed to create monthly_savings table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS custom_savings_goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            current_amount INTEGER NOT NULL DEFAULT 0,
+            target_amount INTEGER NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create custom_savings_goals table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS savings_accounts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            balance INTEGER NOT NULL DEFAULT 0,
+            goal INTEGER,
+            goal_date TEXT,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            UNIQUE (user_id, name)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create savings_accounts table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS savings_allocation_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            savings_account_id INTEGER NOT NULL,
+            percent REAL NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (savings_account_id) REFERENCES savings_accounts(id) ON DELETE CASCADE,
+            UNIQUE (user_id, savings_account_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create savings_allocation_rules table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS savings_transfers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            from_account_id INTEGER NOT NULL,
+            to_account_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (from_account_id) REFERENCES savings_accounts(id) ON DELETE CASCADE,
+            FOREIGN KEY (to_account_id) REFERENCES savings_accounts(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create savings_transfers table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS idempotency_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            endpoint TEXT NOT NULL,
+            idempotency_key TEXT NOT NULL,
+            request_hash TEXT NOT NULL,
+            status INTEGER,
+            content_type TEXT,
+            body BLOB,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+            UNIQUE (user_id, endpoint, idempotency_key)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create idempotency_keys table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS import_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            total INTEGER NOT NULL,
+            processed INTEGER NOT NULL DEFAULT 0,
+            errors TEXT NOT NULL DEFAULT '[]',
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            finished_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create import_jobs table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS retirement_breakdown_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            label TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create retirement_breakdown_items table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS two_factor_backup_codes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            code_hash TEXT NOT NULL,
+            used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create two_factor_backup_codes table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS password_reset_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            expires_at TEXT NOT NULL,
+            used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create password_reset_tokens table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS refresh_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            family_id TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            expires_at TEXT NOT NULL,
+            used_at TEXT,
+            revoked_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create refresh_tokens table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sessions (
+            family_id TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            last_used_at TEXT NOT NULL DEFAULT (datetime('now')),
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create sessions table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            prefix TEXT NOT NULL,
+            key_hash TEXT NOT NULL UNIQUE,
+            scopes TEXT NOT NULL,
+            expires_at TEXT,
+            last_used_at TEXT,
+            revoked_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create api_keys table");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS email_outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            recipient TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            body TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            last_error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            sent_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create email_outbox table");
 }
 
 /// Create a test user and return their ID
@@ -265,7 +688,7 @@ pub async fn create_test_category(
     )
     .bind(user_id)
     .bind(label)
-    .bind(default_amount)
+    .bind(Money::from_f64(default_amount))
     .bind("#71717a")
     .fetch_one(pool)
     .await
@@ -297,7 +720,7 @@ pub async fn create_test_fixed_expense(
     )
     .bind(user_id)
     .bind(label)
-    .bind(amount)
+    .bind(Money::from_f64(amount))
     .fetch_one(pool)
     .await
     .expect("Failed to create test fixed expense")
@@ -310,7 +733,7 @@ pub async fn create_test_income(pool: &SqlitePool, month_id: i64, label: &str, a
     )
     .bind(month_id)
     .bind(label)
-    .bind(amount)
+    .bind(Money::from_f64(amount))
     .fetch_one(pool)
     .await
     .expect("Failed to create test income")
@@ -331,7 +754,7 @@ pub async fn create_test_item(
     .bind(month_id)
     .bind(category_id)
     .bind(description)
-    .bind(amount)
+    .bind(Money::from_f64(amount))
     .bind(spent_on)
     .bind("none")
     .fetch_one(pool)
@@ -351,12 +774,30 @@ pub async fn create_test_budget(
     )
     .bind(month_id)
     .bind(category_id)
-    .bind(allocated_amount)
+    .bind(Money::from_f64(allocated_amount))
     .fetch_one(pool)
     .await
     .expect("Failed to create test budget")
 }
 
+/// Create a test monthly savings snapshot and return its ID
+pub async fn create_test_monthly_savings(
+    pool: &SqlitePool,
+    month_id: i64,
+    savings: f64,
+    retirement_savings: f64,
+) -> i64 {
+    sqlx::query_scalar::<_, i64>(
+        "INSERT INTO monthly_savings (month_id, savings, retirement_savings) VALUES (?, ?, ?) RETURNING id",
+    )
+    .bind(month_id)
+    .bind(Money::from_f64(savings))
+    .bind(Money::from_f64(retirement_savings))
+    .fetch_one(pool)
+    .await
+    .expect("Failed to create test monthly savings")
+}
+
 /// Close a month
 pub async fn close_test_month(pool: &SqlitePool, month_id: i64) {
     sqlx::query("UPDATE months SET is_closed = 1, closed_at = datetime('now') WHERE id = ?")
@@ -1,7 +1,8 @@
 #![allow(unused_must_use)]
 
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::Json;
+use payme::config::{BusyRetry, WebhookPolicy};
 use payme::db::run_migrations;
 use payme::handlers::budget::{
     create_category, delete_category, list_categories, update_category, update_monthly_budget,
@@ -18,6 +19,7 @@ use payme::handlers::savings_goals::{
     CreateSavingsGoal, UpdateSavingsGoal,
 };
 use payme::middleware::auth::Claims;
+use payme::money::Money;
 use sqlx::SqlitePool;
 
 async fn setup() -> (SqlitePool, Claims) {
@@ -40,6 +42,7 @@ async fn add_user(pool: &SqlitePool, username: &str) -> Claims {
         sub: user_id,
         username: username.to_string(),
         exp: 9_999_999_999,
+        sid: None,
     }
 }
 
@@ -91,6 +94,90 @@ async fn migrations_are_idempotent() {
     run_migrations(&pool).await.unwrap();
 }
 
+#[tokio::test]
+async fn migrations_convert_real_balances_to_cents() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::query(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, username TEXT NOT NULL UNIQUE, password_hash TEXT NOT NULL, savings REAL NOT NULL DEFAULT 0, savings_goal REAL NOT NULL DEFAULT 0, created_at TEXT NOT NULL DEFAULT (datetime('now')))",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO users (username, password_hash, savings) VALUES ('alice', 'x', 1250.29)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    run_migrations(&pool).await.unwrap();
+    run_migrations(&pool).await.unwrap();
+
+    let (savings, adjustment): (i64, i64) =
+        sqlx::query_as("SELECT savings, savings_adjustment FROM users WHERE username = 'alice'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(savings, 125029);
+    assert_eq!(adjustment, 125029);
+
+    let declared: Vec<(String,)> = sqlx::query_as(
+        "SELECT type FROM pragma_table_info('users') WHERE name IN ('savings', 'retirement_savings', 'savings_adjustment', 'retirement_savings_adjustment')",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(declared.len(), 4);
+    assert!(declared.iter().all(|(t,)| t == "INTEGER"));
+}
+
+#[tokio::test]
+async fn migrations_convert_real_amounts_to_cents() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    for statement in [
+        "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, username TEXT NOT NULL UNIQUE, password_hash TEXT NOT NULL, savings REAL NOT NULL DEFAULT 0, savings_goal REAL NOT NULL DEFAULT 0, created_at TEXT NOT NULL DEFAULT (datetime('now')))",
+        "CREATE TABLE months (id INTEGER PRIMARY KEY AUTOINCREMENT, user_id INTEGER NOT NULL, year INTEGER NOT NULL, month INTEGER NOT NULL, is_closed INTEGER NOT NULL DEFAULT 0, closed_at TEXT, UNIQUE(user_id, year, month))",
+        "CREATE TABLE income_entries (id INTEGER PRIMARY KEY AUTOINCREMENT, month_id INTEGER NOT NULL, label TEXT NOT NULL, amount REAL NOT NULL, frequency TEXT NOT NULL DEFAULT 'monthly', monthly_amount REAL GENERATED ALWAYS AS (ROUND(CASE frequency WHEN 'biweekly' THEN amount * 26 / 12.0 WHEN 'weekly' THEN amount * 52 / 12.0 WHEN 'annual' THEN amount / 12.0 ELSE amount END, 2)) VIRTUAL)",
+        "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, month_id INTEGER NOT NULL, category_id INTEGER NOT NULL, description TEXT NOT NULL, amount REAL NOT NULL, spent_on TEXT NOT NULL, savings_destination TEXT NOT NULL DEFAULT 'none')",
+        "INSERT INTO users (username, password_hash, savings_goal) VALUES ('alice', 'x', 5000.5)",
+        "INSERT INTO months (user_id, year, month) VALUES (1, 2024, 6)",
+        "INSERT INTO income_entries (month_id, label, amount, frequency) VALUES (1, 'Salary', 1000, 'biweekly')",
+        "INSERT INTO items (month_id, category_id, description, amount, spent_on) VALUES (1, 1, 'Groceries', 42.29, '2024-06-05')",
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+
+    run_migrations(&pool).await.unwrap();
+    run_migrations(&pool).await.unwrap();
+
+    let savings_goal: i64 = sqlx::query_scalar("SELECT savings_goal FROM users")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(savings_goal, 500050);
+    let item: i64 = sqlx::query_scalar("SELECT amount FROM items")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(item, 4229);
+    // The generated monthly figure is rebuilt over the cents column
+    let (amount, monthly_amount): (i64, i64) =
+        sqlx::query_as("SELECT amount, monthly_amount FROM income_entries")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!((amount, monthly_amount), (100000, 216667));
+
+    let declared: Vec<(String,)> = sqlx::query_as(
+        "SELECT type FROM pragma_table_xinfo('income_entries') WHERE name IN ('amount', 'monthly_amount')",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(declared.len(), 2);
+    assert!(declared.iter().all(|(t,)| t == "INTEGER"));
+}
+
 #[tokio::test]
 async fn category_create_and_list() {
     let (pool, claims) = setup().await;
@@ -102,13 +189,15 @@ async fn category_create_and_list() {
             label: "Groceries".to_string(),
             default_amount: 400.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
     .unwrap();
 
     assert_eq!(created.label, "Groceries");
-    assert_eq!(created.default_amount, 400.0);
+    assert_eq!(created.default_amount, Money::from_cents(40000));
     assert_eq!(created.color, "#71717a"); // default color
 
     let Json(list) = list_categories(st(pool), ext(claims)).await.unwrap();
@@ -127,6 +216,8 @@ async fn category_update() {
             label: "Old".to_string(),
             default_amount: 100.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -140,13 +231,15 @@ async fn category_update() {
             label: Some("New".to_string()),
             default_amount: Some(250.0),
             color: Some("#ff0000".to_string()),
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
     .unwrap();
 
     assert_eq!(updated.label, "New");
-    assert_eq!(updated.default_amount, 250.0);
+    assert_eq!(updated.default_amount, Money::from_cents(25000));
     assert_eq!(updated.color, "#ff0000");
 }
 
@@ -161,6 +254,8 @@ async fn category_delete_removes_it_from_list() {
             label: "Dining".to_string(),
             default_amount: 200.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -186,6 +281,8 @@ async fn category_delete_cascades_to_monthly_budgets() {
             label: "Transport".to_string(),
             default_amount: 150.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -194,6 +291,7 @@ async fn category_delete_cascades_to_monthly_budgets() {
     create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 1,
@@ -241,6 +339,8 @@ async fn month_creation_seeds_existing_categories() {
             label: "Rent".to_string(),
             default_amount: 1500.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -253,6 +353,8 @@ async fn month_creation_seeds_existing_categories() {
             label: "Food".to_string(),
             default_amount: 300.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -261,6 +363,7 @@ async fn month_creation_seeds_existing_categories() {
     let Json(summary) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 3,
@@ -275,7 +378,7 @@ async fn month_creation_seeds_existing_categories() {
         .iter()
         .find(|b| b.category_label == "Rent")
         .unwrap();
-    assert_eq!(rent.allocated_amount, 1500.0);
+    assert_eq!(rent.allocated_amount, Money::from_cents(150000));
 }
 
 #[tokio::test]
@@ -285,6 +388,7 @@ async fn month_creation_is_idempotent() {
     let Json(first) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 6,
@@ -296,6 +400,7 @@ async fn month_creation_is_idempotent() {
     let Json(second) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 6,
@@ -320,6 +425,7 @@ async fn month_creation_rejects_invalid_month_number() {
     let result = create_month(
         st(pool),
         ext(claims),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 13,
@@ -339,6 +445,7 @@ async fn category_created_mid_month_seeds_open_months() {
     let Json(summary) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 4,
@@ -366,6 +473,8 @@ async fn category_created_mid_month_seeds_open_months() {
             label: "Entertainment".to_string(),
             default_amount: 100.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -394,6 +503,8 @@ async fn monthly_budget_allocation_can_be_updated() {
             label: "Misc".to_string(),
             default_amount: 50.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -402,6 +513,7 @@ async fn monthly_budget_allocation_can_be_updated() {
     let Json(summary) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 7,
@@ -411,20 +523,23 @@ async fn monthly_budget_allocation_can_be_updated() {
     .unwrap();
 
     let budget = &summary.budgets[0];
-    assert_eq!(budget.allocated_amount, 50.0);
+    assert_eq!(budget.allocated_amount, Money::from_cents(5000));
 
     let Json(updated) = update_monthly_budget(
         st(pool.clone()),
         ext(claims.clone()),
         Path((summary.month.id, budget.id)),
         Json(UpdateMonthlyBudget {
-            allocated_amount: 200.0,
+            allocated_amount: Some(200.0),
+            rollover: None,
+            carry_overspend: None,
+            allocation_percent: None,
         }),
     )
     .await
     .unwrap();
 
-    assert_eq!(updated.allocated_amount, 200.0);
+    assert_eq!(updated.allocated_amount, Money::from_cents(20000));
 }
 
 #[tokio::test]
@@ -439,6 +554,8 @@ async fn closed_month_rejects_budget_update() {
             label: "Bills".to_string(),
             default_amount: 300.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -447,6 +564,7 @@ async fn closed_month_rejects_budget_update() {
     let Json(summary) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 8,
@@ -458,16 +576,24 @@ async fn closed_month_rejects_budget_update() {
     let month_id = summary.month.id;
     let budget_id = summary.budgets[0].id;
 
-    close_month(st(pool.clone()), ext(claims.clone()), Path(month_id))
-        .await
-        .unwrap();
+    close_month(
+        st(pool.clone()),
+        ext(claims.clone()),
+        axum::Extension(WebhookPolicy::default()),
+        Path(month_id),
+    )
+    .await
+    .unwrap();
 
     let result = update_monthly_budget(
         st(pool),
         ext(claims),
         Path((month_id, budget_id)),
         Json(UpdateMonthlyBudget {
-            allocated_amount: 999.0,
+            allocated_amount: Some(999.0),
+            rollover: None,
+            carry_overspend: None,
+            allocation_percent: None,
         }),
     )
     .await;
@@ -486,6 +612,7 @@ async fn close_then_reopen_month() {
     let Json(summary) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 9,
@@ -495,9 +622,14 @@ async fn close_then_reopen_month() {
     .unwrap();
     let month_id = summary.month.id;
 
-    let Json(closed) = close_month(st(pool.clone()), ext(claims.clone()), Path(month_id))
-        .await
-        .unwrap();
+    let Json(closed) = close_month(
+        st(pool.clone()),
+        ext(claims.clone()),
+        axum::Extension(WebhookPolicy::default()),
+        Path(month_id),
+    )
+    .await
+    .unwrap();
     assert!(closed.is_closed);
 
     let Json(reopened) = reopen_month(st(pool.clone()), ext(claims.clone()), Path(month_id))
@@ -514,6 +646,7 @@ async fn income_create_and_list() {
     let Json(summary) = create_month(
         st(pool.clone()),
         ext(claims.clone()),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 1,
@@ -526,10 +659,14 @@ async fn income_create_and_list() {
     create_income(
         st(pool.clone()),
         ext(claims.clone()),
+        axum::Extension(BusyRetry::default()),
         Path(month_id),
         Json(CreateIncome {
             label: "Salary".to_string(),
             amount: 5000.0,
+            gross_amount: None,
+            withholding: None,
+            frequency: "monthly".to_string(),
         }),
     )
     .await
@@ -538,10 +675,14 @@ async fn income_create_and_list() {
     create_income(
         st(pool.clone()),
         ext(claims.clone()),
+        axum::Extension(BusyRetry::default()),
         Path(month_id),
         Json(CreateIncome {
             label: "Freelance".to_string(),
             amount: 800.0,
+            gross_amount: None,
+            withholding: None,
+            frequency: "monthly".to_string(),
         }),
     )
     .await
@@ -551,8 +692,8 @@ async fn income_create_and_list() {
         .await
         .unwrap();
     assert_eq!(entries.len(), 2);
-    let total: f64 = entries.iter().map(|e| e.amount).sum();
-    assert_eq!(total, 5800.0);
+    let total: Money = entries.iter().map(|e| e.amount).sum();
+    assert_eq!(total, Money::from_cents(580000));
 }
 
 #[tokio::test]
@@ -573,8 +714,8 @@ async fn savings_goal_full_crud() {
     .unwrap();
 
     assert_eq!(goal.name, "Emergency Fund");
-    assert_eq!(goal.current_amount, 500.0);
-    assert_eq!(goal.target_amount, 10000.0);
+    assert_eq!(goal.current_amount, Money::from_cents(50000));
+    assert_eq!(goal.target_amount, Money::from_cents(1000000));
 
     // List
     let Json(list) = list_savings_goals(st(pool.clone()), ext(claims.clone()))
@@ -587,6 +728,7 @@ async fn savings_goal_full_crud() {
     let Json(updated) = update_savings_goal(
         st(pool.clone()),
         ext(claims.clone()),
+        axum::Extension(WebhookPolicy::default()),
         Path(goal.id),
         Json(UpdateSavingsGoal {
             name: None,
@@ -596,7 +738,7 @@ async fn savings_goal_full_crud() {
     )
     .await
     .unwrap();
-    assert_eq!(updated.current_amount, 2500.0);
+    assert_eq!(updated.current_amount, Money::from_cents(250000));
     assert_eq!(updated.name, "Emergency Fund"); // unchanged
 
     // Delete
@@ -658,8 +800,8 @@ async fn retirement_breakdown_full_crud() {
         .await
         .unwrap();
     assert_eq!(list.len(), 2);
-    let total: f64 = list.iter().map(|i| i.amount).sum();
-    assert_eq!(total, 57000.0);
+    let total: Money = list.iter().map(|i| i.amount).sum();
+    assert_eq!(total, Money::from_cents(5700000));
 
     // Update
     let Json(updated) = update_retirement_breakdown_item(
@@ -673,7 +815,7 @@ async fn retirement_breakdown_full_crud() {
     )
     .await
     .unwrap();
-    assert_eq!(updated.amount, 50000.0);
+    assert_eq!(updated.amount, Money::from_cents(5000000));
     assert_eq!(updated.label, "401k");
 
     // Delete one
@@ -702,6 +844,8 @@ async fn categories_are_scoped_to_user() {
             label: "Alice's Category".to_string(),
             default_amount: 100.0,
             color: None,
+            icon: None,
+            enforce_budget: None,
         }),
     )
     .await
@@ -766,6 +910,7 @@ async fn months_are_scoped_to_user() {
     create_month(
         st(pool.clone()),
         ext(alice),
+        Query(Default::default()),
         Json(payme::handlers::months::CreateMonthRequest {
             year: 2025,
             month: 1,
@@ -799,6 +944,7 @@ async fn cannot_update_another_users_savings_goal() {
     let result = update_savings_goal(
         st(pool),
         ext(bob),
+        axum::Extension(WebhookPolicy::default()),
         Path(alice_goal.id),
         Json(UpdateSavingsGoal {
             name: Some("Hijacked".to_string()),
@@ -47,6 +47,76 @@ async fn test_export_json() {
     assert_eq!(month["items"].as_array().unwrap().len(), 1);
 }
 
+#[tokio::test]
+async fn test_export_estimate_matches_export() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+    let food = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 100.0).await;
+    for (month, day) in [(5, "2024-05-10"), (6, "2024-06-15")] {
+        let month_id = create_test_month(&pool, user_id, 2024, month).await;
+        create_test_income(&pool, month_id, "Salary", 5000.0).await;
+        create_test_budget(&pool, month_id, food, 500.0).await;
+        create_test_item(&pool, month_id, food, "Groceries", 150.0, day).await;
+        create_test_item(&pool, month_id, fun, "Movies", 20.0, day).await;
+        // Deleted items are exported as tombstones, so the estimate counts them
+        let deleted = create_test_item(&pool, month_id, fun, "Refunded", 5.0, day).await;
+        sqlx::query("UPDATE items SET deleted_at = datetime('now') WHERE id = ?")
+            .bind(deleted)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    let other_user = create_test_user(&pool, "other", "password123").await;
+    let other_cat = create_test_category(&pool, other_user, "Food", 500.0).await;
+    let other_month = create_test_month(&pool, other_user, 2024, 6).await;
+    create_test_item(
+        &pool,
+        other_month,
+        other_cat,
+        "Not mine",
+        10.0,
+        "2024-06-01",
+    )
+    .await;
+
+    let estimate: serde_json::Value = server
+        .get("/api/export/estimate")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let export_response = server
+        .get("/api/export/json")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    let export: serde_json::Value = export_response.json();
+
+    let months = export["months"].as_array().unwrap();
+    let per_month = |key: &str| -> usize {
+        months
+            .iter()
+            .map(|m| m[key].as_array().unwrap().len())
+            .sum()
+    };
+    assert_eq!(estimate["months"], months.len());
+    assert_eq!(estimate["items"], per_month("items"));
+    assert_eq!(estimate["income_entries"], per_month("income_entries"));
+    assert_eq!(estimate["budgets"], per_month("budgets"));
+    assert_eq!(
+        estimate["categories"],
+        export["categories"].as_array().unwrap().len()
+    );
+    assert_eq!(
+        estimate["fixed_expenses"],
+        export["fixed_expenses"].as_array().unwrap().len()
+    );
+
+    let actual_bytes = export_response.as_bytes().len() as f64;
+    let approximate = estimate["approximate_bytes"].as_f64().unwrap();
+    assert!(approximate > actual_bytes / 2.0 && approximate < actual_bytes * 2.0);
+}
+
 #[tokio::test]
 async fn test_import_json() {
     let (server, _pool, _user_id, token) = setup_with_user().await;
@@ -147,6 +217,162 @@ async fn test_export_import_round_trip() {
     );
 }
 
+#[tokio::test]
+async fn test_merge_import_applies_newer_tombstones_only() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let stale = create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+    let fresh = create_test_item(&pool, month_id, cat_id, "Coffee", 5.0, "2024-06-16").await;
+    for (id, uid) in [(stale, "item-a"), (fresh, "item-b")] {
+        sqlx::query("UPDATE items SET uid = ?, updated_at = '2024-06-20 12:00:00' WHERE id = ?")
+            .bind(uid)
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let tombstone = |uid: &str, description: &str, deleted_at: &str| {
+        json!({
+            "category_label": "Food",
+            "description": description,
+            "amount": 1.0,
+            "spent_on": "2024-06-15",
+            "uid": uid,
+            "updated_at": deleted_at,
+            "deleted_at": deleted_at
+        })
+    };
+    let bundle = json!({
+        "version": 1,
+        "fixed_expenses": [],
+        "categories": [{"label": "Food", "default_amount": 500.0, "color": "#22c55e"}],
+        "months": [{
+            "year": 2024,
+            "month": 6,
+            "is_closed": false,
+            "income_entries": [],
+            "budgets": [],
+            "items": [
+                tombstone("item-a", "Groceries", "2024-06-21 08:00:00"),
+                tombstone("item-b", "Coffee", "2024-06-19 08:00:00")
+            ]
+        }]
+    });
+
+    let response = server
+        .post("/api/import/json?merge=true")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&bundle)
+        .await;
+    response.assert_status_ok();
+    let report: serde_json::Value = response.json();
+    assert_eq!(report["deleted"], 1);
+    assert_eq!(report["unchanged"], 1);
+
+    let live: Vec<String> = sqlx::query_scalar(
+        "SELECT description FROM items WHERE month_id = ? AND deleted_at IS NULL",
+    )
+    .bind(month_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(live, vec!["Coffee".to_string()]);
+
+    // The deletion travels on as a tombstone in the next export
+    let exported: serde_json::Value = server
+        .get("/api/export/json")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let items = exported["months"][0]["items"].as_array().unwrap();
+    let groceries = items.iter().find(|i| i["uid"] == "item-a").unwrap();
+    assert_eq!(groceries["deleted_at"], "2024-06-21 08:00:00");
+}
+
+#[tokio::test]
+async fn test_import_job_reports_progress_until_completed() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let months: Vec<_> = (1..=12)
+        .map(|month| {
+            let items: Vec<_> = (1..=25)
+                .map(|day| {
+                    json!({
+                        "category_label": "Food",
+                        "description": format!("Item {day}"),
+                        "amount": 10.0,
+                        "spent_on": format!("2024-{month:02}-{day:02}")
+                    })
+                })
+                .collect();
+            json!({
+                "year": 2024,
+                "month": month,
+                "is_closed": false,
+                "income_entries": [{"label": "Salary", "amount": 3000.0}],
+                "budgets": [{"category_label": "Food", "allocated_amount": 400.0}],
+                "items": items
+            })
+        })
+        .collect();
+    let bundle = json!({
+        "version": 1,
+        "fixed_expenses": [],
+        "categories": [{"label": "Food", "default_amount": 400.0, "color": "#22c55e"}],
+        "months": months
+    });
+
+    let response = server
+        .post("/api/import/jobs")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&bundle)
+        .await;
+    response.assert_status(axum::http::StatusCode::ACCEPTED);
+    let job: serde_json::Value = response.json();
+    assert_eq!(job["total"], 13);
+    let id = job["id"].as_i64().unwrap();
+
+    let mut last = 0;
+    let job = loop {
+        let job: serde_json::Value = server
+            .get(&format!("/api/import/jobs/{id}"))
+            .add_header(auth_name(), auth_value(&token))
+            .await
+            .json();
+        let processed = job["processed"].as_i64().unwrap();
+        assert!(processed >= last, "progress went backwards");
+        last = processed;
+        if job["status"] == "completed" || job["status"] == "failed" {
+            break job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    };
+    assert_eq!(job["status"], "completed");
+    assert_eq!(job["processed"], 13);
+    assert!(job["finished_at"].is_string());
+    assert_eq!(job["errors"].as_array().unwrap().len(), 0);
+
+    let items: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM items i JOIN months m ON i.month_id = m.id WHERE m.user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(items, 300);
+
+    // Jobs are private to the user who started them
+    let other = create_test_user(&pool, "other", "password123").await;
+    server
+        .get(&format!("/api/import/jobs/{id}"))
+        .add_header(auth_name(), auth_value(&generate_token(other, "other")))
+        .await
+        .assert_status_not_found();
+}
+
 #[tokio::test]
 async fn test_import_json_replaces_existing() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -190,3 +416,319 @@ async fn test_import_json_replaces_existing() {
     assert_eq!(categories.len(), 1);
     assert_eq!(categories[0]["label"], "New Category");
 }
+
+#[tokio::test]
+async fn test_import_json_dry_run_reports_missing_category() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_category(&pool, user_id, "Existing", 100.0).await;
+
+    let import_data = json!({
+        "version": 1,
+        "savings": 0.0,
+        "retirement_savings": 0.0,
+        "fixed_expenses": [],
+        "categories": [
+            {"label": "Food", "default_amount": 500.0, "color": "#ef4444"}
+        ],
+        "months": [
+            {
+                "year": 2024,
+                "month": 6,
+                "is_closed": false,
+                "income_entries": [],
+                "budgets": [],
+                "items": [
+                    {"category_label": "Food", "description": "Groceries", "amount": 150.0, "spent_on": "2024-06-15"},
+                    {"category_label": "Travel", "description": "Train", "amount": 40.0, "spent_on": "2024-06-16"}
+                ]
+            }
+        ]
+    });
+
+    let response = server
+        .post("/api/import/json?dry_run=true")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&import_data)
+        .await;
+
+    response.assert_status_ok();
+    let report: serde_json::Value = response.json();
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["would_create"]["items"], 2);
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["path"], "months[0].items[1].category_label");
+
+    let categories: Vec<(String,)> =
+        sqlx::query_as("SELECT label FROM budget_categories WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(categories, vec![("Existing".to_string(),)]);
+
+    let response = server
+        .post("/api/import/json")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&import_data)
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_import_json_dry_run_valid() {
+    let (server, _pool, _user_id, token) = setup_with_user().await;
+
+    let import_data = json!({
+        "version": 1,
+        "savings": 0.0,
+        "retirement_savings": 0.0,
+        "fixed_expenses": [{"label": "Rent", "amount": 1500.0}],
+        "categories": [],
+        "months": []
+    });
+
+    let response = server
+        .post("/api/import/json?dry_run=true")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&import_data)
+        .await;
+
+    response.assert_status_ok();
+    let report: serde_json::Value = response.json();
+    assert_eq!(report["valid"], true);
+    assert_eq!(report["would_create"]["fixed_expenses"], 1);
+    assert!(report["errors"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_encrypted_export_round_trip() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+
+    let response = server
+        .get("/api/export/json")
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("x-export-passphrase", "correct horse")
+        .await;
+
+    response.assert_status_ok();
+    let bundle: serde_json::Value = response.json();
+    assert_eq!(bundle["algorithm"], "argon2id+aes-256-gcm");
+    assert!(bundle["ciphertext"].as_str().is_some());
+    assert!(bundle["salt"].as_str().is_some());
+    assert!(bundle["nonce"].as_str().is_some());
+    assert!(bundle.get("months").is_none());
+
+    let response = server
+        .post("/api/import/json")
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("x-export-passphrase", "battery staple")
+        .json(&bundle)
+        .await;
+
+    response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    sqlx::query("DELETE FROM fixed_expenses WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/api/import/json")
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("x-export-passphrase", "correct horse")
+        .json(&bundle)
+        .await;
+
+    response.assert_status_ok();
+
+    let response = server
+        .get("/api/export/json")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["fixed_expenses"][0]["label"], "Rent");
+    assert_eq!(body["months"][0]["items"][0]["description"], "Groceries");
+}
+
+#[tokio::test]
+async fn test_encrypted_import_requires_passphrase() {
+    let (server, _pool, _user_id, token) = setup_with_user().await;
+
+    let response = server
+        .get("/api/export/json")
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("x-export-passphrase", "correct horse")
+        .await;
+
+    let bundle: serde_json::Value = response.json();
+
+    let response = server
+        .post("/api/import/json")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&bundle)
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+/// Minimal RFC 4180 reader used to check the exported CSV round-trips.
+fn parse_csv(input: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    rows
+}
+
+#[tokio::test]
+async fn test_export_month_csv() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+    create_test_item(
+        &pool,
+        month_id,
+        cat_id,
+        "Pizza, \"large\"",
+        22.5,
+        "2024-06-16",
+    )
+    .await;
+
+    let response = server
+        .get(&format!("/api/months/{}/export.csv", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let disposition = response.headers().get("content-disposition").unwrap();
+    assert!(disposition.to_str().unwrap().contains("payme-2024-06.csv"));
+
+    let rows = parse_csv(&response.text());
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        rows[0],
+        [
+            "date",
+            "category",
+            "description",
+            "amount",
+            "savings_destination",
+            "currency"
+        ]
+    );
+    assert_eq!(rows[2][2], "Pizza, \"large\"");
+    assert_eq!(rows[2][3], "22.50");
+    assert_eq!(rows[2][5], "USD");
+}
+
+#[tokio::test]
+async fn test_export_month_qif() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+    create_test_item(&pool, month_id, cat_id, "Bakery", 8.25, "2024-06-16").await;
+    create_test_item(&pool, month_id, cat_id, "Market", 30.0, "2024-06-20").await;
+
+    let response = server
+        .get(&format!("/api/months/{}/export.qif", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let disposition = response.headers().get("content-disposition").unwrap();
+    assert!(disposition.to_str().unwrap().contains("payme-2024-06.qif"));
+
+    let text = response.text();
+    let mut lines = text.lines();
+    assert_eq!(lines.next(), Some("!Type:Bank"));
+
+    // Each record is a run of code-prefixed fields terminated by `^`
+    let mut records: Vec<Vec<&str>> = Vec::new();
+    let mut current = Vec::new();
+    for line in lines {
+        if line == "^" {
+            records.push(std::mem::take(&mut current));
+        } else {
+            current.push(line);
+        }
+    }
+    assert!(current.is_empty());
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        records[0],
+        ["D06/15/2024", "T-150.00", "PGroceries", "LFood"]
+    );
+    assert_eq!(records[1][1], "T-8.25");
+}
+
+#[tokio::test]
+async fn test_export_month_csv_wrong_user() {
+    let (server, pool, _user_id, token) = setup_with_user().await;
+
+    let other_id = create_test_user(&pool, "other", "password123").await;
+    let month_id = create_test_month(&pool, other_id, 2024, 6).await;
+
+    let response = server
+        .get(&format!("/api/months/{}/export.csv", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_export_year_csv() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let june = create_test_month(&pool, user_id, 2024, 6).await;
+    let july = create_test_month(&pool, user_id, 2024, 7).await;
+    let next_year = create_test_month(&pool, user_id, 2025, 1).await;
+    create_test_item(&pool, june, cat_id, "Groceries", 150.0, "2024-06-15").await;
+    create_test_item(&pool, july, cat_id, "Groceries", 120.0, "2024-07-15").await;
+    create_test_item(&pool, next_year, cat_id, "Groceries", 90.0, "2025-01-15").await;
+
+    let response = server
+        .get("/api/export.csv?year=2024")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let rows = parse_csv(&response.text());
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1][0], "2024-06-15");
+    assert_eq!(rows[2][0], "2024-07-15");
+}
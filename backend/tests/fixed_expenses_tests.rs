@@ -74,6 +74,47 @@ async fn test_update_fixed_expense() {
     assert_eq!(body["amount"], 1600.0);
 }
 
+#[tokio::test]
+async fn test_fixed_expense_amount_change_takes_effect_from_month() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let expense_id = create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+    let create_month = |month: i32| {
+        server
+            .post("/api/months")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"year": 2024, "month": month}))
+    };
+    let rent = |body: serde_json::Value| body["fixed_expenses"][0]["amount"].clone();
+
+    // Created before the increase was scheduled, so it must keep the old amount
+    let july_before: serde_json::Value = create_month(7).await.json();
+
+    let response = server
+        .put(&format!("/api/fixed-expenses/{}", expense_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 1700.0, "effective_from": "2024-06"}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["amount"], 1700.0);
+
+    let may: serde_json::Value = create_month(5).await.json();
+    let august: serde_json::Value = create_month(8).await.json();
+    assert_eq!(rent(may), 1500.0);
+    assert_eq!(rent(august), 1700.0);
+    assert_eq!(rent(july_before), 1500.0);
+    let july_now: serde_json::Value = create_month(7).await.json();
+    assert_eq!(rent(july_now), 1500.0);
+
+    server
+        .put(&format!("/api/fixed-expenses/{}", expense_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"effective_from": "2024-09"}))
+        .await
+        .assert_status_bad_request();
+}
+
 #[tokio::test]
 async fn test_update_fixed_expense_not_found() {
     let (server, _pool, _user_id, token) = setup_with_user().await;
@@ -110,3 +151,130 @@ async fn test_delete_fixed_expense() {
     let body: Vec<serde_json::Value> = list_response.json();
     assert!(body.is_empty());
 }
+
+#[tokio::test]
+async fn test_fixed_expenses_by_category() {
+    let (server, _pool, _user_id, token) = setup_with_user().await;
+
+    for (label, amount, category) in [
+        ("Rent", 1500.0, "Housing"),
+        ("Renters insurance", 20.0, "Housing"),
+        ("Electricity", 90.0, "Utilities"),
+    ] {
+        server
+            .post("/api/fixed-expenses")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"label": label, "amount": amount, "category": category}))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/api/fixed-expenses/by-category")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let groups: Vec<serde_json::Value> = response.json();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0]["category"], "Housing");
+    assert_eq!(groups[0]["total"], 1520.0);
+    assert_eq!(groups[0]["count"], 2);
+    assert_eq!(groups[1]["category"], "Utilities");
+    assert_eq!(groups[1]["total"], 90.0);
+
+    // Seeded months carry the grouping into their summary
+    let summary: serde_json::Value = server
+        .get("/api/months/current")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(summary["fixed_by_category"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_uncategorized_fixed_expenses_grouped() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_fixed_expense(&pool, user_id, "Gym", 40.0).await;
+
+    let groups: Vec<serde_json::Value> = server
+        .get("/api/fixed-expenses/by-category")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["category"], "Uncategorized");
+    assert_eq!(groups[0]["total"], 40.0);
+}
+
+#[tokio::test]
+async fn test_upcoming_reminders_track_paid_status() {
+    use chrono::Datelike;
+
+    let (server, _pool, _user_id, token) = setup_with_user().await;
+    let today = chrono::Utc::now().date_naive();
+
+    server
+        .post("/api/fixed-expenses")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Rent", "amount": 1500.0, "due_day": today.day()}))
+        .await
+        .assert_status_ok();
+    server
+        .post("/api/fixed-expenses")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Gym", "amount": 40.0}))
+        .await
+        .assert_status_ok();
+    server
+        .post("/api/fixed-expenses")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Bad", "amount": 1.0, "due_day": 32}))
+        .await
+        .assert_status_bad_request();
+
+    // Before the month exists the templates are used
+    let reminders: Vec<serde_json::Value> = server
+        .get("/api/reminders/upcoming?days=7")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(reminders.len(), 1);
+    assert_eq!(reminders[0]["label"], "Rent");
+    assert_eq!(reminders[0]["due_on"], today.to_string());
+    assert_eq!(reminders[0]["paid"], false);
+    assert!(reminders[0]["month_id"].is_null());
+
+    let month: serde_json::Value = server
+        .get("/api/months/current")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let rent = month["fixed_expenses"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["label"] == "Rent")
+        .unwrap();
+    assert_eq!(rent["due_day"], today.day());
+
+    server
+        .put(&format!(
+            "/api/months/{}/fixed-expenses/{}",
+            month["month"]["id"], rent["id"]
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"paid": true}))
+        .await
+        .assert_status_ok();
+
+    let reminders: Vec<serde_json::Value> = server
+        .get("/api/reminders/upcoming")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(reminders.len(), 1);
+    assert_eq!(reminders[0]["id"], rent["id"]);
+    assert_eq!(reminders[0]["month_id"], month["month"]["id"]);
+    assert_eq!(reminders[0]["paid"], true);
+}
@@ -0,0 +1,252 @@
+mod common;
+
+use common::{
+    auth_name, auth_value, create_test_category, create_test_item, create_test_month,
+    create_test_pool, create_test_server, create_test_user, generate_token,
+};
+use payme::create_app;
+use serde_json::json;
+
+/// Two users and a server; the first owns a household the second has not joined yet.
+async fn setup_with_household() -> (
+    axum_test::TestServer,
+    sqlx::SqlitePool,
+    i64,
+    String,
+    String,
+    i64,
+) {
+    let pool = create_test_pool().await;
+    let owner_id = create_test_user(&pool, "owner", "password123").await;
+    let partner_id = create_test_user(&pool, "partner", "password123").await;
+    let owner_token = generate_token(owner_id, "owner");
+    let partner_token = generate_token(partner_id, "partner");
+    let app = create_app(pool.clone());
+    let server = create_test_server(app);
+
+    let response = server
+        .post("/api/households")
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"name": "Home"}))
+        .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    let household_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+    (
+        server,
+        pool,
+        owner_id,
+        owner_token,
+        partner_token,
+        household_id,
+    )
+}
+
+#[tokio::test]
+async fn test_member_can_list_and_edit_shared_items() {
+    let (server, pool, owner_id, owner_token, partner_token, household_id) =
+        setup_with_household().await;
+
+    let month_id = create_test_month(&pool, owner_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, owner_id, "Food", 500.0).await;
+    let item_id = create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+
+    server
+        .post(&format!(
+            "/api/households/{}/months/{}",
+            household_id, month_id
+        ))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .await
+        .assert_status_ok();
+
+    server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"username": "partner"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    // Pending invitees have no access yet
+    server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_not_found();
+
+    server
+        .post(&format!("/api/households/{}/accept", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await;
+    response.assert_status_ok();
+    let items: Vec<serde_json::Value> = response.json();
+    assert_eq!(items.len(), 1);
+
+    let response = server
+        .put(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .json(&json!({"amount": 175.0, "version": 1}))
+        .await;
+    response.assert_status_ok();
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Bakery",
+            "amount": 12.0,
+            "spent_on": "2024-06-16"
+        }))
+        .await;
+    response.assert_status_ok();
+
+    let months: Vec<serde_json::Value> = server
+        .get("/api/months")
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .json();
+    assert_eq!(months.len(), 1);
+    assert_eq!(months[0]["id"], month_id);
+}
+
+#[tokio::test]
+async fn test_member_cannot_invite() {
+    let (server, pool, _owner_id, owner_token, partner_token, household_id) =
+        setup_with_household().await;
+    create_test_user(&pool, "third", "password123").await;
+
+    server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"username": "partner"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+    server
+        .post(&format!("/api/households/{}/accept", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .json(&json!({"username": "third"}))
+        .await;
+    response.assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    let response = server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"username": "partner"}))
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_unshared_month_stays_private() {
+    let (server, pool, owner_id, owner_token, partner_token, household_id) =
+        setup_with_household().await;
+
+    let month_id = create_test_month(&pool, owner_id, 2024, 6).await;
+
+    server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"username": "partner"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+    server
+        .post(&format!("/api/households/{}/accept", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_ok();
+
+    server
+        .get(&format!("/api/months/{}", month_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_deleting_owner_hands_household_to_member() {
+    let (server, pool, _owner_id, owner_token, partner_token, household_id) =
+        setup_with_household().await;
+
+    server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"username": "partner"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+    server
+        .post(&format!("/api/households/{}/accept", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_ok();
+
+    server
+        .delete("/api/auth/account")
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"password": "password123"}))
+        .await
+        .assert_status_ok();
+
+    let members: Vec<serde_json::Value> = server
+        .get(&format!("/api/households/{}/members", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .json();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0]["username"], "partner");
+    assert_eq!(members[0]["role"], "owner");
+
+    // Inviting is owner-only, so the new owner can grow the household
+    create_test_user(&pool, "third", "password123").await;
+    server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .json(&json!({"username": "third"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_deleting_sole_member_removes_household() {
+    let (server, pool, _owner_id, owner_token, partner_token, household_id) =
+        setup_with_household().await;
+
+    server
+        .post(&format!("/api/households/{}/invite", household_id))
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"username": "partner"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    server
+        .delete("/api/auth/account")
+        .add_header(auth_name(), auth_value(&owner_token))
+        .json(&json!({"password": "password123"}))
+        .await
+        .assert_status_ok();
+
+    // The pending invite can't be accepted into an ownerless household
+    server
+        .post(&format!("/api/households/{}/accept", household_id))
+        .add_header(auth_name(), auth_value(&partner_token))
+        .await
+        .assert_status_not_found();
+
+    let households: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM households")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(households, 0);
+}
@@ -56,6 +56,133 @@ async fn test_create_income() {
     assert!(body["id"].as_i64().is_some());
 }
 
+#[tokio::test]
+async fn test_create_income_with_withholding() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    let response = server
+        .post(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "label": "Salary",
+            "amount": 3800.0,
+            "gross_amount": 5000.0
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["amount"], 3800.0);
+    assert_eq!(body["gross_amount"], 5000.0);
+    assert_eq!(body["withholding"], 1200.0);
+}
+
+#[tokio::test]
+async fn test_create_income_inconsistent_gross_rejected() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    let response = server
+        .post(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "label": "Salary",
+            "amount": 3800.0,
+            "gross_amount": 5000.0,
+            "withholding": 1000.0
+        }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_biweekly_income_normalized_to_monthly() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    server
+        .post(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Paycheck", "amount": 1000.0, "frequency": "fortnightly"}))
+        .await
+        .assert_status_bad_request();
+
+    let response = server
+        .post(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"label": "Paycheck", "amount": 1000.0, "frequency": "biweekly"}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["amount"], 1000.0);
+    assert_eq!(body["monthly_amount"], 2166.67);
+    create_test_income(&pool, month_id, "Side job", 100.0).await;
+
+    let entries: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(entries[0]["amount"], 1000.0);
+    assert_eq!(entries[0]["frequency"], "biweekly");
+    assert_eq!(entries[1]["frequency"], "monthly");
+    assert_eq!(entries[1]["monthly_amount"], 100.0);
+
+    let month: serde_json::Value = server
+        .get(&format!("/api/months/{}", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(month["total_income"], 2266.67);
+
+    let income_id = body["id"].as_i64().unwrap();
+    let body: serde_json::Value = server
+        .put(&format!("/api/months/{}/income/{}", month_id, income_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"frequency": "annual", "amount": 60000.0}))
+        .await
+        .json();
+    assert_eq!(body["monthly_amount"], 5000.0);
+}
+
+#[tokio::test]
+async fn test_income_summary() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let may = create_test_month(&pool, user_id, 2024, 5).await;
+    let june = create_test_month(&pool, user_id, 2024, 6).await;
+    let july = create_test_month(&pool, user_id, 2024, 7).await;
+    create_test_income(&pool, june, "Gift", 200.0).await;
+    create_test_income(&pool, july, "Bonus", 999.0).await;
+
+    for (month_id, net, withholding) in [(may, 3800.0, 1200.0), (june, 3800.0, 1200.0)] {
+        server
+            .post(&format!("/api/months/{}/income", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({ "label": "Salary", "amount": net, "withholding": withholding }))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get(&format!("/api/months/{}/stats/income", june))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["month"]["gross"], 5200.0);
+    assert_eq!(body["month"]["withholding"], 1200.0);
+    assert_eq!(body["month"]["net"], 4000.0);
+    assert_eq!(body["year_to_date"]["gross"], 10200.0);
+    assert_eq!(body["year_to_date"]["withholding"], 2400.0);
+    assert_eq!(body["year_to_date"]["net"], 7800.0);
+}
+
 #[tokio::test]
 async fn test_create_income_closed_month() {
     let (server, pool, user_id, token) = setup_with_user().await;
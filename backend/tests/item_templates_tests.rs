@@ -0,0 +1,131 @@
+mod common;
+
+use common::{
+    auth_name, auth_value, close_test_month, create_test_category, create_test_month,
+    create_test_pool, create_test_server, create_test_user, generate_token,
+};
+use payme::create_app;
+use serde_json::json;
+
+async fn setup_with_user() -> (axum_test::TestServer, sqlx::SqlitePool, i64, String) {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let app = create_app(pool.clone());
+    let server = create_test_server(app);
+    (server, pool, user_id, token)
+}
+
+#[tokio::test]
+async fn test_template_crud() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let cat_id = create_test_category(&pool, user_id, "Health", 100.0).await;
+
+    let response = server
+        .post("/api/item-templates")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Gym",
+            "default_amount": 45.0
+        }))
+        .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    let template: serde_json::Value = response.json();
+    assert_eq!(template["savings_destination"], "none");
+    let template_id = template["id"].as_i64().unwrap();
+
+    let response = server
+        .put(&format!("/api/item-templates/{}", template_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"default_amount": 50.0}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["default_amount"], 50.0);
+
+    let templates: Vec<serde_json::Value> = server
+        .get("/api/item-templates")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(templates.len(), 1);
+
+    server
+        .delete(&format!("/api/item-templates/{}", template_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    let response = server
+        .post("/api/item-templates")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Gym",
+            "default_amount": 45.0,
+            "savings_destination": "mattress"
+        }))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_create_item_from_template_applies_savings() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let cat_id = create_test_category(&pool, user_id, "Savings", 0.0).await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    let template: serde_json::Value = server
+        .post("/api/item-templates")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Monthly transfer",
+            "default_amount": 200.0,
+            "savings_destination": "savings"
+        }))
+        .await
+        .json();
+    let template_id = template["id"].as_i64().unwrap();
+
+    let response = server
+        .post(&format!(
+            "/api/months/{}/items/from-template/{}",
+            month_id, template_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 250.0, "spent_on": "2024-06-01"}))
+        .await;
+    response.assert_status_ok();
+    let item: serde_json::Value = response.json();
+    assert_eq!(item["description"], "Monthly transfer");
+    assert_eq!(item["amount"], 250.0);
+    assert_eq!(item["savings_destination"], "savings");
+
+    // Without a body the template's default amount is used
+    server
+        .post(&format!(
+            "/api/months/{}/items/from-template/{}",
+            month_id, template_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+
+    let savings: serde_json::Value = server
+        .get("/api/savings")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(savings["savings"], 450.0);
+
+    close_test_month(&pool, month_id).await;
+    let response = server
+        .post(&format!(
+            "/api/months/{}/items/from-template/{}",
+            month_id, template_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_bad_request();
+}
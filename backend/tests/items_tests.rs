@@ -1,11 +1,17 @@
 mod common;
 
 use common::{
-    auth_name, auth_value, close_test_month, create_test_category, create_test_item,
-    create_test_month, create_test_pool, create_test_server, create_test_user, generate_token,
+    auth_name, auth_value, close_test_month, create_test_budget, create_test_category,
+    create_test_item, create_test_month, create_test_pool, create_test_server, create_test_user,
+    generate_token,
 };
-use payme::create_app;
+use payme::config::{AppOptions, BusyRetry};
+use payme::money::Money;
+use payme::{create_app, create_app_with};
 use serde_json::json;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{ConnectOptions, Connection};
+use std::time::Duration;
 
 async fn setup_with_user() -> (axum_test::TestServer, sqlx::SqlitePool, i64, String) {
     let pool = create_test_pool().await;
@@ -36,6 +42,58 @@ async fn test_list_items() {
     assert!(body[0]["category_label"].as_str().is_some());
 }
 
+#[tokio::test]
+async fn test_item_with_removed_category_is_still_listed() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let gone = create_test_category(&pool, user_id, "Gone", 100.0).await;
+    create_test_item(&pool, month_id, food, "Groceries", 150.0, "2024-06-15").await;
+    let orphan_id = create_test_item(&pool, month_id, gone, "Mystery", 20.0, "2024-06-16").await;
+
+    // Databases that predate foreign key enforcement can hold rows like this
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM budget_categories WHERE id = ?")
+        .bind(gone)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    drop(conn);
+
+    let response = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 2);
+    let orphan = body.iter().find(|i| i["id"] == orphan_id).unwrap();
+    assert_eq!(orphan["category_label"], "Missing category");
+    assert_eq!(orphan["category_id"], gone);
+
+    let response = server
+        .get("/api/items/orphaned")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0]["id"], orphan_id);
+    assert_eq!(body[0]["year"], 2024);
+    assert_eq!(body[0]["month"], 6);
+}
+
 #[tokio::test]
 async fn test_create_item() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -61,6 +119,135 @@ async fn test_create_item() {
     assert_eq!(body["category_id"], cat_id);
 }
 
+#[tokio::test]
+async fn test_create_item_defaults_to_base_currency() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    server
+        .put("/api/auth/base-currency")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"currency": "eur"}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Coffee",
+            "amount": 5.0,
+            "spent_on": "2024-06-15"
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["currency"], "EUR");
+}
+
+#[tokio::test]
+async fn test_create_item_unknown_currency() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Coffee",
+            "amount": 5.0,
+            "spent_on": "2024-06-15",
+            "currency": "XYZ"
+        }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_month_totals_grouped_by_currency() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_budget(&pool, month_id, cat_id, 500.0).await;
+
+    for (amount, currency) in [(100.0, "USD"), (50.0, "EUR"), (20.0, "EUR")] {
+        server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": cat_id,
+                "description": "Groceries",
+                "amount": amount,
+                "spent_on": "2024-06-15",
+                "currency": currency
+            }))
+            .await
+            .assert_status_ok();
+    }
+
+    // EUR items can't be totalled until a rate is known for their date
+    let response = server
+        .get(&format!("/api/months/{}", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "MISSING_EXCHANGE_RATE");
+
+    // A rate set after the items' date doesn't apply to them
+    for (rate, effective_on) in [(1.25, "2024-06-01"), (2.0, "2024-07-01")] {
+        server
+            .put("/api/exchange-rates")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "from_currency": "eur",
+                "to_currency": "USD",
+                "rate": rate,
+                "effective_on": effective_on
+            }))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get(&format!("/api/months/{}", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["base_currency"], "USD");
+    assert_eq!(body["total_spent"], 187.5);
+    assert_eq!(body["budgets"][0]["spent_amount"], 187.5);
+    assert_eq!(
+        body["spent_by_currency"],
+        json!([
+            {"currency": "EUR", "total": 70.0},
+            {"currency": "USD", "total": 100.0}
+        ])
+    );
+
+    let stats: serde_json::Value = server
+        .get("/api/stats")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(stats["monthly_trends"][0]["total_spent"], 187.5);
+    assert_eq!(
+        stats["monthly_trends"][0]["spent_by_currency"][0]["total"],
+        70.0
+    );
+}
+
 #[tokio::test]
 async fn test_create_item_invalid_category() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -103,6 +290,150 @@ async fn test_create_item_closed_month() {
     response.assert_status_bad_request();
 }
 
+#[tokio::test]
+async fn test_enforced_budget_rejects_overspend() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food = create_test_category(&pool, user_id, "Food", 100.0).await;
+    create_test_budget(&pool, month_id, food, 100.0).await;
+    let groceries = create_test_item(&pool, month_id, food, "Groceries", 60.0, "2024-06-10").await;
+
+    let post = |amount: f64| {
+        server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": food,
+                "description": "Snacks",
+                "amount": amount,
+                "spent_on": "2024-06-15"
+            }))
+    };
+    let enforce = |on: bool| {
+        server
+            .put(&format!("/api/categories/{}", food))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"enforce_budget": on}))
+    };
+
+    let body: serde_json::Value = enforce(true).await.json();
+    assert_eq!(body["enforce_budget"], true);
+
+    let response = post(50.0).await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+    let body: serde_json::Value = response.json();
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("40.00 USD"));
+    post(40.0).await.assert_status_ok();
+
+    // Editing an item only needs room for the increase
+    server
+        .put(&format!("/api/months/{}/items/{}", month_id, groceries))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 61.0, "version": 1}))
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    server
+        .put(&format!("/api/months/{}/items/{}", month_id, groceries))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 55.0, "version": 1}))
+        .await
+        .assert_status_ok();
+
+    enforce(false).await.assert_status_ok();
+    post(50.0).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_create_item_overspend_warning() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food = create_test_category(&pool, user_id, "Food", 100.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 0.0).await;
+    create_test_budget(&pool, month_id, food, 100.0).await;
+    create_test_item(&pool, month_id, food, "Groceries", 60.0, "2024-06-10").await;
+
+    let post = |category_id: i64, amount: f64| {
+        server
+            .post(&format!("/api/months/{}/items?warn=true", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": category_id,
+                "description": "Snacks",
+                "amount": amount,
+                "spent_on": "2024-06-15"
+            }))
+    };
+
+    let body: serde_json::Value = post(food, 30.0).await.json();
+    assert_eq!(body["warnings"], json!([]));
+
+    let body: serde_json::Value = post(food, 25.0).await.json();
+    let warnings = body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["category_overspent"], true);
+    assert_eq!(warnings[0]["budgeted"], 100.0);
+    assert_eq!(warnings[0]["actual"], 115.0);
+    assert_eq!(warnings[0]["over_by"], 15.0);
+
+    // No budget set for the category
+    let body: serde_json::Value = post(fun, 500.0).await.json();
+    assert_eq!(body["warnings"], json!([]));
+
+    // Without the opt-in the response keeps the plain item shape
+    let body: serde_json::Value = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": food,
+            "description": "More snacks",
+            "amount": 10.0,
+            "spent_on": "2024-06-16"
+        }))
+        .await
+        .json();
+    assert!(body.get("warnings").is_none());
+    assert_eq!(body["amount"], 10.0);
+}
+
+#[tokio::test]
+async fn test_create_item_rolled_back_when_balance_update_fails() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Transfers", 0.0).await;
+    sqlx::query(
+        "CREATE TRIGGER fail_savings BEFORE UPDATE OF savings ON users BEGIN SELECT RAISE(ABORT, 'savings update failed'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "To savings",
+            "amount": 200.0,
+            "spent_on": "2024-06-15",
+            "savings_destination": "savings"
+        }))
+        .await;
+    response.assert_status_internal_server_error();
+
+    let items: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items WHERE month_id = ?")
+        .bind(month_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(items, 0);
+}
+
 #[tokio::test]
 async fn test_update_item() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -116,7 +447,8 @@ async fn test_update_item() {
         .add_header(auth_name(), auth_value(&token))
         .json(&json!({
             "description": "Weekly Groceries",
-            "amount": 175.0
+            "amount": 175.0,
+            "version": 1
         }))
         .await;
 
@@ -126,6 +458,41 @@ async fn test_update_item() {
     assert_eq!(body["amount"], 175.0);
 }
 
+#[tokio::test]
+async fn test_update_item_stale_version() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let item_id = create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+
+    let response = server
+        .put(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 175.0, "version": 1}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["version"], 2);
+
+    let response = server
+        .put(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 200.0, "version": 1}))
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let response = server
+        .put(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 200.0, "version": 2}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["amount"], 200.0);
+    assert_eq!(body["version"], 3);
+}
+
 #[tokio::test]
 async fn test_update_item_change_category() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -139,7 +506,8 @@ async fn test_update_item_change_category() {
         .put(&format!("/api/months/{}/items/{}", month_id, item_id))
         .add_header(auth_name(), auth_value(&token))
         .json(&json!({
-            "category_id": cat_id2
+            "category_id": cat_id2,
+            "version": 1
         }))
         .await;
 
@@ -171,3 +539,754 @@ async fn test_delete_item() {
     let body: Vec<serde_json::Value> = list_response.json();
     assert!(body.is_empty());
 }
+
+#[tokio::test]
+async fn test_bulk_delete_items() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let other_month = create_test_month(&pool, user_id, 2024, 7).await;
+    let cat_id = create_test_category(&pool, user_id, "Transfers", 0.0).await;
+
+    let mut ids = vec![];
+    for (amount, destination) in [
+        (100.0, "savings"),
+        (50.0, "savings"),
+        (300.0, "retirement_savings"),
+        (20.0, "none"),
+    ] {
+        let created: serde_json::Value = server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": cat_id,
+                "description": "Transfer",
+                "amount": amount,
+                "spent_on": "2024-06-15",
+                "savings_destination": destination
+            }))
+            .await
+            .json();
+        ids.push(created["id"].as_i64().unwrap());
+    }
+    let elsewhere = create_test_item(&pool, other_month, cat_id, "Other", 5.0, "2024-07-01").await;
+
+    let response = server
+        .post(&format!("/api/months/{}/items/delete", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"item_ids": [ids[0], ids[1], ids[2], elsewhere]}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["deleted"], json!([ids[0], ids[1], ids[2]]));
+    assert_eq!(body["not_found"], json!([elsewhere]));
+
+    let (savings, retirement): (Money, Money) =
+        sqlx::query_as("SELECT savings, retirement_savings FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(savings, Money::ZERO);
+    assert_eq!(retirement, Money::ZERO);
+
+    let list: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["id"], ids[3]);
+}
+
+#[tokio::test]
+async fn test_move_item() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let june = create_test_month(&pool, user_id, 2024, 6).await;
+    let july = create_test_month(&pool, user_id, 2024, 7).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let item_id = create_test_item(&pool, june, cat_id, "Groceries", 150.0, "2024-07-01").await;
+
+    let response = server
+        .post(&format!("/api/months/{}/items/{}/move", june, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"target_month_id": july}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["id"], item_id);
+    assert_eq!(body["month_id"], july);
+
+    let list = |month_id: i64| {
+        server
+            .get(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+    };
+    let source: Vec<serde_json::Value> = list(june).await.json();
+    assert!(source.is_empty());
+    let target: Vec<serde_json::Value> = list(july).await.json();
+    assert_eq!(target.len(), 1);
+    assert_eq!(target[0]["id"], item_id);
+
+    close_test_month(&pool, june).await;
+    server
+        .post(&format!("/api/months/{}/items/{}/move", july, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"target_month_id": june}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_concurrent_deletes_and_restores_apply_savings_once() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let created: serde_json::Value = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Transfer",
+            "amount": 200.0,
+            "spent_on": "2024-06-15",
+            "savings_destination": "savings"
+        }))
+        .await
+        .json();
+    let item_id = created["id"].as_i64().unwrap();
+    let savings = || async {
+        sqlx::query_scalar::<_, Money>("SELECT savings FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .to_f64()
+    };
+
+    let path = format!("/api/months/{}/items/{}", month_id, item_id);
+    let (first, second) = tokio::join!(
+        server
+            .delete(&path)
+            .add_header(auth_name(), auth_value(&token)),
+        server
+            .delete(&path)
+            .add_header(auth_name(), auth_value(&token)),
+    );
+    let mut statuses = [first.status_code(), second.status_code()];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [
+            axum::http::StatusCode::NO_CONTENT,
+            axum::http::StatusCode::NOT_FOUND
+        ]
+    );
+    assert_eq!(savings().await, 0.0);
+
+    let path = format!("{}/restore", path);
+    let (first, second) = tokio::join!(
+        server
+            .post(&path)
+            .add_header(auth_name(), auth_value(&token)),
+        server
+            .post(&path)
+            .add_header(auth_name(), auth_value(&token)),
+    );
+    let mut statuses = [first.status_code(), second.status_code()];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [
+            axum::http::StatusCode::OK,
+            axum::http::StatusCode::NOT_FOUND
+        ]
+    );
+    assert_eq!(savings().await, 200.0);
+}
+
+#[tokio::test]
+async fn test_restore_deleted_item() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    let created: serde_json::Value = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Transfer",
+            "amount": 200.0,
+            "spent_on": "2024-06-15",
+            "savings_destination": "savings"
+        }))
+        .await
+        .json();
+    let item_id = created["id"].as_i64().unwrap();
+
+    let savings = |pool: sqlx::SqlitePool| async move {
+        sqlx::query_scalar::<_, Money>("SELECT savings FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .to_f64()
+    };
+    assert_eq!(savings(pool.clone()).await, 200.0);
+
+    server
+        .delete(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    assert_eq!(savings(pool.clone()).await, 0.0);
+
+    let list: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert!(list.is_empty());
+
+    let response = server
+        .post(&format!(
+            "/api/months/{}/items/{}/restore",
+            month_id, item_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(savings(pool.clone()).await, 200.0);
+
+    let list: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["id"], item_id);
+}
+
+#[tokio::test]
+async fn test_savings_withdrawal() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let savings = |pool: sqlx::SqlitePool| async move {
+        sqlx::query_scalar::<_, Money>("SELECT savings FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .to_f64()
+    };
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Transfer",
+            "amount": 200.0,
+            "spent_on": "2024-06-01",
+            "savings_destination": "savings"
+        }))
+        .await
+        .assert_status_ok();
+
+    let withdrawal: serde_json::Value = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Car repair",
+            "amount": 50.0,
+            "spent_on": "2024-06-10",
+            "savings_destination": "savings_withdrawal"
+        }))
+        .await
+        .json();
+    assert_eq!(savings(pool.clone()).await, 150.0);
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Too much",
+            "amount": 500.0,
+            "spent_on": "2024-06-12",
+            "savings_destination": "savings_withdrawal"
+        }))
+        .await
+        .assert_status_bad_request();
+    assert_eq!(savings(pool.clone()).await, 150.0);
+
+    server
+        .delete(&format!(
+            "/api/months/{}/items/{}",
+            month_id, withdrawal["id"]
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    assert_eq!(savings(pool.clone()).await, 200.0);
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Too much",
+            "amount": 500.0,
+            "spent_on": "2024-06-12",
+            "savings_destination": "savings_withdrawal",
+            "allow_overdraft": true
+        }))
+        .await
+        .assert_status_ok();
+    assert_eq!(savings(pool.clone()).await, -300.0);
+}
+
+#[tokio::test]
+async fn test_undo_delete_restores_last_deleted_item() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let stale_id = create_test_item(&pool, month_id, cat_id, "Old", 10.0, "2024-06-01").await;
+    sqlx::query("UPDATE items SET deleted_at = datetime('now', '-31 days') WHERE id = ?")
+        .bind(stale_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let undo = format!("/api/months/{}/items/undo-delete", month_id);
+    server
+        .post(&undo)
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_not_found();
+
+    let created: serde_json::Value = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Transfer",
+            "amount": 200.0,
+            "spent_on": "2024-06-15",
+            "savings_destination": "savings"
+        }))
+        .await
+        .json();
+    let item_id = created["id"].as_i64().unwrap();
+
+    server
+        .delete(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    let response = server
+        .post(&undo)
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let restored: serde_json::Value = response.json();
+    assert_eq!(restored, created);
+
+    let savings = sqlx::query_scalar::<_, Money>("SELECT savings FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .to_f64();
+    assert_eq!(savings, 200.0);
+
+    // Only the item deleted outside the window is left, so there's nothing more to undo
+    server
+        .post(&undo)
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_restore_item_outside_window() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let item_id = create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+
+    sqlx::query("UPDATE items SET deleted_at = datetime('now', '-31 days') WHERE id = ?")
+        .bind(item_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let response = server
+        .post(&format!(
+            "/api/months/{}/items/{}/restore",
+            month_id, item_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_not_found();
+
+    let purged = payme::handlers::items::purge_deleted_items(&pool)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+}
+
+#[tokio::test]
+async fn test_closed_month_error_body() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    close_test_month(&pool, month_id).await;
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Coffee",
+            "amount": 5.0,
+            "spent_on": "2024-06-15"
+        }))
+        .await;
+
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "MONTH_CLOSED");
+    assert_eq!(body["error"]["status"], 400);
+    assert_eq!(body["error"]["message"], "Month is closed");
+}
+
+#[tokio::test]
+async fn test_validation_error_body() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "",
+            "amount": -5.0,
+            "spent_on": "2024-06-15"
+        }))
+        .await;
+
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "VALIDATION");
+    assert_eq!(body["error"]["status"], 400);
+    assert_eq!(body["error"]["fields"]["amount"][0], "range");
+    assert_eq!(body["error"]["fields"]["description"][0], "length");
+}
+
+#[tokio::test]
+async fn test_item_amount_rejects_fractional_cents() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Groceries",
+            "amount": 10.005,
+            "spent_on": "2024-06-15"
+        }))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_savings_transfers_do_not_drift() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    let mut ids = vec![];
+    for _ in 0..30 {
+        let created: serde_json::Value = server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": cat_id,
+                "description": "Round-up",
+                "amount": 0.1,
+                "spent_on": "2024-06-15",
+                "savings_destination": "savings"
+            }))
+            .await
+            .json();
+        ids.push(created["id"].as_i64().unwrap());
+    }
+    for id in ids.iter().take(10) {
+        server
+            .delete(&format!("/api/months/{}/items/{}", month_id, id))
+            .add_header(auth_name(), auth_value(&token))
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+    }
+
+    let savings = sqlx::query_scalar::<_, Money>("SELECT savings FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .to_f64();
+    assert_eq!(savings, 2.0);
+}
+
+#[tokio::test]
+async fn test_upload_and_fetch_receipt() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let item_id = create_test_item(&pool, month_id, cat_id, "Groceries", 150.0, "2024-06-15").await;
+    let url = format!("/api/months/{}/items/{}/receipt", month_id, item_id);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend((0..=255u8).cycle().take(4096));
+
+    server
+        .post(&url)
+        .add_header(auth_name(), auth_value(&token))
+        .content_type("image/gif")
+        .bytes(png.clone().into())
+        .await
+        .assert_status_bad_request();
+
+    server
+        .post(&url)
+        .add_header(auth_name(), auth_value(&token))
+        .content_type("image/png")
+        .bytes(png.clone().into())
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    let response = server
+        .get(&url)
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-type"), "image/png");
+    assert_eq!(response.as_bytes().as_ref(), png.as_slice());
+
+    let list: Vec<serde_json::Value> = server
+        .get(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(list[0]["has_receipt"], true);
+
+    let mut oversized = b"\x89PNG\r\n\x1a\n".to_vec();
+    oversized.resize(payme::handlers::receipts::MAX_RECEIPT_BYTES + 1, 0);
+    server
+        .post(&url)
+        .add_header(auth_name(), auth_value(&token))
+        .content_type("image/png")
+        .bytes(oversized.into())
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_search_items_across_months() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let may = create_test_month(&pool, user_id, 2024, 5).await;
+    let june = create_test_month(&pool, user_id, 2024, 6).await;
+    let food = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_item(&pool, may, food, "Coffee beans", 18.0, "2024-05-03").await;
+    create_test_item(&pool, may, food, "Groceries", 80.0, "2024-05-10").await;
+    create_test_item(&pool, june, food, "Iced coffee", 5.5, "2024-06-12").await;
+    close_test_month(&pool, may).await;
+
+    let response = server
+        .get("/api/items/search?q=COFFEE")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total"], 2);
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items[0]["description"], "Iced coffee");
+    assert_eq!(items[0]["month"], 6);
+    assert_eq!(items[0]["is_closed"], false);
+    assert_eq!(items[1]["description"], "Coffee beans");
+    assert_eq!(items[1]["year"], 2024);
+    assert_eq!(items[1]["month"], 5);
+    assert_eq!(items[1]["is_closed"], true);
+
+    let body: serde_json::Value = server
+        .get("/api/items/search?q=coffee&from=2024-05-01&to=2024-05-31&limit=1")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["month_id"], may);
+
+    let body: serde_json::Value = server
+        .get("/api/items/search?limit=2&offset=2")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+
+    // Other users' items never match
+    let other_id = create_test_user(&pool, "other", "password123").await;
+    let other_token = generate_token(other_id, "other");
+    let body: serde_json::Value = server
+        .get("/api/items/search?q=coffee")
+        .add_header(auth_name(), auth_value(&other_token))
+        .await
+        .json();
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn test_create_item_retries_while_database_busy() {
+    // A file database, since an in-memory one has a single connection and is never busy, that
+    // reports busy straight away instead of waiting for the lock
+    let dir = tempfile::tempdir().unwrap();
+    let options = SqliteConnectOptions::new()
+        .filename(dir.path().join("payme.db"))
+        .create_if_missing(true)
+        .busy_timeout(Duration::ZERO);
+    let pool = SqlitePoolOptions::new()
+        .connect_with(options.clone())
+        .await
+        .unwrap();
+    payme::db::run_migrations(&pool).await.unwrap();
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let month_id = create_test_month(&pool, user_id, 2024, 1).await;
+    let category_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let server_with = |max_retries| {
+        create_test_server(create_app_with(
+            pool.clone(),
+            AppOptions {
+                busy_retry: BusyRetry {
+                    max_retries,
+                    base_delay: Duration::from_millis(20),
+                },
+                ..Default::default()
+            },
+        ))
+    };
+    let item = json!({
+        "category_id": category_id,
+        "description": "Groceries",
+        "amount": 50.0,
+        "spent_on": "2024-01-15"
+    });
+
+    let mut writer = options.connect().await.unwrap();
+    sqlx::query("BEGIN IMMEDIATE")
+        .execute(&mut writer)
+        .await
+        .unwrap();
+
+    let response = server_with(0)
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&item)
+        .await;
+    response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "DATABASE_BUSY");
+
+    let release = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        sqlx::query("ROLLBACK").execute(&mut writer).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    server_with(10)
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&item)
+        .await
+        .assert_status_ok();
+    release.await.unwrap();
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items WHERE month_id = ?")
+        .bind(month_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn test_misspelled_savings_destination_is_rejected() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Transfers", 0.0).await;
+    let item_id = create_test_item(&pool, month_id, cat_id, "Transfer", 100.0, "2024-06-15").await;
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Transfer",
+            "amount": 100.0,
+            "spent_on": "2024-06-15",
+            "savings_destination": "savigns"
+        }))
+        .await;
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert!(body["error"]["fields"]["savings_destination"][0]
+        .as_str()
+        .unwrap()
+        .starts_with("Must be one of none, savings"));
+
+    server
+        .put(&format!("/api/months/{}/items/{}", month_id, item_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"savings_destination": "retirment_savings", "version": 1}))
+        .await
+        .assert_status_bad_request();
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items WHERE month_id = ?")
+        .bind(month_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    let savings = sqlx::query_scalar::<_, Money>("SELECT savings FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .to_f64();
+    assert_eq!(savings, 0.0);
+}
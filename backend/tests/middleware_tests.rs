@@ -1,10 +1,12 @@
 mod common;
 
 use common::{
-    auth_name, auth_value, create_test_pool, create_test_server, create_test_user,
-    generate_expired_token, generate_token,
+    auth_name, auth_value, create_test_category, create_test_item, create_test_month,
+    create_test_pool, create_test_server, create_test_user, generate_expired_token, generate_token,
 };
-use payme::create_app;
+use payme::config::{AppOptions, BodyLimits, CorsConfig};
+use payme::{create_app, create_app_with};
+use std::io::Read;
 
 async fn setup() -> axum_test::TestServer {
     let pool = create_test_pool().await;
@@ -110,6 +112,34 @@ async fn test_health_check_no_auth() {
     response.assert_status_ok();
 }
 
+#[tokio::test]
+async fn test_health_check_reports_database() {
+    let server = setup().await;
+
+    let response = server.get("/health").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "healthy");
+    assert_eq!(body["database"], "connected");
+    assert!(body["db_latency_ms"].as_f64().unwrap() >= 0.0);
+    assert!(body["pool"]["size"].as_u64().unwrap() >= 1);
+    assert!(body["pool"]["max_connections"].as_u64().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn test_health_check_database_down() {
+    let pool = create_test_pool().await;
+    let server = create_test_server(create_app(pool.clone()));
+    pool.close().await;
+
+    let response = server.get("/health").await;
+
+    response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "unhealthy");
+}
+
 #[tokio::test]
 async fn test_register_no_auth() {
     let server = setup().await;
@@ -118,7 +148,7 @@ async fn test_register_no_auth() {
         .post("/api/auth/register")
         .json(&serde_json::json!({
             "username": "newuser",
-            "password": "password123"
+            "password": "tulip-harbor-42"
         }))
         .await;
 
@@ -142,3 +172,338 @@ async fn test_login_no_auth() {
 
     response.assert_status_ok();
 }
+
+#[tokio::test]
+async fn test_request_id_echoed() {
+    let server = setup().await;
+
+    let response = server
+        .get("/health")
+        .add_header(
+            axum::http::HeaderName::from_static("x-request-id"),
+            axum::http::HeaderValue::from_static("client-abc-123"),
+        )
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("x-request-id"), "client-abc-123");
+}
+
+#[tokio::test]
+async fn test_request_id_generated() {
+    let server = setup().await;
+
+    // Also applies to requests rejected by the auth middleware
+    let response = server.get("/api/auth/me").await;
+
+    response.assert_status_unauthorized();
+    let id = response.header("x-request-id");
+    let parsed = uuid::Uuid::parse_str(id.to_str().unwrap()).unwrap();
+    assert_eq!(parsed.get_version_num(), 4);
+}
+
+#[tokio::test]
+async fn test_large_response_compressed() {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    for i in 0..50 {
+        create_test_item(
+            &pool,
+            month_id,
+            cat_id,
+            &format!("Item {i}"),
+            1.0,
+            "2024-06-15",
+        )
+        .await;
+    }
+    let server = create_test_server(create_app(pool));
+    let path = format!("/api/months/{}/items", month_id);
+
+    let plain = server
+        .get(&path)
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    plain.assert_status_ok();
+    assert!(plain.maybe_header("content-encoding").is_none());
+
+    let response = server
+        .get(&path)
+        .add_header(auth_name(), auth_value(&token))
+        .add_header(
+            axum::http::header::ACCEPT_ENCODING,
+            axum::http::HeaderValue::from_static("gzip"),
+        )
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("content-encoding"), "gzip");
+    let compressed = response.as_bytes();
+    assert!(compressed.len() < plain.as_bytes().len());
+
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(&compressed[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, plain.as_bytes().to_vec());
+
+    let response = server
+        .get(&path)
+        .add_header(auth_name(), auth_value(&token))
+        .add_header(
+            axum::http::header::ACCEPT_ENCODING,
+            axum::http::HeaderValue::from_static("gzip;q=0.5, br"),
+        )
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-encoding"), "br");
+    assert!(response.as_bytes().len() < plain.as_bytes().len());
+}
+
+#[tokio::test]
+async fn test_small_response_not_compressed() {
+    let server = setup().await;
+
+    let response = server
+        .get("/health")
+        .add_header(
+            axum::http::header::ACCEPT_ENCODING,
+            axum::http::HeaderValue::from_static("gzip"),
+        )
+        .await;
+
+    response.assert_status_ok();
+    assert!(response.maybe_header("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn test_idempotency_key_replays_create() {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let server = create_test_server(create_app(pool.clone()));
+    let path = format!("/api/months/{}/items", month_id);
+    let item = serde_json::json!({
+        "category_id": cat_id,
+        "description": "Coffee",
+        "amount": 5.0,
+        "spent_on": "2024-06-15"
+    });
+    let key = axum::http::HeaderValue::from_static("retry-1");
+
+    let first = server
+        .post(&path)
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("idempotency-key", key.clone())
+        .json(&item)
+        .await;
+    first.assert_status_ok();
+    assert!(first.maybe_header("idempotent-replayed").is_none());
+
+    let second = server
+        .post(&path)
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("idempotency-key", key.clone())
+        .json(&item)
+        .await;
+    second.assert_status_ok();
+    assert_eq!(second.header("idempotent-replayed"), "true");
+    assert_eq!(second.as_bytes(), first.as_bytes());
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items WHERE month_id = ?")
+        .bind(month_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    // Same key, different request
+    let conflict = server
+        .post(&path)
+        .add_header(auth_name(), auth_value(&token))
+        .add_header("idempotency-key", key)
+        .json(&serde_json::json!({
+            "category_id": cat_id,
+            "description": "Tea",
+            "amount": 4.0,
+            "spent_on": "2024-06-15"
+        }))
+        .await;
+    conflict.assert_status(axum::http::StatusCode::CONFLICT);
+
+    // Keys don't carry across endpoints
+    let income = server
+        .post(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .add_header(
+            "idempotency-key",
+            axum::http::HeaderValue::from_static("retry-1"),
+        )
+        .json(&serde_json::json!({"label": "Salary", "amount": 3000.0}))
+        .await;
+    income.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_idempotency_key_ignored_outside_creating_routes() {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let server = create_test_server(create_app(pool.clone()));
+
+    for _ in 0..2 {
+        let response = server
+            .post("/api/auth/api-keys")
+            .add_header(auth_name(), auth_value(&token))
+            .add_header(
+                "idempotency-key",
+                axum::http::HeaderValue::from_static("retry-1"),
+            )
+            .json(&serde_json::json!({"name": "script"}))
+            .await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        assert!(response.maybe_header("idempotent-replayed").is_none());
+    }
+
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM idempotency_keys")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, 0);
+}
+
+async fn setup_with_cors() -> axum_test::TestServer {
+    let pool = create_test_pool().await;
+    let options = AppOptions {
+        cors: CorsConfig {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    create_test_server(create_app_with(pool, options))
+}
+
+#[tokio::test]
+async fn test_cors_allowed_origin() {
+    let server = setup_with_cors().await;
+
+    let response = server
+        .get("/health")
+        .add_header(
+            axum::http::header::ORIGIN,
+            axum::http::HeaderValue::from_static("https://app.example.com"),
+        )
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(
+        response.header("access-control-allow-origin"),
+        "https://app.example.com"
+    );
+    assert_eq!(response.header("access-control-allow-credentials"), "true");
+
+    // Preflight is answered before auth runs
+    let preflight = server
+        .method(axum::http::Method::OPTIONS, "/api/auth/me")
+        .add_header(
+            axum::http::header::ORIGIN,
+            axum::http::HeaderValue::from_static("https://app.example.com"),
+        )
+        .add_header(
+            axum::http::header::ACCESS_CONTROL_REQUEST_METHOD,
+            axum::http::HeaderValue::from_static("GET"),
+        )
+        .add_header(
+            axum::http::header::ACCESS_CONTROL_REQUEST_HEADERS,
+            axum::http::HeaderValue::from_static("authorization"),
+        )
+        .await;
+
+    preflight.assert_status_ok();
+    assert_eq!(
+        preflight.header("access-control-allow-origin"),
+        "https://app.example.com"
+    );
+    assert_eq!(preflight.header("access-control-allow-methods"), "GET");
+    assert_eq!(
+        preflight.header("access-control-allow-headers"),
+        "authorization"
+    );
+}
+
+#[tokio::test]
+async fn test_cors_disallowed_origin() {
+    let server = setup_with_cors().await;
+
+    let response = server
+        .get("/health")
+        .add_header(
+            axum::http::header::ORIGIN,
+            axum::http::HeaderValue::from_static("https://evil.example.com"),
+        )
+        .await;
+
+    response.assert_status_ok();
+    assert!(response
+        .maybe_header("access-control-allow-origin")
+        .is_none());
+    assert!(response
+        .maybe_header("access-control-allow-credentials")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_body_limit_per_route_group() {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let month_id = create_test_month(&pool, user_id, 2024, 1).await;
+    let category_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let options = AppOptions {
+        body_limits: BodyLimits {
+            default: 1024,
+            bulk: 64 * 1024,
+        },
+        ..Default::default()
+    };
+    let server = create_test_server(create_app_with(pool, options));
+    let item = |description: String| {
+        serde_json::json!({
+            "category_id": category_id,
+            "description": description,
+            "amount": 12.5,
+            "spent_on": "2024-01-15"
+        })
+    };
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&item("x".repeat(2048)))
+        .await;
+    response.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "PAYLOAD_TOO_LARGE");
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&item("Groceries".to_string()))
+        .await
+        .assert_status_ok();
+
+    // Past the default limit but within the bulk one
+    let item_ids: Vec<i64> = (1_000_000..1_000_300).collect();
+    server
+        .post(&format!("/api/months/{}/items/delete", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"item_ids": item_ids}))
+        .await
+        .assert_status_ok();
+}
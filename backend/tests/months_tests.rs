@@ -1,8 +1,10 @@
 mod common;
 
 use common::{
-    auth_name, auth_value, close_test_month, create_test_category, create_test_month,
-    create_test_pool, create_test_server, create_test_user, generate_token,
+    auth_name, auth_value, close_test_month, create_test_budget, create_test_category,
+    create_test_fixed_expense, create_test_income, create_test_item, create_test_month,
+    create_test_monthly_savings, create_test_pool, create_test_server, create_test_user,
+    generate_token,
 };
 use payme::create_app;
 
@@ -51,6 +53,90 @@ async fn test_list_months_ordered() {
     assert_eq!(body[2]["month"], 1);
 }
 
+#[tokio::test]
+async fn test_only_the_calendar_month_is_current() {
+    use chrono::Datelike;
+
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let today = chrono::Utc::now().date_naive();
+    let (year, month) = (today.year(), today.month() as i32);
+    let current = create_test_month(&pool, user_id, year, month).await;
+    create_test_month(&pool, user_id, year - 1, month).await;
+    create_test_month(&pool, user_id, year + 1, month).await;
+
+    let months: Vec<serde_json::Value> = server
+        .get("/api/months")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let flags: Vec<_> = months
+        .iter()
+        .map(|m| {
+            (
+                m["year"].as_i64().unwrap() as i32,
+                m["is_current"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        flags,
+        vec![(year + 1, false), (year, true), (year - 1, false)]
+    );
+    assert_eq!(months[2]["days_remaining"], 0);
+
+    let summary: serde_json::Value = server
+        .get(&format!("/api/months/{current}"))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(summary["month"]["is_current"], true);
+    let days_remaining = summary["month"]["days_remaining"].as_u64().unwrap();
+    assert!((1..=31).contains(&days_remaining));
+    assert!(days_remaining <= u64::from(32 - today.day()));
+}
+
+#[tokio::test]
+async fn test_current_month_follows_user_timezone() {
+    use chrono::Datelike;
+
+    let (server, _pool, _user_id, token) = setup_with_user().await;
+
+    server
+        .put("/api/auth/timezone")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"timezone": "Moon/Tranquility"}))
+        .await
+        .assert_status_bad_request();
+
+    // Samoa is UTC+13, so on the evening of the last day of a month in UTC it is already the next
+    let response = server
+        .put("/api/auth/timezone")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"timezone": "Pacific/Apia"}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.json::<serde_json::Value>()["timezone"],
+        "Pacific/Apia"
+    );
+
+    let local = chrono::Utc::now()
+        .with_timezone(&chrono_tz::Pacific::Apia)
+        .date_naive();
+    let body: serde_json::Value = server
+        .get("/api/months/current")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body["month"]["year"], local.year());
+    assert_eq!(body["month"]["month"], local.month());
+    assert_eq!(body["month"]["is_current"], true);
+    assert_eq!(body["month"]["days_remaining"], {
+        let next = local.with_day(1).unwrap() + chrono::Months::new(1);
+        (next - local).num_days()
+    });
+}
+
 #[tokio::test]
 async fn test_get_or_create_current_month_creates() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -97,6 +183,207 @@ async fn test_get_or_create_current_month_returns_existing() {
     assert_eq!(month_id1, month_id2);
 }
 
+#[tokio::test]
+async fn test_get_or_create_current_month_carries_fixed_expenses() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+
+    let response = server
+        .get("/api/months/current")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let fixed = body["fixed_expenses"].as_array().unwrap();
+    assert_eq!(fixed.len(), 1);
+    assert_eq!(fixed[0]["label"], "Rent");
+    assert_eq!(fixed[0]["amount"], 1500.0);
+
+    let response = server
+        .get("/api/months/current")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["fixed_expenses"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_get_or_create_current_month_skips_paused_fixed_expenses() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+    create_test_fixed_expense(&pool, user_id, "Internet", 60.0).await;
+    let paused = create_test_fixed_expense(&pool, user_id, "Gym", 40.0).await;
+    sqlx::query("UPDATE fixed_expenses SET active = 0 WHERE id = ?")
+        .bind(paused)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let body: serde_json::Value = server
+        .get("/api/months/current")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let mut labels: Vec<&str> = body["fixed_expenses"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["label"].as_str().unwrap())
+        .collect();
+    labels.sort_unstable();
+    assert_eq!(labels, ["Internet", "Rent"]);
+}
+
+#[tokio::test]
+async fn test_get_or_create_current_month_rolls_over_unspent_budget() {
+    use chrono::Datelike;
+
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let today = chrono::Utc::now().date_naive();
+    let (year, month) = if today.month() == 1 {
+        (today.year() - 1, 12)
+    } else {
+        (today.year(), today.month() as i32 - 1)
+    };
+    let previous = create_test_month(&pool, user_id, year, month).await;
+    let food = create_test_category(&pool, user_id, "Food", 200.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 100.0).await;
+    let food_budget = create_test_budget(&pool, previous, food, 200.0).await;
+    create_test_budget(&pool, previous, fun, 100.0).await;
+    let spent_on = format!("{year}-{month:02}-15");
+    create_test_item(&pool, previous, food, "Groceries", 150.0, &spent_on).await;
+    create_test_item(&pool, previous, fun, "Cinema", 30.0, &spent_on).await;
+
+    let response = server
+        .put(&format!("/api/months/{}/budgets/{}", previous, food_budget))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"rollover": true}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["allocated_amount"], 200.0);
+    assert_eq!(body["rollover"], true);
+
+    for _ in 0..2 {
+        let response = server
+            .get("/api/months/current")
+            .add_header(auth_name(), auth_value(&token))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        let budgets = body["budgets"].as_array().unwrap();
+        let allocated = |category_id: i64| {
+            budgets
+                .iter()
+                .find(|b| b["category_id"] == category_id)
+                .unwrap()["allocated_amount"]
+                .clone()
+        };
+        assert_eq!(allocated(food), 250.0);
+        assert_eq!(allocated(fun), 100.0);
+    }
+}
+
+#[tokio::test]
+async fn test_create_month_skip_fixed_expenses() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+
+    let response = server
+        .post("/api/months?skip_fixed_expenses=true")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"year": 2024, "month": 6}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["fixed_expenses"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_create_month_applies_fixed_expense_date_ranges() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    create_test_fixed_expense(&pool, user_id, "Rent", 1500.0).await;
+    server
+        .post("/api/fixed-expenses")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({
+            "label": "Storage unit",
+            "amount": 80.0,
+            "start_month": "2024-08",
+            "end_month": "2024-05"
+        }))
+        .await
+        .assert_status_bad_request();
+    server
+        .post("/api/fixed-expenses")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({
+            "label": "Storage unit",
+            "amount": 80.0,
+            "start_month": "2024-05",
+            "end_month": "2024-08"
+        }))
+        .await
+        .assert_status_ok();
+
+    for (month, expected) in [
+        (4, vec!["Rent"]),
+        (5, vec!["Rent", "Storage unit"]),
+        (8, vec!["Rent", "Storage unit"]),
+        (9, vec!["Rent"]),
+    ] {
+        let response = server
+            .post("/api/months")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&serde_json::json!({"year": 2024, "month": month}))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        let mut labels: Vec<&str> = body["fixed_expenses"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["label"].as_str().unwrap())
+            .collect();
+        labels.sort_unstable();
+        assert_eq!(labels, expected, "month {month}");
+    }
+}
+
+#[tokio::test]
+async fn test_create_month_carries_previous_month_without_templates() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let may = create_test_month(&pool, user_id, 2024, 5).await;
+    sqlx::query("INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, ?, ?)")
+        .bind(may)
+        .bind("Gym")
+        .bind(4000)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/api/months")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"year": 2024, "month": 6}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let fixed = body["fixed_expenses"].as_array().unwrap();
+    assert_eq!(fixed.len(), 1);
+    assert_eq!(fixed[0]["label"], "Gym");
+}
+
 #[tokio::test]
 async fn test_get_month_success() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -165,6 +452,42 @@ async fn test_close_month_success() {
     assert!(body["closed_at"].as_str().is_some());
 }
 
+#[tokio::test]
+async fn test_close_month_snapshots_savings() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    // Balances are stored in cents
+    sqlx::query("UPDATE users SET savings = 50000, retirement_savings = 120000 WHERE id = ?")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    server
+        .post(&format!("/api/months/{}/close", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!("/api/months/{}/savings", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["savings"], 500.0);
+    assert_eq!(body["retirement_savings"], 1200.0);
+
+    // The snapshot can't be edited while the month is closed
+    let response = server
+        .put(&format!("/api/months/{}/savings", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"savings": 1.0}))
+        .await;
+    response.assert_status_bad_request();
+}
+
 #[tokio::test]
 async fn test_close_month_already_closed() {
     let (server, pool, user_id, token) = setup_with_user().await;
@@ -177,7 +500,189 @@ async fn test_close_month_already_closed() {
         .add_header(auth_name(), auth_value(&token))
         .await;
 
+    // Closing again changes nothing
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["is_closed"], true);
+    let snapshots: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM monthly_snapshots WHERE month_id = ?")
+            .bind(month_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(snapshots, 0);
+}
+
+#[tokio::test]
+async fn test_close_month_failing_snapshot_leaves_month_open() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    sqlx::query(
+        "CREATE TRIGGER fail_savings_snapshot BEFORE INSERT ON monthly_savings BEGIN SELECT RAISE(ABORT, 'disk I/O error'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    server
+        .post(&format!("/api/months/{}/close", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_internal_server_error();
+
+    let (is_closed, closed_at): (bool, Option<String>) =
+        sqlx::query_as("SELECT is_closed, closed_at FROM months WHERE id = ?")
+            .bind(month_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(!is_closed);
+    assert!(closed_at.is_none());
+    let snapshots: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM monthly_snapshots WHERE month_id = ?")
+            .bind(month_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(snapshots, 0);
+}
+
+#[tokio::test]
+async fn test_locked_month_rejects_edits_until_unlocked() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let item = serde_json::json!({
+        "category_id": cat_id,
+        "description": "Coffee",
+        "amount": 5.0,
+        "spent_on": "2024-06-15"
+    });
+
+    let response = server
+        .post(&format!("/api/months/{}/lock", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["is_locked"], true);
+    assert_eq!(body["is_closed"], false);
+
+    let response = server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&item)
+        .await;
+    response.assert_status(axum::http::StatusCode::LOCKED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "MONTH_LOCKED");
+
+    let response = server
+        .post(&format!("/api/months/{}/income", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"label": "Salary", "amount": 3000.0}))
+        .await;
+    response.assert_status(axum::http::StatusCode::LOCKED);
+
+    let response = server
+        .post(&format!("/api/months/{}/unlock", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["is_locked"], false);
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&item)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_close_locked_month() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    server
+        .post(&format!("/api/months/{}/lock", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post(&format!("/api/months/{}/close", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["is_closed"], true);
+    assert_eq!(body["is_locked"], false);
+
+    // Closed months report the closed state and can only be reopened
+    let response = server
+        .post(&format!("/api/months/{}/unlock", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
     response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "MONTH_CLOSED");
+
+    server
+        .post(&format!("/api/months/{}/reopen", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_close_month_queues_summary_email() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    sqlx::query("UPDATE users SET email = 'test@example.com' WHERE id = ?")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let opted_out = create_test_month(&pool, user_id, 2024, 5).await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    server
+        .post(&format!("/api/months/{}/close", opted_out))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+
+    server
+        .put("/api/auth/notifications")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"month_closed": true, "savings_goal": false}))
+        .await
+        .assert_status_ok();
+    server
+        .post(&format!("/api/months/{}/close", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+
+    let queued: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT kind, recipient, subject FROM email_outbox WHERE user_id = ? ORDER BY id",
+    )
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        queued,
+        vec![(
+            "month_closed".to_string(),
+            "test@example.com".to_string(),
+            "Your June 2024 summary".to_string()
+        )]
+    );
 }
 
 #[tokio::test]
@@ -215,3 +720,285 @@ async fn test_get_month_pdf_not_closed() {
 
     response.assert_status_not_found();
 }
+
+#[tokio::test]
+async fn test_duplicate_month() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let source_id = create_test_month(&pool, user_id, 2024, 5).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_budget(&pool, source_id, cat_id, 650.0).await;
+    create_test_item(&pool, source_id, cat_id, "Groceries", 120.0, "2024-05-10").await;
+    create_test_monthly_savings(&pool, source_id, 0.0, 0.0).await;
+    sqlx::query("UPDATE monthly_savings SET savings_goal = 250000 WHERE month_id = ?")
+        .bind(source_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, ?, ?)")
+        .bind(source_id)
+        .bind("Rent")
+        .bind(150000)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let response = server
+        .post(&format!("/api/months/{}/duplicate", source_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"year": 2024, "month": 6}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["month"]["month"], 6);
+    assert_eq!(body["fixed_expenses"].as_array().unwrap().len(), 1);
+    assert_eq!(body["fixed_expenses"][0]["label"], "Rent");
+    assert_eq!(body["budgets"][0]["allocated_amount"], 650.0);
+    assert_eq!(body["savings"]["savings_goal"], 2500.0);
+    assert!(body["items"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_duplicate_month_conflict() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let source_id = create_test_month(&pool, user_id, 2024, 5).await;
+    create_test_month(&pool, user_id, 2024, 6).await;
+
+    let response = server
+        .post(&format!("/api/months/{}/duplicate", source_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"year": 2024, "month": 6}))
+        .await;
+
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_month_forecast_mid_month() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    let fun_id = create_test_category(&pool, user_id, "Fun", 500.0).await;
+    create_test_budget(&pool, month_id, food_id, 500.0).await;
+    create_test_budget(&pool, month_id, fun_id, 500.0).await;
+    create_test_income(&pool, month_id, "Salary", 3000.0).await;
+    create_test_item(&pool, month_id, food_id, "Groceries", 300.0, "2024-06-05").await;
+    create_test_item(&pool, month_id, fun_id, "Cinema", 100.0, "2024-06-10").await;
+
+    let response = server
+        .get(&format!(
+            "/api/months/{}/forecast?as_of=2024-06-15",
+            month_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["days_elapsed"], 15);
+    assert_eq!(body["days_in_month"], 30);
+    assert_eq!(body["spent_to_date"], 400.0);
+    assert_eq!(body["projected_spent"], 800.0);
+    assert_eq!(body["projected_savings"], 2200.0);
+
+    let categories = body["categories"].as_array().unwrap();
+    let food = categories
+        .iter()
+        .find(|c| c["category_label"] == "Food")
+        .unwrap();
+    assert_eq!(food["projected_spent"], 600.0);
+    assert_eq!(food["projected_over_budget"], true);
+    let fun = categories
+        .iter()
+        .find(|c| c["category_label"] == "Fun")
+        .unwrap();
+    assert_eq!(fun["projected_over_budget"], false);
+}
+
+#[tokio::test]
+async fn test_month_forecast_before_month_starts() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 50.0, "2024-06-01").await;
+
+    let response = server
+        .get(&format!(
+            "/api/months/{}/forecast?as_of=2024-05-31",
+            month_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["days_elapsed"], 0);
+    assert_eq!(body["projected_spent"], 50.0);
+}
+
+#[tokio::test]
+async fn test_forecast_future_months_from_recurring_income_and_fixed_expenses() {
+    use chrono::Datelike;
+
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let today = chrono::Utc::now().date_naive();
+    let month_id = create_test_month(&pool, user_id, today.year(), today.month() as i32).await;
+    create_test_income(&pool, month_id, "Salary", 3000.0).await;
+    let bonus_id = create_test_income(&pool, month_id, "Bonus", 500.0).await;
+    sqlx::query("UPDATE income_entries SET frequency = 'one_time' WHERE id = ?")
+        .bind(bonus_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    create_test_fixed_expense(&pool, user_id, "Rent", 1200.0).await;
+
+    let response = server
+        .get("/api/forecast/months?count=3")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 3);
+    let next = today.with_day(1).unwrap() + chrono::Months::new(1);
+    assert_eq!(body[0]["year"], next.year());
+    assert_eq!(body[0]["month"], next.month());
+    for month in &body {
+        assert_eq!(month["projected"], true);
+        assert_eq!(month["expected_income"], 3000.0);
+        assert_eq!(month["expected_fixed"], 1200.0);
+        assert_eq!(month["projected_net"], 1800.0);
+    }
+
+    // Projecting creates nothing
+    let months: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM months WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(months, 1);
+}
+
+#[tokio::test]
+async fn test_safe_to_spend_mid_month() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_income(&pool, month_id, "Salary", 3000.0).await;
+    sqlx::query(
+        "INSERT INTO monthly_fixed_expenses (month_id, label, amount) VALUES (?, 'Rent', 120000)",
+    )
+    .bind(month_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    create_test_item(&pool, month_id, cat_id, "Groceries", 400.0, "2024-06-05").await;
+    sqlx::query(
+        "INSERT INTO items (month_id, category_id, description, amount, spent_on, savings_destination) VALUES (?, ?, 'Transfer', 20000, '2024-06-01', 'savings')",
+    )
+    .bind(month_id)
+    .bind(cat_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let response = server
+        .get(&format!(
+            "/api/months/{}/safe-to-spend?as_of=2024-06-21&savings_target=500",
+            month_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["savings_contribution"], 500.0);
+    assert_eq!(body["spent_to_date"], 400.0);
+    // 3000 - 1200 - 500 - 400
+    assert_eq!(body["safe_to_spend"], 900.0);
+    assert_eq!(body["days_remaining"], 10);
+    assert_eq!(body["per_day"], 90.0);
+
+    // Transfers beyond the target are already gone, and overspending goes negative
+    create_test_item(&pool, month_id, cat_id, "Laptop", 1500.0, "2024-06-20").await;
+    let body: serde_json::Value = server
+        .get(&format!(
+            "/api/months/{}/safe-to-spend?as_of=2024-07-02&savings_target=100",
+            month_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body["savings_contribution"], 200.0);
+    assert_eq!(body["safe_to_spend"], -300.0);
+    assert_eq!(body["days_remaining"], 0);
+    assert!(body["per_day"].is_null());
+}
+
+#[tokio::test]
+async fn test_close_preview_matches_close() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_budget(&pool, month_id, cat_id, 400.0).await;
+    create_test_income(&pool, month_id, "Salary", 3000.0).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 450.0, "2024-06-05").await;
+    sqlx::query(
+        "UPDATE users SET savings = 75000, retirement_savings = 200000, savings_goal = 500000 WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let preview: serde_json::Value = server
+        .get(&format!("/api/months/{}/close-preview", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(preview["total_income"], 3000.0);
+    assert_eq!(preview["total_spent"], 450.0);
+    assert_eq!(preview["categories"][0]["variance"], -50.0);
+    assert_eq!(preview["blockers"], serde_json::json!([]));
+
+    // Previewing writes nothing
+    let snapshots: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM monthly_savings WHERE month_id = ?")
+            .bind(month_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(snapshots, 0);
+
+    server
+        .post(&format!("/api/months/{}/close", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_ok();
+
+    let snapshot: serde_json::Value = server
+        .get(&format!("/api/months/{}/savings", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    for field in ["savings", "retirement_savings", "savings_goal"] {
+        assert_eq!(snapshot[field], preview["savings"][field]);
+    }
+
+    let after: serde_json::Value = server
+        .get(&format!("/api/months/{}/close-preview", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(
+        after["blockers"],
+        serde_json::json!(["Month is already closed"])
+    );
+}
@@ -1,7 +1,8 @@
 mod common;
 
 use common::{
-    auth_name, auth_value, create_test_pool, create_test_server, create_test_user, generate_token,
+    auth_name, auth_value, create_test_category, create_test_month, create_test_monthly_savings,
+    create_test_pool, create_test_server, create_test_user, generate_token,
 };
 use payme::create_app;
 use serde_json::json;
@@ -15,6 +16,15 @@ async fn setup_with_user() -> (axum_test::TestServer, i64, String) {
     (server, user_id, token)
 }
 
+async fn setup_with_pool() -> (axum_test::TestServer, sqlx::SqlitePool, i64, String) {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let app = create_app(pool.clone());
+    let server = create_test_server(app);
+    (server, pool, user_id, token)
+}
+
 #[tokio::test]
 async fn test_get_savings() {
     let (server, _user_id, token) = setup_with_user().await;
@@ -109,3 +119,500 @@ async fn test_savings_validation() {
 
     response.assert_status_bad_request();
 }
+
+#[tokio::test]
+async fn test_savings_history_ordered() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    let march = create_test_month(&pool, user_id, 2024, 3).await;
+    let january = create_test_month(&pool, user_id, 2024, 1).await;
+    let february = create_test_month(&pool, user_id, 2024, 2).await;
+    create_test_monthly_savings(&pool, january, 1000.0, 5000.0).await;
+    create_test_monthly_savings(&pool, february, 1500.0, 5200.0).await;
+    create_test_monthly_savings(&pool, march, 2100.0, 5400.0).await;
+
+    let response = server
+        .get("/api/savings/history")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 3);
+    assert_eq!(body[0]["month"], 1);
+    assert_eq!(body[0]["savings"], 1000.0);
+    assert_eq!(body[1]["month"], 2);
+    assert_eq!(body[1]["retirement_savings"], 5200.0);
+    assert_eq!(body[2]["month"], 3);
+    assert_eq!(body[2]["savings"], 2100.0);
+}
+
+#[tokio::test]
+async fn test_savings_history_gaps() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    let january = create_test_month(&pool, user_id, 2024, 1).await;
+    create_test_month(&pool, user_id, 2024, 2).await;
+    let march = create_test_month(&pool, user_id, 2024, 3).await;
+    create_test_monthly_savings(&pool, january, 1000.0, 0.0).await;
+    create_test_monthly_savings(&pool, march, 1200.0, 0.0).await;
+
+    let response = server
+        .get("/api/savings/history")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 2);
+
+    let response = server
+        .get("/api/savings/history?carry_forward=true")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 3);
+    assert_eq!(body[1]["month"], 2);
+    assert_eq!(body[1]["savings"], 1000.0);
+    assert_eq!(body[1]["carried_forward"], true);
+
+    let response = server
+        .get("/api/savings/history?months=1")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0]["month"], 3);
+}
+
+#[tokio::test]
+async fn test_items_route_into_savings_accounts() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = common::create_test_category(&pool, user_id, "Transfers", 0.0).await;
+
+    let mut account_ids = vec![];
+    for name in ["Emergency fund", "House"] {
+        let response = server
+            .post("/api/savings-accounts")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"name": name, "goal": 10000.0}))
+            .await;
+        response.assert_status(axum::http::StatusCode::CREATED);
+        let body: serde_json::Value = response.json();
+        account_ids.push(body["id"].as_i64().unwrap());
+    }
+
+    for (account_id, amount, destination) in [
+        (account_ids[0], 300.0, "account"),
+        (account_ids[1], 1000.0, "account"),
+        (account_ids[1], 250.0, "account_withdrawal"),
+    ] {
+        server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": cat_id,
+                "description": "Transfer",
+                "amount": amount,
+                "spent_on": "2024-06-15",
+                "savings_destination": destination,
+                "savings_account_id": account_id
+            }))
+            .await
+            .assert_status_ok();
+    }
+
+    let body: serde_json::Value = server
+        .get("/api/savings")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body["savings"], 0.0);
+    let accounts = body["accounts"].as_array().unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0]["name"], "Emergency fund");
+    assert_eq!(accounts[0]["balance"], 300.0);
+    assert_eq!(accounts[1]["name"], "House");
+    assert_eq!(accounts[1]["balance"], 750.0);
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "No account",
+            "amount": 10.0,
+            "spent_on": "2024-06-15",
+            "savings_destination": "account"
+        }))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .delete(&format!("/api/savings-accounts/{}", account_ids[0]))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_reaching_savings_goal_queues_email() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+
+    sqlx::query("UPDATE users SET email = 'test@example.com', savings_goal = 100000 WHERE id = ?")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    server
+        .put("/api/auth/notifications")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"month_closed": false, "savings_goal": true}))
+        .await
+        .assert_status_ok();
+
+    for savings in [800.0, 1200.0, 1300.0] {
+        server
+            .put("/api/savings")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"savings": savings}))
+            .await
+            .assert_status_ok();
+    }
+
+    // Only crossing the goal sends; staying above it doesn't
+    let subjects: Vec<String> =
+        sqlx::query_scalar("SELECT subject FROM email_outbox WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(subjects, vec!["You reached your savings goal".to_string()]);
+
+    let response = server
+        .get("/api/auth/notifications")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body,
+        json!({"month_closed": false, "savings_goal": true, "monthly_digest": false})
+    );
+}
+
+#[tokio::test]
+async fn test_contribution_splits_across_accounts_by_rule() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    let mut account_ids = vec![];
+    for name in ["Emergency fund", "House"] {
+        let body: serde_json::Value = server
+            .post("/api/savings-accounts")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"name": name, "goal": 10000.0}))
+            .await
+            .json();
+        account_ids.push(body["id"].as_i64().unwrap());
+    }
+
+    server
+        .post("/api/savings/contribute")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 100.0}))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .put("/api/savings/allocation-rules")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"rules": [
+            {"savings_account_id": account_ids[0], "percent": 60.0},
+            {"savings_account_id": account_ids[1], "percent": 30.0}
+        ]}))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .put("/api/savings/allocation-rules")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"rules": [
+            {"savings_account_id": account_ids[0], "percent": 60.0},
+            {"savings_account_id": account_ids[1], "percent": 40.0}
+        ]}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/api/savings/contribute")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 100.0}))
+        .await;
+    response.assert_status_ok();
+    let body: Vec<serde_json::Value> = response.json();
+    assert_eq!(body.len(), 2);
+    assert_eq!(body[0]["amount"], 60.0);
+    assert_eq!(body[1]["amount"], 40.0);
+
+    // Shares round to the cent and still add up to the amount, so nothing is lost
+    server
+        .post("/api/savings/contribute")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"amount": 0.99}))
+        .await
+        .assert_status_ok();
+
+    let body: serde_json::Value = server
+        .get("/api/savings")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let accounts = body["accounts"].as_array().unwrap();
+    assert_eq!(accounts[0]["balance"], 60.59);
+    assert_eq!(accounts[1]["balance"], 40.4);
+}
+
+#[tokio::test]
+async fn test_odd_contribution_split_three_ways_sums_exactly() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    let response = server
+        .put("/api/auth/rounding-mode")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"mode": "half_even"}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["mode"], "half_even");
+    server
+        .put("/api/auth/rounding-mode")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"mode": "truncate"}))
+        .await
+        .assert_status_unprocessable_entity();
+
+    let mut rules = vec![];
+    for (name, percent) in [("Emergency", 33.33), ("House", 33.33), ("Travel", 33.34)] {
+        let body: serde_json::Value = server
+            .post("/api/savings-accounts")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"name": name}))
+            .await
+            .json();
+        rules.push(json!({"savings_account_id": body["id"], "percent": percent}));
+    }
+    server
+        .put("/api/savings/allocation-rules")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({ "rules": rules }))
+        .await
+        .assert_status_ok();
+
+    for (amount, expected) in [(100.01, [33.33, 33.33, 33.35]), (0.05, [0.02, 0.01, 0.02])] {
+        let response = server
+            .post("/api/savings/contribute")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({ "amount": amount }))
+            .await;
+        response.assert_status_ok();
+        let shares: Vec<f64> = response
+            .json::<Vec<serde_json::Value>>()
+            .iter()
+            .map(|share| share["amount"].as_f64().unwrap())
+            .collect();
+        assert_eq!(shares, expected);
+        let cents: i64 = shares.iter().map(|s| (s * 100.0).round() as i64).sum();
+        assert_eq!(cents, (amount * 100.0_f64).round() as i64);
+    }
+}
+
+#[tokio::test]
+async fn test_reconcile_savings_reports_and_fixes_drift() {
+    let (server, pool, user_id, token) = setup_with_pool().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Savings", 0.0).await;
+
+    server
+        .put("/api/savings")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"savings": 1000.0}))
+        .await
+        .assert_status_ok();
+    for (amount, destination) in [
+        (500.0, "savings"),
+        (200.0, "savings_withdrawal"),
+        (150.0, "retirement_savings"),
+    ] {
+        server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": cat_id,
+                "description": "Transfer",
+                "amount": amount,
+                "spent_on": "2024-06-15",
+                "savings_destination": destination
+            }))
+            .await
+            .assert_status_ok();
+    }
+
+    let clean: serde_json::Value = server
+        .post("/api/savings/reconcile")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(clean["savings"]["expected"], 1300.0);
+    assert_eq!(clean["savings"]["discrepancy"], 0.0);
+    assert_eq!(clean["retirement_savings"]["expected"], 150.0);
+    assert_eq!(clean["retirement_savings"]["discrepancy"], 0.0);
+
+    // Simulate an interrupted write; balances are stored in cents
+    sqlx::query("UPDATE users SET savings = 125050 WHERE id = ?")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let report = server
+        .post("/api/savings/reconcile")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    report.assert_status_ok();
+    let body: serde_json::Value = report.json();
+    assert_eq!(body["savings"]["stored"], 1250.5);
+    assert_eq!(body["savings"]["discrepancy"], -49.5);
+    assert_eq!(body["corrected"], false);
+
+    let fixed: serde_json::Value = server
+        .post("/api/savings/reconcile?fix=true")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(fixed["corrected"], true);
+    assert_eq!(fixed["savings"]["expected"], 1300.0);
+
+    let savings: serde_json::Value = server
+        .get("/api/savings")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(savings["savings"], 1300.0);
+}
+
+#[tokio::test]
+async fn test_transfer_between_savings_accounts() {
+    let (server, _pool, _user_id, token) = setup_with_pool().await;
+
+    let mut account_ids = vec![];
+    for (name, balance) in [("House", 1000.0), ("Emergency fund", 200.0)] {
+        let body: serde_json::Value = server
+            .post("/api/savings-accounts")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"name": name, "balance": balance}))
+            .await
+            .json();
+        account_ids.push(body["id"].as_i64().unwrap());
+    }
+
+    let response = server
+        .post("/api/savings/transfer")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "from_account": account_ids[0],
+            "to_account": account_ids[1],
+            "amount": 250.5
+        }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["from"]["balance"], 749.5);
+    assert_eq!(body["to"]["balance"], 450.5);
+    assert_eq!(body["transfer"]["amount"], 250.5);
+
+    // Overdrafts and self-transfers are refused and leave balances alone
+    server
+        .post("/api/savings/transfer")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "from_account": account_ids[1],
+            "to_account": account_ids[0],
+            "amount": 500.0
+        }))
+        .await
+        .assert_status_bad_request();
+    server
+        .post("/api/savings/transfer")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "from_account": account_ids[0],
+            "to_account": account_ids[0],
+            "amount": 10.0
+        }))
+        .await
+        .assert_status_bad_request();
+
+    let savings: serde_json::Value = server
+        .get("/api/savings")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let accounts = savings["accounts"].as_array().unwrap();
+    assert_eq!(accounts[0]["name"], "Emergency fund");
+    assert_eq!(accounts[0]["balance"], 450.5);
+    assert_eq!(accounts[1]["balance"], 749.5);
+
+    let transfers: Vec<serde_json::Value> = server
+        .get("/api/savings/transfers")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0]["from_account_id"], account_ids[0]);
+}
+
+#[tokio::test]
+async fn test_account_goal_required_monthly_contribution() {
+    use chrono::{Datelike, Months, Utc};
+
+    let (server, _user_id, token) = setup_with_user().await;
+
+    // Six monthly contributions, counting this month, before the target date
+    let today = Utc::now().date_naive();
+    let target_date = today.with_day(1).unwrap() + Months::new(6);
+    for (name, goal, goal_date) in [
+        ("Vacation", json!(2000.0), json!(target_date)),
+        ("Emergency fund", json!(1000.0), json!(null)),
+        ("Checking", json!(null), json!(null)),
+    ] {
+        server
+            .post("/api/savings-accounts")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"name": name, "balance": 500.0, "goal": goal, "goal_date": goal_date}))
+            .await
+            .assert_status(axum::http::StatusCode::CREATED);
+    }
+
+    let goals: serde_json::Value = server
+        .get("/api/savings/goals")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    let goals = goals.as_array().unwrap();
+    assert_eq!(goals.len(), 2);
+
+    let emergency = &goals[0];
+    assert_eq!(emergency["name"], "Emergency fund");
+    assert_eq!(emergency["percent_complete"], 50.0);
+    assert!(emergency["required_monthly"].is_null());
+
+    let vacation = &goals[1];
+    assert_eq!(vacation["name"], "Vacation");
+    assert_eq!(vacation["target"], 2000.0);
+    assert_eq!(vacation["percent_complete"], 25.0);
+    assert_eq!(vacation["remaining"], 1500.0);
+    assert_eq!(vacation["target_date"], json!(target_date));
+    assert_eq!(vacation["required_monthly"], 250.0);
+}
@@ -1,8 +1,10 @@
 mod common;
 
+use chrono::{Datelike, Months, Utc};
 use common::{
-    auth_name, auth_value, create_test_category, create_test_fixed_expense, create_test_income,
-    create_test_item, create_test_month, create_test_pool, create_test_server, create_test_user,
+    auth_name, auth_value, close_test_month, create_test_budget, create_test_category,
+    create_test_fixed_expense, create_test_income, create_test_item, create_test_month,
+    create_test_monthly_savings, create_test_pool, create_test_server, create_test_user,
     generate_token,
 };
 use payme::create_app;
@@ -120,3 +122,500 @@ async fn test_stats_change_percent() {
 
     assert_eq!(food_comparison["change_percent"], 50.0);
 }
+
+#[tokio::test]
+async fn test_stats_budget_variance() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food_id = create_test_category(&pool, user_id, "Food", 200.0).await;
+    let fun_id = create_test_category(&pool, user_id, "Fun", 100.0).await;
+    create_test_budget(&pool, month_id, food_id, 200.0).await;
+    create_test_budget(&pool, month_id, fun_id, 100.0).await;
+
+    create_test_item(&pool, month_id, food_id, "Groceries", 150.0, "2024-06-10").await;
+    create_test_item(&pool, month_id, food_id, "Takeout", 100.0, "2024-06-12").await;
+    create_test_item(&pool, month_id, fun_id, "Cinema", 40.0, "2024-06-14").await;
+
+    let response = server
+        .get("/api/stats")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let comparisons = body["category_comparisons"].as_array().unwrap();
+
+    let food = comparisons
+        .iter()
+        .find(|c| c["category_label"] == "Food")
+        .unwrap();
+    assert_eq!(food["budgeted"], 200.0);
+    assert_eq!(food["actual"], 250.0);
+    assert_eq!(food["variance"], -50.0);
+    assert_eq!(food["percent_used"], 125.0);
+
+    let fun = comparisons
+        .iter()
+        .find(|c| c["category_label"] == "Fun")
+        .unwrap();
+    assert_eq!(fun["variance"], 60.0);
+    assert_eq!(fun["percent_used"], 40.0);
+}
+
+#[tokio::test]
+async fn test_stats_budget_alerts() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food_id = create_test_category(&pool, user_id, "Food", 200.0).await;
+    let fun_id = create_test_category(&pool, user_id, "Fun", 100.0).await;
+    let travel_id = create_test_category(&pool, user_id, "Travel", 100.0).await;
+    create_test_budget(&pool, month_id, food_id, 200.0).await;
+    create_test_budget(&pool, month_id, fun_id, 100.0).await;
+    create_test_budget(&pool, month_id, travel_id, 100.0).await;
+
+    create_test_item(&pool, month_id, food_id, "Groceries", 170.0, "2024-06-10").await;
+    create_test_item(&pool, month_id, fun_id, "Concert", 110.0, "2024-06-12").await;
+    create_test_item(&pool, month_id, travel_id, "Train", 50.0, "2024-06-14").await;
+
+    let response = server
+        .get("/api/stats")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let alerts = body["alerts"].as_array().unwrap();
+    assert_eq!(alerts.len(), 2);
+
+    let food = alerts
+        .iter()
+        .find(|a| a["category_label"] == "Food")
+        .unwrap();
+    assert_eq!(food["severity"], "warning");
+    assert_eq!(food["percent_used"], 85.0);
+
+    let fun = alerts
+        .iter()
+        .find(|a| a["category_label"] == "Fun")
+        .unwrap();
+    assert_eq!(fun["severity"], "critical");
+    assert!((fun["percent_used"].as_f64().unwrap() - 110.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_update_alert_thresholds() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let food_id = create_test_category(&pool, user_id, "Food", 200.0).await;
+    create_test_budget(&pool, month_id, food_id, 200.0).await;
+    create_test_item(&pool, month_id, food_id, "Groceries", 120.0, "2024-06-10").await;
+
+    server
+        .put("/api/stats/alert-thresholds")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"warning_percent": 50.0, "critical_percent": 90.0}))
+        .await
+        .assert_status_ok();
+
+    let body: serde_json::Value = server
+        .get("/api/stats")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(body["alerts"][0]["severity"], "warning");
+
+    let response = server
+        .put("/api/stats/alert-thresholds")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"warning_percent": 90.0, "critical_percent": 50.0}))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_category_trend_fills_gaps() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let today = Utc::now().date_naive();
+    let periods: Vec<_> = (0..3)
+        .map(|back| today.checked_sub_months(Months::new(back)).unwrap())
+        .collect();
+    let current =
+        create_test_month(&pool, user_id, periods[0].year(), periods[0].month() as i32).await;
+    create_test_month(&pool, user_id, periods[1].year(), periods[1].month() as i32).await;
+    let oldest =
+        create_test_month(&pool, user_id, periods[2].year(), periods[2].month() as i32).await;
+    let cat_id = create_test_category(&pool, user_id, "Groceries", 300.0).await;
+
+    let spent_on = |d: chrono::NaiveDate| d.with_day(1).unwrap().to_string();
+    create_test_item(
+        &pool,
+        oldest,
+        cat_id,
+        "Market",
+        120.0,
+        &spent_on(periods[2]),
+    )
+    .await;
+    create_test_item(
+        &pool,
+        current,
+        cat_id,
+        "Market",
+        80.0,
+        &spent_on(periods[0]),
+    )
+    .await;
+    create_test_item(
+        &pool,
+        current,
+        cat_id,
+        "Bakery",
+        20.0,
+        &spent_on(periods[0]),
+    )
+    .await;
+
+    let response = server
+        .get(&format!(
+            "/api/stats/trends?category_id={}&months=3",
+            cat_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let points = body["points"].as_array().unwrap();
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0]["month"], periods[2].month());
+    assert_eq!(points[0]["total_spent"], 120.0);
+    assert_eq!(points[1]["total_spent"], 0.0);
+    assert_eq!(points[2]["month"], periods[0].month());
+    assert_eq!(points[2]["total_spent"], 100.0);
+}
+
+#[tokio::test]
+async fn test_category_trend_other_user_category() {
+    let (server, pool, _user_id, token) = setup_with_user().await;
+
+    let other_id = create_test_user(&pool, "other", "password123").await;
+    let cat_id = create_test_category(&pool, other_id, "Groceries", 300.0).await;
+
+    let response = server
+        .get(&format!("/api/stats/trends?category_id={}", cat_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_savings_rate() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let cat_id = create_test_category(&pool, user_id, "Savings", 0.0).await;
+
+    let may = create_test_month(&pool, user_id, 2024, 5).await;
+    let june = create_test_month(&pool, user_id, 2024, 6).await;
+    let july = create_test_month(&pool, user_id, 2024, 7).await;
+    create_test_income(&pool, may, "Salary", 4000.0).await;
+    create_test_income(&pool, june, "Salary", 5000.0).await;
+    create_test_monthly_savings(&pool, may, 10000.0, 0.0).await;
+    create_test_monthly_savings(&pool, june, 10000.0, 500.0).await;
+
+    for (month_id, amount, spent_on) in [(may, 400.0, "2024-05-31"), (june, 1000.0, "2024-06-30")] {
+        server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&serde_json::json!({
+                "category_id": cat_id,
+                "description": "Transfer",
+                "amount": amount,
+                "spent_on": spent_on,
+                "savings_destination": "savings"
+            }))
+            .await
+            .assert_status_ok();
+    }
+    create_test_item(&pool, july, cat_id, "Transfer", 100.0, "2024-07-01").await;
+
+    let response = server
+        .get("/api/stats/savings-rate?months=12")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let points: Vec<serde_json::Value> = response.json();
+    assert_eq!(points.len(), 3);
+
+    // May has no earlier snapshot, so only the transfer counts
+    assert_eq!(points[0]["month"], 5);
+    assert_eq!(points[0]["saved"], 400.0);
+    assert_eq!(points[0]["savings_rate"], 0.1);
+    // June: 1000 transferred plus 500 snapshot growth over 5000 income
+    assert_eq!(points[1]["month"], 6);
+    assert_eq!(points[1]["saved"], 1500.0);
+    assert_eq!(points[1]["savings_rate"], 0.3);
+    // No income means a zero rate rather than a division error
+    assert_eq!(points[2]["total_income"], 0.0);
+    assert_eq!(points[2]["savings_rate"], 0.0);
+
+    let response = server
+        .get("/api/stats/savings-rate?months=0")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_top_categories() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+
+    for (label, spent) in [
+        ("Rent", 1200.0),
+        ("Food", 300.0),
+        ("Fun", 50.0),
+        ("Travel", 300.0),
+        ("Gifts", 25.0),
+        ("Books", 125.0),
+    ] {
+        let cat_id = create_test_category(&pool, user_id, label, 0.0).await;
+        create_test_item(&pool, month_id, cat_id, label, spent, "2024-06-10").await;
+    }
+
+    let response = server
+        .get(&format!(
+            "/api/months/{}/stats/top-categories?limit=3",
+            month_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+    response.assert_status_ok();
+    let top: Vec<serde_json::Value> = response.json();
+    let labels: Vec<&str> = top
+        .iter()
+        .map(|c| c["category_label"].as_str().unwrap())
+        .collect();
+    // Food and Travel tie at 300 and are ordered by label
+    assert_eq!(labels, vec!["Rent", "Food", "Travel"]);
+    assert_eq!(top[0]["total_spent"], 1200.0);
+    assert_eq!(top[0]["percent_of_total"], 60.0);
+
+    let all: Vec<serde_json::Value> = server
+        .get(&format!(
+            "/api/months/{}/stats/top-categories?limit=50",
+            month_id
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(all.len(), 6);
+}
+
+#[tokio::test]
+async fn test_daily_spending() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let month_id = create_test_month(&pool, user_id, 2024, 2).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+    create_test_item(&pool, month_id, cat_id, "Groceries", 40.0, "2024-02-03").await;
+    create_test_item(&pool, month_id, cat_id, "Snacks", 5.0, "2024-02-03").await;
+    create_test_item(&pool, month_id, cat_id, "Dinner", 60.0, "2024-02-29").await;
+
+    let response = server
+        .get(&format!("/api/months/{}/stats/daily", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let days: Vec<serde_json::Value> = response.json();
+    assert_eq!(days.len(), 29);
+    assert_eq!(days[0]["date"], "2024-02-01");
+    assert_eq!(days[2]["total"], 45.0);
+    assert_eq!(days[28]["date"], "2024-02-29");
+    assert_eq!(days[28]["total"], 60.0);
+    let nonzero = days.iter().filter(|d| d["total"] != 0.0).count();
+    assert_eq!(nonzero, 2);
+
+    let response = server
+        .get("/api/stats/daily?year=2024")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let days: Vec<serde_json::Value> = response.json();
+    assert_eq!(days.len(), 366);
+    assert_eq!(days[33]["date"], "2024-02-03");
+    assert_eq!(days[33]["total"], 45.0);
+}
+
+#[tokio::test]
+async fn test_annual_stats_sums_months_of_year() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let food = create_test_category(&pool, user_id, "Food", 0.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 0.0).await;
+    for (month, food_spent, fun_spent) in [(1, 250.0, 50.0), (2, 320.0, 0.0), (3, 280.0, 120.0)] {
+        let month_id = create_test_month(&pool, user_id, 2025, month).await;
+        create_test_income(&pool, month_id, "Salary", 2000.0).await;
+        create_test_budget(&pool, month_id, food, 300.0).await;
+        create_test_budget(&pool, month_id, fun, 100.0).await;
+        let spent_on = format!("2025-{month:02}-10");
+        create_test_item(&pool, month_id, food, "Groceries", food_spent, &spent_on).await;
+        if fun_spent > 0.0 {
+            create_test_item(&pool, month_id, fun, "Concert", fun_spent, &spent_on).await;
+        }
+    }
+    // Other years are left out
+    let other = create_test_month(&pool, user_id, 2024, 12).await;
+    create_test_item(&pool, other, food, "Groceries", 999.0, "2024-12-10").await;
+
+    let response = server
+        .get("/api/stats/annual?year=2025")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["months"], 3);
+    let categories = body["categories"].as_array().unwrap();
+    assert_eq!(categories[0]["category_id"], food);
+    assert_eq!(categories[0]["budgeted"], 900.0);
+    assert_eq!(categories[0]["spent"], 850.0);
+    assert_eq!(categories[0]["variance"], 50.0);
+    assert_eq!(categories[1]["category_id"], fun);
+    assert_eq!(categories[1]["budgeted"], 300.0);
+    assert_eq!(categories[1]["spent"], 170.0);
+    assert_eq!(categories[1]["variance"], 130.0);
+    assert_eq!(body["total_budgeted"], 1200.0);
+    assert_eq!(body["total_spent"], 1020.0);
+    assert_eq!(body["total_income"], 6000.0);
+
+    let empty = server
+        .get("/api/stats/annual?year=2023")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    empty.assert_status_ok();
+    let body: serde_json::Value = empty.json();
+    assert_eq!(body["months"], 0);
+    assert_eq!(body["categories"][0]["spent"], 0.0);
+    assert_eq!(body["total_spent"], 0.0);
+    assert_eq!(body["savings_rate"], 0.0);
+}
+
+#[tokio::test]
+async fn test_range_stats_across_two_months() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+
+    let medical = create_test_category(&pool, user_id, "Medical", 0.0).await;
+    let food = create_test_category(&pool, user_id, "Food", 0.0).await;
+    let january = create_test_month(&pool, user_id, 2025, 1).await;
+    let february = create_test_month(&pool, user_id, 2025, 2).await;
+    // Before the range
+    create_test_item(&pool, january, medical, "Checkup", 500.0, "2025-01-10").await;
+    create_test_item(&pool, january, medical, "Dentist", 120.0, "2025-01-20").await;
+    create_test_item(&pool, january, food, "Groceries", 80.0, "2025-01-25").await;
+    create_test_item(&pool, february, medical, "Pharmacy", 35.5, "2025-02-03").await;
+    create_test_item(&pool, february, food, "Groceries", 60.0, "2025-02-14").await;
+    // After the range
+    create_test_item(&pool, february, medical, "Physio", 90.0, "2025-02-20").await;
+    close_test_month(&pool, january).await;
+
+    let response = server
+        .get("/api/stats/range?from=2025-01-15&to=2025-02-15")
+        .add_header(auth_name(), auth_value(&token))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total_spent"], 295.5);
+    assert_eq!(
+        body["months"],
+        serde_json::json!([
+            {"year": 2025, "month": 1, "total_spent": 200.0},
+            {"year": 2025, "month": 2, "total_spent": 95.5}
+        ])
+    );
+    let categories = body["categories"].as_array().unwrap();
+    assert_eq!(categories[0]["category_label"], "Food");
+    assert_eq!(categories[0]["total_spent"], 140.0);
+    assert_eq!(categories[1]["category_label"], "Medical");
+    assert_eq!(categories[1]["total_spent"], 155.5);
+    assert_eq!(categories[1]["months"][0]["total_spent"], 120.0);
+    assert_eq!(categories[1]["months"][1]["total_spent"], 35.5);
+
+    let medical_only: serde_json::Value = server
+        .get(&format!(
+            "/api/stats/range?from=2025-01-15&to=2025-02-15&category_id={}",
+            medical
+        ))
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(medical_only["total_spent"], 155.5);
+    assert_eq!(medical_only["categories"].as_array().unwrap().len(), 1);
+
+    server
+        .get("/api/stats/range?from=2025-02-15&to=2025-01-15")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_monthly_digest_recaps_previous_month_once() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    sqlx::query(
+        "UPDATE users SET email = 'test@example.com', savings = 250000, savings_goal = 1000000 WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    server
+        .put("/api/auth/notifications")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&serde_json::json!({"month_closed": false, "savings_goal": false, "monthly_digest": true}))
+        .await
+        .assert_status_ok();
+
+    let food = create_test_category(&pool, user_id, "Food", 0.0).await;
+    let fun = create_test_category(&pool, user_id, "Fun", 0.0).await;
+    let month_id = create_test_month(&pool, user_id, 2025, 1).await;
+    create_test_income(&pool, month_id, "Salary", 3000.0).await;
+    create_test_budget(&pool, month_id, food, 300.0).await;
+    create_test_budget(&pool, month_id, fun, 100.0).await;
+    create_test_item(&pool, month_id, food, "Groceries", 250.0, "2025-01-10").await;
+    create_test_item(&pool, month_id, fun, "Concert", 120.0, "2025-01-18").await;
+
+    let today = chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+    let queued = payme::handlers::stats::queue_monthly_digests(&pool, today)
+        .await
+        .unwrap();
+    assert_eq!(queued, 1);
+    let again = payme::handlers::stats::queue_monthly_digests(&pool, today)
+        .await
+        .unwrap();
+    assert_eq!(again, 0);
+
+    let emails: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT kind, subject, body FROM email_outbox WHERE user_id = ? ORDER BY id",
+    )
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(emails.len(), 1);
+    let (kind, subject, body) = &emails[0];
+    assert_eq!(kind, "monthly_digest");
+    assert_eq!(subject, "Your January 2025 recap");
+    assert!(body.contains("Income: 3000.00 USD"));
+    assert!(body.contains("Spent: 370.00 USD"));
+    assert!(body.contains("Food: 250.00 of 300.00 USD (50.00 left)"));
+    assert!(body.contains("Fun: 120.00 of 100.00 USD (over by 20.00)"));
+    assert!(body.contains("Savings goal: 2500.00 of 10000.00 USD (25%)"));
+}
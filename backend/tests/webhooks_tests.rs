@@ -0,0 +1,241 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{body::Bytes, http::HeaderMap, routing::post, Router};
+use common::{
+    auth_name, auth_value, create_test_budget, create_test_category, create_test_month,
+    create_test_pool, create_test_server, create_test_user, generate_token,
+};
+use payme::config::{AppOptions, WebhookPolicy};
+use payme::create_app_with;
+use payme::webhooks::{sign, SIGNATURE_HEADER};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+/// The receivers below are plain http on loopback
+const LOCAL_RECEIVERS: WebhookPolicy = WebhookPolicy {
+    allow_http: true,
+    allow_private_hosts: true,
+};
+
+async fn setup_with_user() -> (axum_test::TestServer, sqlx::SqlitePool, i64, String) {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let options = AppOptions {
+        webhooks: LOCAL_RECEIVERS,
+        ..Default::default()
+    };
+    let app = create_app_with(pool.clone(), options);
+    let server = create_test_server(app);
+    (server, pool, user_id, token)
+}
+
+/// Starts a receiver on a random local port that forwards each request's signature and body.
+async fn spawn_receiver() -> (String, mpsc::UnboundedReceiver<(String, Bytes)>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: Bytes| {
+            let tx = tx.clone();
+            async move {
+                let signature = headers
+                    .get(SIGNATURE_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let _ = tx.send((signature, body));
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{}/hook", addr), rx)
+}
+
+#[tokio::test]
+async fn test_item_created_webhook_is_signed() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let (url, mut rx) = spawn_receiver().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    let response = server
+        .post("/api/webhooks")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"url": url, "events": ["item.created"]}))
+        .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    let webhook: serde_json::Value = response.json();
+    let secret = webhook["secret"].as_str().unwrap().to_string();
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Groceries",
+            "amount": 42.5,
+            "spent_on": "2024-06-15"
+        }))
+        .await
+        .assert_status_ok();
+
+    let (signature, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("webhook was not delivered")
+        .unwrap();
+    assert_eq!(signature, sign(&secret, &body));
+
+    let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload["event"], "item.created");
+    assert_eq!(payload["data"]["description"], "Groceries");
+    assert_eq!(payload["data"]["amount"], 42.5);
+
+    let listed: Vec<serde_json::Value> = server
+        .get("/api/webhooks")
+        .add_header(auth_name(), auth_value(&token))
+        .await
+        .json();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].get("secret").is_none());
+}
+
+#[tokio::test]
+async fn test_failed_delivery_is_recorded() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 500.0).await;
+
+    // Bind then drop a listener so the port refuses connections
+    let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = closed.local_addr().unwrap();
+    drop(closed);
+
+    let webhook: serde_json::Value = server
+        .post("/api/webhooks")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"url": format!("http://{}/hook", addr), "events": ["item.created"]}))
+        .await
+        .json();
+    let webhook_id = webhook["id"].as_i64().unwrap();
+
+    server
+        .post(&format!("/api/months/{}/items", month_id))
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({
+            "category_id": cat_id,
+            "description": "Groceries",
+            "amount": 10.0,
+            "spent_on": "2024-06-15"
+        }))
+        .await
+        .assert_status_ok();
+
+    // Creating the item already spawned a dispatch that may hold the claim, so wait for
+    // whichever run gets there to record the attempt
+    payme::webhooks::dispatch_due(&pool, LOCAL_RECEIVERS).await;
+    let mut deliveries: Vec<serde_json::Value> = vec![];
+    for _ in 0..50 {
+        deliveries = server
+            .get(&format!("/api/webhooks/{}/deliveries", webhook_id))
+            .add_header(auth_name(), auth_value(&token))
+            .await
+            .json();
+        if deliveries[0]["attempts"].as_i64().unwrap() >= 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["event"], "item.created");
+    assert!(deliveries[0]["attempts"].as_i64().unwrap() >= 1);
+    assert!(deliveries[0]["last_error"].is_string());
+    assert_ne!(deliveries[0]["status"], "delivered");
+}
+
+#[tokio::test]
+async fn test_create_webhook_rejects_unknown_event() {
+    let (server, _pool, _user_id, token) = setup_with_user().await;
+
+    let response = server
+        .post("/api/webhooks")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"url": "http://localhost:9/hook", "events": ["item.exploded"]}))
+        .await;
+    response.assert_status_bad_request();
+
+    let response = server
+        .post("/api/webhooks")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"url": "ftp://localhost/hook", "events": ["item.created"]}))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_default_policy_rejects_local_http_webhook() {
+    let pool = create_test_pool().await;
+    let user_id = create_test_user(&pool, "testuser", "password123").await;
+    let token = generate_token(user_id, "testuser");
+    let server = create_test_server(create_app_with(pool, AppOptions::default()));
+
+    for url in ["http://example.com/hook", "https://127.0.0.1/hook"] {
+        server
+            .post("/api/webhooks")
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({"url": url, "events": ["item.created"]}))
+            .await
+            .assert_status_bad_request();
+    }
+}
+
+#[tokio::test]
+async fn test_budget_threshold_crossed_fires_once() {
+    let (server, pool, user_id, token) = setup_with_user().await;
+    let (url, mut rx) = spawn_receiver().await;
+    let month_id = create_test_month(&pool, user_id, 2024, 6).await;
+    let cat_id = create_test_category(&pool, user_id, "Food", 100.0).await;
+    create_test_budget(&pool, month_id, cat_id, 100.0).await;
+
+    server
+        .post("/api/webhooks")
+        .add_header(auth_name(), auth_value(&token))
+        .json(&json!({"url": url, "events": ["budget.threshold_crossed"]}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    for (description, amount) in [("Groceries", 60.0), ("Dinner", 50.0), ("Snacks", 20.0)] {
+        server
+            .post(&format!("/api/months/{}/items", month_id))
+            .add_header(auth_name(), auth_value(&token))
+            .json(&json!({
+                "category_id": cat_id,
+                "description": description,
+                "amount": amount,
+                "spent_on": "2024-06-15"
+            }))
+            .await
+            .assert_status_ok();
+    }
+
+    let (_, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("webhook was not delivered")
+        .unwrap();
+    let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload["event"], "budget.threshold_crossed");
+    assert_eq!(payload["data"]["category_id"], cat_id);
+    assert_eq!(payload["data"]["category_label"], "Food");
+    assert_eq!(payload["data"]["budgeted"], 100.0);
+    assert_eq!(payload["data"]["actual"], 110.0);
+
+    // Spending that stays over budget doesn't fire again
+    let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_deliveries")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(events, 1);
+}
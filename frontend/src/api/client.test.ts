@@ -0,0 +1,43 @@
+import assert from "node:assert/strict";
+import { test } from "node:test";
+import { api } from "./client.ts";
+
+test("concurrent 401s share one refresh and keep the session", async () => {
+  let accessValid = false;
+  let refreshToken = "refresh-1";
+  let revoked = false;
+  let refreshes = 0;
+  const spent = new Set<string>();
+
+  globalThis.fetch = (async (input: RequestInfo | URL) => {
+    if (String(input) === "/api/auth/refresh") {
+      refreshes += 1;
+      const presented = refreshToken;
+      await new Promise((resolve) => setTimeout(resolve, 10));
+      // Like the backend, presenting a spent refresh token revokes the whole session
+      if (revoked || spent.has(presented)) {
+        revoked = true;
+        accessValid = false;
+        return new Response(null, { status: 401 });
+      }
+      spent.add(presented);
+      refreshToken = `${presented}-next`;
+      accessValid = true;
+      return new Response(null, { status: 200 });
+    }
+    if (!accessValid) {
+      return new Response(null, { status: 401 });
+    }
+    return Response.json({ id: 1, username: "alice" });
+  }) as typeof fetch;
+
+  const results = await Promise.all([api.auth.me(), api.auth.me()]);
+
+  assert.deepEqual(results, [
+    { id: 1, username: "alice" },
+    { id: 1, username: "alice" },
+  ]);
+  assert.equal(refreshes, 1);
+  assert.equal(revoked, false);
+  assert.deepEqual(await api.auth.me(), { id: 1, username: "alice" });
+});
@@ -1,17 +1,49 @@
 const BASE_URL = "/api";
 
+const NO_REFRESH_ENDPOINTS = ["/auth/login", "/auth/register", "/auth/refresh", "/auth/logout"];
+
+let refreshing: Promise<boolean> | null = null;
+
+// Refresh tokens are single-use and reusing one ends the session, so requests that get a 401
+// together wait on the same refresh instead of each sending the cookie again
+function refreshSession(): Promise<boolean> {
+  if (!refreshing) {
+    refreshing = fetch(`${BASE_URL}/auth/refresh`, {
+      method: "POST",
+      credentials: "include",
+    })
+      .then((response) => response.ok)
+      .finally(() => {
+        refreshing = null;
+      });
+  }
+  return refreshing;
+}
+
 async function request<T>(
   endpoint: string,
   options: RequestInit = {}
 ): Promise<T> {
-  const response = await fetch(`${BASE_URL}${endpoint}`, {
-    ...options,
-    headers: {
-      "Content-Type": "application/json",
-      ...options.headers,
-    },
-    credentials: "include",
-  });
+  const send = () =>
+    fetch(`${BASE_URL}${endpoint}`, {
+      ...options,
+      headers: {
+        "Content-Type": "application/json",
+        ...options.headers,
+      },
+      credentials: "include",
+    });
+
+  let response = await send();
+
+  // Access tokens are short-lived; renew once from the refresh cookie and retry
+  if (
+    response.status === 401 &&
+    !NO_REFRESH_ENDPOINTS.includes(endpoint) &&
+    (await refreshSession())
+  ) {
+    response = await send();
+  }
 
   if (!response.ok) {
     throw new Error(`HTTP ${response.status}`);
@@ -154,6 +186,7 @@ export const api = {
         amount?: number;
         spent_on?: string;
         savings_destination?: string;
+        version: number;
       }
     ) =>
       request<Item>(`/months/${monthId}/items/${itemId}`, {
@@ -349,6 +382,7 @@ export interface Item {
   amount: number;
   spent_on: string;
   savings_destination: string;
+  version: number;
 }
 
 export interface ItemWithCategory extends Item {
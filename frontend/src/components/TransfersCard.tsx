@@ -52,10 +52,10 @@ export function TransfersCard({
     await onUpdate();
   };
 
-  const handleUpdate = async (id: number) => {
+  const handleUpdate = async (item: ItemWithCategory) => {
     if (!description || !amount) return;
     const catId = categories.length > 0 ? categories[0].id : 1;
-    await api.items.update(monthId, id, {
+    await api.items.update(monthId, item.id, {
       description,
       amount: parseFloat(amount),
       category_id: catId,
@@ -220,7 +220,7 @@ export function TransfersCard({
                     <td className="py-2">
                       <div className="flex gap-0.5 md:gap-1 justify-end">
                         <button
-                          onClick={() => handleUpdate(item.id)}
+                          onClick={() => handleUpdate(item)}
                           className="p-2 md:p-1 text-sage-600 hover:bg-sage-100 dark:hover:bg-charcoal-800 active:bg-sage-200 dark:active:bg-charcoal-700 transition-colors rounded touch-manipulation"
                         >
                           <Check size={14} />